@@ -1,11 +1,202 @@
 #[cfg(test)]
 mod tests {
     use filego::{
-        check::{Check, CheckResult, CheckResultErrorType},
-        merge::Merge,
-        split::{Split, SplitResult},
+        attributes::{FileAttributes, FileMetadata, WINDOWS_ATTRIBUTES},
+        bao::{outboard, outboard_path, BaoVerifier},
+        blocking,
+        boundary::{
+            ContentDefined, KeyframeAligned, MediaContainer, RowGroupAligned,
+        },
+        check::{
+            Check, CheckProgress, CheckResult, CheckResultError,
+            CheckResultErrorType,
+        },
+        checksum::{Algorithm, ChunkChecksum},
+        clock::{Clock, MockClock},
+        control::OperationHandle,
+        device::is_block_device,
+        diff::{Diff, Difference},
+        etag::{chunks_etag, file_etag, file_md5},
+        event::{Event, EventBus, EventStream},
+        header::{ChunkHeader, CHUNK_HEADER_SIZE},
+        health::{ProbeReport, Probes},
+        inspect::{inspect, Anomaly, InspectReport},
+        key::KeyTemplate,
+        layout::{Layout, LayoutRegistry},
+        log::{
+            Operation, OperationLog, OperationRecord, ReplayOutcome,
+            ReplayResult,
+        },
+        manifest::{
+            migrate, sign, write_signature, ChunkInfo, Manifest,
+            ManifestFormat, MigrateError, Piece, SchemaVersion, Signature,
+            SigningKey, UpgradePlan, VerifyingKey,
+        },
+        merge::{Collision, Expected, Merge, MergeProgress, MergeResult},
+        merkle::{
+            self, prove, verify_proof, ChunkProof, MerkleProof, MerkleTree,
+        },
+        mime::sniff,
+        naming::ChunkNaming,
+        par2::{Par2, Par2Result},
+        peek::Peek,
+        profile::Profile,
+        progress::{FnSink, ProgressCounter, ProgressSink},
+        rechunk::{rechunk, RechunkResult},
+        rng::{Rng, SeededRng, SystemRng},
+        scheduler::{Job, JobHandle, JobOutput, JobStatus, Scheduler},
+        serve::{range_stream, Bytes, RangeStream, Stream},
+        session::{Session, SessionStatus},
+        split::{Split, SplitMode, SplitPlan, SplitProgress, SplitResult},
+        stall::{StallMonitor, StallStats},
+        store::{
+            ChunkHead, ChunkStore, DirStore, Mmap, StoreFuture, TieredStore,
+        },
+        sync::{sync, SyncResult},
+        transform::ChunkTransform,
+        tune::{
+            available_memory, available_space, memory_budget, MemoryBudget,
+        },
+        unicode::Normalization,
     };
-    use std::{env, fs, path::PathBuf};
+    use std::{
+        collections::HashMap,
+        env, fs, io,
+        path::{Path, PathBuf},
+        pin::Pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+    use tokio::{
+        io::AsyncReadExt,
+        runtime::{self, Runtime},
+    };
+
+    /// Chunks named as `chunk-0.bin`, `chunk-1.bin`, ...
+    #[derive(Debug)]
+    struct Bin;
+
+    impl Layout for Bin {
+        fn id(&self) -> &str {
+            "bin"
+        }
+
+        fn index(
+            &self,
+            name: &str,
+        ) -> Option<usize> {
+            name.strip_prefix("chunk-")?.strip_suffix(".bin")?.parse().ok()
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct MemoryStore {
+        chunks: Mutex<HashMap<usize, Vec<u8>>>,
+        etags: Mutex<HashMap<usize, String>>,
+        gets: AtomicUsize,
+    }
+
+    impl ChunkStore for MemoryStore {
+        fn head(
+            &self,
+            index: usize,
+        ) -> StoreFuture<'_, Option<ChunkHead>> {
+            let size: Option<usize> =
+                self.chunks.lock().unwrap().get(&index).map(Vec::len);
+            let etag: Option<String> =
+                self.etags.lock().unwrap().get(&index).cloned();
+
+            Box::pin(
+                async move { Ok(size.map(|size| ChunkHead { size, etag })) },
+            )
+        }
+
+        fn get(
+            &self,
+            index: usize,
+        ) -> StoreFuture<'_, Vec<u8>> {
+            self.gets.fetch_add(1, Ordering::SeqCst);
+
+            let chunk: Option<Vec<u8>> =
+                self.chunks.lock().unwrap().get(&index).cloned();
+
+            Box::pin(async move {
+                chunk.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+            })
+        }
+
+        fn put(
+            &self,
+            index: usize,
+            data: Vec<u8>,
+        ) -> StoreFuture<'_, ()> {
+            self.chunks.lock().unwrap().insert(index, data);
+
+            Box::pin(async move { Ok(()) })
+        }
+    }
+
+    async fn memory_store(
+        cache_dir: &Path,
+        total_chunks: usize,
+    ) -> Arc<MemoryStore> {
+        let store: Arc<MemoryStore> = Arc::new(MemoryStore::default());
+
+        for i in 0..total_chunks {
+            let chunk: Vec<u8> =
+                fs::read(cache_dir.join(i.to_string())).unwrap();
+
+            store.put(i, chunk).await.unwrap();
+        }
+
+        store
+    }
+
+    #[derive(Debug)]
+    struct Xor(u8);
+
+    impl ChunkTransform for Xor {
+        fn id(&self) -> &str {
+            "xor"
+        }
+
+        fn encode(
+            &self,
+            _index: usize,
+            chunk: Vec<u8>,
+        ) -> io::Result<Vec<u8>> {
+            Ok(chunk.into_iter().map(|b| b ^ self.0).collect())
+        }
+
+        fn decode(
+            &self,
+            index: usize,
+            chunk: Vec<u8>,
+        ) -> io::Result<Vec<u8>> {
+            self.encode(index, chunk)
+        }
+    }
+
+    /// Peak resident memory of the process in bytes.
+    #[cfg(target_os = "linux")]
+    fn peak_memory() -> usize {
+        let status: String = fs::read_to_string("/proc/self/status").unwrap();
+
+        let line: &str =
+            status.lines().find(|l| l.starts_with("VmHWM:")).unwrap();
+
+        let kib: usize = line
+            .trim_start_matches("VmHWM:")
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .unwrap();
+
+        kib * 1024
+    }
 
     async fn setup(
         cache_name: &str
@@ -45,6 +236,58 @@ mod tests {
         assert!(chunk_count > 0, "No chunks were created.");
     }
 
+    #[tokio::test]
+    async fn test_split_with_chunk_size_larger_than_file() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("chunk_size_larger");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("chunk_size_larger")
+            .join("test.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let file_size: usize = fs::metadata(&in_file).unwrap().len() as usize;
+
+        let split = || {
+            Split::new()
+                .in_file(&in_file)
+                .out_dir(&cache_dir)
+                .chunk_size(1024 * 1024 * 1024)
+        };
+
+        let plan: SplitPlan = split().plan().await.unwrap();
+
+        assert_eq!(plan.file_size, file_size);
+        assert_eq!(plan.chunk_size, file_size);
+        assert_eq!(plan.total_chunks, 1);
+        assert!(plan.buffer_capacity <= file_size);
+
+        let split_result: SplitResult = split().run().await.unwrap();
+
+        assert_eq!(split_result.total_chunks, 1);
+        assert_eq!(split_result.manifest.chunk_size, file_size);
+        assert_eq!(
+            fs::read(cache_dir.join("0")).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn test_check_with_missing_chunks() {
         let (_, cache_dir, _, split_result) =
@@ -92,6 +335,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[allow(clippy::bool_comparison)]
     async fn test_successful_check() {
         let (_, cache_dir, _, split_result) = setup("successful_check").await;
 
@@ -151,4 +395,5607 @@ mod tests {
             "Merge should fail with an empty cache directory."
         );
     }
+
+    #[tokio::test]
+    async fn test_split_record_aligned_keeps_records() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf =
+            root.join(".media").join("input").join("split_record_aligned");
+        let input_path: PathBuf = input_dir.join("records.jsonl");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_record_aligned");
+
+        let records: String =
+            (0..100).map(|i| format!("{{\"id\":{}}}\n", i)).collect();
+
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::write(&input_path, &records).unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&input_path)
+            .out_dir(&cache_dir)
+            .chunk_size(64)
+            .mode(SplitMode::RecordAligned { delimiter: b'\n' })
+            .run()
+            .await
+            .unwrap();
+
+        let mut merged: Vec<u8> = Vec::new();
+
+        for i in 0..split_result.total_chunks {
+            let chunk: Vec<u8> =
+                fs::read(cache_dir.join(i.to_string())).unwrap();
+
+            assert!(chunk.len() <= 64, "Chunk should not exceed chunk_size.");
+            assert_eq!(
+                chunk.last(),
+                Some(&b'\n'),
+                "Chunk should end with the delimiter."
+            );
+
+            merged.extend(chunk);
+        }
+
+        assert_eq!(merged, records.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_split_content_defined_merges_back() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let asset_path: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_content_defined");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_content_defined")
+            .join("test.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&asset_path)
+            .out_dir(&cache_dir)
+            .chunk_size(256 * 1024)
+            .mode(SplitMode::Custom(Arc::new(ContentDefined::new(
+                16 * 1024,
+                64 * 1024,
+            ))))
+            .run()
+            .await
+            .unwrap();
+
+        for i in 0..split_result.total_chunks - 1 {
+            let size: u64 =
+                fs::metadata(cache_dir.join(i.to_string())).unwrap().len();
+
+            assert!(size >= 16 * 1024, "Chunk should not be under min_size.");
+            assert!(size <= 256 * 1024, "Chunk should not exceed chunk_size.");
+        }
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&asset_path).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_keyframe_aligned_starts_at_fragments() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf =
+            root.join(".media").join("input").join("split_keyframe_aligned");
+        let input_path: PathBuf = input_dir.join("video.mp4");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_keyframe_aligned");
+
+        fn mp4_box(
+            kind: &[u8],
+            size: usize,
+        ) -> Vec<u8> {
+            let mut data: Vec<u8> = ((size + 8) as u32).to_be_bytes().to_vec();
+            data.extend_from_slice(kind);
+            data.extend(vec![0; size]);
+            data
+        }
+
+        let mut video: Vec<u8> = mp4_box(b"ftyp", 16);
+        video.extend(mp4_box(b"moov", 200));
+
+        for _ in 0..10 {
+            video.extend(mp4_box(b"moof", 40));
+            video.extend(mp4_box(b"mdat", 300));
+        }
+
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::write(&input_path, &video).unwrap();
+
+        let policy: KeyframeAligned =
+            KeyframeAligned::read(&input_path, MediaContainer::Mp4)
+                .await
+                .unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&input_path)
+            .out_dir(&cache_dir)
+            .chunk_size(1024)
+            .mode(SplitMode::Custom(Arc::new(policy)))
+            .run()
+            .await
+            .unwrap();
+
+        let mut merged: Vec<u8> = Vec::new();
+
+        for i in 0..split_result.total_chunks {
+            let chunk: Vec<u8> =
+                fs::read(cache_dir.join(i.to_string())).unwrap();
+
+            if i > 0 {
+                assert_eq!(
+                    &chunk[4..8],
+                    b"moof",
+                    "Chunk should start at moof."
+                );
+            }
+
+            merged.extend(chunk);
+        }
+
+        assert_eq!(merged, video);
+    }
+
+    #[tokio::test]
+    async fn test_split_keyframe_aligned_ignores_markers_in_samples() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf = root
+            .join(".media")
+            .join("input")
+            .join("split_keyframe_aligned_markers");
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("split_keyframe_aligned_markers");
+
+        fs::create_dir_all(&input_dir).unwrap();
+
+        // mp4 with `moof` inside the samples of `mdat`
+        let mut samples: Vec<u8> = vec![0; 300];
+        samples[100..104].copy_from_slice(b"moof");
+
+        let mut mp4: Vec<u8> = Vec::new();
+
+        for (kind, data) in [
+            (b"ftyp", vec![0; 16]),
+            (b"moof", vec![0; 40]),
+            (b"mdat", samples.clone()),
+            (b"moof", vec![0; 40]),
+            (b"mdat", samples.clone()),
+        ] {
+            mp4.extend(((data.len() + 8) as u32).to_be_bytes());
+            mp4.extend_from_slice(kind);
+            mp4.extend(data);
+        }
+
+        let mp4_path: PathBuf = input_dir.join("video.mp4");
+        fs::write(&mp4_path, &mp4).unwrap();
+
+        let policy: KeyframeAligned =
+            KeyframeAligned::read(&mp4_path, MediaContainer::Mp4)
+                .await
+                .unwrap();
+
+        assert_eq!(policy.safe_points(), &[24, 380]);
+
+        // mp4 with a largesize shorter than its own header
+        let mut short: Vec<u8> = mp4[..24].to_vec();
+        short.extend(1u32.to_be_bytes());
+        short.extend_from_slice(b"moof");
+        short.extend(8u64.to_be_bytes());
+        short.extend(vec![0; 16]);
+
+        let short_path: PathBuf = input_dir.join("short.mp4");
+        fs::write(&short_path, &short).unwrap();
+
+        let error: io::Error =
+            KeyframeAligned::read(&short_path, MediaContainer::Mp4)
+                .await
+                .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // matroska with the `Cluster` id inside a `SimpleBlock`
+        let mut block: Vec<u8> = vec![0xA3, 0x80 | 20];
+        block.extend(vec![0; 8]);
+        block.extend([0x1F, 0x43, 0xB6, 0x75]);
+        block.extend(vec![0; 8]);
+
+        let mut mkv: Vec<u8> = vec![0x1A, 0x45, 0xDF, 0xA3, 0x84, 0, 0, 0, 0];
+        // segment of unknown size
+        mkv.extend([0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF]);
+        mkv.extend([0xFF, 0xFF, 0xFF, 0xFF]);
+
+        for _ in 0..3 {
+            // cluster of unknown size
+            mkv.extend([0x1F, 0x43, 0xB6, 0x75, 0xFF]);
+            mkv.extend(&block);
+            mkv.extend(&block);
+        }
+
+        let mkv_path: PathBuf = input_dir.join("video.mkv");
+        fs::write(&mkv_path, &mkv).unwrap();
+
+        let policy: KeyframeAligned =
+            KeyframeAligned::read(&mkv_path, MediaContainer::Matroska)
+                .await
+                .unwrap();
+
+        assert_eq!(policy.safe_points(), &[21, 70, 119]);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&mkv_path)
+            .out_dir(&cache_dir)
+            .chunk_size(64)
+            .mode(SplitMode::Custom(Arc::new(policy)))
+            .run()
+            .await
+            .unwrap();
+
+        let sizes: Vec<u64> = (0..split_result.total_chunks)
+            .map(|i| fs::metadata(cache_dir.join(i.to_string())).unwrap().len())
+            .collect();
+
+        assert_eq!(sizes, vec![21, 49, 49, 49]);
+    }
+
+    #[tokio::test]
+    async fn test_split_row_group_aligned_starts_at_row_groups() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf =
+            root.join(".media").join("input").join("split_row_group_aligned");
+        let input_path: PathBuf = input_dir.join("data.parquet");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_row_group_aligned");
+
+        // FileMetaData with 2 row groups at offsets 4 and 1004 (compact)
+        let meta: Vec<u8> = vec![
+            0x15, 0x02, // version: 1
+            0x39, 0x2C, // row_groups: list of 2 structs
+            0x19, 0x1C, 0x3C, 0x96, 0x08, 0x00, 0x00, // columns: [4]
+            0x16, 0xC8, 0x01, 0x00, // total_byte_size: 100
+            0x19, 0x1C, 0x3C, 0x96, 0xD8, 0x0F, 0x00,
+            0x00, // columns: [1004]
+            0x00, // end of row group
+            0x00, // end of file metadata
+        ];
+
+        let mut data: Vec<u8> = b"PAR1".to_vec();
+        data.extend(vec![1; 1000]);
+        data.extend(vec![2; 500]);
+        data.extend(&meta);
+        data.extend((meta.len() as u32).to_le_bytes());
+        data.extend(b"PAR1");
+
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::write(&input_path, &data).unwrap();
+
+        let policy: RowGroupAligned =
+            RowGroupAligned::read(&input_path).await.unwrap();
+
+        assert_eq!(policy.row_groups(), &[4..1004, 1004..1504]);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&input_path)
+            .out_dir(&cache_dir)
+            .chunk_size(1100)
+            .mode(SplitMode::Custom(Arc::new(policy)))
+            .run()
+            .await
+            .unwrap();
+
+        let sizes: Vec<u64> = (0..split_result.total_chunks)
+            .map(|i| fs::metadata(cache_dir.join(i.to_string())).unwrap().len())
+            .collect();
+
+        assert_eq!(sizes, vec![1004, 500, meta.len() as u64 + 8]);
+
+        let row_groups: Vec<Option<&str>> = split_result
+            .manifest
+            .chunks
+            .iter()
+            .map(|c| c.get_metadata("row_groups"))
+            .collect();
+
+        assert_eq!(row_groups, vec![Some("0"), Some("1"), None]);
+    }
+
+    #[tokio::test]
+    async fn test_row_group_aligned_rejects_hostile_metadata() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf =
+            root.join(".media").join("input").join("row_group_hostile");
+
+        fs::create_dir_all(&input_dir).unwrap();
+
+        fn parquet(meta: &[u8]) -> Vec<u8> {
+            let mut data: Vec<u8> = b"PAR1".to_vec();
+            data.extend(meta);
+            data.extend((meta.len() as u32).to_le_bytes());
+            data.extend(b"PAR1");
+            data
+        }
+
+        // field 1 of nested structs, deeper than any real metadata
+        let mut nested: Vec<u8> = vec![0x1C; 100_000];
+        nested.extend(vec![0x00; 100_001]);
+
+        // row group with file_offset: -2
+        let negative: Vec<u8> = vec![0x49, 0x1C, 0x56, 0x03, 0x00, 0x00];
+
+        // field 32767 followed by the next one, beyond the range of ids
+        let overflow: Vec<u8> =
+            vec![0x05, 0xFE, 0xFF, 0x03, 0x00, 0x15, 0x00, 0x00];
+
+        // field 40000, beyond the range of ids
+        let out_of_range: Vec<u8> = vec![0x05, 0x80, 0xF1, 0x04, 0x00, 0x00];
+
+        for (name, meta) in [
+            ("nested", nested),
+            ("negative", negative),
+            ("overflow", overflow),
+            ("out_of_range", out_of_range),
+        ] {
+            let path: PathBuf = input_dir.join(format!("{}.parquet", name));
+            fs::write(&path, parquet(&meta)).unwrap();
+
+            let error: io::Error =
+                RowGroupAligned::read(&path).await.unwrap_err();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_transform() {
+        let (root, cache_dir, output_path, split_result) =
+            setup("merge_with_transform").await;
+
+        // encode the chunks in storage
+        for i in 0..split_result.total_chunks {
+            let path: PathBuf = cache_dir.join(i.to_string());
+            let chunk: Vec<u8> =
+                Xor(0x5A).encode(i, fs::read(&path).unwrap()).unwrap();
+
+            fs::write(&path, chunk).unwrap();
+        }
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .transform(Xor(0x5A))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_with_transform_records_manifest() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let asset_path: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_transform");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_with_transform")
+            .join("test.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&asset_path)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .transform(Xor(0x5A))
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join("manifest.json")).await.unwrap();
+
+        assert_eq!(manifest, split_result.manifest);
+        assert_eq!(manifest.transform.as_deref(), Some("xor"));
+        assert_eq!(manifest.total_chunks, split_result.total_chunks);
+
+        assert!(
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .run()
+                .await
+                .is_err(),
+            "Merge should fail without the recorded transform."
+        );
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .transform(Xor(0x5A))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&asset_path).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manifest_chunk_metadata() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("manifest_chunk_metadata");
+        let manifest_path: PathBuf = cache_dir.join("manifest.json");
+
+        let mut manifest: Manifest = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap()
+            .manifest;
+
+        manifest.chunk_mut(0).unwrap().set_metadata("etag", "abc");
+        manifest.write(&manifest_path).await.unwrap();
+
+        let manifest: Manifest = Manifest::read(&manifest_path).await.unwrap();
+
+        assert_eq!(
+            manifest.chunk(0).unwrap().get_metadata("etag"),
+            Some("abc")
+        );
+        assert_eq!(manifest.chunk(1).unwrap().get_metadata("etag"), None);
+    }
+
+    #[tokio::test]
+    async fn test_etag_of_chunks_matches_file() {
+        let (root, cache_dir, _, split_result) =
+            setup("etag_of_chunks_matches_file").await;
+
+        let file_etag: String =
+            file_etag(root.join("assets").join("test.png"), 1024 * 1024)
+                .await
+                .unwrap();
+
+        let chunks_etag: String =
+            chunks_etag(&cache_dir, split_result.total_chunks).await.unwrap();
+
+        assert_eq!(file_etag, chunks_etag);
+        assert_eq!(file_etag, "e5afba945129a0c92d018119c2e56f51-5");
+        assert!(file_etag.ends_with(&format!("-{}", split_result.total_chunks)));
+    }
+
+    #[tokio::test]
+    async fn test_check_remote_without_fetching_chunks() {
+        let (_, cache_dir, _, split_result) =
+            setup("check_remote_without_fetching_chunks").await;
+
+        let store: Arc<MemoryStore> =
+            memory_store(&cache_dir, split_result.total_chunks).await;
+
+        let check_result: CheckResult = Check::new()
+            .remote(store.clone())
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        let check_result: CheckResult = Check::new()
+            .remote(store.clone())
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks + 1)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            check_result.error.unwrap().missing,
+            Some(vec![split_result.total_chunks])
+        );
+        assert_eq!(store.gets.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_remote_compares_etags() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let asset_path: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("check_remote_etags");
+        let manifest_path: PathBuf = cache_dir.join("manifest.json");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&asset_path)
+            .out_dir(&cache_dir)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        let mut manifest: Manifest = split_result.manifest;
+        manifest.chunk_mut(0).unwrap().set_metadata("etag", "\"ABC\"");
+        manifest.write(&manifest_path).await.unwrap();
+
+        let store: Arc<MemoryStore> =
+            memory_store(&cache_dir, split_result.total_chunks).await;
+
+        store.etags.lock().unwrap().insert(0, "\"abc\"".to_string());
+
+        let check_result: CheckResult = Check::new()
+            .from_manifest(&manifest_path)
+            .remote(store.clone())
+            .run()
+            .await
+            .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        store.etags.lock().unwrap().insert(0, "\"def\"".to_string());
+
+        let check_result: CheckResult = Check::new()
+            .from_manifest(&manifest_path)
+            .remote(store.clone())
+            .run()
+            .await
+            .unwrap();
+
+        let error: CheckResultError = check_result.error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Corrupted);
+        assert_eq!(error.corrupted, Some(vec![0]));
+        assert_eq!(store.gets.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_merge_from_store_with_cache() {
+        let (root, cache_dir, output_path, split_result) =
+            setup("merge_from_store_with_cache").await;
+
+        let store: Arc<MemoryStore> =
+            memory_store(&cache_dir, split_result.total_chunks).await;
+
+        let local_cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("merge_from_store_with_cache_local");
+
+        if local_cache_dir.exists() {
+            fs::remove_dir_all(&local_cache_dir).unwrap();
+        }
+
+        for _ in 0..2 {
+            Merge::from_store(store.clone())
+                .total_chunks(split_result.total_chunks)
+                .prefetch(2)
+                .cache_dir(&local_cache_dir)
+                .out_file(&output_path)
+                .run()
+                .await
+                .unwrap();
+
+            assert_eq!(
+                fs::read(&output_path).unwrap(),
+                fs::read(root.join("assets").join("test.png")).unwrap()
+            );
+        }
+
+        assert_eq!(
+            store.gets.load(Ordering::SeqCst),
+            split_result.total_chunks,
+            "Cached chunks should not be fetched again."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_from_store_with_adaptive_prefetch() {
+        let (root, cache_dir, output_path, split_result) =
+            setup("merge_from_store_with_adaptive_prefetch").await;
+
+        let store: Arc<MemoryStore> =
+            memory_store(&cache_dir, split_result.total_chunks).await;
+
+        Merge::from_store(store)
+            .total_chunks(split_result.total_chunks)
+            .prefetch(8)
+            .adaptive_prefetch(true)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_from_store_on_given_runtime() {
+        let (root, cache_dir, output_path, split_result) =
+            setup("merge_from_store_on_given_runtime").await;
+
+        let store: Arc<MemoryStore> =
+            memory_store(&cache_dir, split_result.total_chunks).await;
+
+        let runtime: Runtime = runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .build()
+            .unwrap();
+
+        Merge::from_store(store)
+            .total_chunks(split_result.total_chunks)
+            .runtime(runtime.handle().clone())
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        runtime.shutdown_background();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_blocking_split_check_merge() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let asset_path: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("blocking_split_check_merge");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("blocking_split_check_merge")
+            .join("test.png");
+
+        let split_result: SplitResult = blocking::split(
+            Split::new().in_file(&asset_path).out_dir(&cache_dir),
+        )
+        .unwrap();
+
+        let check_result: CheckResult = blocking::check(
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.file_size)
+                .total_chunks(split_result.total_chunks),
+        )
+        .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        blocking::merge(Merge::new().in_dir(&cache_dir).out_file(&output_path))
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&asset_path).unwrap()
+        );
+
+        // the timer drives the stall timeout
+        blocking::merge(
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .stall_monitor(Arc::new(
+                    StallMonitor::new(std::time::Duration::from_secs(1))
+                        .timeout(std::time::Duration::from_secs(10)),
+                )),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&asset_path).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_many_chunks() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf =
+            root.join(".media").join("input").join("merge_with_many_chunks");
+        let input_path: PathBuf = input_dir.join("data.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_with_many_chunks");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("merge_with_many_chunks")
+            .join("data.bin");
+
+        let data: Vec<u8> = (0..40_000).map(|i| (i % 251) as u8).collect();
+
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::write(&input_path, &data).unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&input_path)
+            .out_dir(&cache_dir)
+            .chunk_size(2)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.total_chunks, 20_000);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&output_path).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_many_chunks_in_manifest_order() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf = root
+            .join(".media")
+            .join("input")
+            .join("merge_with_many_chunks_in_manifest_order");
+        let input_path: PathBuf = input_dir.join("data.bin");
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("merge_with_many_chunks_in_manifest_order");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("merge_with_many_chunks_in_manifest_order")
+            .join("data.bin");
+
+        let data: Vec<u8> = (0..40_000).map(|i| (i % 241) as u8).collect();
+
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::write(&input_path, &data).unwrap();
+
+        Split::new()
+            .in_file(&input_path)
+            .out_dir(&cache_dir)
+            .chunk_size(2)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&output_path).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_split_exceeding_max_chunks() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("split_exceeding_max_chunks");
+
+        if cache_dir.exists() {
+            fs::remove_dir_all(&cache_dir).unwrap();
+        }
+
+        assert!(
+            Split::new()
+                .in_file(root.join("assets").join("test.png"))
+                .out_dir(&cache_dir)
+                .chunk_size(1024)
+                .max_chunks(100)
+                .run()
+                .await
+                .is_err(),
+            "Split should fail when exceeding max_chunks."
+        );
+
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_split_with_zero_padding() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_zero_padding");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_with_zero_padding")
+            .join("test.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(256 * 1024)
+            .zero_padding(true)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(split_result.total_chunks >= 10);
+        assert!(cache_dir.join("00").is_file());
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_into_volumes() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_into_volumes");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_into_volumes")
+            .join("test.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .volume_size(2 * 1024 * 1024 + 512 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.manifest.total_volumes(), 3);
+
+        for v in 0..3 {
+            let manifest: Manifest = Manifest::read(
+                cache_dir.join(format!("vol{}", v)).join("manifest.json"),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(manifest.volume, Some(v));
+        }
+
+        assert!(cache_dir.join("vol1").join("2").is_file());
+        assert!(cache_dir.join("vol2").join("4").is_file());
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+
+        // merge across volumes without the manifests
+        for v in 0..3 {
+            fs::remove_file(
+                cache_dir.join(format!("vol{}", v)).join("manifest.json"),
+            )
+            .unwrap();
+        }
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_par2_recovery_file() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("par2_recovery_file");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        let par2_result: Par2Result = Par2::new()
+            .in_dir(&cache_dir)
+            .total_chunks(split_result.total_chunks)
+            .slice_size(64 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(par2_result.input_slices, 76);
+        assert_eq!(par2_result.recovery_slices, 4);
+
+        let data: Vec<u8> = fs::read(cache_dir.join("recovery.par2")).unwrap();
+
+        let mut packets: Vec<(&[u8], &[u8])> = Vec::new();
+        let mut pos: usize = 0;
+
+        while pos < data.len() {
+            assert_eq!(&data[pos..pos + 8], b"PAR2\0PKT");
+
+            let len: usize =
+                u64::from_le_bytes(data[pos + 8..pos + 16].try_into().unwrap())
+                    as usize;
+
+            packets
+                .push((&data[pos + 48..pos + 64], &data[pos + 64..pos + len]));
+
+            pos += len;
+        }
+
+        assert_eq!(pos, data.len());
+        assert_eq!(packets.len(), 1 + 5 * 2 + 1 + 4);
+
+        // the recovery slice with exponent 0 is the xor of all slices
+        let mut parity: Vec<u8> = vec![0; 64 * 1024];
+
+        for i in 0..split_result.total_chunks {
+            let chunk: Vec<u8> =
+                fs::read(cache_dir.join(i.to_string())).unwrap();
+
+            for slice in chunk.chunks(64 * 1024) {
+                for (p, b) in parity.iter_mut().zip(slice) {
+                    *p ^= b;
+                }
+            }
+        }
+
+        let recovery: &[u8] = packets
+            .iter()
+            .find(|(kind, body)| {
+                *kind == b"PAR 2.0\0RecvSlic" && body[..4] == [0, 0, 0, 0]
+            })
+            .map(|(_, body)| &body[4..])
+            .unwrap();
+
+        assert_eq!(recovery, &parity[..]);
+    }
+
+    #[tokio::test]
+    async fn test_split_with_coreutils_naming() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("split_with_coreutils_naming");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_with_coreutils_naming")
+            .join("test.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .naming(ChunkNaming::coreutils())
+            .run()
+            .await
+            .unwrap();
+
+        assert!(cache_dir.join("xaa").is_file());
+        assert!(cache_dir.join("xae").is_file());
+
+        // same as `cat x* > file`
+        let mut names: Vec<PathBuf> = fs::read_dir(&cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+
+        names.sort();
+
+        let concatenated: Vec<u8> =
+            names.iter().flat_map(|name| fs::read(name).unwrap()).collect();
+
+        assert_eq!(
+            concatenated,
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .naming(ChunkNaming::coreutils())
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .naming(ChunkNaming::coreutils())
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_with_numeric_naming() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_numeric_naming");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_with_numeric_naming")
+            .join("test.png");
+
+        let naming: ChunkNaming = ChunkNaming::Numeric {
+            prefix: "test.png.part".to_string(),
+            width: 3,
+            start: 1,
+        };
+
+        assert_eq!(naming.index("test.png.part012"), Some(11));
+        assert_eq!(naming.index("test.png.part000"), None);
+
+        Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .naming(naming)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(cache_dir.join("test.png.part001").is_file());
+        assert!(cache_dir.join("test.png.part005").is_file());
+
+        // naming is read from the manifest
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+
+        let result: io::Result<SplitResult> = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024)
+            .naming(ChunkNaming::coreutils())
+            .run()
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_hostile_naming_in_manifest_is_rejected() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("hostile_naming");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("hostile_naming")
+            .join("test.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        let namings: [serde_json::Value; 4] = [
+            serde_json::json!({
+                "scheme": "alphabetic",
+                "prefix": "/etc/shadow",
+                "suffix_length": 0,
+            }),
+            serde_json::json!({
+                "scheme": "alphabetic",
+                "prefix": "../../x",
+                "suffix_length": 2,
+            }),
+            serde_json::json!({
+                "scheme": "numeric",
+                "prefix": "..",
+                "width": 1,
+                "start": 0,
+            }),
+            serde_json::json!({
+                "scheme": "numeric",
+                "prefix": "part",
+                "width": 0,
+                "start": 0,
+            }),
+        ];
+
+        for naming in namings {
+            let mut manifest: serde_json::Value =
+                serde_json::to_value(&split_result.manifest).unwrap();
+            manifest["naming"] = naming;
+
+            fs::write(
+                cache_dir.join("manifest.json"),
+                serde_json::to_vec(&manifest).unwrap(),
+            )
+            .unwrap();
+
+            let result: io::Result<Manifest> =
+                Manifest::read(cache_dir.join("manifest.json")).await;
+
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+            let result: io::Result<MergeResult> = Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .run()
+                .await;
+
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+
+        let result: io::Result<SplitResult> = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .naming(ChunkNaming::Alphabetic {
+                prefix: "/tmp/x".to_string(),
+                suffix_length: 2,
+            })
+            .run()
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_merge_multi_part_archive() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_multi_part_archive");
+
+        Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .naming(ChunkNaming::archive("test.png"))
+            .run()
+            .await
+            .unwrap();
+
+        assert!(cache_dir.join("test.png.001").is_file());
+        assert!(cache_dir.join("test.png.005").is_file());
+
+        let naming: ChunkNaming =
+            ChunkNaming::from_first_part(cache_dir.join("test.png.001"))
+                .unwrap();
+
+        assert_eq!(naming, ChunkNaming::archive("test.png"));
+
+        // output file is named after the parts
+        Merge::new().in_dir(&cache_dir).naming(naming).run().await.unwrap();
+
+        assert_eq!(
+            fs::read(cache_dir.join("test.png")).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_hjsplit_parts() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_hjsplit_parts");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        let data: Vec<u8> =
+            fs::read(root.join("assets").join("test.png")).unwrap();
+
+        // parts as written by HJSplit
+        for (i, part) in data.chunks(1024 * 1024).enumerate() {
+            fs::write(cache_dir.join(format!("test.png.{:03}", i + 1)), part)
+                .unwrap();
+        }
+
+        Merge::new().in_dir(&cache_dir).run().await.unwrap();
+
+        assert_eq!(fs::read(cache_dir.join("test.png")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_custom_layout() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_with_custom_layout");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("merge_with_custom_layout")
+            .join("test.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        let data: Vec<u8> =
+            fs::read(root.join("assets").join("test.png")).unwrap();
+
+        let parts: Vec<&[u8]> = data.chunks(1024 * 1024).collect();
+
+        for (i, part) in parts.iter().enumerate() {
+            fs::write(cache_dir.join(format!("chunk-{}.bin", i)), part)
+                .unwrap();
+        }
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .layouts(LayoutRegistry::new().register(Bin))
+            .file_size(data.len())
+            .total_chunks(parts.len())
+            .run()
+            .await
+            .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        // not detected without the layout
+        let result: io::Result<MergeResult> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .layouts(LayoutRegistry::empty())
+            .run()
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .layouts(LayoutRegistry::new().register(Bin))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&output_path).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_split_with_headers() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_headers");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_with_headers")
+            .join("test.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        // the total is only known after the split with content defined chunks
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(256 * 1024)
+            .mode(SplitMode::Custom(Arc::new(ContentDefined::new(
+                16 * 1024,
+                64 * 1024,
+            ))))
+            .headers(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.manifest.id, Some(split_result.id));
+
+        for i in 0..split_result.total_chunks {
+            let header: ChunkHeader =
+                ChunkHeader::read(cache_dir.join(i.to_string()))
+                    .await
+                    .unwrap()
+                    .unwrap();
+
+            let chunk: &ChunkInfo = split_result.manifest.chunk(i).unwrap();
+
+            assert_eq!(header.index, i);
+            assert_eq!(header.total_chunks, split_result.total_chunks);
+            assert_eq!(header.split_id, split_result.id);
+            assert_eq!(header.payload_size, chunk.size);
+            assert_eq!(chunk.stored_size, chunk.size + CHUNK_HEADER_SIZE);
+        }
+
+        // headers are detected without manifest
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_runs_jobs_by_priority() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("scheduler");
+
+        let split = |name: &str| {
+            Split::new()
+                .in_file(root.join("assets").join("test.png"))
+                .out_dir(cache_dir.join(name))
+                .chunk_size(1024 * 1024)
+        };
+
+        let scheduler: Scheduler = Scheduler::new().concurrency(1);
+
+        let a: JobHandle = scheduler.submit(split("a"));
+        let b: JobHandle = scheduler.submit(Job::split(split("b")));
+        let c: JobHandle = scheduler.submit(Job::split(split("c")).priority(1));
+
+        assert_eq!(scheduler.running(), 1);
+        assert_eq!(scheduler.queued(), 2);
+        assert_eq!(a.status(), JobStatus::Running);
+        assert_eq!(b.status(), JobStatus::Queued);
+
+        b.cancel();
+
+        // the cancelled job no longer waits
+        assert_eq!(scheduler.queued(), 1);
+        assert_eq!(b.status(), JobStatus::Cancelled);
+
+        assert_eq!(
+            b.wait().await.unwrap_err().kind(),
+            io::ErrorKind::Interrupted
+        );
+
+        assert!(matches!(c.wait().await.unwrap(), JobOutput::Split(_)));
+
+        // the first job runs before the others
+        assert_eq!(a.status(), JobStatus::Finished);
+        assert_eq!(a.progress(), 4965800);
+
+        // a finished job stays finished
+        a.cancel();
+
+        assert_eq!(a.status(), JobStatus::Finished);
+        assert!(matches!(a.wait().await.unwrap(), JobOutput::Split(_)));
+        assert!(!cache_dir.join("b").exists());
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_with_bandwidth() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("scheduler_with_bandwidth");
+
+        let scheduler: Scheduler = Scheduler::new().bandwidth(20 * 1024 * 1024);
+
+        let start: std::time::Instant = std::time::Instant::now();
+
+        scheduler
+            .submit(
+                Split::new()
+                    .in_file(root.join("assets").join("test.png"))
+                    .out_dir(&cache_dir)
+                    .chunk_size(1024 * 1024),
+            )
+            .wait()
+            .await
+            .unwrap();
+
+        // 4 chunks wait for the bandwidth after the first one
+        assert!(start.elapsed() >= std::time::Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_with_mock_clock() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("scheduler_with_mock_clock");
+
+        let clock: Arc<MockClock> = Arc::new(MockClock::new());
+
+        let start: std::time::Instant = clock.now();
+
+        let scheduler: Scheduler =
+            Scheduler::new().bandwidth(1024 * 1024).clock(clock.clone());
+
+        scheduler
+            .submit(
+                Split::new()
+                    .in_file(root.join("assets").join("test.png"))
+                    .out_dir(&cache_dir)
+                    .chunk_size(1024 * 1024),
+            )
+            .wait()
+            .await
+            .unwrap();
+
+        // 4 chunks wait for a second each after the first one
+        assert_eq!(clock.now() - start, std::time::Duration::from_secs(4));
+        assert_eq!(clock.elapsed(), std::time::Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_split_pause_resume_cancel() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_pause_resume_cancel");
+
+        let split = |handle: &OperationHandle, dir: &str| {
+            Split::new()
+                .in_file(root.join("assets").join("test.png"))
+                .out_dir(cache_dir.join(dir))
+                .chunk_size(1024 * 1024)
+                .handle(handle.clone())
+                .run()
+        };
+
+        // paused after the first chunk until resumed
+        let handle: OperationHandle = OperationHandle::new();
+
+        handle.pause();
+
+        let task = tokio::spawn(split(&handle, "resumed"));
+
+        while handle.progress() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        assert!(handle.is_paused());
+        assert!(!task.is_finished());
+        assert_eq!(handle.progress(), 1024 * 1024);
+
+        handle.resume();
+
+        let result: SplitResult = task.await.unwrap().unwrap();
+
+        assert_eq!(result.total_chunks, 5);
+        assert_eq!(handle.progress(), result.file_size);
+
+        // cancelled while paused
+        let handle: OperationHandle = OperationHandle::new();
+
+        handle.pause();
+
+        let task = tokio::spawn(split(&handle, "cancelled"));
+
+        while handle.progress() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        handle.cancel();
+
+        let err: io::Error = task.await.unwrap().unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(handle.is_cancelled());
+        assert_eq!(handle.progress(), 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("events");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("events.png");
+
+        let events: EventBus = EventBus::new();
+
+        let mut receiver = events.subscribe();
+
+        let result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .events(events.clone())
+            .run()
+            .await
+            .unwrap();
+
+        Check::new()
+            .in_dir(&cache_dir)
+            .file_size(result.file_size)
+            .total_chunks(result.total_chunks + 1)
+            .events(events.clone())
+            .run()
+            .await
+            .unwrap();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .events(events.clone())
+            .run()
+            .await
+            .unwrap();
+
+        let mut received: Vec<Event> = Vec::new();
+
+        while let Ok(event) = receiver.try_recv() {
+            received.push(event);
+        }
+
+        assert_eq!(received.len(), 2 + 5 + 1 + 2 + 5);
+
+        assert_eq!(
+            received[0],
+            Event::SplitStarted { id: result.id, file_size: result.file_size }
+        );
+        assert_eq!(
+            received[1],
+            Event::ChunkWritten { index: 0, size: 1024 * 1024 }
+        );
+        assert_eq!(
+            received[6],
+            Event::SplitFinished { id: result.id, total_chunks: 5 }
+        );
+        assert_eq!(
+            received[7],
+            Event::CheckFailed { error_type: CheckResultErrorType::Missing }
+        );
+        assert_eq!(received[8], Event::MergeStarted { total_chunks: 5 });
+        assert_eq!(
+            received[13],
+            Event::ChunkMerged {
+                index: 4,
+                size: result.file_size - 4 * 1024 * 1024,
+            }
+        );
+        assert_eq!(
+            received[14],
+            Event::MergeFinished { out_file: output_file.clone() }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_sinks() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("progress");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("progress.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let counter: Arc<ProgressCounter> = Arc::new(ProgressCounter::new());
+
+        let (sender, receiver) = std::sync::mpsc::channel::<Event>();
+
+        let sizes: Arc<Mutex<Vec<usize>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let sink: Arc<dyn ProgressSink> = Arc::new(FnSink::new({
+            let sizes: Arc<Mutex<Vec<usize>>> = sizes.clone();
+
+            move |event: &Event| {
+                if let Event::ChunkWritten { size, .. } = event {
+                    sizes.lock().unwrap().push(*size);
+                }
+            }
+        }));
+
+        let result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .progress(sink)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(sizes.lock().unwrap().iter().sum::<usize>(), 4965800);
+
+        // the same sink is shared between processes
+        Check::new()
+            .in_dir(&cache_dir)
+            .file_size(result.file_size)
+            .total_chunks(result.total_chunks)
+            .progress(counter.clone())
+            .run()
+            .await
+            .unwrap();
+
+        assert!(counter.is_finished());
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .progress(counter.clone())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(counter.bytes(), 4965800);
+        assert_eq!(counter.chunks(), 5);
+        assert_eq!(counter.total_chunks(), Some(5));
+        assert!(counter.is_finished());
+
+        // every event is received in order
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .progress(Arc::new(sender))
+            .run()
+            .await
+            .unwrap();
+
+        let received: Vec<Event> = receiver.iter().collect();
+
+        assert_eq!(received.len(), 1 + 5 + 1);
+        assert_eq!(received[0], Event::MergeStarted { total_chunks: 5 });
+        assert_eq!(
+            received[6],
+            Event::MergeFinished { out_file: output_file.clone() }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_operation_log() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("operation_log");
+        let chunks_dir: PathBuf = cache_dir.join("chunks");
+        let output_file: PathBuf = cache_dir.join("test.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let log: OperationLog =
+            OperationLog::new(cache_dir.join("operations.jsonl"));
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&chunks_dir)
+            .chunk_size(1024 * 1024)
+            .checksum(Algorithm::Sha256)
+            .merkle(true)
+            .manifest(true)
+            .log(log.clone())
+            .run()
+            .await
+            .unwrap();
+
+        Check::new()
+            .in_dir(&chunks_dir)
+            .file_size(result.file_size)
+            .total_chunks(result.total_chunks)
+            .log(log.clone())
+            .run()
+            .await
+            .unwrap();
+
+        Merge::new()
+            .in_dir(&chunks_dir)
+            .out_file(&output_file)
+            .expect_hash(
+                Algorithm::Sha256,
+                Algorithm::Sha256.digest(&fs::read(&in_file).unwrap()),
+            )
+            .log(log.clone())
+            .run()
+            .await
+            .unwrap();
+
+        let records: Vec<OperationRecord> = log.records().await.unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].operation,
+            Operation::Split {
+                in_file: in_file.clone(),
+                out_dir: chunks_dir.clone(),
+                id: result.id,
+                file_size: 4965800,
+                stored_size: 4965800,
+                total_chunks: 5,
+                file_hashes: Default::default(),
+                merkle_root: result.manifest.merkle_root.clone(),
+            }
+        );
+        assert!(matches!(
+            records[1].operation,
+            Operation::Check { success: true, error_type: None, .. }
+        ));
+        assert!(matches!(
+            &records[2].operation,
+            Operation::Merge { file_size: 4965800, file_hashes, .. }
+                if file_hashes.contains_key("sha256")
+        ));
+
+        let outcomes = |results: Vec<ReplayResult>| -> Vec<ReplayOutcome> {
+            results.into_iter().map(|r| r.outcome).collect()
+        };
+
+        assert_eq!(
+            outcomes(log.replay().await.unwrap()),
+            vec![ReplayOutcome::Verified; 3]
+        );
+
+        // the chunk sets and files are verified against the records
+        let mut chunk: Vec<u8> = fs::read(chunks_dir.join("2")).unwrap();
+        chunk[0] ^= 0xff;
+        fs::write(chunks_dir.join("2"), chunk).unwrap();
+
+        let mut output: Vec<u8> = fs::read(&output_file).unwrap();
+        output[0] ^= 0xff;
+        fs::write(&output_file, output).unwrap();
+
+        let replayed: Vec<ReplayOutcome> =
+            outcomes(log.replay().await.unwrap());
+
+        assert!(matches!(replayed[0], ReplayOutcome::Mismatch(_)));
+        // the size of the chunks is unchanged
+        assert_eq!(replayed[1], ReplayOutcome::Verified);
+        assert!(matches!(replayed[2], ReplayOutcome::Mismatch(_)));
+
+        fs::remove_file(chunks_dir.join("4")).unwrap();
+
+        assert!(matches!(
+            &outcomes(log.replay().await.unwrap())[1],
+            ReplayOutcome::Mismatch(message) if message.contains("missing")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_inspect() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("inspect");
+
+        if cache_dir.exists() {
+            fs::remove_dir_all(&cache_dir).unwrap();
+        }
+
+        let result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .headers(true)
+            .run()
+            .await
+            .unwrap();
+
+        let report: InspectReport = inspect(&cache_dir).await.unwrap();
+
+        assert!(report.is_healthy());
+        assert!(report.manifest);
+        assert_eq!(report.id, Some(result.id));
+        assert_eq!(report.layout.as_deref(), Some("index"));
+        assert_eq!(report.expected_chunks, Some(5));
+        assert_eq!(report.chunks.len(), 5);
+        assert_eq!(report.total_size, result.manifest.stored_size());
+
+        // without the manifest, a missing chunk is found from the gap
+        fs::remove_file(cache_dir.join("manifest.json")).unwrap();
+        fs::remove_file(cache_dir.join("2")).unwrap();
+
+        let report: InspectReport = inspect(&cache_dir).await.unwrap();
+
+        assert!(!report.manifest);
+        assert_eq!(report.id, Some(result.id));
+        assert_eq!(report.chunks.len(), 4);
+        assert_eq!(report.anomalies, vec![Anomaly::MissingChunk { index: 2 }]);
+    }
+
+    #[tokio::test]
+    async fn test_peek() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf = root.join(".media").join("cache").join("peek");
+
+        if cache_dir.exists() {
+            fs::remove_dir_all(&cache_dir).unwrap();
+        }
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(cache_dir.join("headers"))
+            .chunk_size(1024 * 1024)
+            .headers(true)
+            .run()
+            .await
+            .unwrap();
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(cache_dir.join("manifest"))
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        let original: Vec<u8> = fs::read(&in_file).unwrap();
+
+        // across the boundary of the first two chunks
+        let range = 1024 * 1024 - 32..1024 * 1024 + 32;
+
+        for dir in ["headers", "manifest"] {
+            let bytes: Vec<u8> = Peek::new()
+                .in_dir(cache_dir.join(dir))
+                .range(range.clone())
+                .run()
+                .await
+                .unwrap();
+
+            assert_eq!(bytes, original[range.clone()]);
+        }
+
+        // clamped to the end of the file
+        let bytes: Vec<u8> = Peek::new()
+            .in_dir(cache_dir.join("headers"))
+            .range(original.len() - 16..usize::MAX)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(bytes, original[original.len() - 16..]);
+
+        // the whole file is streamed into the writer
+        let mut bytes: Vec<u8> = Vec::new();
+
+        Peek::new()
+            .in_dir(cache_dir.join("headers"))
+            .range(0..usize::MAX)
+            .run_to(&mut bytes)
+            .await
+            .unwrap();
+
+        assert_eq!(bytes, original);
+
+        // the offsets of the chunks after a missing one are unknown
+        fs::remove_file(cache_dir.join("headers").join("1")).unwrap();
+
+        let error: io::Error = Peek::new()
+            .in_dir(cache_dir.join("headers"))
+            .range(range)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "chunk 1 not found");
+    }
+
+    #[tokio::test]
+    async fn test_diff() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf = root.join(".media").join("cache").join("diff");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("diff.png");
+
+        Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .verify(true)
+            .run()
+            .await
+            .unwrap();
+
+        let diff = || Diff::new().in_dir(&cache_dir).file(&output_file).run();
+
+        assert_eq!(diff().await.unwrap(), None);
+
+        // corrupt a byte in the third chunk
+        let mut data: Vec<u8> = fs::read(&output_file).unwrap();
+
+        data[2 * 1024 * 1024 + 100] ^= 0xFF;
+
+        fs::write(&output_file, &data).unwrap();
+
+        assert_eq!(
+            diff().await.unwrap(),
+            Some(Difference {
+                index: Some(2),
+                offset: 2 * 1024 * 1024 + 100,
+                corrupted: false,
+            })
+        );
+
+        // extra bytes after the chunks
+        data[2 * 1024 * 1024 + 100] ^= 0xFF;
+        data.extend_from_slice(b"extra");
+
+        fs::write(&output_file, &data).unwrap();
+
+        assert_eq!(
+            diff().await.unwrap(),
+            Some(Difference {
+                index: None,
+                offset: data.len() - 5,
+                corrupted: false,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_verify_pinpoints_corrupted_chunk() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_verify_corrupted");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("merge_verify_corrupted.png");
+
+        Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .checksum(Algorithm::Sha256)
+            .file_hash(Algorithm::Sha256)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        // corrupt a byte of the third chunk in storage
+        let chunk_path: PathBuf = cache_dir.join("2");
+        let mut chunk: Vec<u8> = fs::read(&chunk_path).unwrap();
+
+        chunk[100] ^= 0xFF;
+
+        fs::write(&chunk_path, &chunk).unwrap();
+
+        // the checksum of the original file is expected from the manifest
+        let error: io::Error = Merge::new()
+            .from_manifest(cache_dir.join("manifest.json"))
+            .out_file(&output_file)
+            .verify(true)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            error.to_string().contains("chunk 2 at offset 2097152"),
+            "Error should name the corrupted chunk: {}",
+            error
+        );
+        assert!(!output_file.exists(), "Output file should be removed.");
+    }
+
+    #[tokio::test]
+    async fn test_merge_skip_if_matching() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_skip_if_matching");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("merge_skip_if_matching.png");
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        let merge = |expected: Expected| {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_file)
+                .skip_if_matching(expected)
+                .run()
+        };
+
+        // a file of another size is rewritten
+        fs::write(&output_file, b"stale").unwrap();
+
+        merge(result.file_size.into()).await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // a matching file is kept as is
+        let modified = fs::metadata(&output_file).unwrap().modified().unwrap();
+
+        let md5: String = file_md5(&in_file).await.unwrap();
+
+        merge(Expected::Md5(md5.to_uppercase())).await.unwrap();
+        merge(result.file_size.into()).await.unwrap();
+
+        assert_eq!(
+            fs::metadata(&output_file).unwrap().modified().unwrap(),
+            modified
+        );
+
+        // a file of the same size but another hash is rewritten
+        let mut data: Vec<u8> = fs::read(&in_file).unwrap();
+
+        data[0] ^= 0xFF;
+
+        fs::write(&output_file, &data).unwrap();
+
+        merge(Expected::Md5(md5)).await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_attributes() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_with_attributes");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("merge_with_attributes.png");
+
+        Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        // clearing the attributes is supported by most filesystems
+        let result: io::Result<MergeResult> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .attributes(FileAttributes::new().nocow(false))
+            .run()
+            .await;
+
+        if cfg!(target_os = "linux") {
+            assert!(!result.unwrap().skipped);
+            assert_eq!(
+                fs::read(&output_file).unwrap(),
+                fs::read(root.join("assets").join("test.png")).unwrap()
+            );
+        } else {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auto_tune() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("auto_tune");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("auto_tune.png");
+
+        if cfg!(target_os = "linux") {
+            let memory: usize = available_memory().unwrap();
+
+            assert!(memory > 0);
+            assert!(memory_budget().unwrap() <= memory);
+        }
+
+        let scheduler: Scheduler =
+            Scheduler::new().concurrency(4).auto_tune(true);
+
+        scheduler
+            .submit(
+                Split::new()
+                    .in_file(&in_file)
+                    .out_dir(&cache_dir)
+                    .chunk_size(1024 * 1024)
+                    .auto_tune(true),
+            )
+            .wait()
+            .await
+            .unwrap();
+
+        scheduler
+            .submit(
+                Merge::new()
+                    .in_dir(&cache_dir)
+                    .out_file(&output_file)
+                    .auto_tune(true),
+            )
+            .wait()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_with_memory_budget() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("scheduler_with_memory_budget");
+
+        // the buffers of each job are capped to fit the budget
+        let budget: MemoryBudget = MemoryBudget::new(2 * 1024 * 1024);
+
+        let scheduler: Scheduler =
+            Scheduler::new().concurrency(4).memory_budget(budget.clone());
+
+        let handles: Vec<JobHandle> = (0..4)
+            .map(|i| {
+                scheduler.submit(
+                    Split::new()
+                        .in_file(root.join("assets").join("test.png"))
+                        .out_dir(cache_dir.join(i.to_string()))
+                        .chunk_size(1024 * 1024),
+                )
+            })
+            .collect();
+
+        for handle in handles {
+            handle.wait().await.unwrap();
+        }
+
+        assert_eq!(budget.total(), 2 * 1024 * 1024);
+        assert_eq!(budget.available(), budget.total());
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_with_profile() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_profile");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("merge_with_profile.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .profile(true)
+            .run()
+            .await
+            .unwrap();
+
+        let profile: Profile = split_result.profile.unwrap();
+
+        assert!(profile.total > std::time::Duration::ZERO);
+        assert!(profile.read + profile.write <= profile.total);
+
+        let handle: OperationHandle = OperationHandle::new();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .handle(handle.clone())
+            .profile(true)
+            .run()
+            .await
+            .unwrap();
+
+        let profile: Profile = handle.profile().unwrap();
+
+        assert!(profile.total > std::time::Duration::ZERO);
+        assert!(profile.write > std::time::Duration::ZERO);
+
+        // not profiled by default
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(split_result.profile.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_split_with_checksum() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_checksum");
+
+        assert_eq!(
+            Algorithm::Sha256.digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .headers(true)
+            .checksum(Algorithm::Sha256)
+            .profile(true)
+            .run()
+            .await
+            .unwrap();
+
+        let file: Vec<u8> = fs::read(&in_file).unwrap();
+
+        // the checksum covers the payload without the header
+        for chunk in &split_result.manifest.chunks {
+            assert_eq!(
+                chunk.get_metadata("sha256"),
+                Some(
+                    Algorithm::Sha256
+                        .digest(&file[chunk.offset..chunk.offset + chunk.size])
+                        .as_str()
+                )
+            );
+        }
+
+        assert!(split_result.profile.unwrap().hash > std::time::Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_split_with_crc32c_checksum() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("split_with_crc32c_checksum");
+
+        assert_eq!(Algorithm::Crc32c.digest(b"123456789"), "e3069283");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .checksum(Algorithm::Crc32c)
+            .run()
+            .await
+            .unwrap();
+
+        for chunk in &split_result.manifest.chunks {
+            let data: Vec<u8> =
+                fs::read(cache_dir.join(chunk.index.to_string())).unwrap();
+
+            assert_eq!(
+                chunk.get_metadata("crc32c"),
+                Some(Algorithm::Crc32c.digest(&data).as_str())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_with_spot_check() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("check_with_spot_check");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .checksum(Algorithm::Sha256)
+            .run()
+            .await
+            .unwrap();
+
+        let check = |percentage: usize, seed: u64| {
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.file_size)
+                .total_chunks(split_result.total_chunks)
+                .spot_check(percentage)
+                .seed(seed)
+                .run()
+        };
+
+        assert!(check(20, 0).await.unwrap().success);
+
+        // checksums in uppercase hex match as well
+        let mut manifest: Manifest = split_result.manifest.clone();
+
+        for chunk in &mut manifest.chunks {
+            let digest: String = chunk.get_metadata("sha256").unwrap().into();
+            chunk.set_metadata("sha256", digest.to_uppercase());
+        }
+
+        let result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .manifest(manifest)
+            .spot_check(100)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(result.success, "Check should succeed with no errors.");
+
+        // corrupt a chunk without changing its size
+        let path: PathBuf = cache_dir.join("2");
+        let mut data: Vec<u8> = fs::read(&path).unwrap();
+        data[0] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let result: CheckResult = check(100, 0).await.unwrap();
+        let error = result.error.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(error.error_type, CheckResultErrorType::Corrupted);
+        assert_eq!(error.corrupted, Some(vec![2]));
+
+        // the same seed samples the same chunks
+        let first: bool = check(20, 42).await.unwrap().success;
+
+        for _ in 0..3 {
+            assert_eq!(check(20, 42).await.unwrap().success, first);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_with_merkle_root() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_merkle_root");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .headers(true)
+            .checksum(Algorithm::Crc32c)
+            .merkle(true)
+            .run()
+            .await
+            .unwrap();
+
+        let merkle_root: String =
+            split_result.manifest.merkle_root.clone().unwrap();
+
+        let checksums: Vec<&str> = split_result
+            .manifest
+            .chunks
+            .iter()
+            .map(|c| c.get_metadata("sha256").unwrap())
+            .collect();
+
+        let tree: MerkleTree = MerkleTree::from_hex(&checksums).unwrap();
+
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.root_hex(), Some(merkle_root.clone()));
+
+        // each chunk is verified without the others
+        for (index, checksum) in checksums.iter().enumerate() {
+            let proof: MerkleProof = tree.proof(index).unwrap();
+            let checksum: merkle::Hash = merkle::from_hex(checksum).unwrap();
+
+            assert!(proof.verify(&checksum, &tree.root().unwrap()));
+            assert!(!proof.verify(&[0; 32], &tree.root().unwrap()));
+        }
+
+        assert!(tree.proof(5).is_none());
+
+        let check = |root: String| {
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.manifest.stored_size())
+                .total_chunks(split_result.total_chunks)
+                .merkle_root(root)
+                .run()
+        };
+
+        assert!(check(merkle_root.clone()).await.unwrap().success);
+
+        // corrupt a chunk without changing its size
+        let path: PathBuf = cache_dir.join("3");
+        let mut data: Vec<u8> = fs::read(&path).unwrap();
+        let last: usize = data.len() - 1;
+        data[last] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let result: CheckResult = check(merkle_root).await.unwrap();
+        let error = result.error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Corrupted);
+        assert_eq!(error.corrupted, Some(vec![3]));
+    }
+
+    #[tokio::test]
+    async fn test_split_returns_checksums() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_returns_checksums");
+
+        assert_eq!(
+            Algorithm::Blake3.digest(b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(Algorithm::Crc32.digest(b"123456789"), "cbf43926");
+        assert_eq!(Algorithm::from_id("blake3"), Some(Algorithm::Blake3));
+
+        for algorithm in [Algorithm::Blake3, Algorithm::Crc32] {
+            let out_dir: PathBuf = cache_dir.join(algorithm.id());
+
+            let split_result: SplitResult = Split::new()
+                .in_file(root.join("assets").join("test.png"))
+                .out_dir(&out_dir)
+                .chunk_size(1024 * 1024)
+                .checksum(algorithm)
+                .run()
+                .await
+                .unwrap();
+
+            assert_eq!(split_result.checksums.len(), 5);
+
+            for checksum in &split_result.checksums {
+                let data: Vec<u8> =
+                    fs::read(out_dir.join(checksum.index.to_string())).unwrap();
+
+                assert_eq!(
+                    checksum,
+                    &ChunkChecksum {
+                        index: checksum.index,
+                        algorithm,
+                        digest: algorithm.digest(&data),
+                    }
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prove_possession_of_chunk() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("prove_possession_of_chunk");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .headers(true)
+            .merkle(true)
+            .run()
+            .await
+            .unwrap();
+
+        // kept by the coordinator
+        let merkle_root: String = split_result.manifest.merkle_root.unwrap();
+
+        for index in 0..split_result.total_chunks {
+            let proof: ChunkProof = prove(&cache_dir, index).await.unwrap();
+
+            assert!(verify_proof(&merkle_root, &proof));
+        }
+
+        assert!(prove(&cache_dir, split_result.total_chunks).await.is_err());
+
+        // a corrupted chunk fails the proof
+        let path: PathBuf = cache_dir.join("1");
+        let mut data: Vec<u8> = fs::read(&path).unwrap();
+        let last: usize = data.len() - 1;
+        data[last] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let proof: ChunkProof = prove(&cache_dir, 1).await.unwrap();
+
+        assert!(!verify_proof(&merkle_root, &proof));
+        assert!(!verify_proof("invalid", &proof));
+
+        // a missing chunk cannot be proved
+        fs::remove_file(&path).unwrap();
+
+        assert!(prove(&cache_dir, 1).await.is_err());
+    }
+
+    #[test]
+    fn test_key_template() {
+        let template: KeyTemplate =
+            KeyTemplate::parse("{file_id}/{index:06}.bin").unwrap();
+
+        assert_eq!(template.key("my-file", 7), "my-file/000007.bin");
+        assert_eq!(template.key("my-file", 1234567), "my-file/1234567.bin");
+        assert_eq!(template.index("my-file", "my-file/000007.bin"), Some(7));
+        assert_eq!(template.index("my-file", "my-file/7.bin"), None);
+        assert_eq!(template.index("other", "my-file/000007.bin"), None);
+        assert_eq!(template.to_string(), "{file_id}/{index:06}.bin");
+
+        let keys: Vec<String> = template.keys("my-file", 3, [""; 0]).unwrap();
+
+        assert_eq!(
+            keys,
+            ["my-file/000000.bin", "my-file/000001.bin", "my-file/000002.bin"]
+        );
+
+        // collides with the keys in the bucket
+        let error: io::Error =
+            template.keys("my-file", 3, ["my-file/000002.bin"]).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+        // invalid keys
+        let error: io::Error = template.keys("", 1, [""; 0]).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let escaped: KeyTemplate =
+            "{{raw}}/part-{index}".parse::<KeyTemplate>().unwrap();
+
+        assert_eq!(escaped.key("my-file", 12), "{raw}/part-12");
+        assert_eq!(escaped.index("my-file", "{raw}/part-12"), Some(12));
+
+        // chunks would collide without the index
+        for invalid in
+            ["{file_id}.bin", "{index", "{index:6}", "{name}/{index}", "a}"]
+        {
+            assert_eq!(
+                KeyTemplate::parse(invalid).unwrap_err().kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_with_file_hash() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_file_hash");
+
+        let file: Vec<u8> = fs::read(&in_file).unwrap();
+
+        for algorithm in Algorithm::ALL {
+            let split_result: SplitResult = Split::new()
+                .in_file(&in_file)
+                .out_dir(cache_dir.join(algorithm.id()))
+                .chunk_size(1024 * 1024)
+                .file_hash(algorithm)
+                .run()
+                .await
+                .unwrap();
+
+            assert_eq!(split_result.file_hash, Some(algorithm.digest(&file)));
+        }
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(cache_dir.join("none"))
+            .chunk_size(1024 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(split_result.file_hash.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_check_with_checksums() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("check_with_checksums");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .headers(true)
+            .checksum(Algorithm::Blake3)
+            .run()
+            .await
+            .unwrap();
+
+        let check = || {
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.manifest.stored_size())
+                .total_chunks(split_result.total_chunks)
+                .verify_checksums(split_result.checksums.clone())
+                .run()
+        };
+
+        assert!(check().await.unwrap().success);
+
+        // corrupt chunks without changing their sizes
+        for index in [4, 1] {
+            let path: PathBuf = cache_dir.join(index.to_string());
+            let mut data: Vec<u8> = fs::read(&path).unwrap();
+            let last: usize = data.len() - 1;
+            data[last] ^= 0xff;
+            fs::write(&path, &data).unwrap();
+        }
+
+        let result: CheckResult = check().await.unwrap();
+        let error = result.error.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(error.error_type, CheckResultErrorType::Corrupted);
+        assert_eq!(error.error_type.as_code(), "corrupted");
+        assert_eq!(error.corrupted, Some(vec![1, 4]));
+    }
+
+    #[tokio::test]
+    async fn test_check_with_checksums_in_pieces() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("check_with_checksums_in_pieces");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        // chunks larger than the pieces hashed by the check process
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(3 * 1024 * 1024)
+            .headers(true)
+            .checksum(Algorithm::Sha256)
+            .run()
+            .await
+            .unwrap();
+
+        let check = || {
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.manifest.stored_size())
+                .total_chunks(split_result.total_chunks)
+                .verify_checksums(split_result.checksums.clone())
+                .run()
+        };
+
+        assert!(check().await.unwrap().success);
+
+        // corrupt a byte past the first piece of the first chunk
+        let path: PathBuf = cache_dir.join("0");
+        let mut data: Vec<u8> = fs::read(&path).unwrap();
+        data[2 * 1024 * 1024] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let result: CheckResult = check().await.unwrap();
+        let error = result.error.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(error.error_type, CheckResultErrorType::Corrupted);
+        assert_eq!(error.corrupted, Some(vec![0]));
+    }
+
+    #[tokio::test]
+    async fn test_check_strict() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("check_strict");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        let check = || {
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.file_size)
+                .total_chunks(split_result.total_chunks)
+                .strict(true)
+                .run()
+        };
+
+        assert!(check().await.unwrap().success);
+
+        for name in ["5.tmp", ".DS_Store", "9"] {
+            fs::write(cache_dir.join(name), b"extra").unwrap();
+        }
+
+        let result: CheckResult = check().await.unwrap();
+        let error = result.error.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(error.error_type, CheckResultErrorType::Extra);
+        assert_eq!(error.error_type.as_code(), "extra");
+        assert_eq!(
+            error.extra,
+            Some(vec![
+                cache_dir.join(".DS_Store"),
+                cache_dir.join("5.tmp"),
+                cache_dir.join("9"),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_with_signed_manifest() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("signed_manifest");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let split = || {
+            Split::new()
+                .in_file(root.join("assets").join("test.png"))
+                .out_dir(&cache_dir)
+                .chunk_size(1024 * 1024)
+                .manifest(true)
+        };
+
+        let key: SigningKey = SigningKey::from_bytes(&[7; 32]);
+        let other: VerifyingKey =
+            SigningKey::from_bytes(&[8; 32]).verifying_key();
+
+        // checksums are required to sign
+        let unsigned: SplitResult = split().run().await.unwrap();
+
+        let error = sign(&unsigned.manifest, &key).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let split_result: SplitResult =
+            split().checksum(Algorithm::Sha256).run().await.unwrap();
+
+        let signature: Signature = sign(&split_result.manifest, &key).unwrap();
+
+        write_signature(&cache_dir, &signature).await.unwrap();
+
+        let check = |key: VerifyingKey| {
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.file_size)
+                .total_chunks(split_result.total_chunks)
+                .strict(true)
+                .verifying_key(key)
+                .run()
+        };
+
+        assert!(check(key.verifying_key()).await.unwrap().success);
+
+        let result: CheckResult = check(other).await.unwrap();
+        let error = result.error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Signature);
+        assert_eq!(error.error_type.as_code(), "signature");
+
+        // a corrupted chunk
+        let path: PathBuf = cache_dir.join("1");
+        let data: Vec<u8> = fs::read(&path).unwrap();
+        let mut corrupted: Vec<u8> = data.clone();
+        corrupted[0] ^= 0xff;
+        fs::write(&path, &corrupted).unwrap();
+
+        let error = check(key.verifying_key()).await.unwrap().error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Corrupted);
+        assert_eq!(error.corrupted, Some(vec![1]));
+
+        fs::write(&path, &data).unwrap();
+
+        // a modified manifest
+        let mut manifest: Manifest = split_result.manifest.clone();
+        manifest.chunks[1].set_metadata(
+            Algorithm::Sha256.id(),
+            Algorithm::Sha256.digest(&corrupted),
+        );
+        manifest.write(cache_dir.join("manifest.json")).await.unwrap();
+
+        let error = check(key.verifying_key()).await.unwrap().error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Signature);
+    }
+
+    #[tokio::test]
+    async fn test_check_and_merge_from_manifest() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("from_manifest");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("from_manifest")
+            .join("test.png");
+        let manifest_path: PathBuf = cache_dir.join("manifest.json");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .file_hash(Algorithm::Sha256)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        let mut manifest: Manifest =
+            Manifest::read(&manifest_path).await.unwrap();
+
+        assert_eq!(manifest.file_name.as_deref(), Some("test.png"));
+        assert_eq!(
+            manifest.file_hash(),
+            Some((
+                Algorithm::Sha256,
+                Algorithm::Sha256.digest(&fs::read(&in_file).unwrap()).as_str()
+            ))
+        );
+
+        // file_size and total_chunks are not transmitted apart
+        let result: CheckResult =
+            Check::new().from_manifest(&manifest_path).run().await.unwrap();
+
+        assert!(result.success);
+
+        let merge = || {
+            Merge::new()
+                .from_manifest(&manifest_path)
+                .out_file(&output_file)
+                .run()
+        };
+
+        merge().await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // the checksum of the original file is expected
+        manifest
+            .file_hashes
+            .insert(Algorithm::Sha256.id().to_string(), "0".repeat(64));
+        manifest.write(&manifest_path).await.unwrap();
+
+        let error = merge().await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!output_file.exists());
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_with_metadata() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("metadata");
+        let output_dir: PathBuf =
+            root.join(".media").join("output").join("metadata");
+        let in_file: PathBuf = cache_dir.join("test.png");
+        let output_file: PathBuf = output_dir.join("test.png");
+        let chunks_dir: PathBuf = cache_dir.join("chunks");
+        let manifest_path: PathBuf = chunks_dir.join("manifest.bin");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+        let _ = fs::remove_dir_all(&output_dir);
+
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::copy(root.join("assets").join("test.png"), &in_file).unwrap();
+
+        let modified: std::time::SystemTime = std::time::UNIX_EPOCH
+            + std::time::Duration::from_nanos(1_700_000_000_123_456_789);
+
+        fs::File::options()
+            .write(true)
+            .open(&in_file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(&in_file, fs::Permissions::from_mode(0o750))
+                .unwrap();
+        }
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&chunks_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .manifest_format(ManifestFormat::Binary)
+            .preserve_metadata(true)
+            .run()
+            .await
+            .unwrap();
+
+        let metadata: &FileMetadata =
+            result.manifest.file_metadata.as_ref().unwrap();
+
+        assert_eq!(metadata.modified, Some(1_700_000_000_123_456_789));
+        assert!(!metadata.readonly);
+
+        #[cfg(unix)]
+        assert_eq!(metadata.mode, Some(0o750));
+
+        // the metadata is kept by the binary manifest
+        assert_eq!(
+            Manifest::read(&manifest_path).await.unwrap(),
+            result.manifest
+        );
+
+        // the metadata is not restored by default
+        Merge::new()
+            .from_manifest(&manifest_path)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        let merged: fs::Metadata = fs::metadata(&output_file).unwrap();
+
+        assert_ne!(merged.modified().unwrap(), modified);
+
+        Merge::new()
+            .from_manifest(&manifest_path)
+            .out_file(&output_file)
+            .restore_metadata(true)
+            .run()
+            .await
+            .unwrap();
+
+        let restored: fs::Metadata = fs::metadata(&output_file).unwrap();
+
+        assert_eq!(restored.modified().unwrap(), modified);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            assert_eq!(restored.permissions().mode() & 0o7777, 0o750);
+        }
+
+        // the metadata to restore is required in the manifest
+        Manifest { file_metadata: None, ..result.manifest }
+            .write_as(&manifest_path, ManifestFormat::Binary)
+            .await
+            .unwrap();
+
+        let error: io::Error = Merge::new()
+            .from_manifest(&manifest_path)
+            .out_file(&output_file)
+            .restore_metadata(true)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_with_binary_manifest() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("binary_manifest");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("binary_manifest")
+            .join("test.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .naming(ChunkNaming::coreutils())
+            .checksum(Algorithm::Sha256)
+            .file_hash(Algorithm::Blake3)
+            .manifest(true)
+            .manifest_format(ManifestFormat::Binary)
+            .run()
+            .await
+            .unwrap();
+
+        let path: PathBuf = cache_dir.join("manifest.bin");
+        let data: Vec<u8> = fs::read(&path).unwrap();
+
+        assert!(!cache_dir.join("manifest.json").exists());
+        assert_eq!(ManifestFormat::detect(&data), ManifestFormat::Binary);
+        assert_eq!(Manifest::read(&path).await.unwrap(), split_result.manifest);
+        assert!(
+            data.len()
+                < split_result
+                    .manifest
+                    .to_bytes(ManifestFormat::Json)
+                    .unwrap()
+                    .len()
+        );
+
+        let error = Manifest::from_bytes(&data[..data.len() - 1]).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // found by the check and merge processes
+        let result: CheckResult =
+            Check::new().from_manifest(&path).strict(true).run().await.unwrap();
+
+        assert!(result.success);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_map_chunks_of_split() {
+        let (_, cache_dir, _, split_result) =
+            setup("map_chunks_of_split").await;
+
+        let store: DirStore =
+            DirStore::with_manifest(&cache_dir, &split_result.manifest);
+
+        let mut data: Vec<u8> = Vec::new();
+
+        for index in 0..split_result.total_chunks {
+            let chunk: Mmap = store.map(index).await.unwrap();
+
+            assert_eq!(&chunk[..], &store.get(index).await.unwrap()[..]);
+
+            data.extend_from_slice(&chunk);
+        }
+
+        assert_eq!(data.len(), split_result.file_size);
+
+        let error = store.map(split_result.total_chunks).await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_compat() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("manifest_compat");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .naming(ChunkNaming::coreutils())
+            .checksum(Algorithm::Sha256)
+            .file_hash(Algorithm::Sha256)
+            .merkle(true)
+            .volume_size(3 * 1024 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        let manifest: Manifest = split_result.manifest;
+
+        assert_eq!(manifest.compat(SchemaVersion::LATEST).unwrap(), manifest);
+
+        // every field written is described by the schema
+        let schema: serde_json::Value =
+            serde_json::from_str(SchemaVersion::V1.schema()).unwrap();
+        let value: serde_json::Value = serde_json::to_value(&manifest).unwrap();
+
+        for key in value.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{}", key);
+        }
+
+        for key in value["chunks"][0].as_object().unwrap().keys() {
+            assert!(
+                schema["properties"]["chunks"]["items"]["properties"]
+                    .get(key)
+                    .is_some(),
+                "{}",
+                key
+            );
+        }
+
+        // digests are lowercased
+        let mut uppercase: Manifest = manifest.clone();
+        uppercase.merkle_root =
+            uppercase.merkle_root.map(|r| r.to_ascii_uppercase());
+        for chunk in &mut uppercase.chunks {
+            let digest: String =
+                chunk.get_metadata("sha256").unwrap().to_ascii_uppercase();
+            chunk.set_metadata("sha256", digest);
+        }
+
+        assert_eq!(uppercase.compat(SchemaVersion::V1).unwrap(), manifest);
+
+        // chunks must be contiguous
+        let mut broken: Manifest = manifest.clone();
+        broken.chunks[2].offset += 1;
+
+        let error = broken.compat(SchemaVersion::V1).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut newer: Manifest = manifest.clone();
+        newer.version = 2;
+
+        assert!(newer.compat(SchemaVersion::V1).is_err());
+        assert_eq!(
+            SchemaVersion::from_number(manifest.version),
+            Some(SchemaVersion::V1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_expected_size_and_hash() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_expected");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("merge_expected.png");
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .file_hash(Algorithm::Sha256)
+            .run()
+            .await
+            .unwrap();
+
+        let digest: String = result.file_hash.clone().unwrap();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .expect_file_size(result.file_size)
+            .expect_hash(Algorithm::Sha256, digest.to_uppercase())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // the output exceeding the expected size
+        let error = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .expect_file_size(result.file_size - 1)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!output_file.exists());
+
+        // the output of another checksum
+        let error = Merge::from_store(Arc::new(DirStore::new(&cache_dir)))
+            .total_chunks(result.total_chunks)
+            .out_file(&output_file)
+            .expect_hash(Algorithm::Sha256, "0".repeat(64))
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!output_file.exists());
+    }
+
+    #[tokio::test]
+    async fn test_tiered_store() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("tiered_store");
+        let local_dir: PathBuf =
+            root.join(".media").join("cache").join("tiered_store_local");
+        let remote_dir: PathBuf =
+            root.join(".media").join("cache").join("tiered_store_remote");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("tiered_store.png");
+
+        let _ = fs::remove_dir_all(&local_dir);
+        let _ = fs::remove_dir_all(&remote_dir);
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        let chunks: DirStore = DirStore::new(&cache_dir);
+
+        let store: Arc<TieredStore> = Arc::new(
+            TieredStore::new(
+                DirStore::new(&local_dir),
+                Arc::new(DirStore::new(&remote_dir)),
+            )
+            .concurrency(2),
+        );
+
+        for index in 0..result.total_chunks {
+            store.put(index, chunks.get(index).await.unwrap()).await.unwrap();
+
+            // readable before uploaded
+            assert!(store.head(index).await.unwrap().is_some());
+        }
+
+        assert_eq!(store.pending(), result.total_chunks);
+
+        store.flush().await.unwrap();
+
+        assert_eq!(store.pending(), 0);
+
+        // evicted from the local tier after uploaded
+        for index in 0..result.total_chunks {
+            assert!(!store.local().path(index).exists());
+            assert!(remote_dir.join(index.to_string()).exists());
+        }
+
+        Merge::from_store(store)
+            .total_chunks(result.total_chunks)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // kept in the local tier without eviction
+        let store: TieredStore = TieredStore::new(
+            DirStore::new(&local_dir),
+            Arc::new(DirStore::new(&remote_dir)),
+        )
+        .evict(false);
+
+        store.put(0, b"chunk".to_vec()).await.unwrap();
+        store.flush().await.unwrap();
+
+        assert_eq!(fs::read(local_dir.join("0")).unwrap(), b"chunk");
+        assert_eq!(fs::read(remote_dir.join("0")).unwrap(), b"chunk");
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_merkle_root() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("merge_with_merkle_root");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("merge_with_merkle_root.png");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .headers(true)
+            .merkle(true)
+            .run()
+            .await
+            .unwrap();
+
+        let merkle_root: String =
+            split_result.manifest.merkle_root.clone().unwrap();
+
+        let merge = |root: String| {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_file)
+                .merkle_root(root)
+                .run()
+        };
+
+        merge(merkle_root.clone()).await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // another root fails before writing
+        let error = merge("0".repeat(64)).await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(output_file.exists());
+
+        // the chunks from a store are verified in pieces against the manifest
+        let from_store = |root: String| {
+            Merge::from_store(Arc::new(DirStore::new(&cache_dir)))
+                .from_manifest(cache_dir.join("manifest.json"))
+                .out_file(&output_file)
+                .max_buffer_capacity(256 * 1024)
+                .merkle_root(root)
+                .run()
+        };
+
+        from_store(merkle_root.clone()).await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // a corrupted chunk fails and removes the output
+        let path: PathBuf = cache_dir.join("3");
+        let mut data: Vec<u8> = fs::read(&path).unwrap();
+        let last: usize = data.len() - 1;
+        data[last] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let error = merge(merkle_root.clone()).await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "chunk 3 does not match the Merkle root");
+        assert!(!output_file.exists());
+
+        let error = from_store(merkle_root).await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "chunk 3 does not match the Merkle root");
+        assert!(!output_file.exists());
+    }
+
+    #[tokio::test]
+    async fn test_split_and_check_with_sha256sums() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        for (name, headers) in
+            [("sha256sums", false), ("sha256sums_with_headers", true)]
+        {
+            let cache_dir: PathBuf =
+                root.join(".media").join("cache").join(name);
+
+            let _ = fs::remove_dir_all(&cache_dir);
+
+            let split_result: SplitResult = Split::new()
+                .in_file(root.join("assets").join("test.png"))
+                .out_dir(&cache_dir)
+                .chunk_size(1024 * 1024)
+                .headers(headers)
+                .volume_size(5 * 512 * 1024)
+                .sha256sums(true)
+                .run()
+                .await
+                .unwrap();
+
+            // compatible with `sha256sum -c`
+            let expected: String = (0..split_result.total_chunks)
+                .map(|index| {
+                    let path: String = format!("vol{}/{}", index / 2, index);
+                    let data: Vec<u8> =
+                        fs::read(cache_dir.join(&path)).unwrap();
+
+                    format!("{}  {}\n", Algorithm::Sha256.digest(&data), path)
+                })
+                .collect();
+
+            assert_eq!(
+                fs::read_to_string(cache_dir.join("SHA256SUMS")).unwrap(),
+                expected
+            );
+
+            let check = || {
+                Check::new()
+                    .in_dir(&cache_dir)
+                    .file_size(split_result.manifest.stored_size())
+                    .total_chunks(split_result.total_chunks)
+                    .sha256sums(true)
+                    .strict(true)
+                    .run()
+            };
+
+            assert!(check().await.unwrap().success);
+
+            let path: PathBuf = cache_dir.join("vol1").join("2");
+            let mut data: Vec<u8> = fs::read(&path).unwrap();
+            data[0] ^= 0xff;
+            fs::write(&path, &data).unwrap();
+
+            let result: CheckResult = check().await.unwrap();
+            let error = result.error.unwrap();
+
+            assert!(!result.success);
+            assert_eq!(error.error_type, CheckResultErrorType::Corrupted);
+            assert_eq!(error.corrupted, Some(vec![2]));
+
+            // paths outside the input directory are rejected
+            let sums: PathBuf = cache_dir.join("SHA256SUMS");
+
+            for path in ["/etc/hostname", "../test.png", "vol0/../../x"] {
+                fs::write(&sums, format!("{}  {}\n", "0".repeat(64), path))
+                    .unwrap();
+
+                let error: io::Error = check().await.unwrap_err();
+
+                assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_with_seeded_rng() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_seeded_rng");
+
+        let split = |dir: &str, rng: Arc<dyn Rng>| {
+            Split::new()
+                .in_file(root.join("assets").join("test.png"))
+                .out_dir(cache_dir.join(dir))
+                .chunk_size(1024 * 1024)
+                .headers(true)
+                .rng(rng)
+                .run()
+        };
+
+        let a: SplitResult =
+            split("a", Arc::new(SeededRng::new(7))).await.unwrap();
+        let b: SplitResult =
+            split("b", Arc::new(SeededRng::new(7))).await.unwrap();
+        let c: SplitResult = split("c", Arc::new(SystemRng)).await.unwrap();
+
+        // the same seed gives the same chunks byte for byte
+        assert_eq!(a.id, b.id);
+        assert_ne!(a.id, c.id);
+        assert_eq!(a.id, SeededRng::new(7).next_uuid());
+        assert_eq!(a.id.get_version_num(), 4);
+
+        for index in 0..a.total_chunks {
+            assert_eq!(
+                fs::read(cache_dir.join("a").join(index.to_string())).unwrap(),
+                fs::read(cache_dir.join("b").join(index.to_string())).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_check_merge_with_xxh3() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_xxh3");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("merge_with_xxh3.png");
+
+        // canonical outputs of `xxhsum -H3` and `xxhsum -H1`
+        assert_eq!(Algorithm::Xxh3.digest(b""), "2d06800538d394c2");
+        assert_eq!(Algorithm::Xxh64.digest(b""), "ef46db3751d8e999");
+        assert_eq!(Algorithm::from_id("xxh3"), Some(Algorithm::Xxh3));
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .checksum(Algorithm::Xxh3)
+            .file_hash(Algorithm::Xxh3)
+            .run()
+            .await
+            .unwrap();
+
+        let result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .verify_checksums(split_result.checksums.clone())
+            .run()
+            .await
+            .unwrap();
+
+        assert!(result.success);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .expect_hash(Algorithm::Xxh3, split_result.file_hash.unwrap())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bao_verifier() {
+        // lengths around the groups and the parent nodes
+        for len in [0, 1, 1024, 1025, 2048, 3 * 1024 + 1, 64 * 1024 + 7] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+
+            let (hash, encoded) = outboard(&data);
+
+            assert_eq!(hash.to_hex().as_str(), Algorithm::Blake3.digest(&data));
+
+            let mut verifier: BaoVerifier =
+                BaoVerifier::new(*hash.as_bytes(), encoded.clone()).unwrap();
+
+            for part in data.chunks(777) {
+                verifier.update(part).unwrap();
+            }
+
+            assert_eq!(verifier.verified(), len as u64);
+
+            verifier.finish().unwrap();
+
+            if len < 2048 {
+                continue;
+            }
+
+            // the first corrupt group fails without the rest of the data
+            let mut corrupted: Vec<u8> = data.clone();
+            corrupted[1500] ^= 0xff;
+
+            let mut verifier: BaoVerifier =
+                BaoVerifier::new(*hash.as_bytes(), encoded).unwrap();
+
+            let error = verifier.update(&corrupted[..2048]).unwrap_err();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(verifier.verified(), 1024);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_with_bao() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_with_bao");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("merge_with_bao.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .headers(true)
+            .bao(true)
+            .run()
+            .await
+            .unwrap();
+
+        for checksum in &split_result.checksums {
+            assert_eq!(checksum.algorithm, Algorithm::Blake3);
+            assert!(outboard_path(cache_dir.join(checksum.index.to_string()))
+                .exists());
+        }
+
+        let merge = || {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_file)
+                .bao(true)
+                .run()
+        };
+
+        merge().await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // outboards are expected in strict mode
+        let result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.manifest.stored_size())
+            .total_chunks(split_result.total_chunks)
+            .strict(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(result.success);
+
+        // the outboards are not fetched from a store
+        let error = Merge::from_store(Arc::new(DirStore::new(&cache_dir)))
+            .from_manifest(cache_dir.join("manifest.json"))
+            .out_file(&output_file)
+            .bao(true)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let error = Merge::new()
+            .in_dir(&cache_dir)
+            .bao(true)
+            .run_to(Vec::new())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        // a corrupted chunk fails and removes the output
+        let path: PathBuf = cache_dir.join("1");
+        let mut data: Vec<u8> = fs::read(&path).unwrap();
+        data[CHUNK_HEADER_SIZE + 5000] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let error = merge().await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "data is corrupted at offset 4096");
+        assert!(!output_file.exists());
+    }
+
+    // writes 200 GB to the disk, run with `cargo test -- --ignored`
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_split_and_merge_larger_than_memory() {
+        const FILE_SIZE: usize = 100 * 1024 * 1024 * 1024;
+        const CHUNK_SIZE: usize = 32 * 1024 * 1024;
+        const MEMORY_MAX: usize = 512 * 1024 * 1024;
+
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("larger_than_memory");
+        let output_dir: PathBuf =
+            root.join(".media").join("output").join("larger_than_memory");
+        let in_file: PathBuf = output_dir.join("sparse.bin");
+        let output_file: PathBuf = output_dir.join("merged.bin");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+        let _ = fs::remove_dir_all(&output_dir);
+
+        fs::create_dir_all(&output_dir).unwrap();
+
+        // sparse, so it takes no space on the disk
+        fs::File::create(&in_file).unwrap().set_len(FILE_SIZE as u64).unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(CHUNK_SIZE)
+            .checksum(Algorithm::Crc32c)
+            .file_hash(Algorithm::Blake3)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.file_size, FILE_SIZE);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .expect_file_size(FILE_SIZE)
+            .expect_hash(Algorithm::Blake3, split_result.file_hash.unwrap())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::metadata(&output_file).unwrap().len(), FILE_SIZE as u64);
+
+        let peak: usize = peak_memory();
+
+        let _ = fs::remove_dir_all(&cache_dir);
+        let _ = fs::remove_dir_all(&output_dir);
+
+        assert!(peak < MEMORY_MAX, "peak memory of {} bytes", peak);
+    }
+
+    #[tokio::test]
+    async fn test_split_with_mime_type_and_merge_to_out_dir() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf = root.join(".media").join("cache").join("mime");
+        let output_dir: PathBuf =
+            root.join(".media").join("output").join("mime");
+        let in_file: PathBuf = cache_dir.join("test.png");
+        let chunks_dir: PathBuf = cache_dir.join("chunks");
+        let manifest_path: PathBuf = chunks_dir.join("manifest.bin");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+        let _ = fs::remove_dir_all(&output_dir);
+
+        // the asset lost the CR of its PNG signature, which is restored
+        let asset: Vec<u8> =
+            fs::read(root.join("assets").join("test.png")).unwrap();
+
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::write(&in_file, [b"\x89PNG\r\n\x1a\n", &asset[7..]].concat())
+            .unwrap();
+
+        assert_eq!(sniff(b"GIF89a"), Some("image/gif"));
+        assert_eq!(sniff(b"GIF"), None);
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&chunks_dir)
+            .chunk_size(1024 * 1024)
+            .manifest(true)
+            .manifest_format(ManifestFormat::Binary)
+            .sniff_mime(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(result.manifest.file_name.as_deref(), Some("test.png"));
+        assert_eq!(result.manifest.mime_type.as_deref(), Some("image/png"));
+
+        // the MIME type is kept by the binary manifest
+        assert_eq!(
+            Manifest::read(&manifest_path).await.unwrap(),
+            result.manifest
+        );
+
+        // the file is restored under its original name
+        Merge::new()
+            .from_manifest(&manifest_path)
+            .out_dir(&output_dir)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(output_dir.join("test.png")).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // names escaping the output directory are rejected
+        let escaping: PathBuf = cache_dir.join("escaping.json");
+
+        Manifest { file_name: Some("..".to_string()), ..result.manifest }
+            .write(&escaping)
+            .await
+            .unwrap();
+
+        let error: io::Error = Merge::new()
+            .from_manifest(&escaping)
+            .in_dir(&chunks_dir)
+            .out_dir(&output_dir)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_sync_stores() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf = root.join(".media").join("cache").join("sync");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("sync").join("test.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .checksum(Algorithm::Sha256)
+            .run()
+            .await
+            .unwrap();
+
+        let src: Arc<dyn ChunkStore> =
+            Arc::new(DirStore::with_manifest(&cache_dir, &result.manifest));
+        let dst: Arc<MemoryStore> = Arc::new(MemoryStore::default());
+
+        // a chunk copied by an interrupted sync
+        dst.put(0, fs::read(cache_dir.join("0")).unwrap()).await.unwrap();
+
+        let synced: SyncResult = sync(src.clone(), dst.clone())
+            .manifest(result.manifest.clone())
+            .concurrency(2)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(synced.copied, vec![1, 2, 3, 4]);
+        assert_eq!(synced.skipped, vec![0]);
+        assert_eq!(
+            synced.copied_size,
+            result.file_size - result.manifest.chunks[0].stored_size
+        );
+
+        // nothing is copied again, with the chunks listed in the source
+        let synced: SyncResult =
+            sync(src.clone(), dst.clone()).run().await.unwrap();
+
+        assert!(synced.copied.is_empty());
+        assert_eq!(synced.skipped, vec![0, 1, 2, 3, 4]);
+
+        Merge::from_store(dst)
+            .total_chunks(result.total_chunks)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // corrupted chunks are not copied
+        let mut chunk: Vec<u8> = fs::read(cache_dir.join("2")).unwrap();
+        chunk[0] ^= 0xff;
+        fs::write(cache_dir.join("2"), chunk).unwrap();
+
+        let error: io::Error =
+            sync(src.clone(), Arc::new(MemoryStore::default()))
+                .manifest(result.manifest.clone())
+                .run()
+                .await
+                .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let synced: SyncResult = sync(src, Arc::new(MemoryStore::default()))
+            .manifest(result.manifest)
+            .verify(false)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(synced.copied.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_migrate() {
+        let (_, _, _, split_result) = setup("manifest_migrate").await;
+
+        let manifest: Manifest = split_result.manifest;
+
+        assert_eq!(manifest.version(), Ok(SchemaVersion::LATEST));
+
+        // the latest version is kept in any format
+        for format in [ManifestFormat::Json, ManifestFormat::Binary] {
+            assert_eq!(
+                migrate(&manifest.to_bytes(format).unwrap()).unwrap(),
+                manifest
+            );
+        }
+
+        // unknown versions are rejected with typed errors
+        let mut value: serde_json::Value =
+            serde_json::to_value(&manifest).unwrap();
+
+        value["version"] = serde_json::Value::from(99);
+
+        assert_eq!(
+            migrate(&serde_json::to_vec(&value).unwrap()),
+            Err(MigrateError::UnknownVersion(99))
+        );
+
+        value.as_object_mut().unwrap().remove("version");
+
+        assert_eq!(
+            migrate(&serde_json::to_vec(&value).unwrap()),
+            Err(MigrateError::MissingVersion)
+        );
+
+        assert!(matches!(migrate(b"{"), Err(MigrateError::Invalid(_))));
+
+        let unknown: Manifest = Manifest { version: 0, ..manifest };
+
+        assert_eq!(unknown.version(), Err(MigrateError::UnknownVersion(0)));
+        assert_eq!(
+            io::Error::from(MigrateError::UnknownVersion(0)).kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_to_slow_writer() {
+        let (root, cache_dir, _, split_result) =
+            setup("merge_to_slow_writer").await;
+
+        // the reader is slower than the merge
+        let (writer, mut reader) = tokio::io::duplex(64 * 1024);
+
+        let received = tokio::spawn(async move {
+            let mut data: Vec<u8> = Vec::new();
+            let mut buffer: Vec<u8> = vec![0; 256 * 1024];
+
+            loop {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+                match reader.read(&mut buffer).await.unwrap() {
+                    | 0 => break data,
+                    | read => data.extend_from_slice(&buffer[..read]),
+                }
+            }
+        });
+
+        let monitor: Arc<StallMonitor> =
+            Arc::new(StallMonitor::new(std::time::Duration::from_millis(1)));
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .total_chunks(split_result.total_chunks)
+            .stall_monitor(monitor.clone())
+            .run_to(writer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            received.await.unwrap(),
+            fs::read(root.join("assets").join("test.png")).unwrap()
+        );
+
+        let stats: StallStats = monitor.stats();
+
+        assert_eq!(stats.writes, split_result.total_chunks + 1);
+        assert!(stats.stalls > 0);
+        assert!(
+            stats.stalled
+                >= std::time::Duration::from_millis(stats.stalls as u64)
+        );
+        assert!(stats.longest >= std::time::Duration::from_millis(5));
+
+        // the merge is aborted when the reader does not read
+        let (writer, _reader) = tokio::io::duplex(64 * 1024);
+
+        let error: io::Error = Merge::new()
+            .in_dir(&cache_dir)
+            .total_chunks(split_result.total_chunks)
+            .stall_monitor(Arc::new(
+                StallMonitor::new(std::time::Duration::from_millis(1))
+                    .timeout(std::time::Duration::from_millis(50)),
+            ))
+            .run_to(writer)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_session() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("session");
+        let sender_dir: PathBuf = cache_dir.join("sender");
+        let receiver_dir: PathBuf = cache_dir.join("receiver");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("session").join("test.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let mut sender: Session = Session::open(&sender_dir).await.unwrap();
+
+        assert!(sender.manifest().is_none());
+
+        sender
+            .split(
+                Split::new()
+                    .in_file(&in_file)
+                    .chunk_size(1024 * 1024)
+                    .checksum(Algorithm::Sha256),
+            )
+            .await
+            .unwrap();
+
+        let manifest: Manifest = sender.manifest().unwrap().clone();
+
+        let mut receiver: Session = Session::open(&receiver_dir).await.unwrap();
+
+        assert!(receiver.status().await.is_err());
+
+        receiver.receive_manifest(manifest.clone()).await.unwrap();
+
+        for index in 0..3 {
+            let chunk: Vec<u8> =
+                fs::read(sender_dir.join(index.to_string())).unwrap();
+
+            receiver.receive_chunk(index, chunk).await.unwrap();
+        }
+
+        // the session is resumed from the directory
+        let mut receiver: Session = Session::open(&receiver_dir).await.unwrap();
+
+        receiver.receive_manifest(manifest.clone()).await.unwrap();
+
+        let status: SessionStatus = receiver.status().await.unwrap();
+
+        assert_eq!(status.total_chunks, 5);
+        assert_eq!(status.received, 3);
+        assert_eq!(status.missing, vec![3, 4]);
+        assert!(!status.is_complete());
+
+        assert!(receiver.finalize(&output_file).await.is_err());
+
+        // chunks not matching the manifest are not written
+        let mut chunk: Vec<u8> = fs::read(sender_dir.join("3")).unwrap();
+        chunk[0] ^= 0xff;
+
+        let error: io::Error =
+            receiver.receive_chunk(3, chunk.clone()).await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        chunk.pop();
+
+        let error: io::Error =
+            receiver.receive_chunk(3, chunk).await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!receiver_dir.join("3").exists());
+
+        for index in 3..5 {
+            let chunk: Vec<u8> =
+                fs::read(sender_dir.join(index.to_string())).unwrap();
+
+            receiver.receive_chunk(index, chunk).await.unwrap();
+        }
+
+        assert!(receiver.status().await.unwrap().is_complete());
+
+        receiver.finalize(&output_file).await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // the session cannot be started with another manifest
+        let error: io::Error = receiver
+            .receive_manifest(Manifest { id: None, ..manifest.clone() })
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+        // the chunks of a hostile sender are never written outside
+        let hostile_dir: PathBuf = cache_dir.join("hostile");
+
+        let mut receiver: Session = Session::open(&hostile_dir).await.unwrap();
+
+        let error: io::Error = receiver
+            .receive_manifest(Manifest {
+                naming: ChunkNaming::Alphabetic {
+                    prefix: "../../x".to_string(),
+                    suffix_length: 2,
+                },
+                ..manifest
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(receiver.manifest().is_none());
+        assert!(!hostile_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_range_stream() {
+        let (root, cache_dir, _, _) = setup("range_stream").await;
+
+        let asset: Vec<u8> =
+            fs::read(root.join("assets").join("test.png")).unwrap();
+
+        // range spanning three chunks
+        let mut stream: RangeStream =
+            range_stream(&cache_dir, 1_000_000..3_200_000).await.unwrap();
+
+        assert_eq!(stream.file_size(), asset.len());
+        assert_eq!(stream.content_range(), "bytes 1000000-3199999/4965800");
+
+        let mut body: Vec<u8> = Vec::new();
+
+        while let Some(bytes) =
+            std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            let bytes: Bytes = bytes.unwrap();
+
+            assert!(!bytes.is_empty());
+
+            body.extend_from_slice(&bytes);
+        }
+
+        assert_eq!(body, &asset[1_000_000..3_200_000]);
+
+        // range clamped to the end of the file
+        let stream: RangeStream =
+            range_stream(&cache_dir, 4_900_000..usize::MAX).await.unwrap();
+
+        assert_eq!(stream.range(), 4_900_000..asset.len());
+
+        // range past the end of the file
+        let error: io::Error =
+            range_stream(&cache_dir, asset.len()..asset.len() + 1)
+                .await
+                .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_split_scoped() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let out_dir: PathBuf =
+            root.join(".media").join("cache").join("split_scoped");
+
+        // two splits of the same file into the same directory
+        let (first, second): (SplitResult, SplitResult) = tokio::join!(
+            async {
+                Split::new()
+                    .in_file(&in_file)
+                    .out_dir(&out_dir)
+                    .scoped(true)
+                    .manifest(true)
+                    .run()
+                    .await
+                    .unwrap()
+            },
+            async {
+                Split::new()
+                    .in_file(&in_file)
+                    .out_dir(&out_dir)
+                    .scoped(true)
+                    .manifest(true)
+                    .run()
+                    .await
+                    .unwrap()
+            },
+        );
+
+        assert_ne!(first.id, second.id);
+
+        for result in [&first, &second] {
+            assert_eq!(result.out_dir, out_dir.join(result.id.to_string()));
+            assert!(result.out_dir.join("manifest.json").is_file());
+
+            let output_file: PathBuf = root
+                .join(".media")
+                .join("output")
+                .join("split_scoped")
+                .join(result.id.to_string());
+
+            Merge::new()
+                .in_dir(&result.out_dir)
+                .out_file(&output_file)
+                .run()
+                .await
+                .unwrap();
+
+            assert_eq!(
+                fs::read(&output_file).unwrap(),
+                fs::read(&in_file).unwrap()
+            );
+        }
+
+        // the output directory itself is used without scoping
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&out_dir)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(result.out_dir, out_dir);
+    }
+
+    #[tokio::test]
+    async fn test_split_resume() {
+        let (root, cache_dir, output_file, split_result) =
+            setup("split_resume").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+
+        // chunks lost, truncated and corrupted by a crash
+        fs::remove_file(cache_dir.join("2")).unwrap();
+        fs::write(cache_dir.join("3"), b"partial").unwrap();
+
+        let mut corrupted: Vec<u8> = fs::read(cache_dir.join("1")).unwrap();
+        corrupted[0] ^= 0xff;
+        fs::write(cache_dir.join("1"), corrupted).unwrap();
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .checksum(Algorithm::Sha256)
+            .resume(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(result.total_chunks, split_result.total_chunks);
+        assert_eq!(result.resumed, vec![0, 4]);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // the identity of the previous run is kept with headers
+        let headers_dir: PathBuf = cache_dir.join("headers");
+
+        let first: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&headers_dir)
+            .chunk_size(1024 * 1024)
+            .headers(true)
+            .run()
+            .await
+            .unwrap();
+
+        fs::remove_file(headers_dir.join("1")).unwrap();
+
+        let second: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&headers_dir)
+            .chunk_size(1024 * 1024)
+            .headers(true)
+            .resume(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.resumed, vec![0, 2, 3, 4]);
+        assert_eq!(
+            ChunkHeader::read(headers_dir.join("1"))
+                .await
+                .unwrap()
+                .unwrap()
+                .split_id,
+            first.id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_resume_with_widened_padding() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("split_resume_with_widened_padding");
+        let output_file: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("split_resume_with_widened_padding.png");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        // more chunks than the minimum, so the names are widened
+        let split = || {
+            Split::new()
+                .in_file(&in_file)
+                .out_dir(&cache_dir)
+                .chunk_size(1024 * 1024)
+                .mode(SplitMode::Custom(Arc::new(ContentDefined::new(
+                    16 * 1024,
+                    64 * 1024,
+                ))))
+                .zero_padding(true)
+                .headers(true)
+                .manifest(true)
+        };
+
+        let first: SplitResult = split().run().await.unwrap();
+
+        assert!(first.total_chunks > 10);
+        assert!(cache_dir.join("07").exists());
+
+        fs::remove_file(cache_dir.join("07")).unwrap();
+
+        let second: SplitResult = split().resume(true).run().await.unwrap();
+
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.total_chunks, first.total_chunks);
+        assert_eq!(
+            second.resumed,
+            (0..first.total_chunks).filter(|&i| i != 7).collect::<Vec<_>>()
+        );
+        assert!(!cache_dir.join("7").exists());
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_result_arithmetic() {
+        let (_, _, _, split_result) = setup("split_result_arithmetic").await;
+
+        let chunk_size: usize = 1024 * 1024;
+        let last_size: usize = split_result.file_size - 4 * chunk_size;
+
+        assert_eq!(split_result.chunk_size_of(0), Some(chunk_size));
+        assert_eq!(split_result.chunk_size_of(4), Some(last_size));
+        assert_eq!(split_result.chunk_size_of(5), None);
+
+        assert_eq!(split_result.offset_of(3), Some(3 * chunk_size));
+        assert_eq!(
+            split_result.range_of(4),
+            Some(4 * chunk_size..split_result.file_size)
+        );
+
+        assert_eq!(split_result.index_for_offset(0), Some(0));
+        assert_eq!(split_result.index_for_offset(chunk_size - 1), Some(0));
+        assert_eq!(split_result.index_for_offset(chunk_size), Some(1));
+        assert_eq!(
+            split_result.index_for_offset(split_result.file_size - 1),
+            Some(4)
+        );
+        assert_eq!(split_result.index_for_offset(split_result.file_size), None);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_upgrade_plan() {
+        let (root, cache_dir, _, split_result) =
+            setup("manifest_upgrade_plan").await;
+
+        let new_dir: PathBuf = cache_dir.join("new");
+
+        let new_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.png"))
+            .out_dir(&new_dir)
+            .chunk_size(1536 * 1024)
+            .run()
+            .await
+            .unwrap();
+
+        let plan: UpgradePlan = split_result
+            .manifest
+            .upgrade_plan(&new_result.manifest, &[])
+            .unwrap();
+
+        assert_eq!(plan.derived(), vec![0, 1, 2, 3]);
+        assert_eq!(plan.source_size(), 0);
+
+        // the new chunks are sliced from the existing chunks
+        for chunk in &plan.chunks {
+            let mut data: Vec<u8> = Vec::new();
+
+            for piece in &chunk.pieces {
+                match piece {
+                    | Piece::Chunk { index, range } => {
+                        let existing: Vec<u8> =
+                            fs::read(cache_dir.join(index.to_string()))
+                                .unwrap();
+
+                        data.extend_from_slice(&existing[range.clone()]);
+                    },
+                    | Piece::Source { .. } => unreachable!(),
+                }
+            }
+
+            assert_eq!(
+                data,
+                fs::read(new_dir.join(chunk.index.to_string())).unwrap()
+            );
+        }
+
+        // the missing chunks are read from the original file
+        let plan: UpgradePlan = split_result
+            .manifest
+            .upgrade_plan(&new_result.manifest, &[2])
+            .unwrap();
+
+        assert_eq!(plan.derived(), vec![0, 2, 3]);
+        assert_eq!(
+            plan.source_ranges(),
+            vec![2 * 1024 * 1024..3 * 1024 * 1024]
+        );
+
+        // the manifests of different files
+        let mut other: Manifest = new_result.manifest.clone();
+        let mut manifest: Manifest = split_result.manifest.clone();
+
+        manifest.file_hashes.insert("sha256".to_string(), "00".to_string());
+        other.file_hashes.insert("sha256".to_string(), "ff".to_string());
+
+        let error: io::Error = manifest.upgrade_plan(&other, &[]).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_merge_resume() {
+        let (root, cache_dir, output_file, _) = setup("merge_resume").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let asset: Vec<u8> = fs::read(&in_file).unwrap();
+
+        let merge = || {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_file)
+                .expect_file_size(asset.len())
+                .resume(true)
+        };
+
+        // output interrupted within the third chunk, with a marker in the
+        // first chunk to tell whether it is merged again
+        let mut partial: Vec<u8> = asset[..2_500_000].to_vec();
+        partial[0] ^= 0xff;
+
+        fs::create_dir_all(output_file.parent().unwrap()).unwrap();
+        fs::write(&output_file, &partial).unwrap();
+
+        merge().run().await.unwrap();
+
+        let output: Vec<u8> = fs::read(&output_file).unwrap();
+
+        assert_eq!(output.len(), asset.len());
+        assert_eq!(output[0], asset[0] ^ 0xff);
+        assert_eq!(output[1..], asset[1..]);
+
+        // the bytes merged before are verified with the expected checksum
+        let digest: String = Algorithm::Sha256.digest(&asset);
+
+        fs::write(&output_file, &partial).unwrap();
+
+        let error: io::Error = merge()
+            .expect_hash(Algorithm::Sha256, &digest)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&output_file, &asset[..2_500_000]).unwrap();
+
+        merge().expect_hash(Algorithm::Sha256, digest).run().await.unwrap();
+
+        assert_eq!(fs::read(&output_file).unwrap(), asset);
+    }
+
+    #[tokio::test]
+    async fn test_split_on_progress() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let out_dir: PathBuf =
+            root.join(".media").join("cache").join("split_on_progress");
+
+        let reports: Arc<Mutex<Vec<SplitProgress>>> =
+            Arc::new(Mutex::new(Vec::new()));
+
+        let recorder: Arc<Mutex<Vec<SplitProgress>>> = reports.clone();
+
+        let result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&out_dir)
+            .chunk_size(1024 * 1024)
+            .on_progress(move |progress| {
+                recorder.lock().unwrap().push(progress)
+            })
+            .progress_interval(std::time::Duration::ZERO)
+            .run()
+            .await
+            .unwrap();
+
+        let reports: Vec<SplitProgress> = reports.lock().unwrap().clone();
+
+        assert_eq!(reports[0].bytes_read, 0);
+        assert_eq!(reports[0].total_bytes, result.file_size);
+        assert_eq!(reports[0].total_chunks, 5);
+
+        assert!(reports.windows(2).all(|pair| {
+            pair[0].bytes_read <= pair[1].bytes_read
+                && pair[0].chunks_written <= pair[1].chunks_written
+        }));
+
+        let last: SplitProgress = *reports.last().unwrap();
+
+        assert_eq!(last.bytes_read, result.file_size);
+        assert_eq!(last.chunks_written, result.total_chunks);
+        assert_eq!(last.total_chunks, result.total_chunks);
+        assert_eq!(last.fraction(), 1.0);
+
+        // the calls are limited by the interval
+        let calls: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let counter: Arc<AtomicUsize> = calls.clone();
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&out_dir)
+            .chunk_size(1024 * 1024)
+            .on_progress(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+            .progress_interval(std::time::Duration::from_secs(3600))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rechunk() {
+        let (root, cache_dir, output_file, _) = setup("rechunk").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let asset: Vec<u8> = fs::read(&in_file).unwrap();
+
+        // chunks without a manifest
+        let out_dir: PathBuf = cache_dir.join("rechunked");
+
+        let result: RechunkResult =
+            rechunk(&cache_dir, 1536 * 1024, &out_dir).run().await.unwrap();
+
+        assert_eq!(result.file_size, asset.len());
+        assert_eq!(result.total_chunks, 4);
+        assert!(result.manifest.is_none());
+        assert_eq!(fs::metadata(out_dir.join("0")).unwrap().len(), 1536 * 1024);
+
+        Merge::new()
+            .in_dir(&out_dir)
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&output_file).unwrap(), asset);
+
+        // chunks with headers and a manifest
+        let headers_dir: PathBuf = cache_dir.join("headers");
+        let out_dir: PathBuf = cache_dir.join("rechunked_headers");
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&headers_dir)
+            .chunk_size(1024 * 1024)
+            .headers(true)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        let result: RechunkResult =
+            rechunk(&headers_dir, 700 * 1024, &out_dir).run().await.unwrap();
+
+        let manifest: Manifest = result.manifest.unwrap();
+
+        assert_eq!(manifest.total_chunks, 7);
+        assert_eq!(manifest.file_name.as_deref(), Some("test.png"));
+        assert!(!manifest.headers);
+
+        Merge::new()
+            .from_manifest(out_dir.join("manifest.json"))
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&output_file).unwrap(), asset);
+
+        // the input chunks cannot be overwritten
+        let error: io::Error =
+            rechunk(&cache_dir, 1024, &cache_dir).run().await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_split_in_files_and_unbundle() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_in_files");
+        let output_dir: PathBuf =
+            root.join(".media").join("output").join("split_in_files");
+
+        let asset: Vec<u8> =
+            fs::read(root.join("assets").join("test.png")).unwrap();
+
+        // files of different sizes, including an empty one
+        let files: Vec<(&str, &[u8])> = vec![
+            ("a.bin", &asset[..1000]),
+            ("empty.bin", &[]),
+            ("b.bin", &asset[1000..2_000_000]),
+            ("c.bin", &asset[2_000_000..]),
+        ];
+
+        let src_dir: PathBuf = cache_dir.join("src");
+        let chunks_dir: PathBuf = cache_dir.join("chunks");
+
+        fs::create_dir_all(&src_dir).unwrap();
+
+        for (name, data) in &files {
+            fs::write(src_dir.join(name), data).unwrap();
+        }
+
+        let result: SplitResult = Split::new()
+            .in_files(files.iter().map(|(name, _)| src_dir.join(name)))
+            .out_dir(&chunks_dir)
+            .chunk_size(512 * 1024)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(result.file_size, asset.len());
+        assert_eq!(result.manifest.file_name, None);
+        assert_eq!(result.manifest.sources.len(), 4);
+        assert_eq!(result.manifest.sources[2].offset, 1000);
+        assert_eq!(result.manifest.sources[2].size, 1_999_000);
+
+        // the sources survive the binary manifest
+        let bytes: Vec<u8> =
+            result.manifest.to_bytes(ManifestFormat::Binary).unwrap();
+
+        assert_eq!(Manifest::from_bytes(&bytes).unwrap(), result.manifest);
+
+        let bundle: PathBuf = output_dir.join("bundle");
+
+        Merge::new()
+            .from_manifest(chunks_dir.join("manifest.json"))
+            .out_file(&bundle)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&bundle).unwrap(), asset);
+
+        let paths: Vec<PathBuf> = result
+            .manifest
+            .unbundle(&bundle, output_dir.join("files"))
+            .await
+            .unwrap();
+
+        for ((name, data), path) in files.iter().zip(&paths) {
+            assert_eq!(path, &output_dir.join("files").join(name));
+            assert_eq!(fs::read(path).unwrap(), *data);
+        }
+
+        // the names must be unique
+        fs::create_dir_all(src_dir.join("other")).unwrap();
+        fs::write(src_dir.join("other").join("a.bin"), b"a").unwrap();
+
+        let error: io::Error = Split::new()
+            .in_files([
+                src_dir.join("a.bin"),
+                src_dir.join("other").join("a.bin"),
+            ])
+            .out_dir(&chunks_dir)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_merge_and_check_on_progress() {
+        let (_, cache_dir, output_file, result) =
+            setup("merge_and_check_on_progress").await;
+
+        let merges: Arc<Mutex<Vec<MergeProgress>>> =
+            Arc::new(Mutex::new(Vec::new()));
+
+        let recorder: Arc<Mutex<Vec<MergeProgress>>> = merges.clone();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .expect_file_size(result.file_size)
+            .on_progress(move |progress| {
+                recorder.lock().unwrap().push(progress)
+            })
+            .progress_interval(std::time::Duration::ZERO)
+            .run()
+            .await
+            .unwrap();
+
+        let merges: Vec<MergeProgress> = merges.lock().unwrap().clone();
+
+        assert_eq!(merges[0].bytes_written, 0);
+        assert_eq!(merges[0].total_bytes, Some(result.file_size));
+        assert_eq!(merges[0].total_chunks, result.total_chunks);
+
+        assert!(merges.windows(2).all(|pair| {
+            pair[0].bytes_written <= pair[1].bytes_written
+                && pair[0].chunks_merged <= pair[1].chunks_merged
+        }));
+
+        let last: MergeProgress = *merges.last().unwrap();
+
+        assert_eq!(last.bytes_written, result.file_size);
+        assert_eq!(last.chunks_merged, result.total_chunks);
+        assert_eq!(last.fraction(), 1.0);
+
+        let checks: Arc<Mutex<Vec<CheckProgress>>> =
+            Arc::new(Mutex::new(Vec::new()));
+
+        let recorder: Arc<Mutex<Vec<CheckProgress>>> = checks.clone();
+
+        let check: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(result.file_size)
+            .total_chunks(result.total_chunks)
+            .on_progress(move |progress| {
+                recorder.lock().unwrap().push(progress)
+            })
+            .progress_interval(std::time::Duration::ZERO)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(check.success);
+
+        let checks: Vec<CheckProgress> = checks.lock().unwrap().clone();
+
+        assert_eq!(
+            checks.iter().map(|p| p.chunks_inspected).collect::<Vec<_>>(),
+            (0..=result.total_chunks).collect::<Vec<_>>()
+        );
+        assert_eq!(checks.last().unwrap().fraction(), 1.0);
+
+        // the calls are limited by the interval
+        let calls: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let counter: Arc<AtomicUsize> = calls.clone();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .on_progress(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+            .progress_interval(std::time::Duration::from_secs(3600))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_events() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("run_with_events");
+        let output_file: PathBuf =
+            root.join(".media").join("output").join("run_with_events.png");
+
+        let counter: Arc<ProgressCounter> = Arc::new(ProgressCounter::new());
+
+        let (run, mut events) = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .progress(counter.clone())
+            .run_with_events();
+
+        let task = tokio::spawn(run);
+
+        let mut received: Vec<Event> = Vec::new();
+
+        while let Some(event) = events.recv().await {
+            received.push(event);
+        }
+
+        let result: SplitResult = task.await.unwrap().unwrap();
+
+        assert!(matches!(received[0], Event::SplitStarted { .. }));
+        assert_eq!(
+            received.last(),
+            Some(&Event::SplitFinished {
+                id: result.id,
+                total_chunks: result.total_chunks
+            })
+        );
+        assert_eq!(received.len(), result.total_chunks + 2);
+
+        // the sink set before receives the events as well
+        assert!(counter.is_finished());
+        assert_eq!(counter.chunks(), result.total_chunks);
+
+        // as a stream
+        let (run, mut stream): (_, EventStream) = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .run_with_events();
+
+        let task = tokio::spawn(run);
+
+        let mut merged: usize = 0;
+
+        while let Some(event) =
+            std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            if let Event::ChunkMerged { .. } = event {
+                merged += 1;
+            }
+        }
+
+        assert!(!task.await.unwrap().unwrap().skipped);
+        assert_eq!(merged, result.total_chunks);
+
+        let (run, mut events) = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(result.file_size)
+            .total_chunks(result.total_chunks)
+            .run_with_events();
+
+        let (check, event) = tokio::join!(run, events.recv());
+
+        assert!(check.unwrap().success);
+        assert_eq!(event, Some(Event::CheckPassed));
+    }
+
+    #[tokio::test]
+    async fn test_extract_member() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("extract_member");
+        let output_dir: PathBuf =
+            root.join(".media").join("output").join("extract_member");
+
+        let asset: Vec<u8> =
+            fs::read(root.join("assets").join("test.png")).unwrap();
+
+        let files: Vec<(&str, &[u8])> = vec![
+            ("a.bin", &asset[..3_000_000]),
+            ("empty.bin", &[]),
+            ("b.bin", &asset[3_000_000..3_000_100]),
+            ("c.bin", &asset[3_000_100..]),
+        ];
+
+        let src_dir: PathBuf = cache_dir.join("src");
+        let chunks_dir: PathBuf = cache_dir.join("chunks");
+
+        fs::create_dir_all(&src_dir).unwrap();
+
+        for (name, data) in &files {
+            fs::write(src_dir.join(name), data).unwrap();
+        }
+
+        let result: SplitResult = Split::new()
+            .in_files(files.iter().map(|(name, _)| src_dir.join(name)))
+            .out_dir(&chunks_dir)
+            .chunk_size(1024 * 1024)
+            .headers(true)
+            .manifest(true)
+            .run()
+            .await
+            .unwrap();
+
+        for (name, data) in &files {
+            let out_file: PathBuf = output_dir.join(name);
+
+            result
+                .manifest
+                .extract_member(&chunks_dir, name, &out_file)
+                .await
+                .unwrap();
+
+            assert_eq!(fs::read(&out_file).unwrap(), *data);
+        }
+
+        // only the chunks overlapping the source are read
+        fs::remove_file(chunks_dir.join("0")).unwrap();
+
+        result
+            .manifest
+            .extract_member(&chunks_dir, "c.bin", output_dir.join("c.bin"))
+            .await
+            .unwrap();
+
+        let error: io::Error = result
+            .manifest
+            .extract_member(&chunks_dir, "d.bin", output_dir.join("d.bin"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_handle() {
+        let (_, cache_dir, output_file, result) =
+            setup("run_with_handle").await;
+
+        let (run, handle) = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .max_buffer_capacity(1024 * 1024)
+            .run_with_handle();
+
+        // paused after the first chunk until resumed
+        handle.pause();
+
+        let task = tokio::spawn(run);
+
+        while handle.progress() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        assert!(!task.is_finished());
+        assert_eq!(handle.progress(), 1024 * 1024);
+
+        handle.resume();
+
+        assert!(!task.await.unwrap().unwrap().skipped);
+        assert_eq!(handle.progress(), result.file_size);
+
+        // the handle set before is returned
+        let set: OperationHandle = OperationHandle::new();
+
+        let (run, handle) = Split::new()
+            .in_file(
+                env::current_dir().unwrap().join("assets").join("test.png"),
+            )
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .handle(set.clone())
+            .run_with_handle();
+
+        handle.pause();
+
+        let task = tokio::spawn(run);
+
+        while set.progress() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        set.cancel();
+
+        let err: io::Error = task.await.unwrap().unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(handle.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_probes() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let dir: PathBuf = root.join(".media").join("cache").join("probes");
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let probes: Probes = Probes::new().dir(&dir).max_backlog(2);
+
+        assert!(probes.health().ok);
+
+        let report: ProbeReport = probes.readiness(2).await;
+
+        assert!(report.ok);
+        assert_eq!(report.status_code(), 200);
+        assert_eq!(report.checks.len(), 2);
+
+        // the probe file is removed
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let report: ProbeReport = probes.readiness(3).await;
+
+        assert!(!report.ok);
+        assert_eq!(report.status_code(), 503);
+        assert_eq!(report.checks[1].name, "backlog");
+
+        // not writable
+        let report: ProbeReport =
+            Probes::new().dir(dir.join("missing")).readiness(0).await;
+
+        assert!(!report.ok);
+        assert!(report.checks[0].detail.is_some());
+
+        // free space
+        if let Some(space) = available_space(&dir) {
+            let report: ProbeReport = Probes::new()
+                .dir(&dir)
+                .min_free_space(space.saturating_mul(2).max(1))
+                .readiness(0)
+                .await;
+
+            assert!(!report.ok);
+        }
+
+        // HTTP adapter
+        let (code, body): (u16, String) =
+            probes.respond("/readyz", 3).await.unwrap();
+
+        assert_eq!(code, 503);
+        assert!(body.contains("\"backlog\""));
+        assert_eq!(probes.respond("/healthz", 3).await.unwrap().0, 200);
+        assert!(probes.respond("/other", 0).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_result_metrics() {
+        let (_, cache_dir, output_file, result) = setup("result_metrics").await;
+
+        assert!(result.elapsed > std::time::Duration::ZERO);
+        assert!(result.throughput() > 0.0);
+
+        let merge = || {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_file)
+                .skip_if_matching(result.file_size)
+                .run()
+        };
+
+        let _ = fs::remove_file(&output_file);
+
+        let merged: MergeResult = merge().await.unwrap();
+
+        assert_eq!(merged.out_file, output_file);
+        assert_eq!(merged.file_size, result.file_size);
+        assert_eq!(merged.bytes_written, result.file_size);
+        assert!(!merged.skipped);
+        assert!(merged.throughput() > 0.0);
+
+        // nothing is written once skipped
+        let skipped: MergeResult = merge().await.unwrap();
+
+        assert_eq!(skipped.file_size, result.file_size);
+        assert_eq!(skipped.bytes_written, 0);
+        assert!(skipped.skipped);
+        assert_eq!(skipped.throughput(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_merge_on_collision() {
+        let (root, cache_dir, output_file, _) =
+            setup("merge_on_collision").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let file_size: usize = fs::metadata(&in_file).unwrap().len() as usize;
+        let digest: String =
+            Algorithm::Sha256.digest(&fs::read(&in_file).unwrap());
+
+        // the size and checksum tell whether an existing file holds the output
+        let merge = |collision: Collision| {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_file)
+                .expect_file_size(file_size)
+                .expect_hash(Algorithm::Sha256, &digest)
+                .on_collision(collision)
+                .run()
+        };
+
+        let kept = |name: &str| output_file.with_file_name(name);
+
+        for name in ["test (1).png", "test (2).png", "test (1) copy 1.png"] {
+            let _ = fs::remove_file(kept(name));
+        }
+
+        fs::create_dir_all(output_file.parent().unwrap()).unwrap();
+        fs::write(&output_file, b"user data").unwrap();
+
+        // the existing file is kept
+        let error: io::Error = merge(Collision::Fail).await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&output_file).unwrap(), b"user data");
+
+        // both are kept under a free name
+        let suffix: String = " ({n})".to_string();
+
+        let first: MergeResult =
+            merge(Collision::KeepBoth { suffix: suffix.clone() })
+                .await
+                .unwrap();
+
+        assert!(!first.skipped);
+        assert_eq!(first.out_file, kept("test (1).png"));
+        assert_eq!(fs::read(&output_file).unwrap(), b"user data");
+        assert_eq!(
+            fs::read(&first.out_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // the kept file already holds the output
+        let second: MergeResult =
+            merge(Collision::KeepBoth { suffix: suffix.clone() })
+                .await
+                .unwrap();
+
+        assert!(second.skipped);
+        assert_eq!(second.out_file, kept("test (1).png"));
+        assert!(!kept("test (2).png").exists());
+
+        // the kept file differs, so the next number is taken
+        fs::write(kept("test (1).png"), b"edited").unwrap();
+
+        let third: MergeResult =
+            merge(Collision::KeepBoth { suffix }).await.unwrap();
+
+        assert!(!third.skipped);
+        assert_eq!(third.out_file, kept("test (2).png"));
+        assert_eq!(fs::read(kept("test (1).png")).unwrap(), b"edited");
+        assert_eq!(
+            fs::read(&third.out_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // a matching size alone does not tell the files apart
+        fs::write(kept("test (1).png"), vec![0; file_size]).unwrap();
+        fs::remove_file(kept("test (2).png")).unwrap();
+
+        let sized: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(kept("test (1).png"))
+            .expect_file_size(file_size)
+            .on_collision(Collision::KeepBoth { suffix: " copy".into() })
+            .run()
+            .await
+            .unwrap();
+
+        assert!(!sized.skipped);
+        assert_eq!(sized.out_file, kept("test (1) copy 1.png"));
+        assert_eq!(fs::read(kept("test (1).png")).unwrap(), vec![0; file_size]);
+        assert_eq!(
+            fs::read(&sized.out_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        fs::remove_file(&sized.out_file).unwrap();
+
+        // the existing file is replaced
+        merge(Collision::Overwrite).await.unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_plan_chunks() {
+        let root: PathBuf = env::current_dir().unwrap();
+        let input_dir: PathBuf =
+            root.join(".media").join("input").join("split_plan_chunks");
+        let input_path: PathBuf = input_dir.join("records.jsonl");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("split_plan_chunks");
+
+        let records: String =
+            (0..100).map(|i| format!("{{\"id\":{}}}\n", i)).collect();
+
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::write(&input_path, &records).unwrap();
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let split = || {
+            Split::new()
+                .in_file(&input_path)
+                .out_dir(&cache_dir)
+                .chunk_size(64)
+                .mode(SplitMode::RecordAligned { delimiter: b'\n' })
+                .transform(Xor(0x5A))
+                .headers(true)
+                .volume_size(512)
+                .zero_padding(true)
+        };
+
+        let plan: SplitPlan = split().plan().await.unwrap();
+
+        // nothing is written
+        assert!(!cache_dir.exists());
+
+        // more chunks than the minimum as the records are kept whole
+        assert!(plan.total_chunks > records.len().div_ceil(64));
+        assert_eq!(plan.chunks.len(), plan.total_chunks);
+        assert_eq!(plan.chunks.last().unwrap().range.end, records.len());
+
+        let result: SplitResult = split().run().await.unwrap();
+
+        assert_eq!(plan.total_chunks, result.total_chunks);
+
+        for (planned, chunk) in plan.chunks.iter().zip(&result.manifest.chunks)
+        {
+            assert_eq!(planned.index, chunk.index);
+            assert_eq!(
+                Some(planned.range.clone()),
+                result.range_of(chunk.index)
+            );
+            assert_eq!(planned.volume, chunk.volume);
+            assert!(result.out_dir.join(&planned.path).is_file());
+        }
+
+        assert!(plan.chunks.last().unwrap().volume > Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_merge_normalization() {
+        let (root, cache_dir, _, _) = setup("merge_normalization").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+        let out_dir: PathBuf =
+            root.join(".media").join("output").join("merge_normalization");
+
+        let _ = fs::remove_dir_all(&out_dir);
+        fs::create_dir_all(&out_dir).unwrap();
+
+        let nfc: &str = "caf\u{e9}.png";
+        let nfd: &str = "cafe\u{301}.png";
+
+        assert_eq!(Normalization::Nfd.normalize(nfc), nfd);
+        assert_eq!(Normalization::Nfc.normalize(nfd), nfc);
+
+        let merge = |name: &str, normalization: Normalization| {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(out_dir.join(name))
+                .normalization(normalization)
+                .run()
+        };
+
+        // the name is normalized
+        let result: MergeResult = merge(nfc, Normalization::Nfd).await.unwrap();
+
+        assert_eq!(result.out_file, out_dir.join(nfd));
+
+        // the existing file is taken as the output file whatever the form
+        let result: MergeResult = merge(nfc, Normalization::Nfc).await.unwrap();
+
+        assert_eq!(result.out_file, out_dir.join(nfd));
+        assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 1);
+        assert_eq!(
+            fs::read(&result.out_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_require_free_space() {
+        let (root, cache_dir, output_file, _) =
+            setup("require_free_space").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+
+        let _ = fs::remove_file(&output_file);
+
+        // enough space
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .require_free_space(true)
+            .run()
+            .await
+            .unwrap();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .require_free_space(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // the space is unknown on systems other than Linux
+        if available_space(&cache_dir).is_none() {
+            return;
+        }
+
+        fs::remove_file(&output_file).unwrap();
+
+        let error: io::Error = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_file)
+            .expect_file_size(usize::MAX / 2)
+            .require_free_space(true)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+        assert!(!output_file.exists());
+    }
+
+    #[tokio::test]
+    async fn test_windows_attributes_and_streams() {
+        let (root, cache_dir, _, result) =
+            setup("windows_attributes_and_streams").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+
+        // recorded in both formats
+        let metadata: FileMetadata = FileMetadata {
+            attributes: Some(0x2 & WINDOWS_ATTRIBUTES),
+            streams: [("Zone.Identifier".to_string(), "5b5a6f6e655d".into())]
+                .into(),
+            ..Default::default()
+        };
+
+        let manifest: Manifest =
+            Manifest { file_metadata: Some(metadata), ..result.manifest };
+
+        for format in [ManifestFormat::Json, ManifestFormat::Binary] {
+            let data: Vec<u8> = manifest.to_bytes(format).unwrap();
+
+            assert_eq!(Manifest::from_bytes(&data).unwrap(), manifest);
+        }
+
+        // the streams are only supported on Windows
+        #[cfg(not(windows))]
+        {
+            let error: io::Error = Split::new()
+                .in_file(&in_file)
+                .out_dir(&cache_dir)
+                .chunk_size(1024 * 1024)
+                .preserve_streams(true)
+                .run()
+                .await
+                .unwrap_err();
+
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+
+            let copy: PathBuf = cache_dir.join("copy.png");
+
+            fs::copy(&in_file, &copy).unwrap();
+
+            let error: io::Error =
+                manifest.file_metadata.unwrap().apply(&copy).await.unwrap_err();
+
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_concurrency() {
+        let (root, cache_dir, output_file, _) =
+            setup("split_concurrency").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+
+        let split = |dir: PathBuf, concurrency: usize| {
+            Split::new()
+                .in_file(&in_file)
+                .out_dir(dir)
+                .chunk_size(1024 * 1024)
+                .checksum(Algorithm::Sha256)
+                .headers(true)
+                .concurrency(concurrency)
+                .run()
+        };
+
+        let sequential: SplitResult =
+            split(cache_dir.join("sequential"), 1).await.unwrap();
+
+        let parallel: SplitResult =
+            split(cache_dir.join("parallel"), 4).await.unwrap();
+
+        assert_eq!(parallel.total_chunks, sequential.total_chunks);
+        assert_eq!(parallel.manifest.chunks, sequential.manifest.chunks);
+
+        // the headers differ only in the identity of the split
+        for index in 0..parallel.total_chunks {
+            let name: String = index.to_string();
+
+            let header: ChunkHeader =
+                ChunkHeader::read(cache_dir.join("parallel").join(&name))
+                    .await
+                    .unwrap()
+                    .unwrap();
+
+            assert_eq!(header.index, index);
+            assert_eq!(header.total_chunks, parallel.total_chunks);
+            assert_eq!(header.split_id, parallel.id);
+
+            assert_eq!(
+                fs::read(cache_dir.join("parallel").join(&name)).unwrap()
+                    [CHUNK_HEADER_SIZE..],
+                fs::read(cache_dir.join("sequential").join(&name)).unwrap()
+                    [CHUNK_HEADER_SIZE..]
+            );
+        }
+
+        let _ = fs::remove_file(&output_file);
+
+        Merge::new()
+            .in_dir(cache_dir.join("parallel"))
+            .out_file(&output_file)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_direct_io() {
+        let (root, cache_dir, output_file, _) = setup("direct_io").await;
+
+        let in_file: PathBuf = root.join("assets").join("test.png");
+
+        assert!(!is_block_device(&in_file));
+
+        // the chunks are read at their ranges
+        let error: io::Error = Split::new()
+            .in_file(&in_file)
+            .out_dir(cache_dir.join("unaligned"))
+            .chunk_size(1000 * 1000)
+            .direct_io(true)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let error: io::Error = Split::new()
+            .in_file(&in_file)
+            .out_dir(cache_dir.join("volumes"))
+            .chunk_size(1024 * 1024)
+            .volume_size(2 * 1024 * 1024)
+            .direct_io(true)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let result: io::Result<SplitResult> = Split::new()
+            .in_file(&in_file)
+            .out_dir(cache_dir.join("direct"))
+            .chunk_size(1024 * 1024)
+            .checksum(Algorithm::Sha256)
+            .direct_io(true)
+            .run()
+            .await;
+
+        // direct I/O is only supported on Linux
+        if cfg!(not(target_os = "linux")) {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+            return;
+        }
+
+        assert_eq!(result.unwrap().total_chunks, 5);
+
+        let _ = fs::remove_file(&output_file);
+
+        // the buffer is rounded up to the alignment
+        Merge::new()
+            .in_dir(cache_dir.join("direct"))
+            .out_file(&output_file)
+            .max_buffer_capacity(1000 * 1000)
+            .direct_io(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_file).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
 }
@@ -0,0 +1,173 @@
+//! The allocator of this binary records the largest allocation of the
+//! whole process, so the test runs alone instead of with the others.
+
+use std::{
+    alloc::{self, GlobalAlloc, System},
+    env, fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use filego::{
+    checksum::Algorithm,
+    merge::Merge,
+    split::{Split, SplitResult},
+    store::DirStore,
+    transform::ChunkTransform,
+    tune::MemoryBudget,
+};
+
+#[derive(Debug)]
+struct Xor(u8);
+
+impl ChunkTransform for Xor {
+    fn id(&self) -> &str {
+        "xor"
+    }
+
+    fn encode(
+        &self,
+        _index: usize,
+        chunk: Vec<u8>,
+    ) -> io::Result<Vec<u8>> {
+        Ok(chunk.into_iter().map(|b| b ^ self.0).collect())
+    }
+
+    fn decode(
+        &self,
+        index: usize,
+        chunk: Vec<u8>,
+    ) -> io::Result<Vec<u8>> {
+        self.encode(index, chunk)
+    }
+}
+
+/// Size of the largest allocation since reset, so a buffer of a whole
+/// chunk is detected, which holds as the only test of this binary.
+static LARGEST_ALLOCATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Allocator recording the [`LARGEST_ALLOCATION`].
+struct Recording;
+
+unsafe impl GlobalAlloc for Recording {
+    unsafe fn alloc(
+        &self,
+        layout: alloc::Layout,
+    ) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(
+        &self,
+        layout: alloc::Layout,
+    ) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(layout.size(), Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: alloc::Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(
+        &self,
+        ptr: *mut u8,
+        layout: alloc::Layout,
+    ) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Recording = Recording;
+
+#[tokio::test]
+async fn test_split_and_merge_stream_large_chunks() {
+    const FILE_SIZE: usize = 96 * 1024 * 1024;
+    const CHUNK_SIZE: usize = 64 * 1024 * 1024;
+    const BUFFER_CAPACITY: usize = 1024 * 1024;
+    const ALLOCATION_MAX: usize = 16 * 1024 * 1024;
+
+    let root: PathBuf = env::current_dir().unwrap();
+    let cache_dir: PathBuf =
+        root.join(".media").join("cache").join("stream_large_chunks");
+    let output_dir: PathBuf =
+        root.join(".media").join("output").join("stream_large_chunks");
+    let in_file: PathBuf = output_dir.join("sparse.bin");
+    let output_file: PathBuf = output_dir.join("merged.bin");
+
+    let _ = fs::remove_dir_all(&cache_dir);
+    let _ = fs::remove_dir_all(&output_dir);
+
+    fs::create_dir_all(&output_dir).unwrap();
+
+    // sparse, so it takes no space on the disk
+    fs::File::create(&in_file).unwrap().set_len(FILE_SIZE as u64).unwrap();
+
+    let split = |dir: &str| {
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(cache_dir.join(dir))
+            .chunk_size(CHUNK_SIZE)
+            .max_buffer_capacity(BUFFER_CAPACITY)
+            .headers(true)
+            .checksum(Algorithm::Crc32c)
+            .memory_budget(MemoryBudget::new(4 * 1024 * 1024))
+    };
+
+    LARGEST_ALLOCATION.store(0, Ordering::SeqCst);
+
+    let split_result: SplitResult =
+        split("sequential").file_hash(Algorithm::Blake3).run().await.unwrap();
+
+    let parallel: SplitResult =
+        split("parallel").concurrency(2).run().await.unwrap();
+
+    Merge::from_store(Arc::new(DirStore::with_manifest(
+        cache_dir.join("sequential"),
+        &split_result.manifest,
+    )))
+    .total_chunks(split_result.total_chunks)
+    .out_file(&output_file)
+    .max_buffer_capacity(BUFFER_CAPACITY)
+    .expect_file_size(FILE_SIZE)
+    .expect_hash(Algorithm::Blake3, split_result.file_hash.clone().unwrap())
+    .memory_budget(MemoryBudget::new(4 * 1024 * 1024))
+    .run()
+    .await
+    .unwrap();
+
+    let largest: usize = LARGEST_ALLOCATION.load(Ordering::SeqCst);
+
+    assert_eq!(fs::metadata(&output_file).unwrap().len(), FILE_SIZE as u64);
+    assert_eq!(split_result.total_chunks, 2);
+    assert_eq!(parallel.manifest.chunks, split_result.manifest.chunks);
+    assert_eq!(
+        split_result.manifest.chunks[0].get_metadata("crc32c"),
+        Some(Algorithm::Crc32c.digest(&vec![0; CHUNK_SIZE]).as_str())
+    );
+
+    // chunks held in full larger than the whole budget
+    let error: io::Error =
+        split("transformed").transform(Xor(0x5A)).run().await.unwrap_err();
+
+    let _ = fs::remove_dir_all(&cache_dir);
+    let _ = fs::remove_dir_all(&output_dir);
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(
+        largest < ALLOCATION_MAX,
+        "largest allocation of {} bytes",
+        largest
+    );
+}
@@ -0,0 +1,650 @@
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fs::Metadata,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use filego::{
+    health::Probes,
+    session::{Session, SessionStatus},
+    split::Split,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    parse_size,
+    systemd::{self, Notifier},
+};
+
+/// Configuration of the daemon, read from a JSON file.
+///
+/// ```json
+/// {
+///     "interval_ms": 1000,
+///     "status": "127.0.0.1:7070",
+///     "inboxes": [
+///         {
+///             "action": "split",
+///             "inbox": "/srv/in",
+///             "out_dir": "/srv/chunks",
+///             "chunk_size": "8MiB"
+///         },
+///         {
+///             "action": "merge",
+///             "inbox": "/srv/received",
+///             "out_dir": "/srv/out"
+///         }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    /// Interval between the scans of the inboxes in milliseconds.
+    #[serde(default = "interval_default")]
+    interval_ms: u64,
+    /// Address of the status endpoint, if any.
+    #[serde(default)]
+    status: Option<SocketAddr>,
+    /// Minimum space available on the file systems of the output
+    /// directories to be ready, e.g. `1GiB`.
+    #[serde(default)]
+    min_free_space: Option<String>,
+    /// Maximum number of entries pending to be ready.
+    #[serde(default)]
+    max_backlog: Option<usize>,
+    /// Rules of the inboxes.
+    inboxes: Vec<Rule>,
+}
+
+fn interval_default() -> u64 {
+    1000
+}
+
+impl Config {
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+
+    /// Create the probes of the output directories.
+    fn probes(&self) -> io::Result<Probes> {
+        let mut probes: Probes = self
+            .inboxes
+            .iter()
+            .fold(Probes::new(), |probes, rule| probes.dir(rule.out_dir()));
+
+        if let Some(size) = &self.min_free_space {
+            probes =
+                probes.min_free_space(parse_size(size).map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidInput, e)
+                })?);
+        }
+
+        if let Some(backlog) = self.max_backlog {
+            probes = probes.max_backlog(backlog);
+        }
+
+        Ok(probes)
+    }
+}
+
+/// Rule of an inbox directory.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+enum Rule {
+    /// Split each new file in the inbox into a chunk directory named after
+    /// the file in `out_dir`.
+    Split {
+        inbox: PathBuf,
+        out_dir: PathBuf,
+        /// Size of the chunks, e.g. `8MiB`.
+        #[serde(default)]
+        chunk_size: Option<String>,
+        /// Prefix each chunk with a header.
+        #[serde(default)]
+        headers: bool,
+    },
+    /// Merge each complete chunk directory in the inbox into `out_dir`,
+    /// named after the original file.
+    Merge { inbox: PathBuf, out_dir: PathBuf },
+}
+
+impl Rule {
+    fn inbox(&self) -> &Path {
+        match self {
+            | Self::Split { inbox, .. } | Self::Merge { inbox, .. } => inbox,
+        }
+    }
+
+    fn out_dir(&self) -> &Path {
+        match self {
+            | Self::Split { out_dir, .. } | Self::Merge { out_dir, .. } => {
+                out_dir
+            },
+        }
+    }
+
+    fn action(&self) -> &'static str {
+        match self {
+            | Self::Split { .. } => "split",
+            | Self::Merge { .. } => "merge",
+        }
+    }
+}
+
+/// Status of an inbox, served by the status endpoint.
+#[derive(Debug)]
+struct InboxStatus {
+    inbox: PathBuf,
+    action: &'static str,
+    /// Entries processed since the start.
+    processed: usize,
+    /// Entries failed since the start.
+    failed: usize,
+    /// Entries waiting to settle or to be complete at the last scan.
+    pending: usize,
+    /// The last error, if any.
+    last_error: Option<String>,
+}
+
+/// Status of the daemon, shared with the status endpoint.
+#[derive(Debug)]
+struct Status {
+    started: SystemTime,
+    scans: usize,
+    inboxes: Vec<InboxStatus>,
+}
+
+impl Status {
+    /// Get the number of entries pending at the last scan.
+    fn backlog(&self) -> usize {
+        self.inboxes.iter().map(|inbox| inbox.pending).sum()
+    }
+
+    /// Get the summary of the status, e.g. for the service manager.
+    fn summary(&self) -> String {
+        let (processed, failed, pending) =
+            self.inboxes.iter().fold((0, 0, 0), |(a, b, c), inbox| {
+                (a + inbox.processed, b + inbox.failed, c + inbox.pending)
+            });
+
+        format!(
+            "{} processed, {} failed, {} pending after {} scans",
+            processed, failed, pending, self.scans
+        )
+    }
+}
+
+/// Size and modification time of an entry, to tell whether it changed.
+type Stamp = (u64, Option<SystemTime>);
+
+fn stamp(metadata: &Metadata) -> Stamp {
+    (metadata.len(), metadata.modified().ok())
+}
+
+/// Daemon watching the inboxes of the config.
+struct Daemon {
+    config: Config,
+    status: Arc<Mutex<Status>>,
+    /// Stamps of the files at the previous scan, to split a file only once
+    /// it is no longer written to.
+    seen: HashMap<PathBuf, Stamp>,
+    /// Stamps of the entries failed, to retry an entry only once it changed.
+    failed: HashMap<PathBuf, Stamp>,
+}
+
+/// Run the daemon with the config at `config`, scanning the inboxes until
+/// stopped, or once if `once`, returning whether nothing failed.
+///
+/// The state of the daemon is the directories themselves: a file is split
+/// once into a directory named after it, and a chunk directory is merged
+/// once into a file of the same size as the original file. Without `once`,
+/// a file is split only once its size and modification time did not change
+/// between two scans, so files still being written are not split.
+pub async fn run(
+    config: PathBuf,
+    once: bool,
+) -> io::Result<bool> {
+    let config: Config = serde_json::from_slice(&fs::read(&config).await?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    for rule in &config.inboxes {
+        fs::create_dir_all(rule.inbox()).await?;
+        fs::create_dir_all(rule.out_dir()).await?;
+    }
+
+    let status: Arc<Mutex<Status>> = Arc::new(Mutex::new(Status {
+        started: SystemTime::now(),
+        scans: 0,
+        inboxes: config
+            .inboxes
+            .iter()
+            .map(|rule| InboxStatus {
+                inbox: rule.inbox().to_path_buf(),
+                action: rule.action(),
+                processed: 0,
+                failed: 0,
+                pending: 0,
+                last_error: None,
+            })
+            .collect(),
+    }));
+
+    // the socket passed by systemd takes precedence over the address, and
+    // is left untouched when scanning once
+    let listener: Option<TcpListener> = match once {
+        | true => None,
+        | false => match (systemd::listener()?, config.status) {
+            | (Some(listener), _) => Some(TcpListener::from_std(listener)?),
+            | (None, Some(addr)) => Some(TcpListener::bind(addr).await?),
+            | (None, None) => None,
+        },
+    };
+
+    if let Some(listener) = listener {
+        eprintln!("status: http://{}/status", listener.local_addr()?);
+
+        tokio::spawn(serve(listener, status.clone(), config.probes()?));
+    }
+
+    let notifier: Arc<Notifier> = Arc::new(Notifier::from_env());
+
+    notifier.notify("READY=1");
+
+    // the watchdog is pinged by its own task, which runs whenever a scan
+    // awaits, so a long split or merge does not exceed its timeout
+    if let Some(watchdog) = notifier.watchdog().filter(|_| !once) {
+        tokio::spawn(ping(notifier.clone(), watchdog));
+    }
+
+    let interval: Duration = config.interval();
+
+    let mut daemon: Daemon =
+        Daemon { config, status, seen: HashMap::new(), failed: HashMap::new() };
+
+    loop {
+        daemon.scan(once).await;
+
+        if once {
+            let status: MutexGuard<Status> = daemon.status.lock().unwrap();
+
+            return Ok(status.inboxes.iter().all(|i| i.last_error.is_none()));
+        }
+
+        notifier.notify(&format!(
+            "STATUS={}",
+            daemon.status.lock().unwrap().summary()
+        ));
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Ping the watchdog of the service manager at the `interval`.
+async fn ping(
+    notifier: Arc<Notifier>,
+    interval: Duration,
+) {
+    let mut ticks: tokio::time::Interval = tokio::time::interval(interval);
+
+    loop {
+        ticks.tick().await;
+
+        notifier.notify("WATCHDOG=1");
+    }
+}
+
+impl Daemon {
+    /// Scan each inbox once, recording the errors in the status.
+    async fn scan(
+        &mut self,
+        once: bool,
+    ) {
+        for i in 0..self.config.inboxes.len() {
+            let result: io::Result<usize> = match &self.config.inboxes[i] {
+                | Rule::Split { inbox, out_dir, chunk_size, headers } => {
+                    let split: io::Result<Split> =
+                        split_of(chunk_size.as_deref(), *headers);
+
+                    match split {
+                        | Ok(split) => {
+                            let (inbox, out_dir): (PathBuf, PathBuf) =
+                                (inbox.clone(), out_dir.clone());
+
+                            self.scan_split(i, &inbox, &out_dir, split, once)
+                                .await
+                        },
+                        | Err(e) => Err(e),
+                    }
+                },
+                | Rule::Merge { inbox, out_dir } => {
+                    let (inbox, out_dir): (PathBuf, PathBuf) =
+                        (inbox.clone(), out_dir.clone());
+
+                    self.scan_merge(i, &inbox, &out_dir).await
+                },
+            };
+
+            let mut status: MutexGuard<Status> = self.status.lock().unwrap();
+
+            match result {
+                | Ok(pending) => status.inboxes[i].pending = pending,
+                | Err(e) => status.inboxes[i].last_error = Some(e.to_string()),
+            }
+        }
+
+        self.status.lock().unwrap().scans += 1;
+    }
+
+    /// Split the new files in the inbox, returning the number of files
+    /// waiting to settle.
+    async fn scan_split(
+        &mut self,
+        i: usize,
+        inbox: &Path,
+        out_dir: &Path,
+        split: Split,
+        once: bool,
+    ) -> io::Result<usize> {
+        let mut pending: usize = 0;
+
+        let mut entries: fs::ReadDir = fs::read_dir(inbox).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path: PathBuf = entry.path();
+
+            let metadata: Metadata = entry.metadata().await?;
+
+            let name: String = match entry.file_name().into_string() {
+                | Ok(name) if metadata.is_file() && !name.starts_with('.') => {
+                    name
+                },
+                | _ => continue,
+            };
+
+            let dir: PathBuf = out_dir.join(&name);
+
+            let stamp: Stamp = stamp(&metadata);
+
+            if self.failed.get(&path) == Some(&stamp) {
+                continue;
+            }
+
+            // an entry failing does not stop the scan of the others
+            match Session::open(&dir).await {
+                | Ok(session) if session.manifest().is_some() => continue,
+                | Ok(_) => {},
+                | Err(e) => {
+                    self.record(i, path, stamp, Err(e));
+                    continue;
+                },
+            }
+
+            // files still being written are split at a later scan
+            if !once && self.seen.insert(path.clone(), stamp) != Some(stamp) {
+                pending += 1;
+                continue;
+            }
+
+            self.seen.remove(&path);
+
+            let result: io::Result<()> = match Session::open(&dir).await {
+                | Ok(mut session) => session
+                    .split(split.clone().in_file(&path))
+                    .await
+                    .map(|_| ()),
+                | Err(e) => Err(e),
+            };
+
+            self.record(i, path, stamp, result);
+        }
+
+        Ok(pending)
+    }
+
+    /// Merge the complete chunk directories in the inbox, returning the
+    /// number of directories not complete yet.
+    async fn scan_merge(
+        &mut self,
+        i: usize,
+        inbox: &Path,
+        out_dir: &Path,
+    ) -> io::Result<usize> {
+        let mut pending: usize = 0;
+
+        let mut entries: fs::ReadDir = fs::read_dir(inbox).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let dir: PathBuf = entry.path();
+
+            let metadata: Metadata = entry.metadata().await?;
+
+            let name: String = match entry.file_name().into_string() {
+                | Ok(name) if metadata.is_dir() && !name.starts_with('.') => {
+                    name
+                },
+                | _ => continue,
+            };
+
+            // an entry failing does not stop the scan of the others
+            let failed: Stamp = stamp(&metadata);
+
+            if self.failed.get(&dir) == Some(&failed) {
+                continue;
+            }
+
+            let session: Session = match Session::open(&dir).await {
+                | Ok(session) => session,
+                | Err(e) => {
+                    self.record(i, dir, failed, Err(e));
+                    continue;
+                },
+            };
+
+            // the name in the manifest must not escape the output directory
+            let (file_name, file_size): (OsString, usize) =
+                match session.manifest() {
+                    | Some(manifest) => (
+                        manifest
+                            .file_name
+                            .as_deref()
+                            .and_then(|name| Path::new(name).file_name())
+                            .map_or(name.into(), OsStr::to_os_string),
+                        manifest.file_size,
+                    ),
+                    | None => continue,
+                };
+
+            let out_file: PathBuf = out_dir.join(file_name);
+
+            if let Ok(output) = fs::metadata(&out_file).await {
+                if output.len() == file_size as u64 {
+                    continue;
+                }
+            }
+
+            let status: SessionStatus = match session.status().await {
+                | Ok(status) => status,
+                | Err(e) => {
+                    self.record(i, dir, failed, Err(e));
+                    continue;
+                },
+            };
+
+            if !status.is_complete() {
+                pending += 1;
+                continue;
+            }
+
+            let stamp: Stamp =
+                (status.received_size as u64, metadata.modified().ok());
+
+            if self.failed.get(&dir) == Some(&stamp) {
+                continue;
+            }
+
+            let result: io::Result<()> = session.finalize(&out_file).await;
+
+            self.record(i, dir, stamp, result);
+        }
+
+        Ok(pending)
+    }
+
+    /// Record the result of an entry in the status.
+    fn record(
+        &mut self,
+        i: usize,
+        path: PathBuf,
+        stamp: Stamp,
+        result: io::Result<()>,
+    ) {
+        let mut status: MutexGuard<Status> = self.status.lock().unwrap();
+
+        let inbox: &mut InboxStatus = &mut status.inboxes[i];
+
+        match result {
+            | Ok(()) => {
+                eprintln!("{}: {}", inbox.action, path.display());
+
+                self.failed.remove(&path);
+                inbox.processed += 1;
+            },
+            | Err(e) => {
+                eprintln!("error: {}: {}", path.display(), e);
+
+                inbox.failed += 1;
+                inbox.last_error = Some(format!("{}: {}", path.display(), e));
+                self.failed.insert(path, stamp);
+            },
+        }
+    }
+}
+
+/// Create the split process of a rule.
+fn split_of(
+    chunk_size: Option<&str>,
+    headers: bool,
+) -> io::Result<Split> {
+    let split: Split = Split::new().headers(headers);
+
+    match chunk_size {
+        | Some(size) => parse_size(size)
+            .map(|size| split.chunk_size(size))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)),
+        | None => Ok(split),
+    }
+}
+
+/// Serve the status as JSON at `/status` until the daemon stops.
+async fn serve(
+    listener: TcpListener,
+    status: Arc<Mutex<Status>>,
+    probes: Probes,
+) {
+    let probes: Arc<Probes> = Arc::new(probes);
+
+    loop {
+        let stream: TcpStream = match listener.accept().await {
+            | Ok((stream, _)) => stream,
+            | Err(e) => {
+                eprintln!("error: status: {}", e);
+                continue;
+            },
+        };
+
+        let status: Arc<Mutex<Status>> = status.clone();
+        let probes: Arc<Probes> = probes.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, &status, &probes).await {
+                eprintln!("error: status: {}", e);
+            }
+        });
+    }
+}
+
+/// Respond to a request to the status endpoint, or to the health and
+/// readiness probes at `/healthz` and `/readyz`.
+async fn respond(
+    mut stream: TcpStream,
+    status: &Mutex<Status>,
+    probes: &Probes,
+) -> io::Result<()> {
+    let mut request: Vec<u8> = vec![0; 1024];
+
+    let read: usize = stream.read(&mut request).await?;
+
+    let line: String = String::from_utf8_lossy(&request[..read])
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    let path: &str = line.split(' ').nth(1).unwrap_or_default();
+
+    let backlog: usize = status.lock().unwrap().backlog();
+
+    let (code, body): (u16, String) = match path {
+        | "/" | "/status" => (200, status_json(status).to_string()),
+        | _ => match probes.respond(path, backlog).await {
+            | Some(response) => response,
+            | None => (404, json!({ "error": "not found" }).to_string()),
+        },
+    };
+
+    let code: &str = match code {
+        | 200 => "200 OK",
+        | 404 => "404 Not Found",
+        | _ => "503 Service Unavailable",
+    };
+
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                code,
+                body.len(),
+                body
+            )
+            .as_bytes(),
+        )
+        .await?;
+
+    stream.shutdown().await
+}
+
+fn status_json(status: &Mutex<Status>) -> Value {
+    let status: MutexGuard<Status> = status.lock().unwrap();
+
+    json!({
+        "started": status
+            .started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        "scans": status.scans,
+        "inboxes": status
+            .inboxes
+            .iter()
+            .map(|inbox| json!({
+                "inbox": inbox.inbox,
+                "action": inbox.action,
+                "processed": inbox.processed,
+                "failed": inbox.failed,
+                "pending": inbox.pending,
+                "last_error": inbox.last_error,
+            }))
+            .collect::<Vec<Value>>(),
+    })
+}
@@ -0,0 +1,334 @@
+use std::{
+    io::{self, Write},
+    ops::Range,
+    path::PathBuf,
+    pin::Pin,
+    process::ExitCode,
+    task::{Context, Poll},
+};
+
+mod daemon;
+
+mod systemd;
+
+use clap::{Parser, Subcommand};
+use filego::{
+    inspect::{inspect, Anomaly, InspectReport},
+    peek::Peek,
+};
+use tokio::io::AsyncWrite;
+
+/// A file splitting & merging solution.
+#[derive(Debug, Parser)]
+#[command(name = "filego", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Inspect a chunk directory, exiting with `1` if anomalies are found.
+    Inspect {
+        /// Path of the chunk directory.
+        in_dir: PathBuf,
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the bytes at a range of the original file from the chunks.
+    Cat {
+        /// Path of the chunk directory.
+        in_dir: PathBuf,
+        /// Range of the bytes, e.g. `1MiB..1MiB+64`.
+        #[arg(long, value_parser = parse_range)]
+        range: Range<usize>,
+        /// Print the bytes as a hexdump.
+        #[arg(long)]
+        hex: bool,
+    },
+    /// Watch inbox directories, splitting new files and merging complete
+    /// chunk directories following the rules of the config file.
+    Daemon {
+        /// Path of the JSON config file.
+        #[arg(long)]
+        config: PathBuf,
+        /// Scan the inboxes once and exit, with `1` if anything failed.
+        #[arg(long)]
+        once: bool,
+    },
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let cli: Cli = Cli::parse();
+
+    let result: io::Result<bool> = match cli.command {
+        | Command::Inspect { in_dir, json } => run_inspect(in_dir, json).await,
+        | Command::Cat { in_dir, range, hex } => {
+            run_cat(in_dir, range, hex).await
+        },
+        | Command::Daemon { config, once } => daemon::run(config, once).await,
+    };
+
+    match result {
+        | Ok(true) => ExitCode::SUCCESS,
+        | Ok(false) => ExitCode::FAILURE,
+        | Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        },
+    }
+}
+
+/// Run the inspect command, returning whether the directory is healthy.
+async fn run_inspect(
+    in_dir: PathBuf,
+    json: bool,
+) -> io::Result<bool> {
+    let report: InspectReport = inspect(&in_dir).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    Ok(report.is_healthy())
+}
+
+fn print_report(report: &InspectReport) {
+    let or_none = |value: Option<String>| -> String {
+        value.unwrap_or_else(|| "-".to_string())
+    };
+
+    println!("directory:      {}", report.dir.display());
+    println!(
+        "manifest:       {}",
+        match report.manifest_version {
+            | Some(version) => format!("yes (version {})", version),
+            | None => "no".to_string(),
+        }
+    );
+    println!(
+        "headers:        {}",
+        match report.header_version {
+            | Some(version) => format!("yes (version {})", version),
+            | None => "no".to_string(),
+        }
+    );
+    println!("id:             {}", or_none(report.id.map(|id| id.to_string())));
+    println!("layout:         {}", or_none(report.layout.clone()));
+    println!("original name:  {}", or_none(report.original_name.clone()));
+    println!(
+        "chunks:         {}{}",
+        report.chunks.len(),
+        match report.expected_chunks {
+            | Some(expected) => format!(" of {}", expected),
+            | None => String::new(),
+        }
+    );
+
+    if let (Some(min), Some(max)) = (
+        report.chunks.iter().map(|c| c.size).min(),
+        report.chunks.iter().map(|c| c.size).max(),
+    ) {
+        println!("chunk sizes:    {} - {} bytes", min, max);
+    }
+
+    println!("total size:     {} bytes", report.total_size);
+    println!("volumes:        {}", report.volumes);
+    println!(
+        "hashed chunks:  {} of {}",
+        report.hashed_chunks,
+        report.chunks.len()
+    );
+    println!("recovery file:  {}", if report.recovery { "yes" } else { "no" });
+
+    if report.anomalies.is_empty() {
+        println!("anomalies:      none");
+        return;
+    }
+
+    println!("anomalies:");
+
+    for anomaly in &report.anomalies {
+        let message: String = match anomaly {
+            | Anomaly::Empty => "no chunk found".to_string(),
+            | Anomaly::UnsupportedManifest { version } => {
+                format!("manifest version {} is not supported", version)
+            },
+            | Anomaly::MissingChunk { index } => {
+                format!("chunk {} is missing", index)
+            },
+            | Anomaly::SizeMismatch { index, expected, actual } => format!(
+                "chunk {} has {} bytes, expected {} bytes",
+                index, actual, expected
+            ),
+            | Anomaly::HeaderMismatch { index } => {
+                format!("header of chunk {} does not match", index)
+            },
+        };
+
+        println!("  - {}", message);
+    }
+}
+
+/// Run the cat command.
+async fn run_cat(
+    in_dir: PathBuf,
+    range: Range<usize>,
+    hex: bool,
+) -> io::Result<bool> {
+    // the bytes are streamed, as an open range may cover the whole file
+    let mut dump: Dump = Dump {
+        stdout: io::stdout(),
+        hex,
+        offset: range.start,
+        line: Vec::with_capacity(16),
+    };
+
+    Peek::new().in_dir(in_dir).range(range).run_to(&mut dump).await?;
+
+    dump.finish()?;
+
+    Ok(true)
+}
+
+/// Writer of the bytes to the standard output, as they are or as a hexdump
+/// of 16 bytes per line.
+struct Dump {
+    stdout: io::Stdout,
+    hex: bool,
+    offset: usize,
+    line: Vec<u8>,
+}
+
+impl Dump {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> io::Result<usize> {
+        if !self.hex {
+            return self.stdout.write(buf);
+        }
+
+        for &b in buf {
+            self.line.push(b);
+
+            if self.line.len() == 16 {
+                self.write_line()?;
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        let hex: String =
+            self.line.iter().map(|b| format!("{:02x} ", b)).collect();
+
+        let text: String = self
+            .line
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+
+        writeln!(self.stdout, "{:08x}  {:<48} |{}|", self.offset, hex, text)?;
+
+        self.offset += self.line.len();
+        self.line.clear();
+
+        Ok(())
+    }
+
+    /// Write the last line of the hexdump if incomplete.
+    fn finish(mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+        }
+
+        self.stdout.flush()
+    }
+}
+
+impl AsyncWrite for Dump {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.get_mut().write(buf))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(self.get_mut().stdout.flush())
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Parse a range of sizes, e.g. `1MiB..1MiB+64`, `..64` or `1KiB..`.
+fn parse_range(value: &str) -> Result<Range<usize>, String> {
+    let (start, end) = value
+        .split_once("..")
+        .ok_or_else(|| format!("invalid range: {}", value))?;
+
+    let start: usize = if start.is_empty() { 0 } else { parse_size(start)? };
+    let end: usize = if end.is_empty() { usize::MAX } else { parse_size(end)? };
+
+    if start > end {
+        return Err(format!("invalid range: {}", value));
+    }
+
+    Ok(start..end)
+}
+
+/// Parse a sum of sizes, e.g. `1MiB+64`.
+fn parse_size(value: &str) -> Result<usize, String> {
+    let mut total: usize = 0;
+
+    for term in value.split('+') {
+        let term: &str = term.trim();
+
+        let split: usize =
+            term.find(|c: char| !c.is_ascii_digit()).unwrap_or(term.len());
+
+        let (number, unit) = term.split_at(split);
+
+        let number: usize =
+            number.parse().map_err(|_| format!("invalid size: {}", term))?;
+
+        let unit: usize = match unit.trim() {
+            | "" | "B" => 1,
+            | "KB" => 1000,
+            | "KiB" => 1024,
+            | "MB" => 1000 * 1000,
+            | "MiB" => 1024 * 1024,
+            | "GB" => 1000 * 1000 * 1000,
+            | "GiB" => 1024 * 1024 * 1024,
+            | _ => return Err(format!("invalid unit: {}", unit)),
+        };
+
+        total = number
+            .checked_mul(unit)
+            .and_then(|n| total.checked_add(n))
+            .ok_or_else(|| format!("size overflow: {}", value))?;
+    }
+
+    Ok(total)
+}
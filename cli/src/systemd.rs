@@ -0,0 +1,132 @@
+#[cfg(unix)]
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::{env, io, net::TcpListener, time::Duration};
+
+/// The first file descriptor passed by the socket activation.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Notifier of the service manager following `sd_notify(3)`, which does
+/// nothing when not run by systemd with `Type=notify`.
+///
+/// ```ini
+/// [Service]
+/// Type=notify
+/// ExecStart=/usr/bin/filego daemon --config /etc/filego/daemon.json
+/// WatchdogSec=30
+/// ```
+#[derive(Debug)]
+pub struct Notifier {
+    #[cfg(unix)]
+    socket: Option<(UnixDatagram, SocketAddr)>,
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    /// Create the notifier from `NOTIFY_SOCKET` and `WATCHDOG_USEC`.
+    pub fn from_env() -> Self {
+        // the watchdog may be meant for another process
+        let watchdog: Option<Duration> = match env::var("WATCHDOG_PID") {
+            | Ok(pid) if pid != std::process::id().to_string() => None,
+            | _ => env::var("WATCHDOG_USEC")
+                .ok()
+                .and_then(|usec| usec.parse::<u64>().ok())
+                .filter(|usec| *usec > 0)
+                .map(Duration::from_micros),
+        };
+
+        Self {
+            #[cfg(unix)]
+            socket: env::var_os("NOTIFY_SOCKET").and_then(|path| {
+                let addr: SocketAddr = notify_addr(path.as_encoded_bytes())?;
+
+                Some((UnixDatagram::unbound().ok()?, addr))
+            }),
+            watchdog,
+        }
+    }
+
+    /// Get the interval to ping the watchdog at, which is half of its
+    /// timeout, if enabled.
+    pub fn watchdog(&self) -> Option<Duration> {
+        self.watchdog.map(|timeout| timeout / 2)
+    }
+
+    /// Send the `state`, e.g. `READY=1`, ignoring the errors as the service
+    /// keeps running without the service manager.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub fn notify(
+        &self,
+        state: &str,
+    ) {
+        #[cfg(unix)]
+        if let Some((socket, addr)) = &self.socket {
+            let _ = socket.send_to_addr(state.as_bytes(), addr);
+        }
+    }
+}
+
+/// Get the address of the notify socket, either a path or an abstract
+/// name starting with `@`.
+#[cfg(unix)]
+fn notify_addr(path: &[u8]) -> Option<SocketAddr> {
+    match path.split_first() {
+        #[cfg(target_os = "linux")]
+        | Some((b'@', name)) => {
+            use std::os::linux::net::SocketAddrExt;
+
+            SocketAddr::from_abstract_name(name).ok()
+        },
+        | Some((b'/', _)) => {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+            SocketAddr::from_pathname(OsStr::from_bytes(path)).ok()
+        },
+        | _ => None,
+    }
+}
+
+/// Get the listener passed by the socket activation following
+/// `sd_listen_fds(3)`, if any.
+///
+/// ```ini
+/// [Socket]
+/// ListenStream=127.0.0.1:7070
+/// ```
+pub fn listener() -> io::Result<Option<TcpListener>> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::FromRawFd;
+
+        let pid: Option<u32> =
+            env::var("LISTEN_PID").ok().and_then(|pid| pid.parse().ok());
+
+        let fds: usize = env::var("LISTEN_FDS")
+            .ok()
+            .and_then(|fds| fds.parse().ok())
+            .unwrap_or(0);
+
+        if pid != Some(std::process::id()) || fds == 0 {
+            return Ok(None);
+        }
+
+        if fds > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("expected 1 socket passed by systemd, got {}", fds),
+            ));
+        }
+
+        // SAFETY: the descriptor is passed to this process by the service
+        // manager and owned by nothing else
+        let listener: TcpListener =
+            unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) };
+
+        listener.set_nonblocking(true)?;
+
+        Ok(Some(listener))
+    }
+
+    #[cfg(not(unix))]
+    Ok(None)
+}
@@ -0,0 +1,59 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// Run `filego daemon --once` with the config, returning its output.
+fn daemon_once(config: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_filego"))
+        .arg("daemon")
+        .arg("--config")
+        .arg(config)
+        .arg("--once")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_daemon_merges_past_bad_entry() {
+    let root: PathBuf =
+        Path::new(env!("CARGO_TARGET_TMPDIR")).join("daemon_bad_entry");
+
+    let _ = fs::remove_dir_all(&root);
+
+    let (inbox, received, out_dir): (PathBuf, PathBuf, PathBuf) =
+        (root.join("in"), root.join("received"), root.join("out"));
+
+    fs::create_dir_all(&inbox).unwrap();
+    fs::create_dir_all(received.join("a_bad")).unwrap();
+
+    // a truncated manifest next to a good chunk directory
+    fs::write(received.join("a_bad").join("manifest.json"), "{bad").unwrap();
+    fs::write(inbox.join("b_good.txt"), "good data").unwrap();
+
+    let config: PathBuf = root.join("config.json");
+
+    fs::write(
+        &config,
+        format!(
+            r#"{{
+                "inboxes": [
+                    {{ "action": "split", "inbox": {:?}, "out_dir": {:?} }},
+                    {{ "action": "merge", "inbox": {:?}, "out_dir": {:?} }}
+                ]
+            }}"#,
+            inbox, received, received, out_dir
+        ),
+    )
+    .unwrap();
+
+    let output: Output = daemon_once(&config);
+
+    // the bad entry fails, the good one is still merged
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("a_bad"));
+    assert_eq!(fs::read(out_dir.join("b_good.txt")).unwrap(), b"good data");
+
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,129 @@
+//! Command line tool splitting, checking and merging files.
+//!
+//! The manifest written when splitting is the only input of the check and
+//! merge commands. With the `checksum` feature, the checksums of the chunks
+//! and of the file are recorded and verified as well.
+//!
+//! ```sh
+//! cargo run --example cli -- split /path/to/file /path/to/dir [chunk size]
+//! cargo run --example cli -- check /path/to/dir/manifest.json
+//! cargo run --example cli -- merge /path/to/dir/manifest.json /path/to/file
+//! ```
+
+use std::{env, process::ExitCode};
+
+use filego::{
+    check::{Check, CheckResult},
+    config::{CHUNK_SIZE_DEFAULT, MANIFEST_FILE_NAME},
+    merge::{Merge, MergeResult},
+    split::{Split, SplitResult},
+};
+#[cfg(feature = "checksum")]
+use filego::{
+    checksum::{Algorithm, ChunkChecksum},
+    manifest::Manifest,
+};
+use tokio::io as ioa;
+
+const USAGE: &str = "usage:
+  cli split <file> <dir> [chunk size]
+  cli check <manifest>
+  cli merge <manifest> <file>";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result: ioa::Result<bool> = match args.as_slice() {
+        | ["split", in_file, out_dir] => split(in_file, out_dir, None).await,
+        | ["split", in_file, out_dir, chunk_size] => match chunk_size.parse() {
+            | Ok(chunk_size) => split(in_file, out_dir, Some(chunk_size)).await,
+            | Err(_) => Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "invalid chunk size",
+            )),
+        },
+        | ["check", manifest] => check(manifest).await,
+        | ["merge", manifest, out_file] => merge(manifest, out_file).await,
+        | _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    match result {
+        | Ok(true) => ExitCode::SUCCESS,
+        | Ok(false) => ExitCode::FAILURE,
+        | Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+async fn split(
+    in_file: &str,
+    out_dir: &str,
+    chunk_size: Option<usize>,
+) -> ioa::Result<bool> {
+    let split: Split = Split::new()
+        .in_file(in_file)
+        .out_dir(out_dir)
+        .chunk_size(chunk_size.unwrap_or(CHUNK_SIZE_DEFAULT))
+        .manifest(true);
+
+    #[cfg(feature = "checksum")]
+    let split: Split =
+        split.checksum(Algorithm::Sha256).file_hash(Algorithm::Sha256);
+
+    let result: SplitResult = split.run().await?;
+
+    println!(
+        "{} bytes splitted into {} chunks, manifest: {}",
+        result.file_size,
+        result.total_chunks,
+        std::path::Path::new(out_dir).join(MANIFEST_FILE_NAME).display(),
+    );
+
+    Ok(true)
+}
+
+async fn check(manifest: &str) -> ioa::Result<bool> {
+    let check: Check = Check::new().from_manifest(manifest).strict(true);
+
+    #[cfg(feature = "checksum")]
+    let check: Check = check.verify_checksums(ChunkChecksum::of_chunks(
+        &Manifest::read(manifest).await?.chunks,
+    ));
+
+    let result: CheckResult = check.run().await?;
+
+    match result.error {
+        | Some(error) => {
+            println!("{}: {}", error.error_type.as_code(), error.message);
+            Ok(false)
+        },
+        | None => {
+            println!("ok");
+            Ok(true)
+        },
+    }
+}
+
+async fn merge(
+    manifest: &str,
+    out_file: &str,
+) -> ioa::Result<bool> {
+    let result: MergeResult =
+        Merge::new().from_manifest(manifest).out_file(out_file).run().await?;
+
+    println!(
+        "merged into {} in {:.2?} ({:.0} bytes/s)",
+        out_file,
+        result.elapsed,
+        result.throughput(),
+    );
+
+    Ok(true)
+}
@@ -0,0 +1,90 @@
+//! Protocol shared by the `upload` and `receive` examples.
+//!
+//! Every frame is prefixed with its length as a little-endian `u64`. The
+//! first frame is the manifest in the binary format, followed by a frame
+//! for each chunk holding its index as a little-endian `u64` and its data.
+//! The sender closes its half of the connection after the last chunk, and
+//! the receiver replies with a single byte of [`ACCEPTED`] or [`REJECTED`].
+
+#![allow(dead_code)]
+
+use tokio::io::{self as ioa, AsyncReadExt, AsyncWriteExt};
+
+/// Reply of the receiver when the merged file is verified.
+pub const ACCEPTED: u8 = 1;
+
+/// Reply of the receiver when the chunks fail the check.
+pub const REJECTED: u8 = 0;
+
+/// The maximum size of a frame, so a peer cannot exhaust the memory.
+pub const FRAME_SIZE_MAX: usize = 64 * 1024 * 1024;
+
+/// The default address of the receiver.
+pub const ADDR_DEFAULT: &str = "127.0.0.1:7878";
+
+/// Write a frame.
+pub async fn write_frame<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    data: &[u8],
+) -> ioa::Result<()> {
+    writer.write_all(&(data.len() as u64).to_le_bytes()).await?;
+    writer.write_all(data).await
+}
+
+/// Read a frame, returning `None` when the peer closed the connection.
+pub async fn read_frame<R: AsyncReadExt + Unpin>(
+    reader: &mut R
+) -> ioa::Result<Option<Vec<u8>>> {
+    let mut len: [u8; 8] = [0; 8];
+
+    match reader.read_exact(&mut len).await {
+        | Ok(_) => {},
+        | Err(e) if e.kind() == ioa::ErrorKind::UnexpectedEof => {
+            return Ok(None)
+        },
+        | Err(e) => return Err(e),
+    }
+
+    let len: usize = u64::from_le_bytes(len) as usize;
+
+    if len > FRAME_SIZE_MAX {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            "frame exceeds the maximum size",
+        ));
+    }
+
+    let mut data: Vec<u8> = vec![0; len];
+
+    reader.read_exact(&mut data).await?;
+
+    Ok(Some(data))
+}
+
+/// Encode the frame of a chunk.
+pub fn chunk_frame(
+    index: usize,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut frame: Vec<u8> = Vec::with_capacity(8 + data.len());
+
+    frame.extend_from_slice(&(index as u64).to_le_bytes());
+    frame.extend_from_slice(data);
+
+    frame
+}
+
+/// Decode the frame of a chunk into its index and data.
+pub fn parse_chunk_frame(mut frame: Vec<u8>) -> ioa::Result<(usize, Vec<u8>)> {
+    if frame.len() < 8 {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            "chunk frame is too short",
+        ));
+    }
+
+    let data: Vec<u8> = frame.split_off(8);
+    let index: u64 = u64::from_le_bytes(frame.try_into().unwrap());
+
+    Ok((index as usize, data))
+}
@@ -0,0 +1,179 @@
+//! Object store pipeline.
+//!
+//! Splits a file, uploads the chunks in the background through a
+//! [`TieredStore`] into a bucket with keys following a [`KeyTemplate`],
+//! then checks and merges the file from the bucket.
+//!
+//! The bucket is emulated in a local directory, so the example runs without
+//! credentials. Replace the [`ChunkStore`] implementation of [`Bucket`] with
+//! the calls of an S3 client, e.g. `head_object`, `get_object` and
+//! `put_object`, to use a real bucket.
+//!
+//! ```sh
+//! cargo run --example object_store -- /path/to/file /path/to/output
+//! ```
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+use filego::{
+    check::{Check, CheckResult},
+    key::KeyTemplate,
+    manifest::Manifest,
+    merge::Merge,
+    split::{Split, SplitResult},
+    store::{ChunkHead, ChunkStore, DirStore, StoreFuture, TieredStore},
+};
+use tokio::{fs as fsa, io as ioa};
+
+/// Bucket of an object store, emulated in a local directory.
+#[derive(Debug)]
+struct Bucket {
+    root: PathBuf,
+    template: KeyTemplate,
+    file_id: String,
+}
+
+impl Bucket {
+    fn object(
+        &self,
+        index: usize,
+    ) -> PathBuf {
+        self.root.join(self.template.key(&self.file_id, index))
+    }
+}
+
+impl ChunkStore for Bucket {
+    fn head(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Option<ChunkHead>> {
+        Box::pin(async move {
+            match fsa::metadata(self.object(index)).await {
+                | Ok(metadata) => Ok(Some(ChunkHead {
+                    size: metadata.len() as usize,
+                    etag: None,
+                })),
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => Ok(None),
+                | Err(e) => Err(e),
+            }
+        })
+    }
+
+    fn get(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Vec<u8>> {
+        Box::pin(async move { fsa::read(self.object(index)).await })
+    }
+
+    fn put(
+        &self,
+        index: usize,
+        data: Vec<u8>,
+    ) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            let path: PathBuf = self.object(index);
+
+            if let Some(parent) = path.parent() {
+                fsa::create_dir_all(parent).await?;
+            }
+
+            fsa::write(path, data).await
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.len() != 2 {
+        eprintln!("usage: object_store <file> <output file>");
+        return ExitCode::FAILURE;
+    }
+
+    match pipeline(Path::new(&args[0]), Path::new(&args[1])).await {
+        | Ok(()) => ExitCode::SUCCESS,
+        | Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+async fn pipeline(
+    in_file: &Path,
+    out_file: &Path,
+) -> ioa::Result<()> {
+    let work_dir: PathBuf = env::temp_dir()
+        .join(format!("filego-object-store-{}", std::process::id()));
+
+    let staging_dir: PathBuf = work_dir.join("staging");
+
+    let result: SplitResult = Split::new()
+        .in_file(in_file)
+        .out_dir(&staging_dir)
+        .chunk_size(1024 * 1024)
+        .run()
+        .await?;
+
+    let manifest: Manifest = result.manifest;
+
+    let file_id: String = result.id.to_string();
+    let template: KeyTemplate = KeyTemplate::parse("{file_id}/{index:06}")?;
+
+    // keys are validated before uploading, failing on the existing objects
+    let keys: Vec<String> = template.keys(
+        &file_id,
+        manifest.total_chunks,
+        std::iter::empty::<&str>(),
+    )?;
+
+    let bucket: Arc<Bucket> =
+        Arc::new(Bucket { root: work_dir.join("bucket"), template, file_id });
+
+    // chunks are cached locally and uploaded in the background
+    let store: TieredStore =
+        TieredStore::new(DirStore::new(work_dir.join("cache")), bucket.clone())
+            .concurrency(4);
+
+    let staging: DirStore = DirStore::with_manifest(&staging_dir, &manifest);
+
+    for index in 0..manifest.total_chunks {
+        store.put(index, staging.get(index).await?).await?;
+    }
+
+    store.flush().await?;
+
+    println!("uploaded {} objects", keys.len());
+
+    let check: CheckResult = Check::new()
+        .remote(bucket.clone())
+        .file_size(manifest.stored_size())
+        .total_chunks(manifest.total_chunks)
+        .run()
+        .await?;
+
+    if let Some(error) = check.error {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            error.message,
+        ));
+    }
+
+    Merge::from_store(bucket)
+        .total_chunks(manifest.total_chunks)
+        .expect_file_size(manifest.file_size)
+        .out_file(out_file)
+        .run()
+        .await?;
+
+    println!("merged into {}", out_file.display());
+
+    fsa::remove_dir_all(&work_dir).await
+}
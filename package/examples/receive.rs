@@ -0,0 +1,136 @@
+//! Receiver server for the `upload` example.
+//!
+//! Accepts a single upload, stores the chunks as laid out in the received
+//! manifest, checks them against their checksums, then merges them into the
+//! output file while verifying the checksum of the whole file.
+//!
+//! ```sh
+//! cargo run --example receive --features checksum -- /path/to/output
+//! ```
+
+mod common;
+
+use std::{env, path::PathBuf, process::ExitCode};
+
+use filego::{
+    check::{Check, CheckResult},
+    checksum::ChunkChecksum,
+    config::MANIFEST_FILE_NAME,
+    manifest::Manifest,
+    merge::Merge,
+    store::{ChunkStore, DirStore},
+};
+use tokio::{
+    io::{self as ioa, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+
+    let out_file: PathBuf = match args.next() {
+        | Some(path) => PathBuf::from(path),
+        | None => {
+            eprintln!("usage: receive <output file> [addr]");
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let addr: String =
+        args.next().unwrap_or_else(|| common::ADDR_DEFAULT.to_string());
+
+    let result: ioa::Result<bool> = async {
+        let listener: TcpListener = TcpListener::bind(&addr).await?;
+
+        println!("listening on {}", addr);
+
+        let (stream, peer) = listener.accept().await?;
+
+        println!("receiving from {}", peer);
+
+        receive(stream, out_file).await
+    }
+    .await;
+
+    match result {
+        | Ok(true) => ExitCode::SUCCESS,
+        | Ok(false) => ExitCode::FAILURE,
+        | Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+async fn receive(
+    mut stream: TcpStream,
+    out_file: PathBuf,
+) -> ioa::Result<bool> {
+    let in_dir: PathBuf =
+        env::temp_dir().join(format!("filego-receive-{}", std::process::id()));
+
+    let manifest: Manifest = match common::read_frame(&mut stream).await? {
+        | Some(frame) => Manifest::from_bytes(&frame)?,
+        | None => {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::UnexpectedEof,
+                "manifest not received",
+            ))
+        },
+    };
+
+    let store: DirStore = DirStore::with_manifest(&in_dir, &manifest);
+
+    while let Some(frame) = common::read_frame(&mut stream).await? {
+        let (index, data) = common::parse_chunk_frame(frame)?;
+
+        if index >= manifest.total_chunks {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                "chunk index out of range",
+            ));
+        }
+
+        store.put(index, data).await?;
+    }
+
+    let manifest_path: PathBuf = in_dir.join(MANIFEST_FILE_NAME);
+
+    manifest.write(&manifest_path).await?;
+
+    // the chunks are verified before merging, so a corrupted upload does
+    // not produce an output file
+    let result: CheckResult = Check::new()
+        .from_manifest(&manifest_path)
+        .verify_checksums(ChunkChecksum::of_chunks(&manifest.chunks))
+        .run()
+        .await?;
+
+    let accepted: bool = match result.error {
+        | Some(error) => {
+            eprintln!("rejected: {}", error.message);
+            false
+        },
+        | None => {
+            // the checksum of the file recorded in the manifest is verified
+            // while merging
+            Merge::new()
+                .from_manifest(&manifest_path)
+                .out_file(&out_file)
+                .run()
+                .await?;
+
+            println!("merged into {}", out_file.display());
+            true
+        },
+    };
+
+    stream
+        .write_u8(if accepted { common::ACCEPTED } else { common::REJECTED })
+        .await?;
+
+    tokio::fs::remove_dir_all(&in_dir).await?;
+
+    Ok(accepted)
+}
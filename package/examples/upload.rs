@@ -0,0 +1,101 @@
+//! Chunked uploader client.
+//!
+//! Splits a file with SHA-256 checksums recorded in the manifest, then sends
+//! the manifest and the chunks to the `receive` example, which verifies and
+//! merges them.
+//!
+//! ```sh
+//! cargo run --example receive --features checksum -- /path/to/output
+//! cargo run --example upload --features checksum -- /path/to/file
+//! ```
+
+mod common;
+
+use std::{env, path::PathBuf, process::ExitCode};
+
+use filego::{
+    checksum::Algorithm,
+    manifest::{Manifest, ManifestFormat},
+    split::{Split, SplitResult},
+    store::{ChunkStore, DirStore},
+};
+use tokio::{
+    io::{self as ioa, AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+
+    let in_file: PathBuf = match args.next() {
+        | Some(path) => PathBuf::from(path),
+        | None => {
+            eprintln!("usage: upload <file> [addr]");
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let addr: String =
+        args.next().unwrap_or_else(|| common::ADDR_DEFAULT.to_string());
+
+    match upload(in_file, &addr).await {
+        | Ok(true) => {
+            println!("uploaded and verified by {}", addr);
+            ExitCode::SUCCESS
+        },
+        | Ok(false) => {
+            eprintln!("rejected by {}", addr);
+            ExitCode::FAILURE
+        },
+        | Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+async fn upload(
+    in_file: PathBuf,
+    addr: &str,
+) -> ioa::Result<bool> {
+    let out_dir: PathBuf =
+        env::temp_dir().join(format!("filego-upload-{}", std::process::id()));
+
+    let result: SplitResult = Split::new()
+        .in_file(&in_file)
+        .out_dir(&out_dir)
+        .chunk_size(1024 * 1024)
+        .checksum(Algorithm::Sha256)
+        .file_hash(Algorithm::Sha256)
+        .run()
+        .await?;
+
+    let manifest: Manifest = result.manifest;
+    let store: DirStore = DirStore::with_manifest(&out_dir, &manifest);
+
+    let mut stream: TcpStream = TcpStream::connect(addr).await?;
+
+    common::write_frame(
+        &mut stream,
+        &manifest.to_bytes(ManifestFormat::Binary)?,
+    )
+    .await?;
+
+    // chunks are read one at a time, so the memory does not depend on the
+    // size of the file
+    for index in 0..manifest.total_chunks {
+        let data: Vec<u8> = store.get(index).await?;
+
+        common::write_frame(&mut stream, &common::chunk_frame(index, &data))
+            .await?;
+    }
+
+    stream.shutdown().await?;
+
+    let reply: u8 = stream.read_u8().await?;
+
+    tokio::fs::remove_dir_all(&out_dir).await?;
+
+    Ok(reply == common::ACCEPTED)
+}
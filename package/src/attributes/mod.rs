@@ -0,0 +1,680 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs as fsa, io as ioa};
+
+#[cfg(target_os = "linux")]
+const FS_COMPR_FL: libc::c_int = 0x0000_0004;
+
+#[cfg(target_os = "linux")]
+const FS_NOCOMP_FL: libc::c_int = 0x0000_0400;
+
+#[cfg(target_os = "linux")]
+const FS_NOCOW_FL: libc::c_int = 0x0080_0000;
+
+/// Attributes of the file on Windows recorded and restored, i.e. read-only,
+/// hidden, archive and not content indexed.
+pub const WINDOWS_ATTRIBUTES: u32 =
+    0x0000_0001 | 0x0000_0002 | 0x0000_0020 | 0x0000_2000;
+
+/// Attributes of the output file on copy-on-write filesystems, e.g. btrfs.
+///
+/// The attributes are only supported on Linux, and unset attributes are
+/// inherited from the directory. They are applied right after the output
+/// file is created, as they must be set while the file is empty.
+///
+/// On btrfs, a file with copy-on-write disabled is rewritten in place, which
+/// avoids the fragmentation of very large files, but also disables the
+/// checksums and compression of the file. Snapshots still share the extents
+/// of such a file, and the first write to an extent after a snapshot copies
+/// it once, so merging into a new file before taking a snapshot keeps the
+/// snapshot cheap. On ZFS, compression is a property of the dataset and the
+/// attributes are not supported.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     attributes::FileAttributes,
+///     merge::{Merge, MergeResult},
+/// };
+///
+/// async fn example() {
+///     let result: MergeResult = Merge::new()
+///         .in_dir(PathBuf::from("path").join("to").join("dir"))
+///         .out_file(PathBuf::from("path").join("to").join("file"))
+///         .attributes(FileAttributes::new().nocow(true))
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileAttributes {
+    nocow: Option<bool>,
+    compression: Option<bool>,
+}
+
+impl FileAttributes {
+    /// Create new attributes inherited from the directory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether copy-on-write is disabled, like `chattr +C`.
+    pub fn nocow(
+        mut self,
+        nocow: bool,
+    ) -> Self {
+        self.nocow = Some(nocow);
+        self
+    }
+
+    /// Set whether the file is compressed, like `chattr +c`, or never
+    /// compressed, like `chattr +m`.
+    pub fn compression(
+        mut self,
+        compression: bool,
+    ) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Apply the attributes to an empty file.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn apply(
+        &self,
+        file: &fsa::File,
+    ) -> ioa::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        if self.nocow.is_none() && self.compression.is_none() {
+            return Ok(());
+        }
+
+        let fd: libc::c_int = file.as_raw_fd();
+
+        let mut flags: libc::c_int = 0;
+
+        // the kernel reads and writes an int despite the ioctl definition
+        if unsafe { libc::ioctl(fd, libc::FS_IOC_GETFLAGS, &mut flags) } < 0 {
+            return Err(ioa::Error::last_os_error());
+        }
+
+        if let Some(nocow) = self.nocow {
+            flags = toggle(flags, FS_NOCOW_FL, nocow);
+        }
+
+        if let Some(compression) = self.compression {
+            flags = toggle(flags, FS_COMPR_FL, compression);
+            flags = toggle(flags, FS_NOCOMP_FL, !compression);
+        }
+
+        if unsafe { libc::ioctl(fd, libc::FS_IOC_SETFLAGS, &flags) } < 0 {
+            return Err(ioa::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Apply the attributes to an empty file.
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn apply(
+        &self,
+        _file: &fsa::File,
+    ) -> ioa::Result<()> {
+        if self.nocow.is_none() && self.compression.is_none() {
+            return Ok(());
+        }
+
+        Err(ioa::Error::new(
+            ioa::ErrorKind::Unsupported,
+            "file attributes are only supported on Linux",
+        ))
+    }
+}
+
+/// Metadata of the original file recorded in the manifest, see
+/// [`Split::preserve_metadata`] and [`Merge::restore_metadata`].
+///
+/// Only the permission bits are restored, without the setuid, setgid and
+/// sticky bits, as the manifest may come from untrusted networks. For the
+/// same reason, only the extended attributes in the `user` namespace are
+/// recorded, which are only supported on Linux, and only the
+/// [`WINDOWS_ATTRIBUTES`] are recorded on Windows, where the alternate data
+/// streams of NTFS are supported.
+///
+/// [`Split::preserve_metadata`]: crate::split::Split::preserve_metadata
+/// [`Merge::restore_metadata`]: crate::merge::Merge::restore_metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// Permission bits of the file on Unix, e.g. `0o755`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Whether the file is read-only.
+    #[serde(default)]
+    pub readonly: bool,
+    /// Last modification time in nanoseconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    /// Extended attributes with their values in hex, keyed by their names,
+    /// e.g. `user.origin`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
+    /// Attributes of the file on Windows within the [`WINDOWS_ATTRIBUTES`],
+    /// e.g. `0x2` if hidden.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<u32>,
+    /// Alternate data streams of the file on NTFS with their contents in
+    /// hex, keyed by their names, e.g. `Zone.Identifier`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub streams: BTreeMap<String, String>,
+}
+
+impl FileMetadata {
+    /// Read the metadata of a file, including its extended attributes if
+    /// `xattrs` is `true`.
+    pub async fn read<P: AsRef<Path>>(
+        path: P,
+        xattrs: bool,
+    ) -> ioa::Result<Self> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+
+        tokio::task::spawn_blocking(move || {
+            let file: fs::File = fs::File::open(path)?;
+            let metadata: fs::Metadata = file.metadata()?;
+
+            #[cfg(unix)]
+            let mode: Option<u32> = {
+                use std::os::unix::fs::PermissionsExt;
+
+                Some(metadata.permissions().mode() & 0o777)
+            };
+
+            #[cfg(not(unix))]
+            let mode: Option<u32> = None;
+
+            #[cfg(windows)]
+            let attributes: Option<u32> = {
+                use std::os::windows::fs::MetadataExt;
+
+                Some(metadata.file_attributes() & WINDOWS_ATTRIBUTES)
+            };
+
+            #[cfg(not(windows))]
+            let attributes: Option<u32> = None;
+
+            // times before the epoch are not recorded
+            let modified: Option<u64> = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .and_then(|d| u64::try_from(d.as_nanos()).ok());
+
+            Ok(Self {
+                mode,
+                readonly: metadata.permissions().readonly(),
+                modified,
+                xattrs: match xattrs {
+                    | true => read_xattrs(&file)?,
+                    | false => BTreeMap::new(),
+                },
+                attributes,
+                streams: BTreeMap::new(),
+            })
+        })
+        .await
+        .map_err(ioa::Error::other)?
+    }
+
+    /// Read the alternate data streams of a file into the metadata.
+    ///
+    /// The alternate data streams are only supported on Windows, and it
+    /// fails with [`ioa::ErrorKind::Unsupported`] otherwise.
+    pub async fn with_streams<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> ioa::Result<Self> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+
+        self.streams = tokio::task::spawn_blocking(move || read_streams(&path))
+            .await
+            .map_err(ioa::Error::other)??;
+
+        Ok(self)
+    }
+
+    /// Apply the metadata to a file.
+    ///
+    /// The permissions and the attributes on Windows are applied last, so a
+    /// read-only file still gets its modification time, extended attributes
+    /// and alternate data streams.
+    pub async fn apply<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> ioa::Result<()> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let metadata: Self = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let file: fs::File = fs::File::open(&path)?;
+
+            if !metadata.xattrs.is_empty() {
+                write_xattrs(&file, &metadata.xattrs)?;
+            }
+
+            if !metadata.streams.is_empty() {
+                write_streams(&path, &metadata.streams)?;
+            }
+
+            if let Some(modified) = metadata.modified {
+                file.set_modified(UNIX_EPOCH + Duration::from_nanos(modified))?;
+            }
+
+            let mut permissions: fs::Permissions =
+                file.metadata()?.permissions();
+
+            match metadata.mode {
+                #[cfg(unix)]
+                | Some(mode) => {
+                    use std::os::unix::fs::PermissionsExt;
+
+                    permissions.set_mode(mode & 0o777);
+                },
+                | _ => permissions.set_readonly(metadata.readonly),
+            }
+
+            file.set_permissions(permissions)?;
+
+            #[cfg(windows)]
+            if let Some(attributes) = metadata.attributes {
+                windows::set_attributes(&path, attributes)?;
+            }
+
+            Ok(())
+        })
+        .await
+        .map_err(ioa::Error::other)?
+    }
+}
+
+/// Prefix of the names of the extended attributes recorded.
+#[cfg(target_os = "linux")]
+const XATTR_PREFIX: &str = "user.";
+
+#[cfg(target_os = "linux")]
+fn read_xattrs(file: &fs::File) -> ioa::Result<BTreeMap<String, String>> {
+    use std::{ffi::CString, os::fd::AsRawFd};
+
+    let fd: libc::c_int = file.as_raw_fd();
+
+    // the size is queried first, then the list is read
+    let size: isize = unsafe { libc::flistxattr(fd, std::ptr::null_mut(), 0) };
+
+    if size < 0 {
+        let error: ioa::Error = ioa::Error::last_os_error();
+
+        // filesystems without extended attributes have none to record
+        return match error.raw_os_error() {
+            | Some(libc::ENOTSUP) => Ok(BTreeMap::new()),
+            | _ => Err(error),
+        };
+    }
+
+    let mut names: Vec<u8> = vec![0; size as usize];
+
+    let size: isize =
+        unsafe { libc::flistxattr(fd, names.as_mut_ptr().cast(), names.len()) };
+
+    if size < 0 {
+        return Err(ioa::Error::last_os_error());
+    }
+
+    let mut xattrs: BTreeMap<String, String> = BTreeMap::new();
+
+    for name in names[..size as usize].split(|b| *b == 0) {
+        let name: &str = match std::str::from_utf8(name) {
+            | Ok(name) if name.starts_with(XATTR_PREFIX) => name,
+            | _ => continue,
+        };
+
+        let c_name: CString = CString::new(name).map_err(ioa::Error::other)?;
+
+        let size: isize = unsafe {
+            libc::fgetxattr(fd, c_name.as_ptr(), std::ptr::null_mut(), 0)
+        };
+
+        if size < 0 {
+            return Err(ioa::Error::last_os_error());
+        }
+
+        let mut value: Vec<u8> = vec![0; size as usize];
+
+        let size: isize = unsafe {
+            libc::fgetxattr(
+                fd,
+                c_name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+
+        if size < 0 {
+            return Err(ioa::Error::last_os_error());
+        }
+
+        xattrs.insert(name.to_string(), to_hex(&value[..size as usize]));
+    }
+
+    Ok(xattrs)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_xattrs(_file: &fs::File) -> ioa::Result<BTreeMap<String, String>> {
+    Err(ioa::Error::new(
+        ioa::ErrorKind::Unsupported,
+        "extended attributes are only supported on Linux",
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn write_xattrs(
+    file: &fs::File,
+    xattrs: &BTreeMap<String, String>,
+) -> ioa::Result<()> {
+    use std::{ffi::CString, os::fd::AsRawFd};
+
+    let fd: libc::c_int = file.as_raw_fd();
+
+    for (name, value) in xattrs {
+        if !name.starts_with(XATTR_PREFIX) {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                format!(
+                    "extended attribute not in the user namespace: {}",
+                    name
+                ),
+            ));
+        }
+
+        let value: Vec<u8> = from_hex(value).ok_or_else(|| {
+            ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                format!("invalid value of extended attribute: {}", name),
+            )
+        })?;
+
+        let c_name: CString =
+            CString::new(name.as_str()).map_err(ioa::Error::other)?;
+
+        let result: libc::c_int = unsafe {
+            libc::fsetxattr(
+                fd,
+                c_name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+
+        if result < 0 {
+            return Err(ioa::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write_xattrs(
+    _file: &fs::File,
+    _xattrs: &BTreeMap<String, String>,
+) -> ioa::Result<()> {
+    Err(ioa::Error::new(
+        ioa::ErrorKind::Unsupported,
+        "extended attributes are only supported on Linux",
+    ))
+}
+
+/// Get the path of the alternate data stream `name` of the file.
+#[cfg(windows)]
+fn stream_path(
+    path: &Path,
+    name: &str,
+) -> ioa::Result<PathBuf> {
+    // the name must not reach another file or stream type
+    if name.is_empty() || name.contains([':', '/', '\\']) {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("invalid name of alternate data stream: {}", name),
+        ));
+    }
+
+    let mut stream: std::ffi::OsString = path.as_os_str().to_os_string();
+
+    stream.push(":");
+    stream.push(name);
+
+    Ok(PathBuf::from(stream))
+}
+
+#[cfg(windows)]
+fn read_streams(path: &Path) -> ioa::Result<BTreeMap<String, String>> {
+    let mut streams: BTreeMap<String, String> = BTreeMap::new();
+
+    for name in windows::stream_names(path)? {
+        let data: Vec<u8> = fs::read(stream_path(path, &name)?)?;
+
+        streams.insert(name, to_hex(&data));
+    }
+
+    Ok(streams)
+}
+
+#[cfg(not(windows))]
+fn read_streams(_path: &Path) -> ioa::Result<BTreeMap<String, String>> {
+    Err(ioa::Error::new(
+        ioa::ErrorKind::Unsupported,
+        "alternate data streams are only supported on Windows",
+    ))
+}
+
+#[cfg(windows)]
+fn write_streams(
+    path: &Path,
+    streams: &BTreeMap<String, String>,
+) -> ioa::Result<()> {
+    for (name, value) in streams {
+        let data: Vec<u8> = from_hex(value).ok_or_else(|| {
+            ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                format!("invalid value of alternate data stream: {}", name),
+            )
+        })?;
+
+        fs::write(stream_path(path, name)?, data)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn write_streams(
+    _path: &Path,
+    _streams: &BTreeMap<String, String>,
+) -> ioa::Result<()> {
+    Err(ioa::Error::new(
+        ioa::ErrorKind::Unsupported,
+        "alternate data streams are only supported on Windows",
+    ))
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{
+        io, iter,
+        os::windows::ffi::{OsStrExt, OsStringExt},
+        path::Path,
+    };
+
+    use windows_sys::Win32::{
+        Foundation::{ERROR_HANDLE_EOF, HANDLE, INVALID_HANDLE_VALUE},
+        Storage::FileSystem::{
+            FindClose, FindFirstStreamW, FindNextStreamW,
+            FindStreamInfoStandard, GetFileAttributesW, SetFileAttributesW,
+            FILE_ATTRIBUTE_NORMAL, INVALID_FILE_ATTRIBUTES,
+            WIN32_FIND_STREAM_DATA,
+        },
+    };
+
+    use super::WINDOWS_ATTRIBUTES;
+
+    /// Name of the default data stream.
+    const DEFAULT_STREAM: &str = "::$DATA";
+
+    /// Suffix of the names of the data streams.
+    const DATA_SUFFIX: &str = ":$DATA";
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(iter::once(0)).collect()
+    }
+
+    /// Set the attributes within the [`WINDOWS_ATTRIBUTES`], keeping the
+    /// others.
+    pub(super) fn set_attributes(
+        path: &Path,
+        attributes: u32,
+    ) -> io::Result<()> {
+        let path: Vec<u16> = wide(path);
+
+        let current: u32 = unsafe { GetFileAttributesW(path.as_ptr()) };
+
+        if current == INVALID_FILE_ATTRIBUTES {
+            return Err(io::Error::last_os_error());
+        }
+
+        // the normal attribute is only valid alone
+        let kept: u32 = current & !(WINDOWS_ATTRIBUTES | FILE_ATTRIBUTE_NORMAL);
+
+        let attributes: u32 = match kept | (attributes & WINDOWS_ATTRIBUTES) {
+            | 0 => FILE_ATTRIBUTE_NORMAL,
+            | attributes => attributes,
+        };
+
+        if unsafe { SetFileAttributesW(path.as_ptr(), attributes) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Get the names of the alternate data streams of the file.
+    pub(super) fn stream_names(path: &Path) -> io::Result<Vec<String>> {
+        let path: Vec<u16> = wide(path);
+
+        let mut data: WIN32_FIND_STREAM_DATA = Default::default();
+
+        let handle: HANDLE = unsafe {
+            FindFirstStreamW(
+                path.as_ptr(),
+                FindStreamInfoStandard,
+                (&mut data as *mut WIN32_FIND_STREAM_DATA).cast(),
+                0,
+            )
+        };
+
+        if handle == INVALID_HANDLE_VALUE {
+            let error: io::Error = io::Error::last_os_error();
+
+            // files without any stream, e.g. on other file systems
+            return match error.raw_os_error() {
+                | Some(code) if code == ERROR_HANDLE_EOF as i32 => {
+                    Ok(Vec::new())
+                },
+                | _ => Err(error),
+            };
+        }
+
+        let mut names: Vec<String> = Vec::new();
+
+        let result: io::Result<()> = loop {
+            let len: usize = data
+                .cStreamName
+                .iter()
+                .position(|c| *c == 0)
+                .unwrap_or(data.cStreamName.len());
+
+            let name: String =
+                std::ffi::OsString::from_wide(&data.cStreamName[..len])
+                    .to_string_lossy()
+                    .into_owned();
+
+            // e.g. `:Zone.Identifier:$DATA`
+            if name != DEFAULT_STREAM {
+                if let Some(name) = name
+                    .strip_prefix(':')
+                    .and_then(|name| name.strip_suffix(DATA_SUFFIX))
+                {
+                    names.push(name.to_string());
+                }
+            }
+
+            let found: i32 = unsafe {
+                FindNextStreamW(
+                    handle,
+                    (&mut data as *mut WIN32_FIND_STREAM_DATA).cast(),
+                )
+            };
+
+            if found == 0 {
+                let error: io::Error = io::Error::last_os_error();
+
+                break match error.raw_os_error() {
+                    | Some(code) if code == ERROR_HANDLE_EOF as i32 => Ok(()),
+                    | _ => Err(error),
+                };
+            }
+        };
+
+        unsafe { FindClose(handle) };
+
+        result.map(|_| names)
+    }
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn toggle(
+    flags: libc::c_int,
+    flag: libc::c_int,
+    on: bool,
+) -> libc::c_int {
+    if on {
+        flags | flag
+    } else {
+        flags & !flag
+    }
+}
@@ -0,0 +1,305 @@
+use std::path::{Path, PathBuf};
+
+use blake3::{
+    hazmat::{
+        left_subtree_len, merge_subtrees_non_root, merge_subtrees_root,
+        ChainingValue, HasherExt, Mode,
+    },
+    Hash, Hasher, CHUNK_LEN,
+};
+use tokio::io as ioa;
+
+use crate::config::BAO_OUTBOARD_EXTENSION;
+
+/// Size of the length at the start of an outboard in bytes.
+const LENGTH_SIZE: usize = 8;
+
+/// Size of a parent node in an outboard in bytes.
+const PARENT_SIZE: usize = 64;
+
+/// Encode the bao outboard of the data, returning its BLAKE3 hash and the
+/// outboard.
+///
+/// The outboard is the length of the data in little endian followed by the
+/// parent nodes of the BLAKE3 tree in pre-order, as in `bao encode
+/// --outboard`, so the data can be verified as it streams with
+/// [`BaoVerifier`] without the whole of it.
+///
+/// ## Example
+///
+/// ```
+/// use filego::bao::{outboard, BaoVerifier};
+///
+/// let data: Vec<u8> = vec![7; 5000];
+///
+/// let (hash, encoded) = outboard(&data);
+///
+/// assert_eq!(hash, blake3::hash(&data));
+///
+/// let mut verifier: BaoVerifier =
+///     BaoVerifier::new(*hash.as_bytes(), encoded).unwrap();
+///
+/// for part in data.chunks(1500) {
+///     verifier.update(part).unwrap();
+/// }
+///
+/// verifier.finish().unwrap();
+/// ```
+pub fn outboard(data: &[u8]) -> (Hash, Vec<u8>) {
+    let mut encoded: Vec<u8> = Vec::with_capacity(outboard_size(data.len()));
+
+    encoded.extend_from_slice(&(data.len() as u64).to_le_bytes());
+
+    if data.len() <= CHUNK_LEN {
+        return (blake3::hash(data), encoded);
+    }
+
+    let (left, right) = children(data, 0, &mut encoded);
+
+    (merge_subtrees_root(&left, &right, Mode::Hash), encoded)
+}
+
+/// Get the size of the outboard of the data of `len` bytes.
+pub fn outboard_size(len: usize) -> usize {
+    let chunks: usize = len.div_ceil(CHUNK_LEN).max(1);
+
+    LENGTH_SIZE + (chunks - 1) * PARENT_SIZE
+}
+
+/// Get the path of the outboard of a chunk, e.g. `0.obao` for `0`.
+pub fn outboard_path<P: AsRef<Path>>(chunk: P) -> PathBuf {
+    let mut path = chunk.as_ref().as_os_str().to_os_string();
+
+    path.push(".");
+    path.push(BAO_OUTBOARD_EXTENSION);
+
+    PathBuf::from(path)
+}
+
+/// Encode the parent node of the data, returning its children.
+fn children(
+    data: &[u8],
+    offset: u64,
+    encoded: &mut Vec<u8>,
+) -> (ChainingValue, ChainingValue) {
+    let position: usize = encoded.len();
+
+    encoded.extend_from_slice(&[0; PARENT_SIZE]);
+
+    let split: usize = left_subtree_len(data.len() as u64) as usize;
+
+    let left: ChainingValue = subtree(&data[..split], offset, encoded);
+    let right: ChainingValue =
+        subtree(&data[split..], offset + split as u64, encoded);
+
+    encoded[position..position + 32].copy_from_slice(&left);
+    encoded[position + 32..position + PARENT_SIZE].copy_from_slice(&right);
+
+    (left, right)
+}
+
+/// Encode the parent nodes of a subtree, returning its chaining value.
+fn subtree(
+    data: &[u8],
+    offset: u64,
+    encoded: &mut Vec<u8>,
+) -> ChainingValue {
+    if data.len() <= CHUNK_LEN {
+        return Hasher::new()
+            .set_input_offset(offset)
+            .update(data)
+            .finalize_non_root();
+    }
+
+    let (left, right) = children(data, offset, encoded);
+
+    merge_subtrees_non_root(&left, &right, Mode::Hash)
+}
+
+/// Subtree waiting to be verified.
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    hash: ChainingValue,
+    offset: u64,
+    len: u64,
+    root: bool,
+}
+
+/// Verifier of the data streaming against its BLAKE3 hash and bao outboard
+/// from [`outboard`].
+///
+/// Each group of [`CHUNK_LEN`] bytes is verified as soon as it is complete,
+/// so corrupted data is found at the first corrupt group instead of after
+/// reading all data. It keeps at most one group and the path to the root in
+/// memory.
+#[derive(Debug, Clone)]
+pub struct BaoVerifier {
+    outboard: Vec<u8>,
+    cursor: usize,
+    stack: Vec<Pending>,
+    buffer: Vec<u8>,
+    verified: u64,
+}
+
+impl BaoVerifier {
+    /// Create a new verifier of the data with the BLAKE3 hash and the
+    /// outboard.
+    pub fn new(
+        hash: [u8; 32],
+        outboard: Vec<u8>,
+    ) -> ioa::Result<Self> {
+        let len: u64 = match outboard.get(..LENGTH_SIZE) {
+            | Some(bytes) => u64::from_le_bytes(bytes.try_into().unwrap()),
+            | None => return Err(invalid("outboard is truncated")),
+        };
+
+        if outboard.len() != outboard_size(len as usize) {
+            return Err(invalid("outboard size does not match the length"));
+        }
+
+        Ok(Self {
+            outboard,
+            cursor: LENGTH_SIZE,
+            stack: vec![Pending { hash, offset: 0, len, root: true }],
+            buffer: Vec::with_capacity(CHUNK_LEN),
+            verified: 0,
+        })
+    }
+
+    /// Get the number of bytes verified.
+    pub fn verified(&self) -> u64 {
+        self.verified
+    }
+
+    /// Verify the next part of the data, failing with
+    /// [`ioa::ErrorKind::InvalidData`] at the first corrupt group.
+    pub fn update(
+        &mut self,
+        mut data: &[u8],
+    ) -> ioa::Result<()> {
+        while !data.is_empty() {
+            self.advance()?;
+
+            let leaf: Pending = match self.stack.last() {
+                | Some(leaf) => *leaf,
+                | None => {
+                    return Err(invalid("data is longer than the outboard"))
+                },
+            };
+
+            let take: usize =
+                (leaf.len as usize - self.buffer.len()).min(data.len());
+
+            self.buffer.extend_from_slice(&data[..take]);
+
+            data = &data[take..];
+        }
+
+        self.advance()
+    }
+
+    /// Verify the end of the data, failing with
+    /// [`ioa::ErrorKind::InvalidData`] if any of it is missing.
+    pub fn finish(mut self) -> ioa::Result<()> {
+        self.advance()?;
+
+        if !self.stack.is_empty() {
+            return Err(invalid("data is shorter than the outboard"));
+        }
+
+        Ok(())
+    }
+
+    /// Expand the parent nodes and verify the complete group, until the
+    /// next group needs more data.
+    fn advance(&mut self) -> ioa::Result<()> {
+        while let Some(top) = self.stack.last().copied() {
+            if top.len > CHUNK_LEN as u64 {
+                self.stack.pop();
+                self.expand(top)?;
+                continue;
+            }
+
+            if self.buffer.len() < top.len as usize {
+                return Ok(());
+            }
+
+            let matched: bool = if top.root {
+                blake3::hash(&self.buffer) == Hash::from_bytes(top.hash)
+            } else {
+                Hash::from_bytes(
+                    Hasher::new()
+                        .set_input_offset(top.offset)
+                        .update(&self.buffer)
+                        .finalize_non_root(),
+                ) == Hash::from_bytes(top.hash)
+            };
+
+            if !matched {
+                return Err(invalid(format!(
+                    "data is corrupted at offset {}",
+                    top.offset
+                )));
+            }
+
+            self.stack.pop();
+            self.buffer.clear();
+            self.verified += top.len;
+        }
+
+        Ok(())
+    }
+
+    /// Verify the parent node of the subtree, pushing its children.
+    fn expand(
+        &mut self,
+        parent: Pending,
+    ) -> ioa::Result<()> {
+        let node: &[u8] = self
+            .outboard
+            .get(self.cursor..self.cursor + PARENT_SIZE)
+            .ok_or_else(|| invalid("outboard is truncated"))?;
+
+        let left: ChainingValue = node[..32].try_into().unwrap();
+        let right: ChainingValue = node[32..].try_into().unwrap();
+
+        let hash: Hash = if parent.root {
+            merge_subtrees_root(&left, &right, Mode::Hash)
+        } else {
+            Hash::from_bytes(merge_subtrees_non_root(&left, &right, Mode::Hash))
+        };
+
+        if hash != Hash::from_bytes(parent.hash) {
+            return Err(invalid(format!(
+                "outboard is corrupted at offset {}",
+                parent.offset
+            )));
+        }
+
+        self.cursor += PARENT_SIZE;
+
+        let split: u64 = left_subtree_len(parent.len);
+
+        self.stack.push(Pending {
+            hash: right,
+            offset: parent.offset + split,
+            len: parent.len - split,
+            root: false,
+        });
+
+        self.stack.push(Pending {
+            hash: left,
+            offset: parent.offset,
+            len: split,
+            root: false,
+        });
+
+        Ok(())
+    }
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
+    error: E
+) -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::InvalidData, error)
+}
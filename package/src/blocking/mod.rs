@@ -0,0 +1,100 @@
+use std::sync::OnceLock;
+
+use tokio::{io as ioa, runtime::Runtime};
+
+use crate::{
+    check::{Check, CheckResult},
+    merge::{Merge, MergeResult},
+    split::{Split, SplitResult},
+};
+
+/// Runtime to drive the processes, created on first use.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+fn runtime() -> ioa::Result<&'static Runtime> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    // the drivers are required by remote stores and stall timeouts
+    let runtime: Runtime =
+        tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Run the split process and block the current thread until it finishes.
+///
+/// It must not be called within an async runtime.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     blocking,
+///     split::{Split, SplitResult},
+/// };
+///
+/// let result: SplitResult = blocking::split(
+///     Split::new()
+///         .in_file("/path/to/file")
+///         .out_dir(PathBuf::from("path").join("to").join("dir")),
+/// )
+/// .unwrap();
+/// ```
+pub fn split(split: Split) -> ioa::Result<SplitResult> {
+    runtime()?.block_on(split.run())
+}
+
+/// Run the check process and block the current thread until it finishes.
+///
+/// It must not be called within an async runtime.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     blocking,
+///     check::{Check, CheckResult},
+/// };
+///
+/// let result: CheckResult = blocking::check(
+///     Check::new()
+///         .in_dir(PathBuf::from("path").join("to").join("dir"))
+///         .file_size(0) // result from split function...
+///         .total_chunks(0), // result from split function...
+/// )
+/// .unwrap();
+/// ```
+pub fn check(check: Check) -> ioa::Result<CheckResult> {
+    runtime()?.block_on(check.run())
+}
+
+/// Run the merge process and block the current thread until it finishes.
+///
+/// It must not be called within an async runtime.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     blocking,
+///     merge::{Merge, MergeResult},
+/// };
+///
+/// let result: MergeResult = blocking::merge(
+///     Merge::new()
+///         .in_dir(PathBuf::from("path").join("to").join("dir"))
+///         .out_file(PathBuf::from("path").join("to").join("file")),
+/// )
+/// .unwrap();
+/// ```
+pub fn merge(merge: Merge) -> ioa::Result<MergeResult> {
+    runtime()?.block_on(merge.run())
+}
@@ -0,0 +1,346 @@
+use std::{io::SeekFrom, path::Path};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt},
+};
+
+use super::BoundaryPolicy;
+
+/// Container format of a media file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaContainer {
+    /// Fragmented MP4, which starts every fragment with a `moof` box.
+    Mp4,
+    /// Matroska / WebM, which starts every cluster with a `Cluster` element.
+    Matroska,
+}
+
+/// Policy to end every chunk right before the last safe point of a media
+/// container, such as a fragment of MP4 or a cluster of Matroska.
+///
+/// The safe points are found by walking the top-level MP4 boxes or the
+/// Matroska element headers, so the bytes of samples never count as one.
+/// Fragments and clusters usually start with a keyframe, so every chunk
+/// except the first one begins where a player can start decoding. The chunks
+/// are still merged into the byte-exact original file. A chunk without any
+/// safe point, e.g. within a non-fragmented MP4, still ends at `chunk_size`.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use filego::{
+///     boundary::{KeyframeAligned, MediaContainer},
+///     split::{Split, SplitMode},
+/// };
+///
+/// async fn example() {
+///     let policy: KeyframeAligned =
+///         KeyframeAligned::read("/path/to/video.mp4", MediaContainer::Mp4)
+///             .await
+///             .unwrap();
+///
+///     let split: Split = Split::new()
+///         .in_file("/path/to/video.mp4")
+///         .mode(SplitMode::Custom(Arc::new(policy)));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyframeAligned {
+    container: MediaContainer,
+    safe_points: Vec<usize>,
+}
+
+impl KeyframeAligned {
+    /// Read the safe points from the structure of a media file.
+    pub async fn read<P: AsRef<Path>>(
+        path: P,
+        container: MediaContainer,
+    ) -> ioa::Result<Self> {
+        let mut file: fsa::File =
+            fsa::OpenOptions::new().read(true).open(path.as_ref()).await?;
+
+        let file_size: u64 = file.metadata().await?.len();
+
+        let safe_points: Vec<u64> = match container {
+            | MediaContainer::Mp4 => {
+                mp4_fragments(&mut file, file_size).await?
+            },
+            | MediaContainer::Matroska => {
+                matroska_clusters(&mut file, file_size).await?
+            },
+        };
+
+        Ok(Self {
+            container,
+            safe_points: safe_points.into_iter().map(|p| p as usize).collect(),
+        })
+    }
+
+    /// Get the container format of the input file.
+    pub fn container(&self) -> MediaContainer {
+        self.container
+    }
+
+    /// Get the offsets of the safe points in the original file.
+    pub fn safe_points(&self) -> &[usize] {
+        &self.safe_points
+    }
+}
+
+impl BoundaryPolicy for KeyframeAligned {
+    fn boundary(
+        &self,
+        offset: usize,
+        data: &[u8],
+    ) -> usize {
+        let end: usize = offset + data.len();
+
+        // safe points are sorted, find the last one within the chunk
+        let i: usize = self.safe_points.partition_point(|p| *p <= end);
+
+        self.safe_points[..i]
+            .last()
+            .filter(|p| **p > offset)
+            .map_or(data.len(), |p| p - offset)
+    }
+}
+
+fn invalid_data(message: &str) -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Read up to `buf.len()` bytes at `pos`, returns the number of bytes read.
+async fn read_at(
+    file: &mut fsa::File,
+    pos: u64,
+    buf: &mut [u8],
+) -> ioa::Result<usize> {
+    file.seek(SeekFrom::Start(pos)).await?;
+
+    let mut read: usize = 0;
+
+    while read < buf.len() {
+        let n: usize = file.read(&mut buf[read..]).await?;
+
+        if n == 0 {
+            break;
+        }
+
+        read += n;
+    }
+
+    Ok(read)
+}
+
+/// Walk the top-level boxes of an MP4 file and get the `moof` offsets.
+async fn mp4_fragments(
+    file: &mut fsa::File,
+    file_size: u64,
+) -> ioa::Result<Vec<u64>> {
+    let mut fragments: Vec<u64> = Vec::new();
+    let mut pos: u64 = 0;
+
+    while pos < file_size {
+        // size (u32), type, optional largesize (u64)
+        let mut header: [u8; 16] = [0; 16];
+        let read: usize = read_at(file, pos, &mut header).await?;
+
+        if read < 8 {
+            return Err(invalid_data("truncated mp4 box header"));
+        }
+
+        // the size covers the header, of 16 bytes with a largesize
+        let (size, header_size): (u64, u64) = match u32::from_be_bytes([
+            header[0], header[1], header[2], header[3],
+        ]) {
+            // extends to the end of the file
+            | 0 => (file_size - pos, 8),
+            | 1 => {
+                if read < 16 {
+                    return Err(invalid_data("truncated mp4 box header"));
+                }
+
+                let size: u64 = u64::from_be_bytes([
+                    header[8], header[9], header[10], header[11], header[12],
+                    header[13], header[14], header[15],
+                ]);
+
+                (size, 16)
+            },
+            | s => (s as u64, 8),
+        };
+
+        if size < header_size || size > file_size - pos {
+            return Err(invalid_data("invalid mp4 box size"));
+        }
+
+        if &header[4..8] == b"moof" {
+            fragments.push(pos);
+        }
+
+        pos += size;
+    }
+
+    Ok(fragments)
+}
+
+const EBML_ID: u32 = 0x1A45_DFA3;
+const SEGMENT_ID: u32 = 0x1853_8067;
+const CLUSTER_ID: u32 = 0x1F43_B675;
+
+/// IDs of the elements at the level of `Cluster` within a `Segment`, which
+/// also end a `Cluster` of unknown size.
+const SEGMENT_CHILD_IDS: [u32; 8] = [
+    CLUSTER_ID,
+    0x114D_9B74, // SeekHead
+    0x1549_A966, // Info
+    0x1654_AE6B, // Tracks
+    0x1C53_BB6B, // Cues
+    0x1941_A469, // Attachments
+    0x1043_A770, // Chapters
+    0x1254_C367, // Tags
+];
+
+/// Header of an EBML element.
+struct EbmlHeader {
+    id: u32,
+    /// Size of the header itself.
+    len: u64,
+    /// Size of the data, `None` if unknown.
+    size: Option<u64>,
+}
+
+/// Read the header of the EBML element at `pos`.
+async fn ebml_header(
+    file: &mut fsa::File,
+    pos: u64,
+) -> ioa::Result<EbmlHeader> {
+    // ID (1..=4 bytes), size (1..=8 bytes)
+    let mut header: [u8; 12] = [0; 12];
+    let read: usize = read_at(file, pos, &mut header).await?;
+
+    let id_len: usize = header[0].leading_zeros() as usize + 1;
+
+    if read == 0 || id_len > 4 || read < id_len + 1 {
+        return Err(invalid_data("invalid matroska element id"));
+    }
+
+    let id: u32 =
+        header[..id_len].iter().fold(0, |id, b| (id << 8) | *b as u32);
+
+    let size_len: usize = header[id_len].leading_zeros() as usize + 1;
+
+    if size_len > 8 || read < id_len + size_len {
+        return Err(invalid_data("invalid matroska element size"));
+    }
+
+    // remove the length marker from the first byte
+    let first: u8 = header[id_len] & (0xFF_u16 >> size_len) as u8;
+
+    let value: u64 = header[id_len + 1..id_len + size_len]
+        .iter()
+        .fold(first as u64, |v, b| (v << 8) | *b as u64);
+
+    // all ones means the size is unknown
+    let unknown: u64 = (1u64 << (7 * size_len)) - 1;
+
+    Ok(EbmlHeader {
+        id,
+        len: (id_len + size_len) as u64,
+        size: if value == unknown { None } else { Some(value) },
+    })
+}
+
+/// Walk the element headers of a Matroska file and get the `Cluster`
+/// offsets.
+async fn matroska_clusters(
+    file: &mut fsa::File,
+    file_size: u64,
+) -> ioa::Result<Vec<u64>> {
+    let mut clusters: Vec<u64> = Vec::new();
+    let mut pos: u64 = 0;
+
+    // top level: EBML header, segments
+    while pos < file_size {
+        let header: EbmlHeader = ebml_header(file, pos).await?;
+        let data: u64 = pos + header.len;
+
+        let end: u64 = match header.size {
+            | Some(size) if size <= file_size.saturating_sub(data) => {
+                data + size
+            },
+            | None if header.id == SEGMENT_ID => file_size,
+            | _ => return Err(invalid_data("invalid matroska element size")),
+        };
+
+        match header.id {
+            | SEGMENT_ID => {
+                segment_clusters(file, data, end, &mut clusters).await?;
+            },
+            | EBML_ID => {},
+            | _ => return Err(invalid_data("invalid matroska top level")),
+        }
+
+        pos = end;
+    }
+
+    Ok(clusters)
+}
+
+/// Walk the children of a `Segment` within `pos..end`.
+async fn segment_clusters(
+    file: &mut fsa::File,
+    mut pos: u64,
+    end: u64,
+    clusters: &mut Vec<u64>,
+) -> ioa::Result<()> {
+    while pos < end {
+        let header: EbmlHeader = ebml_header(file, pos).await?;
+        let data: u64 = pos + header.len;
+
+        if header.id == CLUSTER_ID {
+            clusters.push(pos);
+        }
+
+        pos = match header.size {
+            | Some(size) if size <= end.saturating_sub(data) => data + size,
+            | None if header.id == CLUSTER_ID => {
+                cluster_end(file, data, end).await?
+            },
+            | _ => return Err(invalid_data("invalid matroska element size")),
+        };
+    }
+
+    Ok(())
+}
+
+/// Find the end of a `Cluster` of unknown size starting its data at `pos`.
+async fn cluster_end(
+    file: &mut fsa::File,
+    mut pos: u64,
+    end: u64,
+) -> ioa::Result<u64> {
+    while pos < end {
+        let header: EbmlHeader = ebml_header(file, pos).await?;
+
+        // the next element of the segment ends the cluster
+        if SEGMENT_CHILD_IDS.contains(&header.id)
+            || header.id == EBML_ID
+            || header.id == SEGMENT_ID
+        {
+            return Ok(pos);
+        }
+
+        let data: u64 = pos + header.len;
+
+        pos = match header.size {
+            | Some(size) if size <= end.saturating_sub(data) => data + size,
+            | _ => return Err(invalid_data("invalid matroska element size")),
+        };
+    }
+
+    Ok(end)
+}
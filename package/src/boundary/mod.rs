@@ -0,0 +1,167 @@
+use std::{collections::BTreeMap, fmt, ops::Range};
+
+#[cfg(feature = "media")]
+mod media;
+#[cfg(feature = "parquet")]
+mod parquet;
+
+#[cfg(feature = "media")]
+pub use media::{KeyframeAligned, MediaContainer};
+#[cfg(feature = "parquet")]
+pub use parquet::RowGroupAligned;
+
+/// Policy to decide where a chunk ends during the split process.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use filego::{
+///     boundary::BoundaryPolicy,
+///     split::{Split, SplitMode},
+/// };
+///
+/// #[derive(Debug)]
+/// struct HalfChunk;
+///
+/// impl BoundaryPolicy for HalfChunk {
+///     fn boundary(
+///         &self,
+///         _offset: usize,
+///         data: &[u8],
+///     ) -> usize {
+///         data.len() / 2
+///     }
+/// }
+///
+/// let split: Split = Split::new().mode(SplitMode::Custom(Arc::new(HalfChunk)));
+/// ```
+pub trait BoundaryPolicy: fmt::Debug + Send + Sync {
+    /// Get the end of the chunk within `data`.
+    ///
+    /// `data` contains `chunk_size` bytes starting from `offset` of the
+    /// original file, or the remaining bytes at the end of the file. The
+    /// returned value is the size of the chunk, which is clamped to
+    /// `1..=data.len()`.
+    fn boundary(
+        &self,
+        offset: usize,
+        data: &[u8],
+    ) -> usize;
+
+    /// Get the custom metadata recorded in the manifest for the chunk at
+    /// `range` of the original file.
+    ///
+    /// By default, no metadata is recorded.
+    fn metadata(
+        &self,
+        range: Range<usize>,
+    ) -> BTreeMap<String, String> {
+        let _ = range;
+
+        BTreeMap::new()
+    }
+}
+
+/// Policy to end every chunk at `chunk_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FixedSize;
+
+impl BoundaryPolicy for FixedSize {
+    fn boundary(
+        &self,
+        _offset: usize,
+        data: &[u8],
+    ) -> usize {
+        data.len()
+    }
+}
+
+/// Policy to end every chunk right after the last `delimiter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordAligned {
+    /// The byte that terminates a record, e.g. `b'\n'`.
+    pub delimiter: u8,
+}
+
+impl BoundaryPolicy for RecordAligned {
+    fn boundary(
+        &self,
+        _offset: usize,
+        data: &[u8],
+    ) -> usize {
+        data.iter()
+            .rposition(|b| *b == self.delimiter)
+            .map_or(data.len(), |i| i + 1)
+    }
+}
+
+/// Policy to end chunks based on the content with a rolling hash.
+///
+/// Inserting or removing bytes in the original file only affects the
+/// chunks around the change, which is useful for deduplication.
+/// The `chunk_size` of the split process works as the maximum size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentDefined {
+    min_size: usize,
+    mask: u64,
+}
+
+impl ContentDefined {
+    /// Create a new content-defined policy.
+    ///
+    /// Chunks will be at least `min_size` bytes and around `avg_size`
+    /// bytes on average.
+    pub fn new(
+        min_size: usize,
+        avg_size: usize,
+    ) -> Self {
+        let bits: u32 = avg_size.max(1).next_power_of_two().trailing_zeros();
+
+        // use the high bits, which depend on a wider window of bytes
+        let mask: u64 =
+            if bits == 0 { 0 } else { u64::MAX << (u64::BITS - bits) };
+
+        Self { min_size, mask }
+    }
+}
+
+impl BoundaryPolicy for ContentDefined {
+    fn boundary(
+        &self,
+        _offset: usize,
+        data: &[u8],
+    ) -> usize {
+        let mut hash: u64 = 0;
+
+        for (i, b) in data.iter().enumerate() {
+            hash = (hash << 1).wrapping_add(GEAR[*b as usize]);
+
+            if i + 1 >= self.min_size && hash & self.mask == 0 {
+                return i + 1;
+            }
+        }
+
+        data.len()
+    }
+}
+
+/// Random values for the gear rolling hash.
+const GEAR: [u64; 256] = {
+    let mut table: [u64; 256] = [0; 256];
+    let mut state: u64 = 0;
+    let mut i: usize = 0;
+
+    // splitmix64
+    while i < 256 {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z: u64 = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+
+    table
+};
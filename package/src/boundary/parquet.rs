@@ -0,0 +1,449 @@
+use std::{collections::BTreeMap, io::SeekFrom, ops::Range, path::Path};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt},
+};
+
+use super::BoundaryPolicy;
+
+/// Magic bytes at the start and the end of a Parquet file.
+const MAGIC: &[u8; 4] = b"PAR1";
+
+/// Key of the chunk metadata listing the row groups within the chunk.
+const ROW_GROUPS_KEY: &str = "row_groups";
+
+/// Maximum nesting of the values skipped in the Parquet metadata.
+const MAX_DEPTH: usize = 64;
+
+/// Policy to end every chunk right before the last row group boundary of a
+/// Parquet file.
+///
+/// Every chunk except the first one starts at a row group (or the footer),
+/// so workers can parse their chunks with the footer of the file. A row group
+/// larger than `chunk_size` still ends at `chunk_size`. The indices of the
+/// row groups within each chunk are recorded in the `row_groups` metadata of
+/// the chunk in the manifest, e.g. `"2,3"`.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use filego::{
+///     boundary::RowGroupAligned,
+///     split::{Split, SplitMode},
+/// };
+///
+/// async fn example() {
+///     let policy: RowGroupAligned =
+///         RowGroupAligned::read("/path/to/file.parquet").await.unwrap();
+///
+///     let split: Split = Split::new()
+///         .in_file("/path/to/file.parquet")
+///         .mode(SplitMode::Custom(Arc::new(policy)));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowGroupAligned {
+    row_groups: Vec<Range<usize>>,
+    footer: usize,
+}
+
+impl RowGroupAligned {
+    /// Read the row groups from the metadata of a Parquet file.
+    pub async fn read<P: AsRef<Path>>(path: P) -> ioa::Result<Self> {
+        let mut file: fsa::File =
+            fsa::OpenOptions::new().read(true).open(path.as_ref()).await?;
+
+        let file_size: usize = file.metadata().await?.len() as usize;
+
+        if file_size < 12 {
+            return Err(invalid_data("file is too small to be parquet"));
+        }
+
+        // footer: metadata, metadata length, magic
+        let mut tail: [u8; 8] = [0; 8];
+        file.seek(SeekFrom::Start((file_size - 8) as u64)).await?;
+        file.read_exact(&mut tail).await?;
+
+        if &tail[4..] != MAGIC {
+            return Err(invalid_data("file is not parquet"));
+        }
+
+        let meta_len: usize =
+            u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]) as usize;
+
+        if meta_len + 12 > file_size {
+            return Err(invalid_data("invalid parquet metadata length"));
+        }
+
+        let footer: usize = file_size - 8 - meta_len;
+
+        let mut meta: Vec<u8> = vec![0; meta_len];
+        file.seek(SeekFrom::Start(footer as u64)).await?;
+        file.read_exact(&mut meta).await?;
+
+        let starts: Vec<usize> =
+            CompactReader::new(&meta).row_group_starts()?;
+
+        let mut row_groups: Vec<Range<usize>> =
+            Vec::with_capacity(starts.len());
+
+        for (i, start) in starts.iter().enumerate() {
+            let end: usize = starts.get(i + 1).copied().unwrap_or(footer);
+
+            if *start >= end || end > footer {
+                return Err(invalid_data("invalid parquet row group offset"));
+            }
+
+            row_groups.push(*start..end);
+        }
+
+        Ok(Self { row_groups, footer })
+    }
+
+    /// Get the byte ranges of the row groups in the original file.
+    pub fn row_groups(&self) -> &[Range<usize>] {
+        &self.row_groups
+    }
+
+    /// Get the start of the footer in the original file.
+    pub fn footer(&self) -> usize {
+        self.footer
+    }
+}
+
+impl BoundaryPolicy for RowGroupAligned {
+    fn boundary(
+        &self,
+        offset: usize,
+        data: &[u8],
+    ) -> usize {
+        let end: usize = offset + data.len();
+
+        self.row_groups
+            .iter()
+            .map(|r| r.start)
+            .chain(std::iter::once(self.footer))
+            .filter(|b| *b > offset && *b <= end)
+            .max()
+            .map_or(data.len(), |b| b - offset)
+    }
+
+    fn metadata(
+        &self,
+        range: Range<usize>,
+    ) -> BTreeMap<String, String> {
+        let row_groups: Vec<String> = self
+            .row_groups
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.start < range.end && range.start < r.end)
+            .map(|(i, _)| i.to_string())
+            .collect();
+
+        let mut metadata: BTreeMap<String, String> = BTreeMap::new();
+
+        if !row_groups.is_empty() {
+            metadata.insert(ROW_GROUPS_KEY.to_string(), row_groups.join(","));
+        }
+
+        metadata
+    }
+}
+
+fn invalid_data(message: &str) -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Minimal reader of the thrift compact protocol for the Parquet metadata.
+struct CompactReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CompactReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Read the start offsets of the row groups in `FileMetaData`.
+    fn row_group_starts(&mut self) -> ioa::Result<Vec<usize>> {
+        let mut starts: Vec<usize> = Vec::new();
+        let mut last_id: i16 = 0;
+
+        // FileMetaData
+        while let Some((id, kind)) = self.field_header(&mut last_id)? {
+            if id != 4 || kind != TYPE_LIST {
+                self.skip(kind, 0)?;
+                continue;
+            }
+
+            // row_groups: list<RowGroup>
+            let (size, elem) = self.list_header()?;
+
+            for _ in 0..size {
+                if elem != TYPE_STRUCT {
+                    return Err(invalid_data("invalid parquet row group"));
+                }
+
+                starts.push(self.row_group_start()?);
+            }
+        }
+
+        Ok(starts)
+    }
+
+    /// Read a `RowGroup` and get the start offset of its first column.
+    fn row_group_start(&mut self) -> ioa::Result<usize> {
+        let mut start: Option<usize> = None;
+        let mut file_offset: Option<usize> = None;
+        let mut last_id: i16 = 0;
+
+        while let Some((id, kind)) = self.field_header(&mut last_id)? {
+            match (id, kind) {
+                // columns: list<ColumnChunk>
+                | (1, TYPE_LIST) => {
+                    let (size, elem) = self.list_header()?;
+
+                    for _ in 0..size {
+                        if elem != TYPE_STRUCT {
+                            return Err(invalid_data("invalid parquet column"));
+                        }
+
+                        let column: Option<usize> =
+                            self.column_chunk_start()?;
+
+                        start = match (start, column) {
+                            | (Some(a), Some(b)) => Some(a.min(b)),
+                            | (a, b) => a.or(b),
+                        };
+                    }
+                },
+                // file_offset: i64
+                | (5, TYPE_I64) => {
+                    file_offset = Some(self.offset()?);
+                },
+                | _ => self.skip(kind, 0)?,
+            }
+        }
+
+        start
+            .or(file_offset)
+            .ok_or_else(|| invalid_data("missing parquet row group offset"))
+    }
+
+    /// Read a `ColumnChunk` and get the offset of its first page.
+    fn column_chunk_start(&mut self) -> ioa::Result<Option<usize>> {
+        let mut start: Option<usize> = None;
+        let mut last_id: i16 = 0;
+
+        while let Some((id, kind)) = self.field_header(&mut last_id)? {
+            if id != 3 || kind != TYPE_STRUCT {
+                self.skip(kind, 0)?;
+                continue;
+            }
+
+            // meta_data: ColumnMetaData
+            let mut data_page: Option<usize> = None;
+            let mut dictionary_page: Option<usize> = None;
+            let mut meta_last_id: i16 = 0;
+
+            while let Some((id, kind)) = self.field_header(&mut meta_last_id)? {
+                match (id, kind) {
+                    | (9, TYPE_I64) => {
+                        data_page = Some(self.offset()?);
+                    },
+                    | (11, TYPE_I64) => {
+                        dictionary_page = Some(self.offset()?);
+                    },
+                    | _ => self.skip(kind, 0)?,
+                }
+            }
+
+            start = match (data_page, dictionary_page) {
+                | (Some(a), Some(b)) if b > 0 => Some(a.min(b)),
+                | (a, _) => a,
+            };
+        }
+
+        Ok(start)
+    }
+
+    fn byte(&mut self) -> ioa::Result<u8> {
+        let b: u8 = *self.data.get(self.pos).ok_or_else(|| {
+            invalid_data("unexpected end of parquet metadata")
+        })?;
+
+        self.pos += 1;
+
+        Ok(b)
+    }
+
+    fn varint(&mut self) -> ioa::Result<u64> {
+        let mut value: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let b: u8 = self.byte()?;
+
+            value |= ((b & 0x7F) as u64) << shift;
+
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(invalid_data("invalid varint in parquet metadata"))
+    }
+
+    fn varint_i64(&mut self) -> ioa::Result<i64> {
+        let value: u64 = self.varint()?;
+
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Read an `i64` offset in the file, which must not be negative.
+    fn offset(&mut self) -> ioa::Result<usize> {
+        usize::try_from(self.varint_i64()?)
+            .map_err(|_| invalid_data("negative offset in parquet metadata"))
+    }
+
+    /// Read a field header, returns `None` at the end of a struct.
+    fn field_header(
+        &mut self,
+        last_id: &mut i16,
+    ) -> ioa::Result<Option<(i16, u8)>> {
+        let b: u8 = self.byte()?;
+
+        if b == TYPE_STOP {
+            return Ok(None);
+        }
+
+        let delta: i16 = (b >> 4) as i16;
+
+        let id: i16 = if delta == 0 {
+            i16::try_from(self.varint_i64()?).map_err(|_| {
+                invalid_data("field id out of range in parquet metadata")
+            })?
+        } else {
+            last_id.checked_add(delta).ok_or_else(|| {
+                invalid_data("field id out of range in parquet metadata")
+            })?
+        };
+
+        *last_id = id;
+
+        Ok(Some((id, b & 0x0F)))
+    }
+
+    fn list_header(&mut self) -> ioa::Result<(usize, u8)> {
+        let b: u8 = self.byte()?;
+
+        let size: usize = match b >> 4 {
+            | 15 => self.varint()? as usize,
+            | s => s as usize,
+        };
+
+        Ok((size, b & 0x0F))
+    }
+
+    /// Skip an element of a list, set or map.
+    fn skip_element(
+        &mut self,
+        kind: u8,
+        depth: usize,
+    ) -> ioa::Result<()> {
+        match kind {
+            // booleans in collections are encoded as one byte
+            | TYPE_TRUE | TYPE_FALSE => {
+                self.byte()?;
+
+                Ok(())
+            },
+            | _ => self.skip(kind, depth),
+        }
+    }
+
+    /// Skip a value of `kind` nested at `depth`.
+    fn skip(
+        &mut self,
+        kind: u8,
+        depth: usize,
+    ) -> ioa::Result<()> {
+        if depth >= MAX_DEPTH {
+            return Err(invalid_data("parquet metadata is nested too deeply"));
+        }
+
+        match kind {
+            | TYPE_TRUE | TYPE_FALSE => {},
+            | TYPE_BYTE => {
+                self.byte()?;
+            },
+            | TYPE_I16 | TYPE_I32 | TYPE_I64 => {
+                self.varint()?;
+            },
+            | TYPE_DOUBLE => {
+                for _ in 0..8 {
+                    self.byte()?;
+                }
+            },
+            | TYPE_BINARY => {
+                let len: usize = self.varint()? as usize;
+
+                if len > self.data.len() - self.pos {
+                    return Err(invalid_data(
+                        "unexpected end of parquet metadata",
+                    ));
+                }
+
+                self.pos += len;
+            },
+            | TYPE_LIST | TYPE_SET => {
+                let (size, elem) = self.list_header()?;
+
+                for _ in 0..size {
+                    self.skip_element(elem, depth + 1)?;
+                }
+            },
+            | TYPE_MAP => {
+                let size: usize = self.varint()? as usize;
+
+                if size > 0 {
+                    let types: u8 = self.byte()?;
+
+                    for _ in 0..size {
+                        self.skip_element(types >> 4, depth + 1)?;
+                        self.skip_element(types & 0x0F, depth + 1)?;
+                    }
+                }
+            },
+            | TYPE_STRUCT => {
+                let mut last_id: i16 = 0;
+
+                while let Some((_, kind)) = self.field_header(&mut last_id)? {
+                    self.skip(kind, depth + 1)?;
+                }
+            },
+            | _ => {
+                return Err(invalid_data("invalid type in parquet metadata"))
+            },
+        }
+
+        Ok(())
+    }
+}
+
+const TYPE_STOP: u8 = 0;
+const TYPE_TRUE: u8 = 1;
+const TYPE_FALSE: u8 = 2;
+const TYPE_BYTE: u8 = 3;
+const TYPE_I16: u8 = 4;
+const TYPE_I32: u8 = 5;
+const TYPE_I64: u8 = 6;
+const TYPE_DOUBLE: u8 = 7;
+const TYPE_BINARY: u8 = 8;
+const TYPE_LIST: u8 = 9;
+const TYPE_SET: u8 = 10;
+const TYPE_MAP: u8 = 11;
+const TYPE_STRUCT: u8 = 12;
@@ -0,0 +1,576 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{io as ioa, task::JoinHandle};
+#[cfg(feature = "xxhash")]
+use xxhash_rust::{xxh3, xxh64};
+
+use crate::{
+    config::CHECKSUM_WORKERS_DEFAULT,
+    manifest::ChunkInfo,
+    profile::{Phase, Profiler},
+    rng::Rng,
+};
+
+/// Algorithm of the checksums of the chunks.
+///
+/// [`Algorithm::Sha256`] and [`Algorithm::Blake3`] resist deliberate
+/// tampering, while [`Algorithm::Crc32c`] and [`Algorithm::Crc32`] only
+/// detect accidental corruption but are much faster, e.g. for transfers
+/// within a trusted network. With the `xxhash` feature, `Algorithm::Xxh3`
+/// and `Algorithm::Xxh64` detect accidental corruption with 64 bits, at a
+/// speed close to the memory bandwidth.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{checksum::Algorithm, split::Split};
+///
+/// async fn example() {
+///     Split::new()
+///         .in_file(PathBuf::from("path").join("to").join("file"))
+///         .out_dir(PathBuf::from("path").join("to").join("dir"))
+///         .checksum(Algorithm::Sha256)
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    /// SHA-256.
+    Sha256,
+    /// BLAKE3, accelerated by SIMD if available.
+    Blake3,
+    /// XXH3 with 64 bits, accelerated by SIMD if available.
+    #[cfg(feature = "xxhash")]
+    Xxh3,
+    /// XXH64.
+    #[cfg(feature = "xxhash")]
+    Xxh64,
+    /// CRC-32C (Castagnoli), accelerated by SSE 4.2 or ARMv8 CRC if
+    /// available.
+    Crc32c,
+    /// CRC-32 (IEEE), as used by zip and gzip.
+    Crc32,
+}
+
+impl Algorithm {
+    /// All the algorithms, from the strongest.
+    #[cfg(not(feature = "xxhash"))]
+    pub const ALL: [Self; 4] =
+        [Self::Sha256, Self::Blake3, Self::Crc32c, Self::Crc32];
+
+    /// All the algorithms, from the strongest.
+    #[cfg(feature = "xxhash")]
+    pub const ALL: [Self; 6] = [
+        Self::Sha256,
+        Self::Blake3,
+        Self::Xxh3,
+        Self::Xxh64,
+        Self::Crc32c,
+        Self::Crc32,
+    ];
+
+    /// Get the algorithm from its identity.
+    pub fn from_id<I: AsRef<str>>(id: I) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.id() == id.as_ref())
+    }
+
+    /// Get the identity of the algorithm, which is the key of the checksum
+    /// in the metadata of the chunks, e.g. `sha256`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            | Self::Sha256 => "sha256",
+            | Self::Blake3 => "blake3",
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh3 => "xxh3",
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh64 => "xxh64",
+            | Self::Crc32c => "crc32c",
+            | Self::Crc32 => "crc32",
+        }
+    }
+
+    /// Compute the checksum of the data in hex.
+    pub fn digest(
+        &self,
+        data: &[u8],
+    ) -> String {
+        match self {
+            | Self::Sha256 => to_hex(&Sha256::digest(data)),
+            | Self::Blake3 => blake3::hash(data).to_hex().to_string(),
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh3 => to_hex(&xxh3::xxh3_64(data).to_be_bytes()),
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh64 => to_hex(&xxh64::xxh64(data, 0).to_be_bytes()),
+            | Self::Crc32c => to_hex(&crc32c::crc32c(data).to_be_bytes()),
+            | Self::Crc32 => to_hex(&crc32fast::hash(data).to_be_bytes()),
+        }
+    }
+}
+
+/// Checksum of a chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkChecksum {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Algorithm of the checksum.
+    pub algorithm: Algorithm,
+    /// Checksum of the payload of the chunk in hex.
+    pub digest: String,
+}
+
+impl ChunkChecksum {
+    /// Get the checksums recorded in the metadata of the chunks.
+    pub fn of_chunks(chunks: &[ChunkInfo]) -> Vec<Self> {
+        chunks
+            .iter()
+            .flat_map(|chunk| {
+                Algorithm::ALL.into_iter().filter_map(|algorithm| {
+                    Some(Self {
+                        index: chunk.index,
+                        algorithm,
+                        digest: chunk.get_metadata(algorithm.id())?.to_string(),
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+/// Task computing the checksums of a chunk, with the time spent.
+type Hashing = JoinHandle<(Vec<String>, Duration)>;
+
+/// Checksums of the chunks computed on worker threads, so hashing runs
+/// concurrently with reading and writing.
+pub(crate) struct Workers {
+    algorithms: Arc<[Algorithm]>,
+    pending: VecDeque<(usize, Hashing)>,
+}
+
+impl Workers {
+    /// Create new workers computing the checksums with the algorithms.
+    pub(crate) fn new(algorithms: Vec<Algorithm>) -> Self {
+        Self {
+            algorithms: algorithms.into(),
+            pending: VecDeque::with_capacity(CHECKSUM_WORKERS_DEFAULT),
+        }
+    }
+
+    /// Compute the checksum of the chunk at `index` on a worker thread,
+    /// waiting for the oldest one first if all workers are busy.
+    pub(crate) async fn spawn(
+        &mut self,
+        index: usize,
+        data: Vec<u8>,
+        chunks: &mut [ChunkInfo],
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        if self.pending.len() >= CHECKSUM_WORKERS_DEFAULT {
+            self.next(chunks, profiler).await?;
+        }
+
+        let algorithms: Arc<[Algorithm]> = self.algorithms.clone();
+
+        let handle: Hashing = tokio::task::spawn_blocking(move || {
+            let start: Instant = Instant::now();
+
+            let digests: Vec<String> =
+                algorithms.iter().map(|a| a.digest(&data)).collect();
+
+            (digests, start.elapsed())
+        });
+
+        self.pending.push_back((index, handle));
+
+        Ok(())
+    }
+
+    /// Wait for all checksums, recording them in the metadata of the chunks.
+    pub(crate) async fn finish(
+        mut self,
+        chunks: &mut [ChunkInfo],
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        while !self.pending.is_empty() {
+            self.next(chunks, profiler).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the oldest checksum.
+    async fn next(
+        &mut self,
+        chunks: &mut [ChunkInfo],
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        let (index, handle) = match self.pending.pop_front() {
+            | Some(pending) => pending,
+            | None => return Ok(()),
+        };
+
+        let (digests, elapsed): (Vec<String>, Duration) =
+            handle.await.map_err(ioa::Error::other)?;
+
+        profiler.add(Phase::Hash, elapsed);
+
+        if let Some(chunk) = chunks.get_mut(index) {
+            for (algorithm, digest) in self.algorithms.iter().zip(digests) {
+                chunk.set_metadata(algorithm.id(), digest);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Incremental hasher of an [`Algorithm`].
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    #[cfg(feature = "xxhash")]
+    Xxh3(Box<xxh3::Xxh3>),
+    #[cfg(feature = "xxhash")]
+    Xxh64(xxh64::Xxh64),
+    Crc32c(u32),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            | Algorithm::Sha256 => Self::Sha256(Sha256::new()),
+            | Algorithm::Blake3 => Self::Blake3(Box::default()),
+            #[cfg(feature = "xxhash")]
+            | Algorithm::Xxh3 => Self::Xxh3(Box::default()),
+            #[cfg(feature = "xxhash")]
+            | Algorithm::Xxh64 => Self::Xxh64(xxh64::Xxh64::new(0)),
+            | Algorithm::Crc32c => Self::Crc32c(0),
+            | Algorithm::Crc32 => Self::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(
+        &mut self,
+        data: &[u8],
+    ) {
+        match self {
+            | Self::Sha256(hasher) => hasher.update(data),
+            | Self::Blake3(hasher) => {
+                hasher.update(data);
+            },
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh3(hasher) => hasher.update(data),
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh64(hasher) => hasher.update(data),
+            | Self::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+            | Self::Crc32(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            | Self::Sha256(hasher) => to_hex(&hasher.finalize()),
+            | Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh3(hasher) => to_hex(&hasher.digest().to_be_bytes()),
+            #[cfg(feature = "xxhash")]
+            | Self::Xxh64(hasher) => to_hex(&hasher.digest().to_be_bytes()),
+            | Self::Crc32c(crc) => to_hex(&crc.to_be_bytes()),
+            | Self::Crc32(hasher) => to_hex(&hasher.finalize().to_be_bytes()),
+        }
+    }
+}
+
+/// Checksums of a whole file, or of a chunk streamed in pieces, computed on a
+/// worker thread, updated with the data in order while reading and writing
+/// the next one.
+pub(crate) struct FileHasher {
+    hashers: Option<Vec<Hasher>>,
+    task: Option<JoinHandle<(Vec<Hasher>, Duration)>>,
+}
+
+impl FileHasher {
+    /// Create a new hasher of the file with the algorithm.
+    pub(crate) fn new(algorithm: Algorithm) -> Self {
+        Self::with_algorithms(&[algorithm])
+    }
+
+    /// Create a new hasher with each of the algorithms.
+    pub(crate) fn with_algorithms(algorithms: &[Algorithm]) -> Self {
+        Self {
+            hashers: Some(
+                algorithms.iter().copied().map(Hasher::new).collect(),
+            ),
+            task: None,
+        }
+    }
+
+    /// Update the checksums with the next data on a worker thread, waiting
+    /// for the previous one first.
+    pub(crate) async fn update(
+        &mut self,
+        data: Vec<u8>,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        let mut hashers: Vec<Hasher> = self.take(profiler).await?;
+
+        self.task = Some(tokio::task::spawn_blocking(move || {
+            let start: Instant = Instant::now();
+
+            for hasher in &mut hashers {
+                hasher.update(&data);
+            }
+
+            (hashers, start.elapsed())
+        }));
+
+        Ok(())
+    }
+
+    /// Update the checksums with the next data on the current thread, e.g.
+    /// on a worker thread already, without copying the data.
+    pub(crate) fn update_blocking(
+        &mut self,
+        data: &[u8],
+    ) {
+        for hasher in self.hashers.iter_mut().flatten() {
+            hasher.update(data);
+        }
+    }
+
+    /// Wait for the last data, returning the checksum of the first algorithm
+    /// in hex.
+    pub(crate) async fn finish(
+        self,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<String> {
+        self.finish_all(profiler)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ioa::Error::other("hasher has no algorithm"))
+    }
+
+    /// Wait for the last data, returning the checksums of the algorithms in
+    /// hex.
+    pub(crate) async fn finish_all(
+        mut self,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<Vec<String>> {
+        Ok(self
+            .take(profiler)
+            .await?
+            .into_iter()
+            .map(Hasher::finalize)
+            .collect())
+    }
+
+    /// Get the checksums of the algorithms in hex after updating them on the
+    /// current thread.
+    pub(crate) fn finish_blocking(self) -> Vec<String> {
+        self.hashers.into_iter().flatten().map(Hasher::finalize).collect()
+    }
+
+    async fn take(
+        &mut self,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<Vec<Hasher>> {
+        if let Some(task) = self.task.take() {
+            let (hashers, elapsed): (Vec<Hasher>, Duration) =
+                task.await.map_err(ioa::Error::other)?;
+
+            profiler.add(Phase::Hash, elapsed);
+
+            return Ok(hashers);
+        }
+
+        self.hashers
+            .take()
+            .ok_or_else(|| ioa::Error::other("hasher is finished"))
+    }
+}
+
+/// Get the algorithm and the checksum recorded for a chunk, if any.
+pub(crate) fn chunk_checksum(chunk: &ChunkInfo) -> Option<(Algorithm, &str)> {
+    Algorithm::ALL
+        .into_iter()
+        .find_map(|a| chunk.get_metadata(a.id()).map(|digest| (a, digest)))
+}
+
+/// Pick `count` of the items in `0..total` at random, sorted.
+pub(crate) fn sample(
+    total: usize,
+    count: usize,
+    rng: &dyn Rng,
+) -> Vec<usize> {
+    let mut items: Vec<usize> = (0..total).collect();
+
+    let count: usize = count.min(total);
+
+    // partial Fisher-Yates shuffle
+    for i in 0..count {
+        let z: u64 = rng.next_u64();
+
+        items.swap(i, i + (z % (total - i) as u64) as usize);
+    }
+
+    items.truncate(count);
+    items.sort_unstable();
+
+    items
+}
+
+/// Format the lines of a `SHA256SUMS` file from the checksums in hex and the
+/// paths relative to its directory.
+pub(crate) fn format_sha256sums(sums: &[(String, String)]) -> String {
+    sums.iter()
+        .map(|(digest, path)| format!("{}  {}\n", digest, path))
+        .collect()
+}
+
+/// Parse the lines of a `SHA256SUMS` file into the checksums in hex and the
+/// paths, in both the text and the binary modes of `sha256sum`.
+pub(crate) fn parse_sha256sums(
+    content: &str
+) -> ioa::Result<Vec<(String, String)>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (digest, path) = line
+                .split_once(' ')
+                .filter(|(digest, _)| digest.len() == 64)
+                .and_then(|(digest, rest)| {
+                    let path: &str = rest
+                        .strip_prefix(' ')
+                        .or_else(|| rest.strip_prefix('*'))?;
+
+                    Some((digest, path))
+                })
+                .ok_or_else(|| {
+                    ioa::Error::new(
+                        ioa::ErrorKind::InvalidData,
+                        format!("invalid line in SHA256SUMS: {}", line),
+                    )
+                })?;
+
+            Ok((digest.to_string(), path.to_string()))
+        })
+        .collect()
+}
+
+/// Compute the SHA-256 checksum of a whole file in hex on a worker thread.
+///
+/// The file is streamed through the hasher, so the memory does not depend
+/// on the size of the file.
+pub(crate) async fn file_sha256(path: PathBuf) -> ioa::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let mut file: std::fs::File = std::fs::File::open(path)?;
+
+        let mut hasher: Sha256 = Sha256::new();
+
+        std::io::copy(&mut file, &mut hasher)?;
+
+        Ok(to_hex(&hasher.finalize()))
+    })
+    .await
+    .map_err(ioa::Error::other)?
+}
+
+/// Compute the checksum of a file with the algorithm in hex.
+///
+/// The file is streamed through the hasher, so the memory does not depend
+/// on the size of the file.
+pub(crate) async fn file_digest(
+    path: PathBuf,
+    algorithm: Algorithm,
+) -> ioa::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let mut file: std::fs::File = std::fs::File::open(path)?;
+
+        let mut hasher: Hasher = Hasher::new(algorithm);
+        let mut buffer: Vec<u8> = vec![0; 64 * 1024];
+
+        loop {
+            let read: usize = std::io::Read::read(&mut file, &mut buffer)?;
+
+            if read == 0 {
+                break;
+            }
+
+            hasher.update(&buffer[..read]);
+        }
+
+        Ok(hasher.finalize())
+    })
+    .await
+    .map_err(ioa::Error::other)?
+}
+
+/// Compute the checksum of a range of a file with the algorithm in hex.
+///
+/// The range is streamed through the hasher, and a range beyond the end of
+/// the file is hashed up to the end.
+pub(crate) async fn range_digest(
+    path: PathBuf,
+    range: Range<usize>,
+    algorithm: Algorithm,
+) -> ioa::Result<String> {
+    range_digest_with(path, range, algorithm, 64 * 1024).await
+}
+
+/// Compute the checksum of a range of a file with the algorithm in hex,
+/// streamed through a buffer of `buffer_capacity` bytes.
+pub(crate) async fn range_digest_with(
+    path: PathBuf,
+    range: Range<usize>,
+    algorithm: Algorithm,
+    buffer_capacity: usize,
+) -> ioa::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let mut file: std::fs::File = std::fs::File::open(path)?;
+
+        std::io::Seek::seek(
+            &mut file,
+            std::io::SeekFrom::Start(range.start as u64),
+        )?;
+
+        let mut input: std::io::Take<std::fs::File> =
+            std::io::Read::take(file, range.len() as u64);
+
+        let mut hasher: Hasher = Hasher::new(algorithm);
+        let mut buffer: Vec<u8> =
+            vec![0; buffer_capacity.min(range.len()).max(1)];
+
+        loop {
+            let read: usize = std::io::Read::read(&mut input, &mut buffer)?;
+
+            if read == 0 {
+                break;
+            }
+
+            hasher.update(&buffer[..read]);
+        }
+
+        Ok(hasher.finalize())
+    })
+    .await
+    .map_err(ioa::Error::other)?
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
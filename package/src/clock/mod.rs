@@ -0,0 +1,118 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use tokio::time;
+
+/// Future returned by [`Clock::sleep_until`].
+pub type SleepFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Source of the time for the time-dependent parts, such as the bandwidth of
+/// a [`Scheduler`](crate::scheduler::Scheduler).
+///
+/// It defaults to the [`SystemClock`], and the [`MockClock`] makes them
+/// deterministic under test.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use filego::{clock::MockClock, scheduler::Scheduler};
+///
+/// fn example() {
+///     let scheduler: Scheduler = Scheduler::new()
+///         .bandwidth(1024 * 1024)
+///         .clock(Arc::new(MockClock::new()));
+/// }
+/// ```
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Get the current time.
+    fn now(&self) -> Instant;
+
+    /// Wait until the `deadline`.
+    fn sleep_until(
+        &self,
+        deadline: Instant,
+    ) -> SleepFuture<'_>;
+}
+
+/// Clock of the system, sleeping with the timer of the runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(
+        &self,
+        deadline: Instant,
+    ) -> SleepFuture<'_> {
+        Box::pin(time::sleep_until(time::Instant::from_std(deadline)))
+    }
+}
+
+/// Clock advanced manually, which jumps to the deadline instead of sleeping.
+///
+/// The time starts at the creation of the clock and stands still otherwise,
+/// so the time slept by a process is exactly the time the clock advanced.
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    now: Mutex<Instant>,
+}
+
+impl MockClock {
+    /// Create a new clock starting at the current time.
+    pub fn new() -> Self {
+        let start: Instant = Instant::now();
+
+        Self { start, now: Mutex::new(start) }
+    }
+
+    /// Advance the clock by `duration`.
+    pub fn advance(
+        &self,
+        duration: Duration,
+    ) {
+        *self.lock() += duration;
+    }
+
+    /// Get the time the clock advanced since its creation.
+    pub fn elapsed(&self) -> Duration {
+        *self.lock() - self.start
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Instant> {
+        self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.lock()
+    }
+
+    fn sleep_until(
+        &self,
+        deadline: Instant,
+    ) -> SleepFuture<'_> {
+        Box::pin(async move {
+            let mut now = self.lock();
+
+            *now = (*now).max(deadline);
+        })
+    }
+}
@@ -1,5 +1,85 @@
+use std::time::Duration;
+
 /// The default chunk size in bytes.
 pub const CHUNK_SIZE_DEFAULT: usize = 2 * 1024 * 1024;
 
 /// The default maximum size of the buffer capacity in bytes.
 pub const BUFFER_CAPACITY_MAX_DEFAULT: usize = 10 * 1024 * 1024;
+
+/// The default maximum total number of chunks splitted from a file.
+pub const TOTAL_CHUNKS_MAX_DEFAULT: usize = 1_000_000;
+
+/// The file name of the manifest in the chunk directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// The file name of the manifest in the binary format in the chunk
+/// directory.
+pub const MANIFEST_BINARY_FILE_NAME: &str = "manifest.bin";
+
+/// The default number of chunks fetched ahead from a store.
+pub const PREFETCH_DEFAULT: usize = 4;
+
+/// The prefix of the name of the volume directories, e.g. `vol0`.
+pub const VOLUME_DIR_PREFIX: &str = "vol";
+
+/// The file name of the PAR2 recovery file in the chunk directory.
+pub const PAR2_FILE_NAME: &str = "recovery.par2";
+
+/// The default size of the PAR2 recovery data as a percentage of the chunks.
+pub const PAR2_REDUNDANCY_DEFAULT: usize = 5;
+
+/// The default number of jobs running at the same time in a scheduler.
+pub const SCHEDULER_CONCURRENCY_DEFAULT: usize = 2;
+
+/// The default number of events kept for each subscriber of an event bus.
+pub const EVENT_CAPACITY_DEFAULT: usize = 256;
+
+/// The fraction of the available memory used by an auto-tuned process, e.g.
+/// `4` for a quarter.
+pub const AUTO_TUNE_MEMORY_FRACTION: usize = 4;
+
+/// The estimated memory used by a job in a scheduler, to limit the number of
+/// jobs running at the same time when auto-tuned.
+pub const JOB_MEMORY_ESTIMATE: usize =
+    CHUNK_SIZE_DEFAULT + BUFFER_CAPACITY_MAX_DEFAULT;
+
+/// The default number of chunks hashed at the same time on worker threads.
+pub const CHECKSUM_WORKERS_DEFAULT: usize = 4;
+
+/// The default number of chunks uploaded at the same time by a tiered store.
+pub const UPLOAD_CONCURRENCY_DEFAULT: usize = 4;
+
+/// The file name of the detached signature of the manifest in the chunk
+/// directory.
+pub const SIGNATURE_FILE_NAME: &str = "manifest.json.sig";
+
+/// The file name of the `sha256sum` compatible checksums in the chunk
+/// directory.
+pub const SHA256SUMS_FILE_NAME: &str = "SHA256SUMS";
+
+/// The extension appended to the name of a chunk for its bao outboard, e.g.
+/// `0.obao`.
+pub const BAO_OUTBOARD_EXTENSION: &str = "obao";
+
+/// The default size of the buffer reading the chunks of a range stream.
+pub const RANGE_BUFFER_SIZE_DEFAULT: usize = 64 * 1024;
+
+/// The default interval between the calls of the progress callback of the
+/// split, merge and check processes.
+pub const PROGRESS_INTERVAL_DEFAULT: Duration = Duration::from_millis(100);
+
+/// The alignment of the buffers, offsets and sizes of direct I/O in bytes,
+/// which covers the logical block size of common devices.
+pub const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+/// The key of the chunk metadata holding the entity tag of the chunk in a
+/// store, e.g. S3 ETag.
+pub const ETAG_METADATA_KEY: &str = "etag";
+
+/// The size of the blocks of a chunk compared with a file by the diff
+/// process.
+pub const DIFF_BLOCK_SIZE: usize = 64 * 1024;
+
+/// The size of the pieces of a chunk fetched from a store and hashed by the
+/// check process.
+pub const CHECK_PIECE_SIZE: usize = 1024 * 1024;
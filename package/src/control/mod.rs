@@ -0,0 +1,210 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::{io as ioa, sync::watch};
+
+use crate::{clock::Clock, profile::Profile};
+
+/// Handle to control a running split or merge process.
+///
+/// The process checks the handle after each chunk, so pausing or cancelling
+/// takes effect once the current chunk is done.
+///
+/// A handle is set with e.g. [`Split::handle`](crate::split::Split::handle),
+/// or returned with the process by e.g.
+/// [`Split::run_with_handle`](crate::split::Split::run_with_handle).
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{control::OperationHandle, split::Split};
+///
+/// async fn example() {
+///     let handle: OperationHandle = OperationHandle::new();
+///
+///     let task = tokio::spawn(
+///         Split::new()
+///             .in_file("/path/to/file")
+///             .out_dir(PathBuf::from("path").join("to").join("dir"))
+///             .handle(handle.clone())
+///             .run(),
+///     );
+///
+///     handle.pause();
+///     // ...
+///     handle.resume();
+///
+///     println!("{} bytes processed", handle.progress());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OperationHandle {
+    pub(crate) control: Arc<Control>,
+}
+
+impl OperationHandle {
+    /// Create a new handle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pause the process after the current chunk.
+    pub fn pause(&self) {
+        self.control.paused.send_replace(true);
+    }
+
+    /// Resume the paused process.
+    pub fn resume(&self) {
+        self.control.paused.send_replace(false);
+    }
+
+    /// Check whether the process is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.control.paused.borrow()
+    }
+
+    /// Cancel the process after the current chunk, which fails with
+    /// [`ioa::ErrorKind::Interrupted`].
+    pub fn cancel(&self) {
+        self.control.cancelled.store(true, Ordering::Release);
+
+        // wake up the paused process
+        self.control.paused.send_replace(false);
+    }
+
+    /// Check whether the process is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.control.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Get the number of bytes processed.
+    pub fn progress(&self) -> usize {
+        self.control.progress()
+    }
+
+    /// Get the time spent in each phase of the finished process, if
+    /// profiled.
+    pub fn profile(&self) -> Option<Profile> {
+        *self.control.profile.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Control shared between a running process and its owner.
+#[derive(Debug)]
+pub(crate) struct Control {
+    progress: AtomicUsize,
+    throttle: Option<Arc<Throttle>>,
+    paused: watch::Sender<bool>,
+    cancelled: AtomicBool,
+    profile: Mutex<Option<Profile>>,
+}
+
+impl Default for Control {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Control {
+    /// Create a new control limited by the throttle, if any.
+    pub(crate) fn new(throttle: Option<Arc<Throttle>>) -> Self {
+        Self {
+            progress: AtomicUsize::new(0),
+            throttle,
+            paused: watch::Sender::new(false),
+            cancelled: AtomicBool::new(false),
+            profile: Mutex::new(None),
+        }
+    }
+
+    /// Record `bytes` processed, waiting for the throttle if any, or while
+    /// paused.
+    pub(crate) async fn advance(
+        &self,
+        bytes: usize,
+    ) -> ioa::Result<()> {
+        if let Some(throttle) = &self.throttle {
+            throttle.acquire(bytes).await;
+        }
+
+        self.progress.fetch_add(bytes, Ordering::Relaxed);
+
+        let mut paused: watch::Receiver<bool> = self.paused.subscribe();
+
+        // the sender lives as long as the control
+        let _ = paused.wait_for(|paused| !*paused).await;
+
+        if self.cancelled.load(Ordering::Acquire) {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::Interrupted,
+                "operation cancelled",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Get the number of bytes processed.
+    pub(crate) fn progress(&self) -> usize {
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    /// Record the profile of the finished process.
+    pub(crate) fn set_profile(
+        &self,
+        profile: Option<Profile>,
+    ) {
+        *self.profile.lock().unwrap_or_else(|e| e.into_inner()) = profile;
+    }
+}
+
+/// Limit of the bytes processed per second, shared between processes.
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    rate: usize,
+    clock: Arc<dyn Clock>,
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    /// Create a new throttle of `rate` bytes per second with the clock.
+    pub(crate) fn new(
+        rate: usize,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let next: Mutex<Instant> = Mutex::new(clock.now());
+
+        Self { rate: rate.max(1), clock, next }
+    }
+
+    /// Get the rate of the throttle in bytes per second.
+    pub(crate) fn rate(&self) -> usize {
+        self.rate
+    }
+
+    /// Wait until `bytes` can be processed within the rate.
+    pub(crate) async fn acquire(
+        &self,
+        bytes: usize,
+    ) {
+        let start: Instant = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+
+            let start: Instant = (*next).max(self.clock.now());
+
+            *next = start
+                + Duration::from_secs_f64(bytes as f64 / self.rate as f64);
+
+            start
+        };
+
+        self.clock.sleep_until(start).await;
+    }
+}
@@ -0,0 +1,370 @@
+use std::{
+    fs,
+    future::Future,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncWrite},
+    task::JoinHandle,
+};
+
+use crate::config::DIRECT_IO_ALIGNMENT;
+
+/// Check whether `path` is a block device, e.g. `/dev/sda`, which the split
+/// process reads from and the merge process writes to in place of a file,
+/// so disk images are chunked and restored without an intermediate file.
+///
+/// It is always `false` on systems other than Unix.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::device::{device_size, is_block_device};
+///
+/// async fn example() {
+///     if is_block_device("/dev/sda") {
+///         let size: usize = device_size("/dev/sda").await.unwrap();
+///
+///         println!("{} bytes", size);
+///     }
+/// }
+/// ```
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn is_block_device<P: AsRef<Path>>(path: P) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        fs::metadata(path).is_ok_and(|m| m.file_type().is_block_device())
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Get the size of the block device at `path` in bytes, which is not
+/// reported by its metadata.
+///
+/// The size is read via the `BLKGETSIZE64` ioctl on Linux, and by seeking
+/// to the end of the device elsewhere.
+pub async fn device_size<P: AsRef<Path>>(path: P) -> ioa::Result<usize> {
+    let path: PathBuf = path.as_ref().to_path_buf();
+
+    tokio::task::spawn_blocking(move || size_of(&fs::File::open(path)?))
+        .await
+        .map_err(ioa::Error::other)?
+}
+
+/// Get the size of the file or the block device at `path` in bytes.
+pub(crate) async fn input_size(path: &Path) -> ioa::Result<usize> {
+    match is_block_device(path) {
+        | true => device_size(path).await,
+        | false => Ok(fsa::metadata(path).await?.len() as usize),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn size_of(file: &fs::File) -> ioa::Result<usize> {
+    linux::size_of(file)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn size_of(mut file: &fs::File) -> ioa::Result<usize> {
+    Ok(file.seek(SeekFrom::End(0))? as usize)
+}
+
+/// Set the `options` to bypass the page cache with `O_DIRECT`, which is
+/// only supported on Linux.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+pub(crate) fn direct(options: &mut fs::OpenOptions) -> ioa::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.custom_flags(libc::O_DIRECT);
+
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Err(ioa::Error::new(
+            ioa::ErrorKind::Unsupported,
+            "direct I/O is only supported on Linux",
+        ))
+    }
+}
+
+/// Stop bypassing the page cache for the `file`, e.g. to write an unaligned
+/// tail.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn buffered(file: &fs::File) -> ioa::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::clear_direct(file)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Ok(())
+    }
+}
+
+/// Round `size` up to the [`DIRECT_IO_ALIGNMENT`].
+fn aligned(size: usize) -> usize {
+    size.div_ceil(DIRECT_IO_ALIGNMENT) * DIRECT_IO_ALIGNMENT
+}
+
+/// Buffer starting at the [`DIRECT_IO_ALIGNMENT`] in memory with a size of
+/// a multiple of it, as required by direct I/O.
+struct AlignedBuffer {
+    data: Vec<u8>,
+    start: usize,
+    size: usize,
+}
+
+impl AlignedBuffer {
+    fn new(size: usize) -> Self {
+        let size: usize = aligned(size.max(1));
+
+        // the heap allocation is never moved as the vector is never resized
+        let data: Vec<u8> = vec![0; size + DIRECT_IO_ALIGNMENT];
+        let start: usize = data.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+
+        Self { data, start, size }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.data[self.start..self.start + self.size]
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.data[self.start..self.start + self.size]
+    }
+}
+
+/// Read `size` bytes at the `offset` of the `file` opened with
+/// [`direct`], which must be aligned.
+pub(crate) fn read_direct(
+    mut file: &fs::File,
+    offset: usize,
+    size: usize,
+) -> ioa::Result<Vec<u8>> {
+    let mut buffer: AlignedBuffer = AlignedBuffer::new(size);
+
+    file.seek(SeekFrom::Start(offset as u64))?;
+
+    let mut read: usize = 0;
+
+    // the reads are aligned until the end of the file
+    while read < size {
+        match file.read(&mut buffer.as_mut_slice()[read..])? {
+            | 0 => return Err(ioa::ErrorKind::UnexpectedEof.into()),
+            | n => read += n,
+        }
+    }
+
+    Ok(buffer.as_slice()[..size].to_vec())
+}
+
+type Pending = JoinHandle<(fs::File, AlignedBuffer, ioa::Result<()>)>;
+
+/// Writer of a file opened with [`direct`], writing its buffer in aligned
+/// blocks on a worker thread, and the unaligned tail when flushed after it
+/// stops bypassing the page cache.
+pub(crate) struct DirectWriter {
+    file: Option<fs::File>,
+    buffer: Option<AlignedBuffer>,
+    filled: usize,
+    pending: Option<Pending>,
+}
+
+impl DirectWriter {
+    /// Create a new writer with a buffer of at least `capacity` bytes,
+    /// writing from the current position of the `file`.
+    pub(crate) async fn new(
+        file: fsa::File,
+        capacity: usize,
+    ) -> ioa::Result<Self> {
+        let mut file: fs::File = file.into_std().await;
+
+        // the blocks are unaligned after resuming within a block
+        if !file.stream_position()?.is_multiple_of(DIRECT_IO_ALIGNMENT as u64) {
+            buffered(&file)?;
+        }
+
+        Ok(Self {
+            file: Some(file),
+            buffer: Some(AlignedBuffer::new(capacity)),
+            filled: 0,
+            pending: None,
+        })
+    }
+
+    /// Write the first `len` bytes of the buffer on a worker thread.
+    fn start(
+        &mut self,
+        len: usize,
+    ) {
+        let (file, buffer) = match (self.file.take(), self.buffer.take()) {
+            | (Some(file), Some(buffer)) => (file, buffer),
+            | _ => return,
+        };
+
+        self.pending = Some(tokio::task::spawn_blocking(move || {
+            let result: ioa::Result<()> = write_blocks(&file, &buffer, len);
+
+            (file, buffer, result)
+        }));
+    }
+
+    /// Wait for the buffer being written, if any.
+    fn poll_pending(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ioa::Result<()>> {
+        let pending: &mut Pending = match &mut self.pending {
+            | Some(pending) => pending,
+            | None => return Poll::Ready(Ok(())),
+        };
+
+        let (file, buffer, result) =
+            ready!(Pin::new(pending).poll(cx)).map_err(ioa::Error::other)?;
+
+        self.pending = None;
+        self.file = Some(file);
+        self.buffer = Some(buffer);
+        self.filled = 0;
+
+        Poll::Ready(result)
+    }
+}
+
+impl AsyncWrite for DirectWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<ioa::Result<usize>> {
+        let this: &mut Self = self.get_mut();
+
+        ready!(this.poll_pending(cx))?;
+
+        let buffer: &mut AlignedBuffer = match &mut this.buffer {
+            | Some(buffer) => buffer,
+            | None => return Poll::Ready(Err(ioa::ErrorKind::Other.into())),
+        };
+
+        let capacity: usize = buffer.size;
+
+        if this.filled == capacity {
+            this.start(capacity);
+
+            ready!(this.poll_pending(cx))?;
+
+            return Pin::new(this).poll_write(cx, data);
+        }
+
+        let n: usize = (capacity - this.filled).min(data.len());
+
+        buffer.as_mut_slice()[this.filled..this.filled + n]
+            .copy_from_slice(&data[..n]);
+
+        this.filled += n;
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ioa::Result<()>> {
+        let this: &mut Self = self.get_mut();
+
+        ready!(this.poll_pending(cx))?;
+
+        if this.filled > 0 {
+            this.start(this.filled);
+
+            ready!(this.poll_pending(cx))?;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ioa::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Write the first `len` bytes of the `buffer`, the aligned blocks first,
+/// then the unaligned tail without bypassing the page cache.
+fn write_blocks(
+    mut file: &fs::File,
+    buffer: &AlignedBuffer,
+    len: usize,
+) -> ioa::Result<()> {
+    let blocks: usize = len - len % DIRECT_IO_ALIGNMENT;
+
+    file.write_all(&buffer.as_slice()[..blocks])?;
+
+    if blocks < len {
+        buffered(file)?;
+
+        file.write_all(&buffer.as_slice()[blocks..len])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{fs, os::fd::AsRawFd};
+
+    use tokio::io as ioa;
+
+    /// `BLKGETSIZE64` of `<linux/fs.h>`, defined with the `size_t` although
+    /// the kernel writes a `u64`.
+    const BLKGETSIZE64: libc::Ioctl = libc::_IOR::<libc::size_t>(0x12, 114);
+
+    /// Get the size of the block device in bytes.
+    pub(super) fn size_of(file: &fs::File) -> ioa::Result<usize> {
+        let mut size: u64 = 0;
+
+        if unsafe { libc::ioctl(file.as_raw_fd(), BLKGETSIZE64, &mut size) } < 0
+        {
+            return Err(ioa::Error::last_os_error());
+        }
+
+        usize::try_from(size).map_err(ioa::Error::other)
+    }
+
+    /// Clear the `O_DIRECT` flag of the file.
+    pub(super) fn clear_direct(file: &fs::File) -> ioa::Result<()> {
+        let fd: libc::c_int = file.as_raw_fd();
+
+        let flags: libc::c_int = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+
+        if flags < 0 {
+            return Err(ioa::Error::last_os_error());
+        }
+
+        if unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_DIRECT) }
+            < 0
+        {
+            return Err(ioa::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use md5::{Digest, Md5};
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt},
+};
+
+use crate::config::BUFFER_CAPACITY_MAX_DEFAULT;
+
+/// Compute the S3 multipart ETag of a file uploaded in parts of `part_size`.
+///
+/// The ETag is the MD5 of the concatenated MD5 of each part, followed by
+/// `-` and the number of parts, e.g. `"d41d8cd98f00b204e9800998ecf8427e-3"`.
+/// Note that objects uploaded with a single `PutObject` request use the plain
+/// MD5 of the file instead.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{config::CHUNK_SIZE_DEFAULT, etag::file_etag};
+///
+/// async fn example() {
+///     let etag: String =
+///         file_etag("/path/to/file", CHUNK_SIZE_DEFAULT).await.unwrap();
+/// }
+/// ```
+pub async fn file_etag<P: AsRef<Path>>(
+    path: P,
+    part_size: usize,
+) -> ioa::Result<String> {
+    if part_size == 0 {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            "part_size must be greater than 0",
+        ));
+    }
+
+    let mut input: fsa::File =
+        fsa::OpenOptions::new().read(true).open(path.as_ref()).await?;
+
+    let mut buffer: Vec<u8> =
+        vec![0; part_size.min(BUFFER_CAPACITY_MAX_DEFAULT)];
+
+    let mut etag: Md5 = Md5::new();
+    let mut part: Md5 = Md5::new();
+    let mut part_read: usize = 0;
+    let mut parts: usize = 0;
+
+    loop {
+        let limit: usize = buffer.len().min(part_size - part_read);
+
+        let read: usize = input.read(&mut buffer[..limit]).await?;
+
+        if read == 0 {
+            break;
+        }
+
+        part.update(&buffer[..read]);
+        part_read += read;
+
+        if part_read == part_size {
+            etag.update(part.finalize_reset());
+            part_read = 0;
+            parts += 1;
+        }
+    }
+
+    if part_read > 0 || parts == 0 {
+        etag.update(part.finalize());
+        parts += 1;
+    }
+
+    Ok(format!("{}-{}", to_hex(&etag.finalize()), parts))
+}
+
+/// Compute the MD5 of a file in hex, which is the ETag of an object uploaded
+/// with a single `PutObject` request.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::etag::file_md5;
+///
+/// async fn example() {
+///     let md5: String = file_md5("/path/to/file").await.unwrap();
+/// }
+/// ```
+pub async fn file_md5<P: AsRef<Path>>(path: P) -> ioa::Result<String> {
+    let mut input: fsa::File =
+        fsa::OpenOptions::new().read(true).open(path.as_ref()).await?;
+
+    let mut buffer: Vec<u8> = vec![0; BUFFER_CAPACITY_MAX_DEFAULT];
+
+    let mut md5: Md5 = Md5::new();
+
+    loop {
+        let read: usize = input.read(&mut buffer).await?;
+
+        if read == 0 {
+            break;
+        }
+
+        md5.update(&buffer[..read]);
+    }
+
+    Ok(to_hex(&md5.finalize()))
+}
+
+/// Compute the S3 multipart ETag of the chunks in a directory, with each
+/// chunk uploaded as a part.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::etag::chunks_etag;
+///
+/// async fn example() {
+///     let etag: String = chunks_etag(
+///         PathBuf::from("path").join("to").join("dir"),
+///         0, // result from split function...
+///     )
+///     .await
+///     .unwrap();
+/// }
+/// ```
+pub async fn chunks_etag<P: AsRef<Path>>(
+    in_dir: P,
+    total_chunks: usize,
+) -> ioa::Result<String> {
+    let mut etag: Md5 = Md5::new();
+
+    for i in 0..total_chunks {
+        let target_file: PathBuf = in_dir.as_ref().join(i.to_string());
+
+        etag.update(Md5::digest(fsa::read(&target_file).await?));
+    }
+
+    Ok(format!("{}-{}", to_hex(&etag.finalize()), total_chunks))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,209 @@
+use std::{path::PathBuf, sync::Arc};
+#[cfg(feature = "stream")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "stream")]
+pub use futures_core::Stream;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{
+    check::CheckResultErrorType, config::EVENT_CAPACITY_DEFAULT,
+    manifest::Uuid, progress::ProgressSink,
+};
+
+/// Lifecycle event emitted by the split, check and merge processes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The split process started.
+    SplitStarted {
+        /// Identity of the split.
+        id: Uuid,
+        /// Size of the input file in bytes.
+        file_size: usize,
+    },
+    /// A chunk is written by the split process.
+    ChunkWritten {
+        /// Index of the chunk.
+        index: usize,
+        /// Size of the chunk before transform in bytes.
+        size: usize,
+    },
+    /// The split process finished.
+    SplitFinished {
+        /// Identity of the split.
+        id: Uuid,
+        /// Total number of chunks.
+        total_chunks: usize,
+    },
+    /// The check process passed.
+    CheckPassed,
+    /// The check process failed.
+    CheckFailed {
+        /// Error type of the result.
+        error_type: CheckResultErrorType,
+    },
+    /// The merge process started.
+    MergeStarted {
+        /// Total number of chunks.
+        total_chunks: usize,
+    },
+    /// A chunk is merged into the output file.
+    ChunkMerged {
+        /// Index of the chunk.
+        index: usize,
+        /// Size of the chunk after decoding in bytes.
+        size: usize,
+    },
+    /// The merge process finished.
+    MergeFinished {
+        /// Path of the output file.
+        out_file: PathBuf,
+    },
+}
+
+/// Bus of the lifecycle events, shared between processes and subscribers.
+///
+/// Events are dropped when there is no subscriber, and a subscriber lagging
+/// behind by more than the capacity misses the oldest events.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     event::{Event, EventBus},
+///     split::Split,
+/// };
+///
+/// async fn example() {
+///     let events: EventBus = EventBus::new();
+///
+///     let mut receiver = events.subscribe();
+///
+///     tokio::spawn(async move {
+///         while let Ok(event) = receiver.recv().await {
+///             if let Event::ChunkWritten { index, .. } = event {
+///                 println!("chunk {} written", index);
+///             }
+///         }
+///     });
+///
+///     Split::new()
+///         .in_file("/path/to/file")
+///         .out_dir(PathBuf::from("path").join("to").join("dir"))
+///         .events(events)
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    /// Create a new event bus.
+    ///
+    /// By default, the capacity follows the [`EVENT_CAPACITY_DEFAULT`].
+    pub fn new() -> Self {
+        Self::with_capacity(EVENT_CAPACITY_DEFAULT)
+    }
+
+    /// Create a new event bus keeping at most `capacity` events for each
+    /// subscriber.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+
+        Self { sender }
+    }
+
+    /// Subscribe to the events emitted after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Emit an event to the subscribers.
+    pub(crate) fn emit(
+        &self,
+        event: Event,
+    ) {
+        // no subscriber is not an error
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stream of the events of a single process, returned with the process by
+/// e.g. [`Split::run_with_events`](crate::split::Split::run_with_events).
+///
+/// Each event is kept in order without being dropped, unlike the
+/// subscribers of the [`EventBus`], and the stream ends once the process
+/// finishes. The events are emitted as the process is polled, so the
+/// process should run concurrently with the stream, e.g. spawned or joined.
+///
+/// With the `stream` feature, it implements the [`Stream`] of the
+/// `futures` ecosystem, e.g. to forward the events to a websocket.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{
+///     event::{Event, EventStream},
+///     split::{Split, SplitResult},
+/// };
+///
+/// async fn example() {
+///     let (run, mut events) = Split::new()
+///         .in_file("/path/to/file")
+///         .out_dir("/path/to/dir")
+///         .run_with_events();
+///
+///     let task = tokio::spawn(run);
+///
+///     while let Some(event) = events.recv().await {
+///         println!("{:?}", event);
+///     }
+///
+///     let result: SplitResult = task.await.unwrap().unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EventStream {
+    receiver: mpsc::UnboundedReceiver<Event>,
+}
+
+impl EventStream {
+    /// Create a stream with the sink passing the events to it.
+    pub(crate) fn channel() -> (Arc<dyn ProgressSink>, Self) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        (Arc::new(sender), Self { receiver })
+    }
+
+    /// Receive the next event, or `None` once the process finishes.
+    pub async fn recv(&mut self) -> Option<Event> {
+        self.receiver.recv().await
+    }
+}
+
+#[cfg(feature = "stream")]
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
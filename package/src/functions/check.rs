@@ -1,14 +1,60 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use tokio::{fs as fsa, io as ioa};
 
+#[cfg(feature = "bao")]
+use crate::bao::outboard_path;
+#[cfg(feature = "signing")]
+use crate::manifest::{
+    read_signature, secure_checksum, verify, Signature, VerifyingKey,
+};
+#[cfg(feature = "checksum")]
+use crate::{
+    checksum::{
+        chunk_checksum, file_sha256, parse_sha256sums, sample, Algorithm,
+        ChunkChecksum, FileHasher,
+    },
+    config::{CHECK_PIECE_SIZE, VOLUME_DIR_PREFIX},
+    header::{ChunkHeader, CHUNK_HEADER_SIZE},
+    merkle::{self, Hash, MerkleTree},
+    rng::{Rng, SeededRng, SystemRng},
+};
+use crate::{
+    config::{
+        ETAG_METADATA_KEY, MANIFEST_BINARY_FILE_NAME, MANIFEST_FILE_NAME,
+        PAR2_FILE_NAME, PROGRESS_INTERVAL_DEFAULT, SHA256SUMS_FILE_NAME,
+        SIGNATURE_FILE_NAME,
+    },
+    event::{Event, EventBus, EventStream},
+    functions::merge::read_chunks,
+    layout::LayoutRegistry,
+    log::{Operation, OperationLog},
+    manifest::{find_manifest, manifest_dir, Manifest},
+    naming::ChunkNaming,
+    progress::{OnProgress, ProgressSink, Reporter, Tee},
+    store::{ChunkHead, ChunkStore, DirStore},
+};
+
 /// Error type of the result from the check process.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum CheckResultErrorType {
     /// Some of the chunks are missing to merge the file.
     Missing,
     /// The actual file size is not equal the input file size.
     Size,
+    /// Some of the chunks do not match their checksums.
+    Corrupted,
+    /// Some of the files in the input directory are not chunks.
+    Extra,
+    /// The manifest is not signed by the trusted key.
+    Signature,
 }
 
 impl CheckResultErrorType {
@@ -17,6 +63,9 @@ impl CheckResultErrorType {
         match code.as_ref() {
             | "missing" => Some(Self::Missing),
             | "size" => Some(Self::Size),
+            | "corrupted" => Some(Self::Corrupted),
+            | "extra" => Some(Self::Extra),
+            | "signature" => Some(Self::Signature),
             | _ => None,
         }
     }
@@ -26,6 +75,9 @@ impl CheckResultErrorType {
         match self {
             | Self::Missing => "missing",
             | Self::Size => "size",
+            | Self::Corrupted => "corrupted",
+            | Self::Extra => "extra",
+            | Self::Signature => "signature",
         }
     }
 
@@ -37,6 +89,7 @@ impl CheckResultErrorType {
 
 /// Error of the result from the check process.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct CheckResultError {
     /// Type of error of the check.
     pub error_type: CheckResultErrorType,
@@ -44,10 +97,15 @@ pub struct CheckResultError {
     pub message: String,
     /// Missing chunk(s) to merge the file.
     pub missing: Option<Vec<usize>>,
+    /// Corrupted chunk(s) not matching their checksums.
+    pub corrupted: Option<Vec<usize>>,
+    /// Extra file(s) in the input directory that are not chunks.
+    pub extra: Option<Vec<PathBuf>>,
 }
 
 /// Result of the check process.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct CheckResult {
     /// Successful / Failed check.
     pub success: bool,
@@ -55,6 +113,25 @@ pub struct CheckResult {
     pub error: Option<CheckResultError>,
 }
 
+/// Progress of the check process, see [`Check::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckProgress {
+    /// The number of chunks inspected.
+    pub chunks_inspected: usize,
+    /// The total number of chunks.
+    pub total_chunks: usize,
+}
+
+impl CheckProgress {
+    /// Get the fraction of the chunks inspected, from `0.0` to `1.0`.
+    pub fn fraction(&self) -> f64 {
+        match self.total_chunks {
+            | 0 => 1.0,
+            | total => self.chunks_inspected as f64 / total as f64,
+        }
+    }
+}
+
 /// Process to check the file integrity.
 ///
 /// The function will return [`CheckResult`] (that may come
@@ -81,14 +158,70 @@ pub struct CheckResult {
 #[derive(Debug, Clone)]
 pub struct Check {
     in_dir: Option<PathBuf>,
+    store: Option<Arc<dyn ChunkStore>>,
     file_size: Option<usize>,
     total_chunks: Option<usize>,
+    naming: ChunkNaming,
+    layouts: LayoutRegistry,
+    events: Option<EventBus>,
+    progress: Option<Arc<dyn ProgressSink>>,
+    on_progress: Option<OnProgress<CheckProgress>>,
+    progress_interval: Duration,
+    log: Option<OperationLog>,
+    strict: bool,
+    manifest_path: Option<PathBuf>,
+    #[cfg(feature = "checksum")]
+    manifest: Option<Manifest>,
+    #[cfg(feature = "checksum")]
+    spot_check: Option<usize>,
+    #[cfg(feature = "checksum")]
+    rng: Option<Arc<dyn Rng>>,
+    #[cfg(feature = "checksum")]
+    merkle_root: Option<String>,
+    #[cfg(feature = "checksum")]
+    checksums: Option<Vec<ChunkChecksum>>,
+    #[cfg(feature = "checksum")]
+    sha256sums: bool,
+    #[cfg(feature = "signing")]
+    verifying_key: Option<VerifyingKey>,
+    #[cfg(feature = "signing")]
+    signature: Option<Signature>,
 }
 
 impl Check {
     /// Create a new check process.
     pub fn new() -> Self {
-        Self { in_dir: None, file_size: None, total_chunks: None }
+        Self {
+            in_dir: None,
+            store: None,
+            file_size: None,
+            total_chunks: None,
+            naming: ChunkNaming::Index,
+            layouts: LayoutRegistry::new(),
+            events: None,
+            progress: None,
+            on_progress: None,
+            progress_interval: PROGRESS_INTERVAL_DEFAULT,
+            log: None,
+            strict: false,
+            manifest_path: None,
+            #[cfg(feature = "checksum")]
+            manifest: None,
+            #[cfg(feature = "checksum")]
+            spot_check: None,
+            #[cfg(feature = "checksum")]
+            rng: None,
+            #[cfg(feature = "checksum")]
+            merkle_root: None,
+            #[cfg(feature = "checksum")]
+            checksums: None,
+            #[cfg(feature = "checksum")]
+            sha256sums: false,
+            #[cfg(feature = "signing")]
+            verifying_key: None,
+            #[cfg(feature = "signing")]
+            signature: None,
+        }
     }
 
     /// Set the input directory.
@@ -100,6 +233,20 @@ impl Check {
         self
     }
 
+    /// Set the store of the chunks instead of the input directory.
+    ///
+    /// Only the metadata of the chunks is fetched from the store, so the
+    /// chunks can be checked without downloading them. When both the store
+    /// and the manifest expose the `etag` of a chunk, a mismatch fails the
+    /// check with [`CheckResultErrorType::Corrupted`].
+    pub fn remote(
+        mut self,
+        store: Arc<dyn ChunkStore>,
+    ) -> Self {
+        self.store = Some(store);
+        self
+    }
+
     /// Set the size of the original file.
     pub fn file_size(
         mut self,
@@ -118,10 +265,406 @@ impl Check {
         self
     }
 
+    /// Read the input directory, the file size and the total number of
+    /// chunks from the manifest at `path`, e.g. written by
+    /// [`Split::manifest`], so they are not transmitted apart from the
+    /// chunks.
+    ///
+    /// The input directory is the directory of the manifest, and the file
+    /// size is the total size of the chunks in storage. The values set
+    /// explicitly take precedence. With the `checksum` feature, the manifest
+    /// is also used as the `manifest` unless set.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::check::{Check, CheckResult};
+    ///
+    /// async fn example() {
+    ///     let result: CheckResult = Check::new()
+    ///         .from_manifest("/path/to/dir/manifest.json")
+    ///         .run()
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`Split::manifest`]: crate::split::Split::manifest
+    pub fn from_manifest<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Self {
+        self.manifest_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the scheme of the names of the chunks in the input directory.
+    ///
+    /// By default, the names are the indices of the chunks. It is ignored
+    /// when the input directory contains a manifest.
+    pub fn naming(
+        mut self,
+        naming: ChunkNaming,
+    ) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Set the layouts to detect the chunk set in the input directory.
+    ///
+    /// By default, the layouts follow the [`LayoutRegistry::new`]. The chunk
+    /// set is detected when no chunk is named by the indices.
+    pub fn layouts(
+        mut self,
+        layouts: LayoutRegistry,
+    ) -> Self {
+        self.layouts = layouts;
+        self
+    }
+
+    /// Read every listed chunk and compare it with its checksum, e.g. from
+    /// the [`SplitResult::checksums`], so corrupted chunks of the correct
+    /// size are found.
+    ///
+    /// A mismatch fails the check with [`CheckResultErrorType::Corrupted`],
+    /// listing the corrupted chunks. The header of a chunk, if any, is
+    /// excluded from its checksum.
+    ///
+    /// [`SplitResult::checksums`]: crate::split::SplitResult::checksums
+    #[cfg(feature = "checksum")]
+    pub fn verify_checksums(
+        mut self,
+        checksums: Vec<ChunkChecksum>,
+    ) -> Self {
+        self.checksums = Some(checksums);
+        self
+    }
+
+    /// Set whether to compare every chunk file listed in the
+    /// [`SHA256SUMS_FILE_NAME`] file of the input directory with its
+    /// checksum, e.g. written when splitting with [`Split::sha256sums`].
+    ///
+    /// By default, it is `false`. A mismatch or a listed file not found
+    /// fails the check with [`CheckResultErrorType::Corrupted`], listing the
+    /// corrupted chunks. A listed path other than a file name, optionally
+    /// within a volume directory, fails the check process with
+    /// [`ioa::ErrorKind::InvalidData`]. It is ignored when checking a store.
+    ///
+    /// [`Split::sha256sums`]: crate::split::Split::sha256sums
+    #[cfg(feature = "checksum")]
+    pub fn sha256sums(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.sha256sums = enabled;
+        self
+    }
+
+    /// Hash a random sample of the chunks and compare them with the
+    /// checksums in the manifest, giving probabilistic assurance without
+    /// reading every chunk.
+    ///
+    /// The `percentage` of the chunks with checksums, at least one, is
+    /// sampled. The checksums are recorded by [`Split::checksum`], and a
+    /// mismatch fails the check with [`CheckResultErrorType::Corrupted`].
+    ///
+    /// [`Split::checksum`]: crate::split::Split::checksum
+    #[cfg(feature = "checksum")]
+    pub fn spot_check(
+        mut self,
+        percentage: usize,
+    ) -> Self {
+        self.spot_check = Some(percentage);
+        self
+    }
+
+    /// Set the seed of the random sample of the spot check.
+    ///
+    /// By default, the seed is random. The same seed samples the same
+    /// chunks, e.g. to reproduce a failed check. It is a shorthand of the
+    /// [`rng`](Self::rng) with a [`SeededRng`].
+    #[cfg(feature = "checksum")]
+    pub fn seed(
+        self,
+        seed: u64,
+    ) -> Self {
+        self.rng(Arc::new(SeededRng::new(seed)))
+    }
+
+    /// Set the source of the random sample of the spot check.
+    ///
+    /// By default, it is the [`SystemRng`](crate::rng::SystemRng).
+    #[cfg(feature = "checksum")]
+    pub fn rng(
+        mut self,
+        rng: Arc<dyn Rng>,
+    ) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+
+    /// Verify every chunk against the trusted root of the Merkle tree in hex,
+    /// e.g. kept apart from the chunks when splitting with
+    /// [`Split::merkle`].
+    ///
+    /// A mismatch fails the check with [`CheckResultErrorType::Corrupted`],
+    /// listing the chunks differing from the checksums in the manifest.
+    ///
+    /// [`Split::merkle`]: crate::split::Split::merkle
+    #[cfg(feature = "checksum")]
+    pub fn merkle_root<R: Into<String>>(
+        mut self,
+        root: R,
+    ) -> Self {
+        self.merkle_root = Some(root.into());
+        self
+    }
+
+    /// Set the manifest with the checksums for the spot check and the Merkle
+    /// root.
+    ///
+    /// By default, the manifest in the input directory is used, so it is
+    /// required when checking a store.
+    #[cfg(feature = "checksum")]
+    pub fn manifest(
+        mut self,
+        manifest: Manifest,
+    ) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Verify the manifest against the key of the sender, then every chunk
+    /// against the checksums in the signed manifest, e.g. before merging
+    /// chunks downloaded over untrusted networks.
+    ///
+    /// The manifest and the signature in the input directory are used unless
+    /// set, so they are required when checking a store. An invalid signature
+    /// fails the check with [`CheckResultErrorType::Signature`], and a chunk
+    /// not matching its checksum or not found fails the check with
+    /// [`CheckResultErrorType::Corrupted`].
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::{
+    ///     check::{Check, CheckResult},
+    ///     manifest::VerifyingKey,
+    /// };
+    ///
+    /// async fn example(key: VerifyingKey) {
+    ///     let result: CheckResult = Check::new()
+    ///         .in_dir("/path/to/dir")
+    ///         .file_size(0) // result from split function...
+    ///         .total_chunks(0) // result from split function...
+    ///         .verifying_key(key)
+    ///         .run()
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub fn verifying_key(
+        mut self,
+        key: VerifyingKey,
+    ) -> Self {
+        self.verifying_key = Some(key);
+        self
+    }
+
+    /// Set the signature of the manifest, see [`verifying_key`].
+    ///
+    /// By default, the signature in the [`SIGNATURE_FILE_NAME`] file of the
+    /// input directory is used.
+    ///
+    /// [`verifying_key`]: Self::verifying_key
+    #[cfg(feature = "signing")]
+    pub fn signature(
+        mut self,
+        signature: Signature,
+    ) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
+    /// Set whether to fail on the files in the input directory that are not
+    /// chunks, e.g. `5.tmp`, `.DS_Store`, or indices beyond `total_chunks`.
+    ///
+    /// By default, it is `false`. When enabled, such files fail the check
+    /// with [`CheckResultErrorType::Extra`]. The manifest and its signature,
+    /// the PAR2 recovery file, the [`SHA256SUMS_FILE_NAME`] file, the bao
+    /// outboards and the volume directories are expected. It is ignored when
+    /// checking a store.
+    pub fn strict(
+        mut self,
+        strict: bool,
+    ) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set the bus to emit the lifecycle events to.
+    pub fn events(
+        mut self,
+        events: EventBus,
+    ) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Set the sink to pass the progress to, which receives every event
+    /// in order, unlike the subscribers of the [`events`](Self::events).
+    pub fn progress(
+        mut self,
+        sink: Arc<dyn ProgressSink>,
+    ) -> Self {
+        self.progress = Some(sink);
+        self
+    }
+
+    /// Set the callback to report the [`CheckProgress`] to, e.g. to drive
+    /// a progress bar.
+    ///
+    /// The callback is called on the task running the process when it
+    /// starts and once all chunks are inspected, and in between at most
+    /// once per `interval`, which follows the [`PROGRESS_INTERVAL_DEFAULT`]
+    /// by default. It should return quickly.
+    pub fn on_progress<F: Fn(CheckProgress) + Send + Sync + 'static>(
+        mut self,
+        f: F,
+    ) -> Self {
+        self.on_progress = Some(OnProgress::new(f));
+        self
+    }
+
+    /// Set the minimum interval between the calls of the callback set by
+    /// [`on_progress`](Self::on_progress).
+    pub fn progress_interval(
+        mut self,
+        interval: Duration,
+    ) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Set the log to record the inputs and results of the process to.
+    ///
+    /// By default, the process is not recorded. The process fails if the
+    /// record cannot be written.
+    pub fn log(
+        mut self,
+        log: OperationLog,
+    ) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    fn emit(
+        &self,
+        event: Event,
+    ) {
+        if let Some(progress) = &self.progress {
+            progress.on_event(&event);
+        }
+
+        if let Some(events) = &self.events {
+            events.emit(event);
+        }
+    }
+
     /// Run the check process.
-    pub async fn run(self) -> ioa::Result<CheckResult> {
-        let in_dir: &Path = match self.in_dir {
-            | Some(ref p) => {
+    ///
+    /// Each chunk is checked with a single metadata request to the store,
+    /// which takes `O(n)` requests for `n` chunks without reading them,
+    /// unless the checksums of the chunks are verified, e.g. with
+    /// `verify_checksums`, `spot_check`, `merkle_root` or `verifying_key`.
+    /// The chunks verified are then read, in pieces if the store fetches
+    /// ranges, otherwise in full.
+    pub async fn run(mut self) -> ioa::Result<CheckResult> {
+        let result: CheckResult = self.check().await?;
+
+        if let Some(log) = &self.log {
+            log.append(Operation::Check {
+                in_dir: self.in_dir.clone().filter(|_| self.store.is_none()),
+                file_size: self.file_size.unwrap_or(0),
+                total_chunks: self.total_chunks.unwrap_or(0),
+                success: result.success,
+                error_type: result
+                    .error
+                    .as_ref()
+                    .map(|e| e.error_type.to_code()),
+            })
+            .await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Run the check process with the stream of its events, e.g. to
+    /// forward the result to a frontend without a callback.
+    ///
+    /// The events are passed to the sink set by
+    /// [`progress`](Self::progress) as well, and the stream ends once the
+    /// process finishes.
+    pub fn run_with_events(
+        mut self
+    ) -> (impl Future<Output = ioa::Result<CheckResult>>, EventStream) {
+        let (sink, events): (Arc<dyn ProgressSink>, EventStream) =
+            EventStream::channel();
+
+        self.progress = Some(Tee::join(self.progress.take(), sink));
+
+        (self.run(), events)
+    }
+
+    async fn check(&mut self) -> ioa::Result<CheckResult> {
+        self.naming.validate()?;
+
+        // values not set are read from the manifest
+        let given: Option<Manifest> = match self.manifest_path.take() {
+            | Some(path) => {
+                let manifest: Manifest = Manifest::read(&path).await?;
+
+                if self.store.is_none() && self.in_dir.is_none() {
+                    self.in_dir = Some(manifest_dir(&path, &manifest));
+                }
+
+                self.file_size.get_or_insert(manifest.stored_size());
+                self.total_chunks.get_or_insert(manifest.total_chunks);
+
+                #[cfg(feature = "checksum")]
+                self.manifest.get_or_insert_with(|| manifest.clone());
+
+                Some(manifest)
+            },
+            | None => None,
+        };
+
+        // etags of the chunks to compare with the ones exposed by the store
+        #[cfg(feature = "checksum")]
+        let known: Option<&Manifest> =
+            given.as_ref().or(self.manifest.as_ref());
+        #[cfg(not(feature = "checksum"))]
+        let known: Option<&Manifest> = given.as_ref();
+
+        let etags: HashMap<usize, String> = known
+            .map(|manifest| {
+                manifest
+                    .chunks
+                    .iter()
+                    .filter_map(|c| {
+                        Some((
+                            c.index,
+                            c.get_metadata(ETAG_METADATA_KEY)?.to_string(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let store: Arc<dyn ChunkStore> = match (&self.store, &self.in_dir) {
+            | (Some(store), _) => store.clone(),
+            | (None, Some(p)) => {
                 let p: &Path = p.as_ref();
 
                 // if in_dir not exists
@@ -140,9 +683,35 @@ impl Check {
                     ));
                 }
 
-                p
+                let manifest: Option<Manifest> = match given {
+                    | Some(manifest) => Some(manifest),
+                    | None => find_manifest(p).await?,
+                };
+
+                // names and volumes of the chunks follow the manifest
+                match manifest {
+                    | Some(manifest) => {
+                        Arc::new(DirStore::with_manifest(p, &manifest))
+                    },
+                    | None => {
+                        let store: DirStore =
+                            DirStore::new(p).naming(self.naming.clone());
+
+                        // chunk sets of the registered layouts
+                        if self.naming.is_index()
+                            && store.head(0).await?.is_none()
+                        {
+                            match self.layouts.detect(p).await? {
+                                | Some(detected) => Arc::new(detected),
+                                | None => Arc::new(store),
+                            }
+                        } else {
+                            Arc::new(store)
+                        }
+                    },
+                }
             },
-            | None => {
+            | (None, None) => {
                 return Err(ioa::Error::new(
                     ioa::ErrorKind::InvalidInput,
                     "in_dir is not set",
@@ -172,36 +741,81 @@ impl Check {
 
         let mut actual_size: usize = 0;
         let mut missing: Vec<usize> = Vec::new();
+        let mut corrupted: Vec<usize> = Vec::new();
+
+        let mut reporter: Reporter<CheckProgress> = Reporter::new(
+            self.on_progress.as_ref(),
+            self.progress_interval,
+            CheckProgress { chunks_inspected: 0, total_chunks },
+        );
+
+        reporter.report(true);
 
         for i in 0..total_chunks {
-            let target_file: PathBuf = in_dir.join(i.to_string());
+            match store.head(i).await? {
+                | Some(ChunkHead { size, etag }) => {
+                    actual_size += size;
 
-            if !target_file.exists() || !target_file.is_file() {
-                missing.push(i);
-                continue;
+                    if let (Some(etag), Some(expected)) = (etag, etags.get(&i))
+                    {
+                        if !etag_eq(&etag, expected) {
+                            corrupted.push(i);
+                        }
+                    }
+                },
+                | None => missing.push(i),
             }
 
-            actual_size += fsa::OpenOptions::new()
-                .read(true)
-                .open(&target_file)
-                .await?
-                .metadata()
-                .await?
-                .len() as usize;
+            reporter.progress.chunks_inspected = i + 1;
+            reporter.report(i + 1 == total_chunks);
         }
 
         if !missing.is_empty() {
+            self.emit(Event::CheckFailed {
+                error_type: CheckResultErrorType::Missing,
+            });
+
             return Ok(CheckResult {
                 success: false,
                 error: Some(CheckResultError {
                     error_type: CheckResultErrorType::Missing,
                     message: "Missing chunk(s)".to_string(),
                     missing: Some(missing),
+                    corrupted: None,
+                    extra: None,
                 }),
             });
         }
 
+        if let (true, None, Some(in_dir)) =
+            (self.strict, &self.store, &self.in_dir)
+        {
+            let extra: Vec<PathBuf> =
+                self.extra_files(in_dir, total_chunks).await?;
+
+            if !extra.is_empty() {
+                self.emit(Event::CheckFailed {
+                    error_type: CheckResultErrorType::Extra,
+                });
+
+                return Ok(CheckResult {
+                    success: false,
+                    error: Some(CheckResultError {
+                        error_type: CheckResultErrorType::Extra,
+                        message: "Extra file(s) in in_dir".to_string(),
+                        missing: None,
+                        corrupted: None,
+                        extra: Some(extra),
+                    }),
+                });
+            }
+        }
+
         if actual_size != file_size {
+            self.emit(Event::CheckFailed {
+                error_type: CheckResultErrorType::Size,
+            });
+
             return Ok(CheckResult {
                 success: false,
                 error: Some(CheckResultError {
@@ -210,12 +824,443 @@ impl Check {
                         "the size of chunks is not equal to file_size parameter"
                             .to_string(),
                     missing: None,
+                    corrupted: None,
+                    extra: None,
+                }),
+            });
+        }
+
+        if !corrupted.is_empty() {
+            self.emit(Event::CheckFailed {
+                error_type: CheckResultErrorType::Corrupted,
+            });
+
+            return Ok(CheckResult {
+                success: false,
+                error: Some(CheckResultError {
+                    error_type: CheckResultErrorType::Corrupted,
+                    message: "Corrupted chunk(s)".to_string(),
+                    missing: None,
+                    corrupted: Some(corrupted),
+                    extra: None,
                 }),
             });
         }
 
+        #[cfg(feature = "checksum")]
+        if let Some(error) = self.verify_content(&store).await? {
+            self.emit(Event::CheckFailed { error_type: error.error_type });
+
+            return Ok(CheckResult { success: false, error: Some(error) });
+        }
+
+        self.emit(Event::CheckPassed);
+
         Ok(CheckResult { success: true, error: None })
     }
+
+    /// Find the files in the input directory and its volume directories that
+    /// are not chunks, sorted by their paths.
+    async fn extra_files(
+        &self,
+        in_dir: &Path,
+        total_chunks: usize,
+    ) -> ioa::Result<Vec<PathBuf>> {
+        let naming: ChunkNaming = match find_manifest(in_dir).await? {
+            | Some(manifest) => manifest.naming,
+            | None => self.naming.clone(),
+        };
+
+        let mut entries: Vec<(usize, PathBuf)> = Vec::new();
+
+        let volumes: Vec<PathBuf> =
+            read_chunks(in_dir, &naming, &mut entries).await?;
+
+        for dir in &volumes {
+            read_chunks(dir, &naming, &mut entries).await?;
+        }
+
+        // chunk sets of the registered layouts
+        if entries.is_empty() && naming.is_index() {
+            if let Some(detected) = self.layouts.detect(in_dir).await? {
+                entries = detected.chunks.into_iter().collect();
+            }
+        }
+
+        let mut expected: HashSet<PathBuf> = entries
+            .into_iter()
+            .filter(|(index, _)| *index < total_chunks)
+            .map(|(_, path)| path)
+            .collect();
+
+        // the bao outboards next to the chunks
+        #[cfg(feature = "bao")]
+        {
+            let outboards: Vec<PathBuf> =
+                expected.iter().map(outboard_path).collect();
+
+            expected.extend(outboards);
+        }
+
+        expected.insert(in_dir.join(MANIFEST_FILE_NAME));
+        expected.insert(in_dir.join(MANIFEST_BINARY_FILE_NAME));
+        expected.insert(in_dir.join(SIGNATURE_FILE_NAME));
+        expected.insert(in_dir.join(PAR2_FILE_NAME));
+        expected.insert(in_dir.join(SHA256SUMS_FILE_NAME));
+
+        for dir in &volumes {
+            expected.insert(dir.clone());
+            expected.insert(dir.join(MANIFEST_FILE_NAME));
+            expected.insert(dir.join(MANIFEST_BINARY_FILE_NAME));
+        }
+
+        let mut extra: Vec<PathBuf> = Vec::new();
+
+        let dirs =
+            std::iter::once(in_dir).chain(volumes.iter().map(|v| v.as_path()));
+
+        for dir in dirs {
+            let mut read_dir: fsa::ReadDir = fsa::read_dir(dir).await?;
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let path: PathBuf = entry.path();
+
+                if !expected.contains(&path) {
+                    extra.push(path);
+                }
+            }
+        }
+
+        extra.sort_unstable();
+
+        Ok(extra)
+    }
+
+    /// Verify the content of the chunks with the SHA256SUMS file, the
+    /// checksums, the spot check and the Merkle root if set, returning the
+    /// error of the corrupted chunks.
+    #[cfg(feature = "checksum")]
+    async fn verify_content(
+        &self,
+        store: &Arc<dyn ChunkStore>,
+    ) -> ioa::Result<Option<CheckResultError>> {
+        #[cfg(feature = "signing")]
+        if let Some(key) = &self.verifying_key {
+            if let Some(error) = self.verify_signed(store, key).await? {
+                return Ok(Some(error));
+            }
+        }
+
+        if let (true, None, Some(in_dir)) =
+            (self.sha256sums, &self.store, &self.in_dir)
+        {
+            let corrupted: Vec<usize> =
+                self.sha256sums_corrupted(in_dir).await?;
+
+            if !corrupted.is_empty() {
+                return Ok(Some(CheckResultError {
+                    error_type: CheckResultErrorType::Corrupted,
+                    message: "Corrupted chunk(s)".to_string(),
+                    missing: None,
+                    corrupted: Some(corrupted),
+                    extra: None,
+                }));
+            }
+        }
+
+        if let Some(checksums) = &self.checksums {
+            let mut corrupted: Vec<usize> = Vec::new();
+
+            for checksum in checksums {
+                let digest: String = chunk_digest(
+                    store,
+                    None,
+                    checksum.index,
+                    checksum.algorithm,
+                )
+                .await?;
+
+                if !digest.eq_ignore_ascii_case(&checksum.digest)
+                    && !corrupted.contains(&checksum.index)
+                {
+                    corrupted.push(checksum.index);
+                }
+            }
+
+            if !corrupted.is_empty() {
+                return Ok(Some(CheckResultError {
+                    error_type: CheckResultErrorType::Corrupted,
+                    message: "Corrupted chunk(s)".to_string(),
+                    missing: None,
+                    corrupted: Some(corrupted),
+                    extra: None,
+                }));
+            }
+        }
+
+        if self.spot_check.is_none() && self.merkle_root.is_none() {
+            return Ok(None);
+        }
+
+        let manifest: Manifest =
+            match (&self.manifest, &self.store, &self.in_dir) {
+                | (Some(manifest), _, _) => Some(manifest.clone()),
+                | (None, None, Some(in_dir)) => find_manifest(in_dir).await?,
+                | _ => None,
+            }
+            .ok_or_else(|| {
+                ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "manifest is required to verify the content",
+                )
+            })?;
+
+        if let Some(percentage) = self.spot_check {
+            let corrupted: Vec<usize> =
+                self.sample_corrupted(store, &manifest, percentage).await?;
+
+            if !corrupted.is_empty() {
+                return Ok(Some(CheckResultError {
+                    error_type: CheckResultErrorType::Corrupted,
+                    message: "Corrupted chunk(s)".to_string(),
+                    missing: None,
+                    corrupted: Some(corrupted),
+                    extra: None,
+                }));
+            }
+        }
+
+        if let Some(root) = &self.merkle_root {
+            let mut leaves: Vec<Hash> =
+                Vec::with_capacity(manifest.chunks.len());
+            let mut corrupted: Vec<usize> = Vec::new();
+
+            for chunk in &manifest.chunks {
+                let digest: String = chunk_digest(
+                    store,
+                    Some(manifest.headers),
+                    chunk.index,
+                    Algorithm::Sha256,
+                )
+                .await?;
+
+                if chunk.get_metadata(Algorithm::Sha256.id())
+                    != Some(digest.as_str())
+                {
+                    corrupted.push(chunk.index);
+                }
+
+                leaves.extend(merkle::from_hex(&digest));
+            }
+
+            let matched: bool = MerkleTree::new(&leaves)
+                .root_hex()
+                .is_some_and(|r| r.eq_ignore_ascii_case(root));
+
+            if !matched {
+                return Ok(Some(CheckResultError {
+                    error_type: CheckResultErrorType::Corrupted,
+                    message: "the Merkle root of chunks is not equal to \
+                              merkle_root parameter"
+                        .to_string(),
+                    missing: None,
+                    corrupted: Some(corrupted),
+                    extra: None,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Verify the signature of the manifest, then every chunk against the
+    /// checksums in the manifest, returning the error if any.
+    #[cfg(feature = "signing")]
+    async fn verify_signed(
+        &self,
+        store: &Arc<dyn ChunkStore>,
+        key: &VerifyingKey,
+    ) -> ioa::Result<Option<CheckResultError>> {
+        let in_dir: Option<&Path> =
+            self.in_dir.as_deref().filter(|_| self.store.is_none());
+
+        let manifest: Manifest = match (&self.manifest, in_dir) {
+            | (Some(manifest), _) => Some(manifest.clone()),
+            | (None, Some(in_dir)) => find_manifest(in_dir).await?,
+            | (None, None) => None,
+        }
+        .ok_or_else(|| {
+            ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "manifest is required to verify the signature",
+            )
+        })?;
+
+        let signature: Signature = match (&self.signature, in_dir) {
+            | (Some(signature), _) => *signature,
+            | (None, Some(in_dir)) => read_signature(in_dir).await?,
+            | (None, None) => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "signature is required to verify the manifest",
+                ))
+            },
+        };
+
+        if let Err(e) = verify(&manifest, &signature, key) {
+            return Ok(Some(CheckResultError {
+                error_type: CheckResultErrorType::Signature,
+                message: e.to_string(),
+                missing: None,
+                corrupted: None,
+                extra: None,
+            }));
+        }
+
+        let mut corrupted: Vec<usize> = Vec::new();
+
+        for chunk in &manifest.chunks {
+            // signed without checksums by other tools
+            let (algorithm, expected) = match secure_checksum(chunk) {
+                | Some(checksum) => checksum,
+                | None => {
+                    return Ok(Some(CheckResultError {
+                        error_type: CheckResultErrorType::Signature,
+                        message: "secure checksums are not recorded in the \
+                                  signed manifest"
+                            .to_string(),
+                        missing: None,
+                        corrupted: None,
+                        extra: None,
+                    }))
+                },
+            };
+
+            let matched: bool = match chunk_digest(
+                store,
+                Some(manifest.headers),
+                chunk.index,
+                algorithm,
+            )
+            .await
+            {
+                | Ok(digest) => digest.eq_ignore_ascii_case(expected),
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => false,
+                | Err(e) => return Err(e),
+            };
+
+            if !matched {
+                corrupted.push(chunk.index);
+            }
+        }
+
+        if !corrupted.is_empty() {
+            return Ok(Some(CheckResultError {
+                error_type: CheckResultErrorType::Corrupted,
+                message: "Corrupted chunk(s)".to_string(),
+                missing: None,
+                corrupted: Some(corrupted),
+                extra: None,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Hash the chunk files listed in the SHA256SUMS file of the input
+    /// directory, returning the corrupted ones.
+    #[cfg(feature = "checksum")]
+    async fn sha256sums_corrupted(
+        &self,
+        in_dir: &Path,
+    ) -> ioa::Result<Vec<usize>> {
+        let content: String =
+            fsa::read_to_string(in_dir.join(SHA256SUMS_FILE_NAME)).await?;
+
+        let naming: ChunkNaming = match find_manifest(in_dir).await? {
+            | Some(manifest) => manifest.naming,
+            | None => self.naming.clone(),
+        };
+
+        let mut corrupted: Vec<usize> = Vec::new();
+
+        for (line, (expected, path)) in
+            parse_sha256sums(&content)?.into_iter().enumerate()
+        {
+            let path: PathBuf = in_dir.join(sha256sums_entry(&path)?);
+
+            let matched: bool = match file_sha256(path.clone()).await {
+                | Ok(digest) => digest.eq_ignore_ascii_case(&expected),
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => false,
+                | Err(e) => return Err(e),
+            };
+
+            // the index from the name of the chunk, or the order of the lines
+            if !matched {
+                corrupted.push(
+                    path.file_name()
+                        .and_then(|name| naming.index(name.to_str()?))
+                        .unwrap_or(line),
+                );
+            }
+        }
+
+        corrupted.sort_unstable();
+        corrupted.dedup();
+
+        Ok(corrupted)
+    }
+
+    /// Hash a random sample of the chunks, returning the corrupted ones.
+    #[cfg(feature = "checksum")]
+    async fn sample_corrupted(
+        &self,
+        store: &Arc<dyn ChunkStore>,
+        manifest: &Manifest,
+        percentage: usize,
+    ) -> ioa::Result<Vec<usize>> {
+        if percentage == 0 {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "percentage must be greater than 0",
+            ));
+        }
+
+        let checksums: Vec<(usize, Algorithm, &str)> = manifest
+            .chunks
+            .iter()
+            .filter_map(|c| chunk_checksum(c).map(|(a, d)| (c.index, a, d)))
+            .collect();
+
+        if checksums.is_empty() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "checksums are not recorded in the manifest",
+            ));
+        }
+
+        let count: usize =
+            (checksums.len() * percentage.min(100)).div_ceil(100).max(1);
+
+        let rng: Arc<dyn Rng> =
+            self.rng.clone().unwrap_or_else(|| Arc::new(SystemRng));
+
+        let mut corrupted: Vec<usize> = Vec::new();
+
+        for i in sample(checksums.len(), count, rng.as_ref()) {
+            let (index, algorithm, expected) = checksums[i];
+
+            let digest: String =
+                chunk_digest(store, Some(manifest.headers), index, algorithm)
+                    .await?;
+
+            if !digest.eq_ignore_ascii_case(expected) {
+                corrupted.push(index);
+            }
+        }
+
+        Ok(corrupted)
+    }
 }
 
 impl Default for Check {
@@ -223,3 +1268,125 @@ impl Default for Check {
         Self::new()
     }
 }
+
+/// Get the path of an entry of a SHA256SUMS file relative to its
+/// directory, which must be a file name, e.g. `3`, or a file name within a
+/// volume directory, e.g. `vol0/3`.
+#[cfg(feature = "checksum")]
+fn sha256sums_entry(path: &str) -> ioa::Result<&Path> {
+    let entry: &Path = Path::new(path);
+
+    let names: Vec<&str> = entry
+        .components()
+        .map(|c| match c {
+            | std::path::Component::Normal(name) => name.to_str(),
+            | _ => None,
+        })
+        .collect::<Option<Vec<&str>>>()
+        .unwrap_or_default();
+
+    let valid: bool = match names.as_slice() {
+        | [_] => true,
+        | [volume, _] => volume
+            .strip_prefix(VOLUME_DIR_PREFIX)
+            .is_some_and(|v| v.parse::<usize>().is_ok()),
+        | _ => false,
+    };
+
+    if !valid || path.contains('\\') {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("invalid path in SHA256SUMS: {}", path),
+        ));
+    }
+
+    Ok(entry)
+}
+
+/// Compare entity tags, ignoring the quotes and the case of the hex.
+fn etag_eq(
+    a: &str,
+    b: &str,
+) -> bool {
+    a.trim_matches('"').eq_ignore_ascii_case(b.trim_matches('"'))
+}
+
+/// Compute the checksum of the payload of the chunk at `index` on a worker
+/// thread, detecting the header if `headers` is unknown.
+///
+/// The chunk is fetched and hashed in pieces of [`CHECK_PIECE_SIZE`] bytes
+/// if the store fetches ranges, otherwise in full.
+#[cfg(feature = "checksum")]
+async fn chunk_digest(
+    store: &Arc<dyn ChunkStore>,
+    headers: Option<bool>,
+    index: usize,
+    algorithm: Algorithm,
+) -> ioa::Result<String> {
+    if !store.ranged() {
+        let mut data: Vec<u8> = store.get(index).await?;
+
+        if headers.unwrap_or_else(|| ChunkHeader::of_chunk(&data).is_some()) {
+            data.drain(..CHUNK_HEADER_SIZE.min(data.len()));
+        }
+
+        return tokio::task::spawn_blocking(move || algorithm.digest(&data))
+            .await
+            .map_err(ioa::Error::other);
+    }
+
+    let size: usize = match store.head(index).await? {
+        | Some(head) => head.size,
+        | None => {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                format!("chunk {} not found in store", index),
+            ))
+        },
+    };
+
+    let headers: bool = match headers {
+        | Some(headers) => headers,
+        | None => ChunkHeader::decode(
+            &store.get_range(index, 0..CHUNK_HEADER_SIZE).await?,
+        )
+        .is_some_and(|header| {
+            size.checked_sub(CHUNK_HEADER_SIZE) == Some(header.payload_size)
+        }),
+    };
+
+    let mut offset: usize =
+        if headers { CHUNK_HEADER_SIZE.min(size) } else { 0 };
+
+    let mut hasher: FileHasher = FileHasher::new(algorithm);
+
+    loop {
+        let end: usize = (offset + CHECK_PIECE_SIZE).min(size);
+
+        let piece: Vec<u8> = store.get_range(index, offset..end).await?;
+
+        // the chunk is shorter than its size in the store
+        if piece.is_empty() {
+            break;
+        }
+
+        offset += piece.len();
+
+        hasher = tokio::task::spawn_blocking(move || {
+            hasher.update_blocking(&piece);
+            hasher
+        })
+        .await
+        .map_err(ioa::Error::other)?;
+
+        if offset >= size {
+            break;
+        }
+    }
+
+    hasher
+        .finish_blocking()
+        .into_iter()
+        .next()
+        .ok_or_else(|| ioa::Error::other("hasher has no algorithm"))
+}
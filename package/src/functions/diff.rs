@@ -0,0 +1,410 @@
+#[cfg(feature = "checksum")]
+use std::ops::Range;
+use std::{
+    fmt,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt},
+};
+
+#[cfg(feature = "checksum")]
+use crate::{
+    checksum::{chunk_checksum, range_digest, Algorithm},
+    manifest::{find_manifest, ChunkInfo, Manifest},
+};
+use crate::{
+    config::DIFF_BLOCK_SIZE,
+    functions::peek::{spans, Span},
+    header::CHUNK_HEADER_SIZE,
+    layout::LayoutRegistry,
+    naming::ChunkNaming,
+    transform::ChunkTransform,
+};
+
+/// First difference between a file and the chunks, from the [`Diff`]
+/// process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Difference {
+    /// Index of the first differing chunk, or `None` if the file is longer
+    /// than the chunks.
+    pub index: Option<usize>,
+    /// Offset of the first differing byte in the file, or the offset of the
+    /// chunk if it is `corrupted`.
+    pub offset: usize,
+    /// Whether the chunk itself does not match its checksum in the manifest
+    /// while the file contains the same bytes, e.g. a chunk corrupted in
+    /// storage.
+    pub corrupted: bool,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self.index {
+            | Some(index) if self.corrupted => write!(
+                f,
+                "chunk {} at offset {} does not match its checksum",
+                index, self.offset
+            ),
+            | Some(index) => write!(
+                f,
+                "file differs from chunk {} at offset {}",
+                index, self.offset
+            ),
+            | None => {
+                write!(f, "file has extra bytes from offset {}", self.offset)
+            },
+        }
+    }
+}
+
+/// Process to find the first difference between a file and the chunks in a
+/// directory, e.g. to pinpoint the corruption of a merged file.
+///
+/// With the `checksum` feature and the checksums of the chunks in the
+/// manifest, each range of the file is hashed and compared with the checksum
+/// of its chunk, and only the first mismatching chunk is read to pinpoint the
+/// byte. Otherwise, each chunk is compared with its range in the file. Either
+/// way, the first differing chunk is found without merging the chunks again,
+/// and the chunks are read by blocks.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::diff::{Diff, Difference};
+///
+/// async fn example() {
+///     let difference: Option<Difference> = Diff::new()
+///         .in_dir(PathBuf::from("path").join("to").join("dir"))
+///         .file(PathBuf::from("path").join("to").join("file"))
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Diff {
+    in_dir: Option<PathBuf>,
+    file: Option<PathBuf>,
+    transform: Option<Arc<dyn ChunkTransform>>,
+    naming: ChunkNaming,
+    layouts: LayoutRegistry,
+}
+
+impl Diff {
+    /// Create a new diff process.
+    pub fn new() -> Self {
+        Self {
+            in_dir: None,
+            file: None,
+            transform: None,
+            naming: ChunkNaming::Index,
+            layouts: LayoutRegistry::new(),
+        }
+    }
+
+    /// Set the input directory of the chunks.
+    pub fn in_dir<InDir: AsRef<Path>>(
+        mut self,
+        path: InDir,
+    ) -> Self {
+        self.in_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the file to compare with the chunks.
+    pub fn file<File: AsRef<Path>>(
+        mut self,
+        path: File,
+    ) -> Self {
+        self.file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the transform to decode the chunks.
+    pub fn transform<T: ChunkTransform + 'static>(
+        mut self,
+        transform: T,
+    ) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+
+    pub(crate) fn transform_arc(
+        mut self,
+        transform: Option<Arc<dyn ChunkTransform>>,
+    ) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set the scheme of the names of the chunks in the input directory.
+    ///
+    /// By default, the names are the indices of the chunks. It is ignored
+    /// when the input directory contains a manifest.
+    pub fn naming(
+        mut self,
+        naming: ChunkNaming,
+    ) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Set the layouts to detect the chunk set in the input directory.
+    ///
+    /// By default, the layouts follow the [`LayoutRegistry::new`].
+    pub fn layouts(
+        mut self,
+        layouts: LayoutRegistry,
+    ) -> Self {
+        self.layouts = layouts;
+        self
+    }
+
+    /// Run the diff process, returning `None` if the file matches the
+    /// chunks.
+    pub async fn run(self) -> ioa::Result<Option<Difference>> {
+        let in_dir: &Path = match self.in_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if in_dir not a directory
+                if !p.is_dir() {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        "in_dir is not a directory",
+                    ));
+                }
+
+                p
+            },
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "in_dir is not set",
+                ))
+            },
+        };
+
+        let file: &Path = match self.file {
+            | Some(ref p) => p.as_ref(),
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "file is not set",
+                ))
+            },
+        };
+
+        let spans: Vec<Span> =
+            spans(in_dir, self.transform.as_ref(), &self.naming, &self.layouts)
+                .await?;
+
+        let mut input: fsa::File =
+            fsa::OpenOptions::new().read(true).open(file).await?;
+
+        let file_size: usize = input.metadata().await?.len() as usize;
+
+        let end: usize = spans.last().map_or(0, |s| s.offset + s.size);
+
+        // only the chunk not matching its checksum is compared
+        #[cfg(feature = "checksum")]
+        if let Some(checksums) = self.checksums(in_dir, &spans).await? {
+            for (span, (algorithm, digest)) in spans.iter().zip(checksums) {
+                let range: Range<usize> = span.offset..span.offset + span.size;
+
+                if range.end <= file_size
+                    && range_digest(file.to_path_buf(), range, algorithm)
+                        .await?
+                        .eq_ignore_ascii_case(&digest)
+                {
+                    continue;
+                }
+
+                let offset: Option<usize> =
+                    self.first_difference(span, &mut input, file_size).await?;
+
+                return Ok(Some(Difference {
+                    index: Some(span.index),
+                    offset: offset.unwrap_or(span.offset),
+                    corrupted: offset.is_none(),
+                }));
+            }
+
+            return Ok(extra_bytes(file_size, end));
+        }
+
+        for span in &spans {
+            if let Some(offset) =
+                self.first_difference(span, &mut input, file_size).await?
+            {
+                return Ok(Some(Difference {
+                    index: Some(span.index),
+                    offset,
+                    corrupted: false,
+                }));
+            }
+        }
+
+        Ok(extra_bytes(file_size, end))
+    }
+
+    /// Get the checksums of the chunks recorded in the manifest, if every
+    /// chunk has one for its original bytes.
+    #[cfg(feature = "checksum")]
+    async fn checksums(
+        &self,
+        in_dir: &Path,
+        spans: &[Span],
+    ) -> ioa::Result<Option<Vec<(Algorithm, String)>>> {
+        let manifest: Manifest = match find_manifest(in_dir).await? {
+            // the checksums of transformed chunks are of the stored bytes
+            | Some(manifest) if manifest.transform.is_none() => manifest,
+            | _ => return Ok(None),
+        };
+
+        Ok(spans
+            .iter()
+            .map(|span| {
+                let chunk: &ChunkInfo = manifest.chunk(span.index)?;
+                let (algorithm, digest) = chunk_checksum(chunk)?;
+
+                Some((algorithm, digest.to_string()))
+            })
+            .collect())
+    }
+
+    /// Get the offset of the first byte of the file differing from the
+    /// chunk, or `None` if the file contains the chunk at its offset.
+    ///
+    /// The chunk is compared by blocks, so only a transformed chunk is
+    /// decoded in memory as a whole.
+    async fn first_difference(
+        &self,
+        span: &Span,
+        input: &mut fsa::File,
+        file_size: usize,
+    ) -> ioa::Result<Option<usize>> {
+        let mut source: ChunkSource = match &self.transform {
+            | Some(transform) => {
+                let decoded: Vec<u8> =
+                    span.read(0..span.size, Some(transform)).await?;
+
+                if decoded.len() != span.size {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidData,
+                        format!(
+                            "chunk {} is shorter than its size",
+                            span.index
+                        ),
+                    ));
+                }
+
+                ChunkSource::Decoded(decoded)
+            },
+            | None => {
+                let mut chunk: fsa::File =
+                    fsa::OpenOptions::new().read(true).open(&span.path).await?;
+
+                let skip: usize =
+                    if span.header { CHUNK_HEADER_SIZE } else { 0 };
+
+                chunk.seek(SeekFrom::Start(skip as u64)).await?;
+
+                ChunkSource::File(chunk)
+            },
+        };
+
+        input.seek(SeekFrom::Start(span.offset as u64)).await?;
+
+        let mut expected: Vec<u8> = vec![0; DIFF_BLOCK_SIZE.min(span.size)];
+        let mut actual: Vec<u8> = vec![0; expected.len()];
+
+        let mut position: usize = 0;
+
+        while position < span.size {
+            let len: usize = expected.len().min(span.size - position);
+
+            match &mut source {
+                | ChunkSource::File(chunk) => {
+                    chunk.read_exact(&mut expected[..len]).await?;
+                },
+                | ChunkSource::Decoded(decoded) => expected[..len]
+                    .copy_from_slice(&decoded[position..position + len]),
+            }
+
+            let read: usize = read_full(input, &mut actual[..len]).await?;
+
+            // the first differing byte, or the end of a short file
+            let differing: Option<usize> = actual[..read]
+                .iter()
+                .zip(&expected[..read])
+                .position(|(a, e)| a != e)
+                .or((read < len).then_some(read));
+
+            if let Some(i) = differing {
+                return Ok(Some((span.offset + position + i).min(file_size)));
+            }
+
+            position += len;
+        }
+
+        Ok(None)
+    }
+}
+
+/// Bytes of a chunk compared with a file.
+enum ChunkSource {
+    /// Chunk file read by blocks.
+    File(fsa::File),
+    /// Transformed chunk decoded as a whole.
+    Decoded(Vec<u8>),
+}
+
+/// Get the difference of the bytes after the end of the chunks, if any.
+fn extra_bytes(
+    file_size: usize,
+    end: usize,
+) -> Option<Difference> {
+    (file_size > end).then_some(Difference {
+        index: None,
+        offset: end,
+        corrupted: false,
+    })
+}
+
+/// Read until `buffer` is full or the end of the input, returning the
+/// number of bytes read.
+async fn read_full(
+    input: &mut fsa::File,
+    buffer: &mut [u8],
+) -> ioa::Result<usize> {
+    let mut read: usize = 0;
+
+    while read < buffer.len() {
+        let n: usize = input.read(&mut buffer[read..]).await?;
+
+        if n == 0 {
+            break;
+        }
+
+        read += n;
+    }
+
+    Ok(read)
+}
+
+impl Default for Diff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use tokio::{fs as fsa, io as ioa};
+
+use crate::{
+    config::{ETAG_METADATA_KEY, PAR2_FILE_NAME},
+    functions::merge::read_chunks,
+    header::ChunkHeader,
+    layout::{Layout, LayoutRegistry},
+    manifest::{find_manifest, Manifest, Uuid, MANIFEST_VERSION},
+    naming::ChunkNaming,
+    store::{ChunkHead, ChunkStore, DirStore},
+};
+
+/// Report of a chunk directory from the [`inspect`] function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InspectReport {
+    /// Path of the inspected directory.
+    pub dir: PathBuf,
+    /// Whether the directory or its first volume contains a manifest.
+    pub manifest: bool,
+    /// Version of the manifest, if any.
+    pub manifest_version: Option<u32>,
+    /// Version of the chunk headers, if the chunks come with headers.
+    pub header_version: Option<u32>,
+    /// Identity of the split from the manifest or the chunk headers.
+    pub id: Option<Uuid>,
+    /// Identity of the naming scheme or the detected layout, e.g. `index`.
+    pub layout: Option<String>,
+    /// Name of the original file, if kept by the layout.
+    pub original_name: Option<String>,
+    /// Total number of chunks expected, if recorded in the manifest.
+    pub expected_chunks: Option<usize>,
+    /// Chunks found in the directory, sorted by their indices.
+    pub chunks: Vec<InspectChunk>,
+    /// Total size of the chunks found in bytes.
+    pub total_size: usize,
+    /// Total number of volumes, or `0` if not splitted into volumes.
+    pub volumes: usize,
+    /// Number of chunks with a hash recorded in the manifest.
+    pub hashed_chunks: usize,
+    /// Whether the directory contains a PAR2 recovery file.
+    pub recovery: bool,
+    /// Anomalies found in the directory.
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl InspectReport {
+    /// Check whether no anomaly is found.
+    pub fn is_healthy(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
+/// Chunk found by the [`inspect`] function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InspectChunk {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Path of the chunk.
+    pub path: PathBuf,
+    /// Size of the chunk in storage.
+    pub size: usize,
+}
+
+/// Anomaly found by the [`inspect`] function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Anomaly {
+    /// No chunk is found in the directory.
+    Empty,
+    /// The manifest is written by a newer version.
+    UnsupportedManifest {
+        /// Version of the manifest.
+        version: u32,
+    },
+    /// The chunk is missing.
+    MissingChunk {
+        /// Index of the chunk.
+        index: usize,
+    },
+    /// The size of the chunk differs from the manifest.
+    SizeMismatch {
+        /// Index of the chunk.
+        index: usize,
+        /// Size recorded in the manifest.
+        expected: usize,
+        /// Size of the chunk in storage.
+        actual: usize,
+    },
+    /// The header of the chunk is missing or does not belong to the split.
+    HeaderMismatch {
+        /// Index of the chunk.
+        index: usize,
+    },
+}
+
+/// Inspect a chunk directory without reading the content of the chunks.
+///
+/// The chunks are found with the manifest if any, or with the names of the
+/// files otherwise, falling back to the layouts of [`LayoutRegistry::new`].
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::inspect::{inspect, InspectReport};
+///
+/// async fn example() {
+///     let report: InspectReport =
+///         inspect(PathBuf::from("path").join("to").join("dir")).await.unwrap();
+///
+///     for anomaly in &report.anomalies {
+///         println!("{:?}", anomaly);
+///     }
+/// }
+/// ```
+pub async fn inspect<InDir: AsRef<Path>>(
+    in_dir: InDir
+) -> ioa::Result<InspectReport> {
+    let in_dir: &Path = in_dir.as_ref();
+
+    // if in_dir not exists
+    if !in_dir.exists() {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::NotFound,
+            "in_dir path not found",
+        ));
+    }
+
+    // if in_dir not a directory
+    if !in_dir.is_dir() {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            "in_dir is not a directory",
+        ));
+    }
+
+    let manifest: Option<Manifest> = find_manifest(in_dir).await?;
+
+    let mut report: InspectReport = InspectReport {
+        dir: in_dir.to_path_buf(),
+        manifest: manifest.is_some(),
+        manifest_version: manifest.as_ref().map(|m| m.version),
+        header_version: None,
+        id: manifest.as_ref().and_then(|m| m.id),
+        layout: None,
+        original_name: None,
+        expected_chunks: manifest.as_ref().map(|m| m.total_chunks),
+        chunks: Vec::new(),
+        total_size: 0,
+        volumes: manifest.as_ref().map_or(0, |m| m.total_volumes()),
+        hashed_chunks: 0,
+        recovery: fsa::try_exists(in_dir.join(PAR2_FILE_NAME)).await?,
+        anomalies: Vec::new(),
+    };
+
+    match &manifest {
+        | Some(manifest) => {
+            inspect_manifest(in_dir, manifest, &mut report).await?
+        },
+        | None => inspect_names(in_dir, &mut report).await?,
+    }
+
+    if report.chunks.is_empty() {
+        report.anomalies.push(Anomaly::Empty);
+        return Ok(report);
+    }
+
+    report.total_size = report.chunks.iter().map(|c| c.size).sum();
+
+    // headers are expected on every chunk if the first one has a header
+    let first: Option<ChunkHeader> =
+        ChunkHeader::read(&report.chunks[0].path).await?;
+
+    let headers: bool = match &manifest {
+        | Some(manifest) => manifest.headers,
+        | None => first.is_some(),
+    };
+
+    if headers {
+        let split_id: Option<Uuid> =
+            report.id.or_else(|| first.map(|h| h.split_id));
+
+        report.id = split_id;
+        report.header_version = first.map(|h| h.version);
+
+        for chunk in &report.chunks {
+            let matched: bool = match ChunkHeader::read(&chunk.path).await? {
+                | Some(header) => {
+                    header.index == chunk.index
+                        && Some(header.split_id) == split_id
+                },
+                | None => false,
+            };
+
+            if !matched {
+                report
+                    .anomalies
+                    .push(Anomaly::HeaderMismatch { index: chunk.index });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Inspect the chunks described by the manifest.
+async fn inspect_manifest(
+    in_dir: &Path,
+    manifest: &Manifest,
+    report: &mut InspectReport,
+) -> ioa::Result<()> {
+    if manifest.version > MANIFEST_VERSION {
+        report
+            .anomalies
+            .push(Anomaly::UnsupportedManifest { version: manifest.version });
+    }
+
+    report.layout = Some(manifest.naming.id().to_string());
+    report.original_name = manifest.naming.original_name().map(str::to_string);
+
+    report.hashed_chunks = manifest
+        .chunks
+        .iter()
+        .filter(|c| c.get_metadata(ETAG_METADATA_KEY).is_some())
+        .count();
+
+    let store: DirStore = DirStore::with_manifest(in_dir, manifest);
+
+    for index in 0..manifest.total_chunks {
+        let size: usize = match store.head(index).await? {
+            | Some(ChunkHead { size, .. }) => size,
+            | None => {
+                report.anomalies.push(Anomaly::MissingChunk { index });
+                continue;
+            },
+        };
+
+        if let Some(chunk) = manifest.chunk(index) {
+            if chunk.stored_size != size {
+                report.anomalies.push(Anomaly::SizeMismatch {
+                    index,
+                    expected: chunk.stored_size,
+                    actual: size,
+                });
+            }
+        }
+
+        report.chunks.push(InspectChunk {
+            index,
+            path: store.path(index),
+            size,
+        });
+    }
+
+    Ok(())
+}
+
+/// Inspect the chunks found by their names.
+async fn inspect_names(
+    in_dir: &Path,
+    report: &mut InspectReport,
+) -> ioa::Result<()> {
+    let naming: ChunkNaming = ChunkNaming::Index;
+
+    let mut entries: Vec<(usize, PathBuf)> = Vec::new();
+
+    let volumes: Vec<PathBuf> =
+        read_chunks(in_dir, &naming, &mut entries).await?;
+
+    for dir in &volumes {
+        read_chunks(dir, &naming, &mut entries).await?;
+    }
+
+    report.volumes = volumes.len();
+
+    let mut chunks: BTreeMap<usize, PathBuf> = entries.into_iter().collect();
+
+    if chunks.is_empty() {
+        if let Some(detected) = LayoutRegistry::new().detect(in_dir).await? {
+            report.layout = Some(detected.id);
+            report.original_name = detected.original_name;
+            chunks = detected.chunks;
+        }
+    } else {
+        report.layout = Some(naming.id().to_string());
+    }
+
+    // gaps between the chunks found
+    let last: usize = match chunks.keys().next_back() {
+        | Some(last) => *last,
+        | None => return Ok(()),
+    };
+
+    for index in 0..=last {
+        let path: PathBuf = match chunks.remove(&index) {
+            | Some(path) => path,
+            | None => {
+                report.anomalies.push(Anomaly::MissingChunk { index });
+                continue;
+            },
+        };
+
+        let size: usize = fsa::metadata(&path).await?.len() as usize;
+
+        report.chunks.push(InspectChunk { index, path, size });
+    }
+
+    Ok(())
+}
@@ -1,14 +1,325 @@
 use std::{
+    collections::{BTreeMap, VecDeque},
+    ffi::{OsStr, OsString},
     fs,
+    future::Future,
+    io::SeekFrom,
+    ops::Range,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use tokio::{
     fs as fsa,
-    io::{self as ioa, AsyncReadExt, AsyncWriteExt},
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
+    runtime::Handle,
+    sync::OwnedSemaphorePermit,
+    task::JoinHandle,
 };
 
-use crate::config::BUFFER_CAPACITY_MAX_DEFAULT;
+#[cfg(feature = "checksum")]
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "bao")]
+use crate::bao::{outboard_path, outboard_size, BaoVerifier};
+#[cfg(feature = "unicode")]
+use crate::unicode::Normalization;
+use crate::{
+    attributes::{FileAttributes, FileMetadata},
+    config::{
+        BUFFER_CAPACITY_MAX_DEFAULT, PREFETCH_DEFAULT,
+        PROGRESS_INTERVAL_DEFAULT, VOLUME_DIR_PREFIX,
+    },
+    control::{Control, OperationHandle},
+    device::{device_size, direct, is_block_device, DirectWriter},
+    diff::{Diff, Difference},
+    event::{Event, EventBus, EventStream},
+    header::{ChunkHeader, CHUNK_HEADER_SIZE},
+    layout::LayoutRegistry,
+    log::{Operation, OperationLog},
+    manifest::{find_manifest, manifest_dir, Manifest, Uuid},
+    naming::ChunkNaming,
+    profile::{Phase, Profile, Profiler},
+    progress::{throughput, OnProgress, ProgressSink, Reporter, Tee},
+    stall::StallMonitor,
+    store::{ChunkStore, DirStore},
+    transform::ChunkTransform,
+    tune::{memory_budget, require_space, MemoryBudget},
+};
+#[cfg(feature = "checksum")]
+use crate::{
+    checksum::{Algorithm, FileHasher},
+    merkle::{self, Hash, MerkleTree},
+};
+
+/// Expected output file of the merge process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    /// Size of the output file in bytes.
+    Size(usize),
+    /// MD5 of the output file in hex.
+    #[cfg(feature = "etag")]
+    Md5(String),
+}
+
+impl Expected {
+    /// Check whether the file matches the expectation.
+    async fn matches(
+        &self,
+        path: &Path,
+    ) -> ioa::Result<bool> {
+        let size: usize = match fsa::metadata(path).await {
+            | Ok(metadata) if metadata.is_file() => metadata.len() as usize,
+            | Ok(_) => return Ok(false),
+            | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                return Ok(false)
+            },
+            | Err(e) => return Err(e),
+        };
+
+        match self {
+            | Self::Size(expected) => Ok(size == *expected),
+            #[cfg(feature = "etag")]
+            | Self::Md5(expected) => Ok(crate::etag::file_md5(path)
+                .await?
+                .eq_ignore_ascii_case(expected)),
+        }
+    }
+}
+
+impl From<usize> for Expected {
+    fn from(size: usize) -> Self {
+        Self::Size(size)
+    }
+}
+
+/// Policy of the merge process when the output file already exists, see
+/// [`Merge::on_collision`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Collision {
+    /// Delete the existing file and replace it.
+    #[default]
+    Overwrite,
+    /// Keep the existing file and merge into a new file named with the
+    /// `suffix` between the stem and the extension, unless the existing file
+    /// already holds the output.
+    ///
+    /// `{n}` in the suffix is replaced by the first number from `1` whose
+    /// name is free, e.g. `file (1).png` then `file (2).png` with the suffix
+    /// ` ({n})`. A suffix without `{n}` is followed by the number, e.g.
+    /// `file copy 1.png` with the suffix ` copy`. The new file is created
+    /// only if the name is still free, so concurrent merges never share it.
+    ///
+    /// The existing file holds the output if its size and checksum match the
+    /// original file, known from the manifest or the expected hash with the
+    /// `checksum` feature. The merge is then [skipped](MergeResult::skipped)
+    /// with that file as the output. The files always differ if the checksum
+    /// of the original file is unknown, as a matching size alone does not
+    /// tell them apart.
+    KeepBoth {
+        /// Suffix of the new file name.
+        suffix: String,
+    },
+    /// Fail with [`ioa::ErrorKind::AlreadyExists`].
+    Fail,
+    /// Move the existing file to the trash or recycle bin of the platform
+    /// and replace it, e.g. for desktop applications.
+    #[cfg(feature = "trash")]
+    Trash,
+}
+
+impl Collision {
+    /// Get the path to merge into instead of `out_file` if it exists.
+    async fn resolve(
+        &self,
+        out_file: &Path,
+        identity: &Identity,
+    ) -> ioa::Result<Resolved> {
+        let merge: Resolved =
+            Resolved::Merge { path: out_file.to_path_buf(), claimed: false };
+
+        // the block device is written in place
+        if !fsa::try_exists(out_file).await? || is_block_device(out_file) {
+            return Ok(merge);
+        }
+
+        match self {
+            | Self::Overwrite => Ok(merge),
+            #[cfg(feature = "trash")]
+            | Self::Trash => {
+                let path: PathBuf = out_file.to_path_buf();
+
+                tokio::task::spawn_blocking(move || trash::delete(&path))
+                    .await
+                    .map_err(ioa::Error::other)?
+                    .map_err(ioa::Error::other)?;
+
+                Ok(merge)
+            },
+            | Self::KeepBoth { suffix } => {
+                if identity.matches(out_file).await? {
+                    return Ok(Resolved::Same(out_file.to_path_buf()));
+                }
+
+                let mut n: usize = 1;
+
+                loop {
+                    let path: PathBuf = kept_name(out_file, suffix, n);
+
+                    // claim the name, so no other merge takes it meanwhile
+                    match fsa::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)
+                        .await
+                    {
+                        | Ok(_) => {
+                            return Ok(Resolved::Merge { path, claimed: true })
+                        },
+                        | Err(e) if e.kind() == ioa::ErrorKind::AlreadyExists => {
+                            if identity.matches(&path).await? {
+                                return Ok(Resolved::Same(path));
+                            }
+                        },
+                        | Err(e) => return Err(e),
+                    }
+
+                    n += 1;
+                }
+            },
+            | Self::Fail => Err(ioa::Error::new(
+                ioa::ErrorKind::AlreadyExists,
+                format!("out_file already exists: {}", out_file.display()),
+            )),
+        }
+    }
+}
+
+/// Output file resolved by [`Collision::resolve`].
+enum Resolved {
+    /// Merge into the path, already created empty if `claimed`.
+    Merge { path: PathBuf, claimed: bool },
+    /// The existing file at the path already holds the output.
+    Same(PathBuf),
+}
+
+/// Size and checksum of the output, known before the merge, to tell whether
+/// an existing file already holds it.
+#[derive(Debug, Clone, Default)]
+struct Identity {
+    size: Option<usize>,
+    #[cfg(feature = "checksum")]
+    hash: Option<(Algorithm, String)>,
+}
+
+impl Identity {
+    /// Check whether the file matches, never if the size or the checksum is
+    /// unknown.
+    async fn matches(
+        &self,
+        path: &Path,
+    ) -> ioa::Result<bool> {
+        let size: usize = match self.size {
+            | Some(size) => size,
+            | None => return Ok(false),
+        };
+
+        if !Expected::Size(size).matches(path).await? {
+            return Ok(false);
+        }
+
+        #[cfg(feature = "checksum")]
+        if let Some((algorithm, digest)) = &self.hash {
+            return Ok(crate::checksum::file_digest(
+                path.to_path_buf(),
+                *algorithm,
+            )
+            .await?
+            .eq_ignore_ascii_case(digest));
+        }
+
+        // a matching size alone does not tell the files apart
+        Ok(false)
+    }
+}
+
+/// Get the path of `out_file` kept with the `suffix` numbered `n`, see
+/// [`Collision::KeepBoth`].
+fn kept_name(
+    out_file: &Path,
+    suffix: &str,
+    n: usize,
+) -> PathBuf {
+    let suffix: String = match suffix.contains("{n}") {
+        | true => suffix.replace("{n}", &n.to_string()),
+        | false => format!("{} {}", suffix, n),
+    };
+
+    let mut name: OsString =
+        out_file.file_stem().map(OsStr::to_os_string).unwrap_or_default();
+
+    name.push(suffix);
+
+    if let Some(extension) = out_file.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+
+    out_file.with_file_name(name)
+}
+
+/// Progress of the merge process, see [`Merge::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeProgress {
+    /// The number of bytes written to the output file.
+    pub bytes_written: usize,
+    /// Size of the original file, if known from the manifest or
+    /// [`Merge::expect_file_size`].
+    pub total_bytes: Option<usize>,
+    /// The number of chunks merged.
+    pub chunks_merged: usize,
+    /// The total number of chunks.
+    pub total_chunks: usize,
+}
+
+impl MergeProgress {
+    /// Get the fraction of the output written, from `0.0` to `1.0`, by the
+    /// bytes if the size of the original file is known, otherwise by the
+    /// chunks.
+    pub fn fraction(&self) -> f64 {
+        match (self.total_bytes, self.total_chunks) {
+            | (Some(0), _) | (None, 0) => 1.0,
+            | (Some(total), _) => self.bytes_written as f64 / total as f64,
+            | (None, total) => self.chunks_merged as f64 / total as f64,
+        }
+    }
+}
+
+/// Result of the merge process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MergeResult {
+    /// Path of the output file.
+    pub out_file: PathBuf,
+    /// Size of the output file, or of the block device written in place.
+    pub file_size: usize,
+    /// The number of bytes written by this run, excluding the bytes left by
+    /// a previous run if [`resumed`](Merge::resume).
+    pub bytes_written: usize,
+    /// Whether the merge is skipped as the output file matches, see
+    /// [`Merge::skip_if_matching`].
+    pub skipped: bool,
+    /// Time elapsed from the start to the end of the process.
+    pub elapsed: Duration,
+}
+
+impl MergeResult {
+    /// Get the average throughput of the process in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        throughput(self.bytes_written, self.elapsed)
+    }
+}
 
 /// Process to merge chunks from a directory to a path.
 ///
@@ -17,10 +328,10 @@ use crate::config::BUFFER_CAPACITY_MAX_DEFAULT;
 /// ```no_run
 /// use std::path::PathBuf;
 ///
-/// use filego::merge::Merge;
+/// use filego::merge::{Merge, MergeResult};
 ///
 /// async fn example() {
-///     let result: bool = Merge::new()
+///     let result: MergeResult = Merge::new()
 ///         .in_dir(PathBuf::from("path").join("to").join("dir"))
 ///         .out_file(PathBuf::from("path").join("to").join("file"))
 ///         .run()
@@ -32,7 +343,45 @@ use crate::config::BUFFER_CAPACITY_MAX_DEFAULT;
 pub struct Merge {
     in_dir: Option<PathBuf>,
     out_file: Option<PathBuf>,
+    out_dir: Option<PathBuf>,
     cap_max: usize,
+    transform: Option<Arc<dyn ChunkTransform>>,
+    store: Option<Arc<dyn ChunkStore>>,
+    total_chunks: Option<usize>,
+    manifest_path: Option<PathBuf>,
+    prefetch: usize,
+    adaptive_prefetch: bool,
+    cache_dir: Option<PathBuf>,
+    runtime: Option<Handle>,
+    naming: ChunkNaming,
+    layouts: LayoutRegistry,
+    control: Option<Arc<Control>>,
+    events: Option<EventBus>,
+    progress: Option<Arc<dyn ProgressSink>>,
+    on_progress: Option<OnProgress<MergeProgress>>,
+    progress_interval: Duration,
+    stall: Option<Arc<StallMonitor>>,
+    log: Option<OperationLog>,
+    verify: bool,
+    skip_if_matching: Option<Expected>,
+    collision: Collision,
+    #[cfg(feature = "unicode")]
+    normalization: Option<Normalization>,
+    resume: bool,
+    require_free_space: bool,
+    expect_file_size: Option<usize>,
+    #[cfg(feature = "checksum")]
+    expect_hash: Option<(Algorithm, String)>,
+    #[cfg(feature = "checksum")]
+    merkle_root: Option<String>,
+    #[cfg(feature = "bao")]
+    bao: bool,
+    attributes: FileAttributes,
+    restore_metadata: bool,
+    direct_io: bool,
+    auto_tune: bool,
+    budget: Option<MemoryBudget>,
+    profile: bool,
 }
 
 impl Merge {
@@ -41,10 +390,80 @@ impl Merge {
         Self {
             in_dir: None,
             out_file: None,
+            out_dir: None,
             cap_max: BUFFER_CAPACITY_MAX_DEFAULT,
+            transform: None,
+            store: None,
+            total_chunks: None,
+            manifest_path: None,
+            prefetch: PREFETCH_DEFAULT,
+            adaptive_prefetch: false,
+            cache_dir: None,
+            runtime: None,
+            naming: ChunkNaming::Index,
+            layouts: LayoutRegistry::new(),
+            control: None,
+            events: None,
+            progress: None,
+            on_progress: None,
+            progress_interval: PROGRESS_INTERVAL_DEFAULT,
+            stall: None,
+            log: None,
+            verify: false,
+            skip_if_matching: None,
+            collision: Collision::Overwrite,
+            #[cfg(feature = "unicode")]
+            normalization: None,
+            resume: false,
+            require_free_space: false,
+            expect_file_size: None,
+            #[cfg(feature = "checksum")]
+            expect_hash: None,
+            #[cfg(feature = "checksum")]
+            merkle_root: None,
+            #[cfg(feature = "bao")]
+            bao: false,
+            attributes: FileAttributes::new(),
+            restore_metadata: false,
+            direct_io: false,
+            auto_tune: false,
+            budget: None,
+            profile: false,
         }
     }
 
+    /// Create a new merge process from the chunks in a store instead of the
+    /// input directory.
+    ///
+    /// The chunks are fetched from the store in parallel ahead of writing,
+    /// in pieces of the buffer capacity if the store fetches ranges, see
+    /// [`ChunkStore::ranged`], and the `total_chunks` must be set.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::{path::PathBuf, sync::Arc};
+    ///
+    /// use filego::{
+    ///     merge::{Merge, MergeResult},
+    ///     store::DirStore,
+    /// };
+    ///
+    /// async fn example() {
+    ///     let result: MergeResult = Merge::from_store(Arc::new(DirStore::new(
+    ///         PathBuf::from("path").join("to").join("dir"),
+    ///     )))
+    ///     .total_chunks(0) // result from split function...
+    ///     .out_file(PathBuf::from("path").join("to").join("file"))
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// }
+    /// ```
+    pub fn from_store(store: Arc<dyn ChunkStore>) -> Self {
+        Self { store: Some(store), ..Self::new() }
+    }
+
     /// Set the input directory.
     pub fn in_dir<InDir: AsRef<Path>>(
         mut self,
@@ -55,6 +474,10 @@ impl Merge {
     }
 
     /// Set the output file.
+    ///
+    /// It is not required when the chunks are named as multi-part archives,
+    /// e.g. `file.7z.001`, in which case the output file is written as
+    /// `file.7z` in the input directory.
     pub fn out_file<OutFile: AsRef<Path>>(
         mut self,
         path: OutFile,
@@ -63,6 +486,22 @@ impl Merge {
         self
     }
 
+    /// Set the output directory, in which the output file is written under
+    /// its original name.
+    ///
+    /// The name is read from the manifest, as recorded by
+    /// [`Split`](crate::split::Split), or kept by the naming of multi-part
+    /// archives. Only the final component of the name is used, so the file
+    /// cannot be written outside of the directory. It is ignored if the
+    /// output file is set.
+    pub fn out_dir<OutDir: AsRef<Path>>(
+        mut self,
+        path: OutDir,
+    ) -> Self {
+        self.out_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Set the maximum size of the buffer capacity.
     ///
     /// By default, the buffer capacity is based on the size of the inputs in
@@ -77,136 +516,2534 @@ impl Merge {
         self
     }
 
-    /// Run the merge process.
-    pub async fn run(self) -> ioa::Result<bool> {
-        let in_dir: &Path = match self.in_dir {
-            | Some(ref p) => {
-                let p: &Path = p.as_ref();
+    /// Set the transform to decode each chunk before writing it to the
+    /// output file.
+    ///
+    /// When the input directory contains a manifest, the identity of the
+    /// transform must match the one recorded in the manifest. When a
+    /// transform is set, each chunk is read into memory as a whole instead
+    /// of through the buffer.
+    pub fn transform<T: ChunkTransform + 'static>(
+        mut self,
+        transform: T,
+    ) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
 
-                // if in_dir not exists
-                if !p.exists() {
-                    return Err(ioa::Error::new(
-                        ioa::ErrorKind::NotFound,
-                        "in_dir path not found",
-                    ));
-                }
+    /// Set the scheme of the names of the chunks in the input directory.
+    ///
+    /// By default, the names are the indices of the chunks. With
+    /// [`ChunkNaming::coreutils`], the chunks produced by `split(1)` can be
+    /// merged. It is ignored when the input directory contains a manifest.
+    ///
+    /// When no chunk is named by the indices, the chunk set is detected with
+    /// the layouts, e.g. `file.ext.001` as produced by HJSplit or 7-Zip.
+    pub fn naming(
+        mut self,
+        naming: ChunkNaming,
+    ) -> Self {
+        self.naming = naming;
+        self
+    }
 
-                // if in_dir not a directory
-                if !p.is_dir() {
-                    return Err(ioa::Error::new(
-                        ioa::ErrorKind::InvalidInput,
-                        "in_dir is not a directory",
-                    ));
-                }
+    /// Set the layouts to detect the chunk set in the input directory.
+    ///
+    /// By default, the layouts follow the [`LayoutRegistry::new`].
+    pub fn layouts(
+        mut self,
+        layouts: LayoutRegistry,
+    ) -> Self {
+        self.layouts = layouts;
+        self
+    }
 
-                p
-            },
-            | None => {
-                return Err(ioa::Error::new(
-                    ioa::ErrorKind::InvalidInput,
-                    "in_dir is not set",
-                ))
-            },
-        };
+    /// Set the total number of chunks to merge from the store.
+    pub fn total_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.total_chunks = Some(chunks);
+        self
+    }
 
-        let out_file: &Path = match self.out_file {
-            | Some(ref p) => p.as_ref(),
-            | None => {
-                return Err(ioa::Error::new(
-                    ioa::ErrorKind::InvalidInput,
-                    "out_file is not set",
-                ))
-            },
-        };
+    /// Read the input directory, the total number of chunks and the expected
+    /// size of the output file from the manifest at `path`, e.g. written by
+    /// [`Split::manifest`], so they are not transmitted apart from the
+    /// chunks.
+    ///
+    /// The input directory is the directory of the manifest, and the chunks
+    /// are merged following the manifest. The values set explicitly take
+    /// precedence. With the `checksum` feature, the checksum of the original
+    /// file recorded by [`Split::file_hash`] is also expected unless set.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::merge::Merge;
+    ///
+    /// async fn example() {
+    ///     Merge::new()
+    ///         .from_manifest("/path/to/dir/manifest.json")
+    ///         .out_file("/path/to/file")
+    ///         .run()
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`Split::manifest`]: crate::split::Split::manifest
+    /// [`Split::file_hash`]: crate::split::Split::file_hash
+    pub fn from_manifest<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Self {
+        self.manifest_path = Some(path.as_ref().to_path_buf());
+        self
+    }
 
-        // check file size for buffer capacity
-        let input_size: usize = if let Some(file) = fs::read_dir(in_dir)?
-            .filter_map(Result::ok)
-            .filter(|entry| entry.path().is_file())
-            .map(|entry| entry.path())
-            .next()
-        {
-            fsa::metadata(file).await?.len() as usize
-        } else {
-            return Err(ioa::Error::new(
-                ioa::ErrorKind::NotFound,
-                "No files found in in_dir",
-            ));
-        };
+    /// Set the maximum number of chunks fetched ahead from the store.
+    ///
+    /// By default, the number follows the [`PREFETCH_DEFAULT`].
+    pub fn prefetch(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.prefetch = chunks.max(1);
+        self
+    }
 
-        let buffer_capacity: usize = input_size.min(self.cap_max);
+    /// Set whether to adjust the number of chunks fetched ahead based on the
+    /// observed latency of the store.
+    ///
+    /// By default, the adaptive prefetch is disabled. When enabled, the
+    /// number starts from 1, increases by 1 while the latency is stable, and
+    /// halves when the latency spikes, without exceeding the `prefetch`.
+    pub fn adaptive_prefetch(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.adaptive_prefetch = enabled;
+        self
+    }
 
-        // delete outpath target if exists
-        if out_file.exists() {
-            if out_file.is_dir() {
-                fsa::remove_dir_all(&out_file).await?;
-            } else {
-                fsa::remove_file(&out_file).await?;
-            }
-        }
+    /// Set the local directory to cache the chunks fetched from the store.
+    ///
+    /// Chunks found in the cache directory are not fetched from the store
+    /// again, which makes the merge process cheap to retry.
+    pub fn cache_dir<CacheDir: AsRef<Path>>(
+        mut self,
+        path: CacheDir,
+    ) -> Self {
+        self.cache_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
 
-        // create outpath
-        if let Some(parent) = out_file.parent() {
-            fsa::create_dir_all(parent).await?;
-        }
+    /// Set the runtime to spawn the tasks fetching chunks from the store.
+    ///
+    /// By default, the tasks are spawned on the current runtime. Setting the
+    /// handle of an existing runtime lets the embedding application control
+    /// the threads used by the merge process.
+    pub fn runtime(
+        mut self,
+        handle: Handle,
+    ) -> Self {
+        self.runtime = Some(handle);
+        self
+    }
 
-        let output: fsa::File = fsa::OpenOptions::new()
-            .create(true)
-            .truncate(false)
-            .write(true)
-            .open(out_file)
-            .await?;
+    /// Set whether to cap the buffer capacity by the memory available to the
+    /// process, e.g. the limit of the container.
+    ///
+    /// By default, it is `false`. When enabled, the buffer capacity does not
+    /// exceed the [`memory_budget`](crate::tune::memory_budget). When
+    /// merging from a store, the number of chunks fetched ahead is also
+    /// capped by the budget.
+    pub fn auto_tune(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.auto_tune = enabled;
+        self
+    }
 
-        // writer
-        let mut writer: ioa::BufWriter<fsa::File> =
-            ioa::BufWriter::with_capacity(buffer_capacity, output);
-
-        // get inputs
-        let mut entries: Vec<PathBuf> = fs::read_dir(in_dir)?
-            .filter_map(Result::ok)
-            .filter(|entry| entry.path().is_file())
-            .map(|entry| entry.path())
-            .collect();
-
-        entries.sort_by_key(|entry| {
-            entry
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .parse::<usize>()
-                .unwrap()
-        });
+    /// Set the budget of the memory shared with other processes.
+    ///
+    /// By default, the memory is not limited. When set, the buffers of the
+    /// streamed chunks and the chunks fetched ahead are capped to fit the
+    /// budget and reserved from it before running, while the process fails
+    /// with [`ioa::ErrorKind::InvalidInput`] if the chunks held in full
+    /// exceed the whole budget.
+    pub fn memory_budget(
+        mut self,
+        budget: MemoryBudget,
+    ) -> Self {
+        self.budget = Some(budget);
+        self
+    }
 
-        // merge
-        for entry in entries {
-            let input: fsa::File =
-                fsa::OpenOptions::new().read(true).open(&entry).await?;
+    /// Set the control shared with the owner of the process.
+    pub(crate) fn control(
+        mut self,
+        control: Arc<Control>,
+    ) -> Self {
+        self.control = Some(control);
+        self
+    }
 
-            let mut reader: ioa::BufReader<fsa::File> =
-                ioa::BufReader::with_capacity(buffer_capacity, input);
+    /// Set the handle to pause, resume or cancel the process, and to get
+    /// its progress in bytes.
+    pub fn handle(
+        self,
+        handle: OperationHandle,
+    ) -> Self {
+        self.control(handle.control)
+    }
 
-            let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+    /// Set whether to pinpoint the corruption when the output file does not
+    /// match the [expected size](Self::expect_file_size) or checksum.
+    ///
+    /// By default, it is `false`. When enabled and the output file
+    /// mismatches, it is compared with the chunks by the [`Diff`] process
+    /// before it is removed, so the merge process fails with
+    /// [`ioa::ErrorKind::InvalidData`] reporting the first corrupted chunk
+    /// and offset. With the checksums of the chunks in the manifest, only
+    /// the mismatching chunk is read. It is ignored when merging from a
+    /// store.
+    pub fn verify(
+        mut self,
+        verify: bool,
+    ) -> Self {
+        self.verify = verify;
+        self
+    }
 
-            loop {
-                let read: usize = reader.read(&mut buffer).await?;
+    /// Skip merging when the output file already exists and matches the
+    /// expected size or hash, e.g. when re-running a pipeline.
+    ///
+    /// By default, the output file is always rewritten.
+    pub fn skip_if_matching<E: Into<Expected>>(
+        mut self,
+        expected: E,
+    ) -> Self {
+        self.skip_if_matching = Some(expected.into());
+        self
+    }
 
-                if read == 0 {
-                    break;
-                }
+    /// Set the policy when the output file already exists, e.g. to keep the
+    /// files of the user in a synced directory.
+    ///
+    /// By default, it is [`Collision::Overwrite`]. The policy applies once
+    /// the merge is not [skipped](Self::skip_if_matching), and is ignored if
+    /// [`resumed`](Self::resume), as the existing file is then taken as the
+    /// output left by a previous run. A block device is always written in
+    /// place, without being deleted or truncated.
+    pub fn on_collision(
+        mut self,
+        collision: Collision,
+    ) -> Self {
+        self.collision = collision;
+        self
+    }
 
-                writer.write_all(&buffer[..read]).await?;
-            }
-        }
+    /// Set the Unicode normalization form of the name of the output file,
+    /// e.g. of the original name recorded on another platform.
+    ///
+    /// By default, the name is kept as is. When set, the name is normalized,
+    /// and an existing file next to it whose name differs only in the
+    /// normalization is taken as the output file, so no duplicate looking
+    /// file is created.
+    #[cfg(feature = "unicode")]
+    pub fn normalization(
+        mut self,
+        normalization: Normalization,
+    ) -> Self {
+        self.normalization = Some(normalization);
+        self
+    }
+
+    /// Set whether to continue the output file left by a previous run, e.g.
+    /// after a crash, instead of deleting it and starting over.
+    ///
+    /// By default, it is `false`. When enabled, the chunks whose bytes are
+    /// in the output file in full are skipped, and the bytes of a partially
+    /// merged chunk are truncated and merged again. The sizes of the chunks
+    /// are read from the manifest, or from the chunks themselves without
+    /// it, so transformed chunks are not resumed without a manifest. The
+    /// output file is read back if its checksum is expected. It is ignored
+    /// when merging from a store.
+    pub fn resume(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.resume = enabled;
+        self
+    }
 
-        writer.flush().await?;
+    /// Set whether to check the space available for the output file before
+    /// writing.
+    ///
+    /// By default, it is `false`. When enabled, the merge process fails with
+    /// [`ioa::ErrorKind::StorageFull`] without writing if the file system of
+    /// the output file has less space available than the size of the file,
+    /// instead of failing halfway. The size is taken from the manifest or
+    /// the [expected size](Self::expect_file_size), otherwise from the sizes
+    /// of the chunks, and the existing output file is counted as available
+    /// as it is replaced. The check passes where the space is unknown, e.g.
+    /// on systems other than Linux.
+    pub fn require_free_space(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.require_free_space = enabled;
+        self
+    }
 
-        Ok(true)
+    /// Set the expected size of the output file in bytes.
+    ///
+    /// By default, the size is not verified. When set, the size is verified
+    /// while writing, and the merge process fails with
+    /// [`ioa::ErrorKind::InvalidData`] and removes the partial output file
+    /// if it does not match.
+    pub fn expect_file_size(
+        mut self,
+        file_size: usize,
+    ) -> Self {
+        self.expect_file_size = Some(file_size);
+        self
     }
-}
 
-impl Default for Merge {
-    fn default() -> Self {
-        Self::new()
+    /// Set the expected checksum of the output file in hex.
+    ///
+    /// By default, the checksum is not verified. When set, the checksum is
+    /// computed while writing without reading the output file again, and
+    /// the merge process fails with [`ioa::ErrorKind::InvalidData`] and
+    /// removes the output file if it does not match.
+    #[cfg(feature = "checksum")]
+    pub fn expect_hash<Digest: Into<String>>(
+        mut self,
+        algorithm: Algorithm,
+        digest: Digest,
+    ) -> Self {
+        self.expect_hash = Some((algorithm, digest.into()));
+        self
+    }
+
+    /// Set the Merkle root to verify the chunks against while merging, e.g.
+    /// the one recorded when splitting with [`Split::merkle`].
+    ///
+    /// By default, the chunks are not verified. When set, the SHA-256
+    /// checksums in the manifest must produce the root, and each chunk is
+    /// verified against its checksum as it is merged. A streamed chunk is
+    /// verified once written, so on a mismatch the merge process fails with
+    /// [`ioa::ErrorKind::InvalidData`] and removes the output file. When
+    /// merging from a store, the manifest is required to be set with
+    /// [`Merge::from_manifest`].
+    ///
+    /// [`Split::merkle`]: crate::split::Split::merkle
+    #[cfg(feature = "checksum")]
+    pub fn merkle_root<R: Into<String>>(
+        mut self,
+        root: R,
+    ) -> Self {
+        self.merkle_root = Some(root.into());
+        self
+    }
+
+    /// Set whether to verify each chunk as it streams against its bao
+    /// outboard, e.g. written when splitting with [`Split::bao`].
+    ///
+    /// By default, it is `false`. When enabled, the BLAKE3 checksums in the
+    /// manifest are the trusted roots, and the merge process fails with
+    /// [`ioa::ErrorKind::InvalidData`] at the first corrupt group of
+    /// [`blake3::CHUNK_LEN`] bytes and removes the output file, instead of
+    /// after writing the whole chunk. The outboards are not fetched from a
+    /// store, so merging from a store or with [`Merge::run_to`] fails with
+    /// [`ioa::ErrorKind::InvalidInput`] when enabled.
+    ///
+    /// [`Split::bao`]: crate::split::Split::bao
+    #[cfg(feature = "bao")]
+    pub fn bao(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.bao = enabled;
+        self
+    }
+
+    /// Set the attributes of the output file on copy-on-write filesystems.
+    ///
+    /// By default, the attributes are inherited from the directory.
+    pub fn attributes(
+        mut self,
+        attributes: FileAttributes,
+    ) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Set whether to restore the metadata of the original file recorded in
+    /// the manifest to the output file, e.g. when splitting with
+    /// [`Split::preserve_metadata`].
+    ///
+    /// By default, it is `false`. When enabled, the merge process fails with
+    /// [`ioa::ErrorKind::InvalidInput`] before merging if the manifest does
+    /// not record the metadata, so it is required to be set with
+    /// [`Merge::from_manifest`] when merging from a store. The metadata is
+    /// never restored to a block device.
+    ///
+    /// [`Split::preserve_metadata`]: crate::split::Split::preserve_metadata
+    pub fn restore_metadata(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.restore_metadata = enabled;
+        self
+    }
+
+    /// Set whether to write the output file bypassing the page cache with
+    /// `O_DIRECT`, e.g. to restore a disk image to a block device without
+    /// evicting the cache of the system.
+    ///
+    /// By default, it is `false`. When enabled, the output is written in
+    /// blocks aligned to the [`DIRECT_IO_ALIGNMENT`] from a buffer of the
+    /// `max_buffer_capacity`, and the unaligned tail through the page
+    /// cache. It is only supported on Linux.
+    ///
+    /// [`DIRECT_IO_ALIGNMENT`]: crate::config::DIRECT_IO_ALIGNMENT
+    pub fn direct_io(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.direct_io = enabled;
+        self
+    }
+
+    /// Set whether to collect the time spent in each phase of the process.
+    ///
+    /// By default, it is `false`. When enabled, the
+    /// [`Profile`](crate::profile::Profile) is available from
+    /// [`OperationHandle::profile`] after running, and emitted via `tracing`
+    /// with the `tracing` feature.
+    pub fn profile(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.profile = enabled;
+        self
+    }
+
+    /// Set the bus to emit the lifecycle events to.
+    pub fn events(
+        mut self,
+        events: EventBus,
+    ) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Set the sink to pass the progress to, which receives every event
+    /// in order, unlike the subscribers of the [`events`](Self::events).
+    pub fn progress(
+        mut self,
+        sink: Arc<dyn ProgressSink>,
+    ) -> Self {
+        self.progress = Some(sink);
+        self
+    }
+
+    /// Set the callback to report the [`MergeProgress`] to, e.g. to drive
+    /// a progress bar.
+    ///
+    /// The callback is called on the task running the process when it
+    /// starts and finishes, and in between at most once per `interval`,
+    /// which follows the [`PROGRESS_INTERVAL_DEFAULT`] by default. It
+    /// should return quickly.
+    pub fn on_progress<F: Fn(MergeProgress) + Send + Sync + 'static>(
+        mut self,
+        f: F,
+    ) -> Self {
+        self.on_progress = Some(OnProgress::new(f));
+        self
+    }
+
+    /// Set the minimum interval between the calls of the callback set by
+    /// [`on_progress`](Self::on_progress).
+    pub fn progress_interval(
+        mut self,
+        interval: Duration,
+    ) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Set the monitor of the writes, detecting the writes stalled by a slow
+    /// output and aborting the process following its policy, e.g. when
+    /// merging to a socket with [`Merge::run_to`].
+    pub fn stall_monitor(
+        mut self,
+        monitor: Arc<StallMonitor>,
+    ) -> Self {
+        self.stall = Some(monitor);
+        self
+    }
+
+    /// Set the log to record the inputs and results of the process to.
+    ///
+    /// By default, the process is not recorded. The process fails if the
+    /// record cannot be written.
+    pub fn log(
+        mut self,
+        log: OperationLog,
+    ) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    fn emit(
+        &self,
+        event: Event,
+    ) {
+        if let Some(progress) = &self.progress {
+            progress.on_event(&event);
+        }
+
+        if let Some(events) = &self.events {
+            events.emit(event);
+        }
+    }
+
+    /// Finish the profile and record it in the control if any.
+    fn finish(
+        &self,
+        profiler: Profiler,
+    ) {
+        let profile: Option<Profile> = profiler.finish("merge");
+
+        if let Some(control) = &self.control {
+            control.set_profile(profile);
+        }
+    }
+
+    /// Run the merge process.
+    ///
+    /// Without a manifest, the input directory and its volume directories
+    /// are listed and sorted once, which takes `O(n log n)` time and `O(n)`
+    /// memory for `n` chunks. With a manifest in the input directory or its
+    /// first volume, the chunks are opened in order without listing the
+    /// directory or checking their metadata.
+    ///
+    /// The memory does not depend on the size of the file, as described in
+    /// the [crate documentation](crate#memory).
+    pub async fn run(mut self) -> ioa::Result<MergeResult> {
+        let started: Instant = Instant::now();
+
+        let written: Option<usize> = self.merge().await?;
+
+        let elapsed: Duration = started.elapsed();
+
+        // the output file is set once merged
+        let out_file: PathBuf = self.out_file.clone().unwrap_or_default();
+
+        // the metadata of a block device has no size
+        let file_size: usize = match is_block_device(&out_file) {
+            | true => device_size(&out_file).await?,
+            | false => fsa::metadata(&out_file).await?.len() as usize,
+        };
+
+        if let Some(log) = &self.log {
+            #[cfg_attr(not(feature = "checksum"), allow(unused_mut))]
+            let mut file_hashes: BTreeMap<String, String> = BTreeMap::new();
+
+            #[cfg(feature = "checksum")]
+            if let Some((algorithm, digest)) = &self.expect_hash {
+                file_hashes.insert(algorithm.id().to_string(), digest.clone());
+            }
+
+            log.append(Operation::Merge {
+                in_dir: self.in_dir.clone().filter(|_| self.store.is_none()),
+                out_file: out_file.clone(),
+                file_size,
+                file_hashes,
+            })
+            .await?;
+        }
+
+        Ok(MergeResult {
+            out_file,
+            file_size,
+            bytes_written: written.unwrap_or(0),
+            skipped: written.is_none(),
+            elapsed,
+        })
+    }
+
+    /// Run the merge process with the stream of its events, e.g. to
+    /// forward the progress to a frontend without a callback.
+    ///
+    /// The events are passed to the sink set by
+    /// [`progress`](Self::progress) as well, and the stream ends once the
+    /// process finishes.
+    pub fn run_with_events(
+        mut self
+    ) -> (impl Future<Output = ioa::Result<MergeResult>>, EventStream) {
+        let (sink, events): (Arc<dyn ProgressSink>, EventStream) =
+            EventStream::channel();
+
+        self.progress = Some(Tee::join(self.progress.take(), sink));
+
+        (self.run(), events)
+    }
+
+    /// Run the merge process with the handle to pause, resume or cancel it,
+    /// e.g. to hold the work while the application is in the background.
+    ///
+    /// The handle set by [`handle`](Self::handle) is returned if any,
+    /// otherwise a new one. The process parks after the current chunk while
+    /// paused, and fails with [`ioa::ErrorKind::Interrupted`] once
+    /// cancelled.
+    pub fn run_with_handle(
+        mut self
+    ) -> (impl Future<Output = ioa::Result<MergeResult>>, OperationHandle) {
+        let control: Arc<Control> =
+            self.control.get_or_insert_with(Default::default).clone();
+
+        (self.run(), OperationHandle { control })
+    }
+
+    /// Read the manifest given by [`Merge::from_manifest`], if any, with the
+    /// values not set read from it.
+    async fn given(&mut self) -> ioa::Result<Option<Manifest>> {
+        match self.manifest_path.take() {
+            | Some(path) => {
+                let manifest: Manifest = Manifest::read(&path).await?;
+
+                if self.store.is_none() && self.in_dir.is_none() {
+                    self.in_dir = Some(manifest_dir(&path, &manifest));
+                }
+
+                self.total_chunks.get_or_insert(manifest.total_chunks);
+                self.expect_file_size.get_or_insert(manifest.file_size);
+
+                #[cfg(feature = "checksum")]
+                if self.expect_hash.is_none() {
+                    self.expect_hash = manifest
+                        .file_hash()
+                        .map(|(algorithm, digest)| (algorithm, digest.into()));
+                }
+
+                Ok(Some(manifest))
+            },
+            | None => Ok(None),
+        }
+    }
+
+    /// Merge the chunks, returning the number of bytes written, or `None`
+    /// if skipped.
+    async fn merge(&mut self) -> ioa::Result<Option<usize>> {
+        self.naming.validate()?;
+
+        let given: Option<Manifest> = self.given().await?;
+
+        if let Some(store) = self.store.clone() {
+            if self.out_file.is_none() {
+                if let Some(out_dir) = &self.out_dir {
+                    let name: Option<&str> =
+                        given.as_ref().and_then(|m| m.file_name.as_deref());
+
+                    self.out_file = Some(named_output(out_dir, name)?);
+                }
+            }
+
+            return self.run_store(store, given).await;
+        }
+
+        let in_dir: &Path = match self.in_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if in_dir not exists
+                if !p.exists() {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::NotFound,
+                        "in_dir path not found",
+                    ));
+                }
+
+                // if in_dir not a directory
+                if !p.is_dir() {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        "in_dir is not a directory",
+                    ));
+                }
+
+                p
+            },
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "in_dir is not set",
+                ))
+            },
+        };
+
+        // read manifest if exists
+        let manifest: Option<Manifest> = match given {
+            | Some(manifest) => Some(manifest),
+            | None => find_manifest(in_dir).await?,
+        };
+
+        let metadata: Option<&FileMetadata> =
+            manifest.as_ref().and_then(|m| m.file_metadata.as_ref());
+
+        self.expect_metadata(metadata)?;
+
+        // check transform recorded in manifest
+        if let Some(manifest) = &manifest {
+            let transform: Option<&str> =
+                self.transform.as_ref().map(|t| t.id());
+
+            if manifest.transform.as_deref() != transform {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "transform does not match the manifest",
+                ));
+            }
+        }
+
+        let naming: ChunkNaming = match &manifest {
+            | Some(manifest) => manifest.naming.clone(),
+            | None => self.naming.clone(),
+        };
+
+        let mut original_name: Option<String> =
+            naming.original_name().map(str::to_string);
+
+        // get inputs, the order is known without listing with manifest
+        let entries: Option<Vec<PathBuf>> = match manifest {
+            | Some(_) => None,
+            | None => {
+                let mut entries: Vec<PathBuf> =
+                    list_chunks(in_dir, &naming).await?;
+
+                // chunk sets of the registered layouts
+                if entries.is_empty() && naming.is_index() {
+                    if let Some(detected) = self.layouts.detect(in_dir).await? {
+                        original_name = detected.original_name;
+                        entries = detected.chunks.into_values().collect();
+                    }
+                }
+
+                Some(entries)
+            },
+        };
+
+        // the original name is recorded in the manifest, or kept by the
+        // naming of multi-part archives
+        let out_file: PathBuf = match (&self.out_file, &self.out_dir) {
+            | (Some(p), _) => p.clone(),
+            | (None, Some(out_dir)) => {
+                let name: Option<&str> = manifest
+                    .as_ref()
+                    .and_then(|m| m.file_name.as_deref())
+                    .or(original_name.as_deref());
+
+                named_output(out_dir, name)?
+            },
+            | (None, None) => match original_name {
+                | Some(name) => in_dir.join(name),
+                | None => {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        "out_file is not set",
+                    ))
+                },
+            },
+        };
+
+        let out_file: PathBuf = self.normalized(out_file).await?;
+
+        if self.skipped(&out_file).await? {
+            // the output file is recorded with its original name if derived
+            self.out_file = Some(out_file);
+
+            return Ok(None);
+        }
+
+        let resolved: Resolved = match self.resume {
+            | true => Resolved::Merge { path: out_file, claimed: false },
+            | false => {
+                let identity: Identity = self.identity(manifest.as_ref());
+
+                self.collision.resolve(&out_file, &identity).await?
+            },
+        };
+
+        let (out_file, claimed): (PathBuf, bool) = match resolved {
+            | Resolved::Merge { path, claimed } => (path, claimed),
+            | Resolved::Same(path) => return Ok(self.same(path)),
+        };
+
+        // the output file is recorded with its original name if derived, or
+        // under another name if both are kept
+        self.out_file = Some(out_file.clone());
+
+        // checksums of the chunks proven by the Merkle root
+        #[cfg(feature = "checksum")]
+        let leaves: Option<Vec<Hash>> = match &self.merkle_root {
+            | Some(root) => Some(merkle_leaves(manifest.as_ref(), root)?),
+            | None => None,
+        };
+
+        // BLAKE3 checksums of the chunks as the roots of the outboards
+        #[cfg(feature = "bao")]
+        let roots: Option<Vec<Hash>> = match self.bao {
+            | true => Some(bao_roots(manifest.as_ref())?),
+            | false => None,
+        };
+
+        let total_chunks: usize = match (&manifest, &entries) {
+            | (Some(manifest), _) => manifest.total_chunks,
+            | (None, Some(entries)) => entries.len(),
+            | (None, None) => 0,
+        };
+
+        if total_chunks == 0 {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                "No files found in in_dir",
+            ));
+        }
+
+        self.emit(Event::MergeStarted { total_chunks });
+
+        // chunks may come with headers identifying the original file
+        let split_id: Option<Uuid> = match (&manifest, &entries) {
+            | (Some(manifest), _) => manifest.id.filter(|_| manifest.headers),
+            | (None, Some(entries)) => {
+                ChunkHeader::read(&entries[0]).await?.map(|h| h.split_id)
+            },
+            | (None, None) => None,
+        };
+
+        // check file size for buffer capacity
+        let input_size: usize = match (&manifest, &entries) {
+            | (Some(manifest), _) => manifest.chunk_size,
+            | (None, Some(entries)) => {
+                fsa::metadata(&entries[0]).await?.len() as usize
+            },
+            | (None, None) => 0,
+        };
+
+        let buffer_capacity: usize = self.fit(
+            self.tuned(input_size.min(self.cap_max)).max(1),
+            self.streamed_buffers(),
+        );
+
+        // data passed to the verifiers at a time
+        let unit: usize = match self.transform {
+            | Some(_) => input_size,
+            | None => buffer_capacity,
+        };
+
+        // buffers of the writer and the reader, or the decoded chunk, and
+        // the data being verified
+        let _reservation: Option<OwnedSemaphorePermit> = self
+            .reserve(
+                buffer_capacity * 2
+                    + input_size * 2 * self.transform.is_some() as usize
+                    + self.verify_memory(unit, input_size),
+            )
+            .await?;
+
+        let mut profiler: Profiler = Profiler::new(self.profile);
+
+        let chunks: DirStore = match &manifest {
+            | Some(manifest) => DirStore::with_manifest(in_dir, manifest),
+            | None => DirStore::new(in_dir),
+        };
+
+        // chunks merged in full by a previous run and their size
+        let (first, merged): (usize, usize) = match self.resume {
+            | true => {
+                let path = |index: usize| match &entries {
+                    | Some(entries) => entries[index].clone(),
+                    | None => chunks.path(index),
+                };
+
+                self.resume_point(
+                    &out_file,
+                    manifest.as_ref(),
+                    total_chunks,
+                    split_id.is_some(),
+                    path,
+                )
+                .await?
+            },
+            | false => (0, 0),
+        };
+
+        if self.require_free_space {
+            let known: Option<usize> = manifest
+                .as_ref()
+                .map(|manifest| manifest.file_size)
+                .or(self.expect_file_size);
+
+            let file_size: usize = match (known, &entries) {
+                | (Some(file_size), _) => file_size,
+                | (None, Some(entries)) => {
+                    let mut size: usize = 0;
+
+                    for entry in entries {
+                        size += fsa::metadata(entry).await?.len() as usize;
+                    }
+
+                    size
+                },
+                | (None, None) => 0,
+            };
+
+            self.preflight(&out_file, file_size).await?;
+        }
+
+        let output: fsa::File = match merged {
+            | 0 => create_output(&out_file, claimed, self.direct_io).await?,
+            | _ => resume_output(&out_file, merged, self.direct_io).await?,
+        };
+
+        self.attributes.apply(&output)?;
+
+        // writer
+        let mut writer: Box<dyn AsyncWrite + Send + Unpin> =
+            self.writer(output, buffer_capacity).await?;
+
+        let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+
+        let mut verifier: Verifier = Verifier::new(self);
+
+        let mut reporter: Reporter<MergeProgress> = Reporter::new(
+            self.on_progress.as_ref(),
+            self.progress_interval,
+            MergeProgress {
+                bytes_written: merged,
+                total_bytes: manifest
+                    .as_ref()
+                    .map(|manifest| manifest.file_size)
+                    .or(self.expect_file_size),
+                chunks_merged: first,
+                total_chunks,
+            },
+        );
+
+        reporter.report(true);
+
+        if merged > 0 {
+            discard(
+                &out_file,
+                verifier
+                    .resume(&out_file, merged, &mut buffer, &mut profiler)
+                    .await,
+            )
+            .await?;
+        }
+
+        // merge
+        for index in first..total_chunks {
+            let entry: PathBuf = match &entries {
+                | Some(entries) => entries[index].clone(),
+                | None => chunks.path(index),
+            };
+
+            if let Some(transform) = &self.transform {
+                let started: Option<Instant> = profiler.start();
+
+                let mut chunk: Vec<u8> = fsa::read(&entry).await?;
+
+                profiler.record(Phase::Read, started);
+
+                if let Some(split_id) = split_id {
+                    let size: usize = chunk.len();
+
+                    chunk = strip_header(chunk, size, index, split_id)?;
+                }
+
+                #[cfg(feature = "bao")]
+                if let Some(roots) = &roots {
+                    let started: Option<Instant> = profiler.start();
+
+                    let result: ioa::Result<()> =
+                        match bao_verifier(roots, index, &entry).await {
+                            | Ok(mut bao) => {
+                                bao.update(&chunk).and_then(|_| bao.finish())
+                            },
+                            | Err(e) => Err(e),
+                        };
+
+                    profiler.record(Phase::Hash, started);
+
+                    discard(&out_file, result).await?;
+                }
+
+                #[cfg(feature = "checksum")]
+                if let Some(leaves) = &leaves {
+                    let started: Option<Instant> = profiler.start();
+
+                    let checksum: Hash = Sha256::digest(&chunk).into();
+
+                    profiler.record(Phase::Hash, started);
+
+                    discard(&out_file, verify_leaf(leaves, index, checksum))
+                        .await?;
+                }
+
+                let started: Option<Instant> = profiler.start();
+
+                let chunk: Vec<u8> = transform.decode(index, chunk)?;
+
+                profiler.record(Phase::Transform, started);
+
+                discard(
+                    &out_file,
+                    verifier.update(&chunk, &mut profiler).await,
+                )
+                .await?;
+
+                let started: Option<Instant> = profiler.start();
+
+                self.write(&mut writer, &chunk).await?;
+
+                profiler.record(Phase::Write, started);
+
+                if let Some(control) = &self.control {
+                    control.advance(chunk.len()).await?;
+                }
+
+                self.emit(Event::ChunkMerged { index, size: chunk.len() });
+
+                reporter.progress.bytes_written += chunk.len();
+                reporter.progress.chunks_merged = index + 1;
+                reporter.report(false);
+
+                continue;
+            }
+
+            let mut input: fsa::File =
+                fsa::OpenOptions::new().read(true).open(&entry).await?;
+
+            if let Some(split_id) = split_id {
+                let mut header: [u8; CHUNK_HEADER_SIZE] =
+                    [0; CHUNK_HEADER_SIZE];
+
+                input.read_exact(&mut header).await?;
+
+                match ChunkHeader::decode(&header) {
+                    | Some(header) => header.expect(index, split_id)?,
+                    | None => return Err(header_not_found(index)),
+                };
+            }
+
+            let mut size: usize = 0;
+
+            #[cfg(feature = "checksum")]
+            let mut hasher: Option<Sha256> =
+                leaves.as_ref().map(|_| Sha256::new());
+
+            #[cfg(feature = "bao")]
+            let mut bao: Option<BaoVerifier> = match &roots {
+                | Some(roots) => Some(
+                    discard(
+                        &out_file,
+                        bao_verifier(roots, index, &entry).await,
+                    )
+                    .await?,
+                ),
+                | None => None,
+            };
+
+            loop {
+                let started: Option<Instant> = profiler.start();
+
+                let read: usize = input.read(&mut buffer).await?;
+
+                profiler.record(Phase::Read, started);
+
+                if read == 0 {
+                    break;
+                }
+
+                #[cfg(feature = "checksum")]
+                if let Some(hasher) = &mut hasher {
+                    let started: Option<Instant> = profiler.start();
+
+                    hasher.update(&buffer[..read]);
+
+                    profiler.record(Phase::Hash, started);
+                }
+
+                // verified before written, except the incomplete group
+                #[cfg(feature = "bao")]
+                if let Some(bao) = &mut bao {
+                    let started: Option<Instant> = profiler.start();
+
+                    let result: ioa::Result<()> = bao.update(&buffer[..read]);
+
+                    profiler.record(Phase::Hash, started);
+
+                    discard(&out_file, result).await?;
+                }
+
+                discard(
+                    &out_file,
+                    verifier.update(&buffer[..read], &mut profiler).await,
+                )
+                .await?;
+
+                let started: Option<Instant> = profiler.start();
+
+                self.write(&mut writer, &buffer[..read]).await?;
+
+                profiler.record(Phase::Write, started);
+
+                size += read;
+
+                if let Some(control) = &self.control {
+                    control.advance(read).await?;
+                }
+
+                reporter.progress.bytes_written += read;
+                reporter.report(false);
+            }
+
+            #[cfg(feature = "bao")]
+            if let Some(bao) = bao {
+                discard(&out_file, bao.finish()).await?;
+            }
+
+            // the chunk is streamed, so it is verified after written
+            #[cfg(feature = "checksum")]
+            if let (Some(leaves), Some(hasher)) = (&leaves, hasher) {
+                let checksum: Hash = hasher.finalize().into();
+
+                discard(&out_file, verify_leaf(leaves, index, checksum))
+                    .await?;
+            }
+
+            self.emit(Event::ChunkMerged { index, size });
+
+            reporter.progress.chunks_merged = index + 1;
+            reporter.report(false);
+        }
+
+        let started: Option<Instant> = profiler.start();
+
+        self.flush(&mut writer).await?;
+
+        profiler.record(Phase::Write, started);
+
+        let mut result: ioa::Result<()> = verifier.finish(&mut profiler).await;
+
+        // pinpoint the mismatch before the output file is discarded
+        if let (true, Err(e)) = (self.verify, &result) {
+            let diff: ioa::Result<Option<Difference>> = Diff::new()
+                .in_dir(in_dir)
+                .file(&out_file)
+                .transform_arc(self.transform.clone())
+                .naming(naming.clone())
+                .layouts(self.layouts.clone())
+                .run()
+                .await;
+
+            if let Some(difference) = discard(&out_file, diff).await? {
+                result = Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidData,
+                    format!("{}: {}", e, difference),
+                ));
+            }
+        }
+
+        discard(&out_file, result).await?;
+
+        self.restore(&out_file, metadata).await?;
+
+        self.finish(profiler);
+
+        reporter.report(true);
+
+        self.emit(Event::MergeFinished { out_file });
+
+        Ok(Some(reporter.progress.bytes_written - merged))
+    }
+
+    /// Get the number of chunks in the output file left by a previous run
+    /// in full and their size.
+    async fn resume_point<F: Fn(usize) -> PathBuf>(
+        &self,
+        out_file: &Path,
+        manifest: Option<&Manifest>,
+        total_chunks: usize,
+        headers: bool,
+        path: F,
+    ) -> ioa::Result<(usize, usize)> {
+        let existing: usize = match fsa::metadata(out_file).await {
+            | Ok(metadata) if metadata.is_file() => metadata.len() as usize,
+            | Ok(_) => return Ok((0, 0)),
+            | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                return Ok((0, 0))
+            },
+            | Err(e) => return Err(e),
+        };
+
+        let mut merged: usize = 0;
+
+        for index in 0..total_chunks {
+            let size: usize = match manifest {
+                | Some(manifest) => match manifest.chunk(index) {
+                    | Some(chunk) => chunk.size,
+                    | None => return Ok((index, merged)),
+                },
+                // the size before transform is unknown
+                | None if self.transform.is_some() => return Ok((0, 0)),
+                | None => {
+                    let stored: usize =
+                        fsa::metadata(path(index)).await?.len() as usize;
+
+                    match headers {
+                        | true => stored.saturating_sub(CHUNK_HEADER_SIZE),
+                        | false => stored,
+                    }
+                },
+            };
+
+            if merged + size > existing {
+                return Ok((index, merged));
+            }
+
+            merged += size;
+        }
+
+        Ok((total_chunks, merged))
+    }
+
+    /// Ensure the metadata to restore is recorded in the manifest.
+    fn expect_metadata(
+        &self,
+        metadata: Option<&FileMetadata>,
+    ) -> ioa::Result<()> {
+        if self.restore_metadata && metadata.is_none() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "file metadata is not recorded in the manifest",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Restore the metadata of the original file to the output file.
+    async fn restore(
+        &self,
+        out_file: &Path,
+        metadata: Option<&FileMetadata>,
+    ) -> ioa::Result<()> {
+        match metadata {
+            | Some(metadata)
+                if self.restore_metadata && !is_block_device(out_file) =>
+            {
+                metadata.apply(out_file).await
+            },
+            | _ => Ok(()),
+        }
+    }
+
+    /// Reserve `bytes` from the memory budget if any, failing if they exceed
+    /// the whole budget, e.g. as the chunks are held in full.
+    async fn reserve(
+        &self,
+        bytes: usize,
+    ) -> ioa::Result<Option<OwnedSemaphorePermit>> {
+        match &self.budget {
+            | Some(budget) if bytes > budget.total() => Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "chunk size exceeds the memory budget",
+            )),
+            | Some(budget) => Ok(Some(budget.reserve(bytes).await?)),
+            | None => Ok(None),
+        }
+    }
+
+    /// Cap the buffer capacity so `buffers` of that capacity fit the memory
+    /// budget if set.
+    fn fit(
+        &self,
+        buffer_capacity: usize,
+        buffers: usize,
+    ) -> usize {
+        match &self.budget {
+            | Some(budget) => {
+                buffer_capacity.min(budget.total() / buffers).max(1)
+            },
+            | None => buffer_capacity,
+        }
+    }
+
+    /// Get the number of buffers held at a time when streaming the chunks,
+    /// which are the buffer of the writer, the data read, and the data being
+    /// hashed on a worker thread with the next one.
+    fn streamed_buffers(&self) -> usize {
+        #[cfg(feature = "checksum")]
+        {
+            2 + 2 * self.expect_hash.is_some() as usize
+        }
+
+        #[cfg(not(feature = "checksum"))]
+        2
+    }
+
+    /// Check whether the chunks in the store are streamed through the buffer
+    /// in pieces instead of being held in full, which requires the store to
+    /// fetch ranges and the chunks to be merged as they are.
+    fn streams_store(
+        &self,
+        store: &Arc<dyn ChunkStore>,
+    ) -> bool {
+        store.ranged() && self.transform.is_none() && self.cache_dir.is_none()
+    }
+
+    /// Get the size of the pieces fetched from the store, which is the
+    /// buffer capacity if any, and holds a whole header.
+    fn store_piece(
+        &self,
+        input_size: usize,
+        buffer_capacity: usize,
+    ) -> usize {
+        let piece: usize = match buffer_capacity {
+            | 0 => self.fit(
+                self.tuned(input_size.min(self.cap_max)),
+                self.streamed_buffers(),
+            ),
+            | capacity => capacity,
+        };
+
+        piece.max(CHUNK_HEADER_SIZE)
+    }
+
+    /// Get the memory of verifying the output while merging, where `unit` is
+    /// the size of the data verified at a time.
+    fn verify_memory(
+        &self,
+        #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+        unit: usize,
+        #[cfg_attr(not(feature = "bao"), allow(unused_variables))]
+        chunk_size: usize,
+    ) -> usize {
+        // the data being hashed on a worker thread and the next one
+        #[cfg(feature = "checksum")]
+        let memory: usize = unit * 2 * self.expect_hash.is_some() as usize;
+
+        #[cfg(not(feature = "checksum"))]
+        let memory: usize = 0;
+
+        #[cfg(feature = "bao")]
+        let memory: usize = match self.bao {
+            | true => memory + outboard_size(chunk_size),
+            | false => memory,
+        };
+
+        memory
+    }
+
+    /// Cap the buffer capacity by the memory budget if auto-tuned.
+    fn tuned(
+        &self,
+        buffer_capacity: usize,
+    ) -> usize {
+        match memory_budget().filter(|_| self.auto_tune) {
+            | Some(budget) => buffer_capacity.min(budget),
+            | None => buffer_capacity,
+        }
+    }
+
+    /// Check the space available for the output file of `file_size` bytes,
+    /// counting the existing output file as available.
+    async fn preflight(
+        &self,
+        out_file: &Path,
+        file_size: usize,
+    ) -> ioa::Result<()> {
+        // the file is written in place of the data on the device
+        if is_block_device(out_file) {
+            if device_size(out_file).await? < file_size {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::StorageFull,
+                    "file_size exceeds the size of the block device",
+                ));
+            }
+
+            return Ok(());
+        }
+
+        let existing: usize = match fsa::metadata(out_file).await {
+            | Ok(metadata) if metadata.is_file() => metadata.len() as usize,
+            | _ => 0,
+        };
+
+        require_space(out_file, file_size.saturating_sub(existing))
+    }
+
+    /// Get the output file with its name normalized if set.
+    async fn normalized(
+        &self,
+        out_file: PathBuf,
+    ) -> ioa::Result<PathBuf> {
+        #[cfg(feature = "unicode")]
+        if let Some(normalization) = &self.normalization {
+            return normalization.resolve(&out_file).await;
+        }
+
+        Ok(out_file)
+    }
+
+    /// Check whether the merge is skipped as the output file matches.
+    async fn skipped(
+        &self,
+        out_file: &Path,
+    ) -> ioa::Result<bool> {
+        let expected: &Expected = match &self.skip_if_matching {
+            | Some(expected) => expected,
+            | None => return Ok(false),
+        };
+
+        if !expected.matches(out_file).await? {
+            return Ok(false);
+        }
+
+        self.emit(Event::MergeFinished { out_file: out_file.to_path_buf() });
+
+        Ok(true)
+    }
+
+    /// Get the size and checksum of the output known before the merge.
+    fn identity(
+        &self,
+        manifest: Option<&Manifest>,
+    ) -> Identity {
+        Identity {
+            size: self
+                .expect_file_size
+                .or(manifest.map(|manifest| manifest.file_size)),
+            #[cfg(feature = "checksum")]
+            hash: self.expect_hash.clone().or_else(|| {
+                manifest
+                    .and_then(Manifest::file_hash)
+                    .map(|(algorithm, digest)| (algorithm, digest.into()))
+            }),
+        }
+    }
+
+    /// Skip the merge as the existing file at `out_file` already holds the
+    /// output kept by [`Collision::KeepBoth`].
+    fn same(
+        &mut self,
+        out_file: PathBuf,
+    ) -> Option<usize> {
+        self.emit(Event::MergeFinished { out_file: out_file.clone() });
+
+        self.out_file = Some(out_file);
+
+        None
+    }
+
+    /// Run the merge process from the store, with the manifest if given.
+    async fn run_store(
+        &mut self,
+        store: Arc<dyn ChunkStore>,
+        manifest: Option<Manifest>,
+    ) -> ioa::Result<Option<usize>> {
+        let out_file: &Path = match self.out_file {
+            | Some(ref p) => p.as_ref(),
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "out_file is not set",
+                ))
+            },
+        };
+
+        let metadata: Option<&FileMetadata> =
+            manifest.as_ref().and_then(|m| m.file_metadata.as_ref());
+
+        self.expect_metadata(metadata)?;
+
+        #[cfg(feature = "bao")]
+        self.expect_no_bao()?;
+
+        let out_file: PathBuf = self.normalized(out_file.to_path_buf()).await?;
+
+        if self.skipped(&out_file).await? {
+            self.out_file = Some(out_file);
+
+            return Ok(None);
+        }
+
+        let identity: Identity = self.identity(None);
+
+        let (out_file, claimed): (PathBuf, bool) =
+            match self.collision.resolve(&out_file, &identity).await? {
+                | Resolved::Merge { path, claimed } => (path, claimed),
+                | Resolved::Same(path) => return Ok(self.same(path)),
+            };
+
+        // the output file is recorded under another name if both are kept
+        self.out_file = Some(out_file.clone());
+
+        let out_file: &Path = &out_file;
+
+        let (total_chunks, input_size): (usize, usize) =
+            self.store_size(&store).await?;
+
+        let buffer_capacity: usize = self.fit(
+            self.tuned(input_size.min(self.cap_max)),
+            self.streamed_buffers(),
+        );
+
+        self.emit(Event::MergeStarted { total_chunks });
+
+        let mut profiler: Profiler = Profiler::new(self.profile);
+
+        // the size is estimated from the first chunk if not expected
+        if self.require_free_space {
+            let file_size: usize =
+                self.expect_file_size.unwrap_or(total_chunks * input_size);
+
+            self.preflight(out_file, file_size).await?;
+        }
+
+        let output: fsa::File =
+            create_output(out_file, claimed, self.direct_io).await?;
+
+        self.attributes.apply(&output)?;
+
+        let mut writer: Box<dyn AsyncWrite + Send + Unpin> =
+            self.writer(output, buffer_capacity).await?;
+
+        let written: usize = self
+            .write_store(
+                store,
+                manifest.as_ref(),
+                total_chunks,
+                input_size,
+                buffer_capacity,
+                &mut writer,
+                Some(out_file),
+                &mut profiler,
+            )
+            .await?;
+
+        self.restore(out_file, metadata).await?;
+
+        self.finish(profiler);
+
+        self.emit(Event::MergeFinished { out_file: out_file.to_path_buf() });
+
+        Ok(Some(written))
+    }
+
+    /// Run the merge process into the `writer`, e.g. a socket or a pipe,
+    /// instead of the output file.
+    ///
+    /// The chunks are read from the store if set, otherwise from the input
+    /// directory, with the total number of chunks from the manifest if not
+    /// set. The chunks are verified as set, but the output is not checked
+    /// as a file, so the skip if matching, the verification against the
+    /// chunks, the restore of the metadata and the log are ignored. The
+    /// [`Event::MergeFinished`] is emitted with an empty path.
+    ///
+    /// A slow writer stalls the process, which can be detected and aborted
+    /// with a [`StallMonitor`].
+    pub async fn run_to<W: AsyncWrite + Unpin>(
+        mut self,
+        mut writer: W,
+    ) -> ioa::Result<()> {
+        self.naming.validate()?;
+
+        #[cfg(feature = "bao")]
+        self.expect_no_bao()?;
+
+        let mut manifest: Option<Manifest> = self.given().await?;
+
+        let store: Arc<dyn ChunkStore> = match (&self.store, &self.in_dir) {
+            | (Some(store), _) => store.clone(),
+            | (None, Some(in_dir)) => {
+                if manifest.is_none() {
+                    manifest = find_manifest(in_dir).await?;
+                }
+
+                match &manifest {
+                    | Some(manifest) => {
+                        self.total_chunks.get_or_insert(manifest.total_chunks);
+
+                        Arc::new(DirStore::with_manifest(in_dir, manifest))
+                    },
+                    | None => Arc::new(
+                        DirStore::new(in_dir).naming(self.naming.clone()),
+                    ),
+                }
+            },
+            | (None, None) => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "in_dir is not set",
+                ))
+            },
+        };
+
+        let (total_chunks, input_size): (usize, usize) =
+            self.store_size(&store).await?;
+
+        self.emit(Event::MergeStarted { total_chunks });
+
+        let mut profiler: Profiler = Profiler::new(self.profile);
+
+        self.write_store(
+            store,
+            manifest.as_ref(),
+            total_chunks,
+            input_size,
+            0,
+            &mut writer,
+            None,
+            &mut profiler,
+        )
+        .await?;
+
+        self.finish(profiler);
+
+        self.emit(Event::MergeFinished { out_file: PathBuf::new() });
+
+        Ok(())
+    }
+
+    /// Fail if the chunks are to be verified against their bao outboards,
+    /// which are not fetched from a store.
+    #[cfg(feature = "bao")]
+    fn expect_no_bao(&self) -> ioa::Result<()> {
+        if self.bao {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "bao is not supported when merging from a store",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Get the total number of chunks and the size of the first chunk in
+    /// the store.
+    async fn store_size(
+        &self,
+        store: &Arc<dyn ChunkStore>,
+    ) -> ioa::Result<(usize, usize)> {
+        let total_chunks: usize = match self.total_chunks {
+            | Some(s) => s,
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "total_chunks is not set",
+                ))
+            },
+        };
+
+        // check chunk size for buffer capacity
+        let input_size: usize = match store.head(0).await? {
+            | Some(head) => head.size,
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::NotFound,
+                    "No chunks found in store",
+                ))
+            },
+        };
+
+        Ok((total_chunks, input_size))
+    }
+
+    /// Write the chunks in the store to the writer in order, verified with
+    /// the manifest if any, discarding the output file if any when the
+    /// verification fails, returning the number of bytes written.
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+    async fn write_store<W: AsyncWrite + Unpin>(
+        &self,
+        store: Arc<dyn ChunkStore>,
+        manifest: Option<&Manifest>,
+        total_chunks: usize,
+        input_size: usize,
+        buffer_capacity: usize,
+        writer: &mut W,
+        out_file: Option<&Path>,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<usize> {
+        // checksums of the chunks proven by the Merkle root
+        #[cfg(feature = "checksum")]
+        let leaves: Option<Vec<Hash>> = match &self.merkle_root {
+            | Some(root) => {
+                let result: ioa::Result<Vec<Hash>> =
+                    merkle_leaves(manifest, root);
+
+                Some(match out_file {
+                    | Some(out_file) => discard(out_file, result).await?,
+                    | None => result?,
+                })
+            },
+            | None => None,
+        };
+
+        // the checksum of the chunk fetched in pieces so far
+        #[cfg(feature = "checksum")]
+        let mut hasher: Option<Sha256> = None;
+
+        let cache: Option<Arc<DirStore>> =
+            self.cache_dir.as_ref().map(|dir| Arc::new(DirStore::new(dir)));
+
+        let streamed: bool = self.streams_store(&store);
+
+        // the chunks are fetched in pieces if streamed, otherwise in full
+        let piece: usize = match streamed {
+            | true => self.store_piece(input_size, buffer_capacity),
+            | false => input_size,
+        };
+
+        // the buffer of the writer and the data being verified
+        let fixed: usize =
+            buffer_capacity + self.verify_memory(piece, input_size);
+
+        // pieces fetched ahead are kept in memory
+        let mut prefetch: usize =
+            match memory_budget().filter(|_| self.auto_tune) {
+                | Some(budget) => self.prefetch.min(budget / piece.max(1)),
+                | None => self.prefetch,
+            };
+
+        if let Some(budget) = &self.budget {
+            prefetch = prefetch
+                .min(budget.total().saturating_sub(fixed) / piece.max(1));
+        }
+
+        let prefetch: usize = prefetch.max(1);
+
+        let _reservation: Option<OwnedSemaphorePermit> =
+            self.reserve(prefetch * piece + fixed).await?;
+
+        // pieces in flight are aborted on return, with the reservation
+        let mut pending: Pending = Pending(VecDeque::with_capacity(prefetch));
+
+        let mut window: PrefetchWindow =
+            PrefetchWindow::new(prefetch, self.adaptive_prefetch);
+
+        // the next piece to fetch, and the size of its chunk once known
+        let mut next: StorePiece =
+            StorePiece { index: 0, range: 0..0, size: None };
+
+        let mut split_id: Option<Uuid> = None;
+
+        let mut verifier: Verifier = Verifier::new(self);
+
+        // bytes of the current chunk merged so far
+        let mut merged: usize = 0;
+
+        let mut reporter: Reporter<MergeProgress> = Reporter::new(
+            self.on_progress.as_ref(),
+            self.progress_interval,
+            MergeProgress {
+                bytes_written: 0,
+                total_bytes: self.expect_file_size,
+                chunks_merged: 0,
+                total_chunks,
+            },
+        );
+
+        reporter.report(true);
+
+        loop {
+            // fetch pieces ahead
+            while next.index < total_chunks && pending.0.len() < window.size {
+                if streamed {
+                    let size: usize = match next.size {
+                        | Some(size) => size,
+                        | None => match store.head(next.index).await? {
+                            | Some(head) => head.size,
+                            | None => return Err(chunk_not_found(next.index)),
+                        },
+                    };
+
+                    let end: usize = (next.range.start + piece).min(size);
+
+                    next.range.end = end;
+                    next.size = Some(size);
+                }
+
+                let task = fetch(store.clone(), cache.clone(), next.clone());
+
+                let handle: Fetch = match &self.runtime {
+                    | Some(handle) => handle.spawn(task),
+                    | None => tokio::spawn(task),
+                };
+
+                pending.0.push_back((next.clone(), handle));
+
+                next = match next.size {
+                    | Some(size) if next.range.end < size => StorePiece {
+                        index: next.index,
+                        range: next.range.end..next.range.end,
+                        size: Some(size),
+                    },
+                    | _ => StorePiece {
+                        index: next.index + 1,
+                        range: 0..0,
+                        size: None,
+                    },
+                };
+            }
+
+            let started: Option<Instant> = profiler.start();
+
+            // the piece stays pending until fetched, so it is aborted too
+            let outcome: ioa::Result<(Vec<u8>, Duration)> =
+                match pending.0.front_mut() {
+                    | Some((_, handle)) => {
+                        handle.await.map_err(ioa::Error::other)?
+                    },
+                    | None => break,
+                };
+
+            let fetched: StorePiece = match pending.0.pop_front() {
+                | Some((piece, _)) => piece,
+                | None => break,
+            };
+
+            let (data, latency): (Vec<u8>, Duration) = outcome?;
+
+            profiler.record(Phase::Read, started);
+
+            window.observe(latency);
+
+            let index: usize = fetched.index;
+
+            // size of the whole chunk, fetched in full if not known
+            let size: usize = fetched.size.unwrap_or(data.len());
+
+            let first: bool = fetched.range.start == 0;
+
+            // chunks may come with headers identifying the original file
+            if index == 0 && first {
+                split_id = header_of(&data, size).map(|h| h.split_id);
+            }
+
+            let data: Vec<u8> = match split_id {
+                | Some(split_id) if first => {
+                    strip_header(data, size, index, split_id)?
+                },
+                | _ => data,
+            };
+
+            // the last piece of the chunk
+            let last: bool =
+                fetched.size.is_none_or(|size| fetched.range.end == size);
+
+            #[cfg(feature = "checksum")]
+            if leaves.is_some() {
+                let started: Option<Instant> = profiler.start();
+
+                let mut chunk: Sha256 = match first {
+                    | true => Sha256::new(),
+                    | false => hasher.take().unwrap_or_default(),
+                };
+
+                chunk.update(&data);
+
+                profiler.record(Phase::Hash, started);
+
+                hasher = Some(chunk);
+            }
+
+            // the chunk is verified at its last piece, before it is written
+            #[cfg(feature = "checksum")]
+            if let (Some(leaves), true) = (&leaves, last) {
+                let checksum: Hash =
+                    hasher.take().unwrap_or_default().finalize().into();
+
+                let result: ioa::Result<()> =
+                    verify_leaf(leaves, index, checksum);
+
+                match out_file {
+                    | Some(out_file) => discard(out_file, result).await?,
+                    | None => result?,
+                }
+            }
+
+            let started: Option<Instant> = profiler.start();
+
+            let data: Vec<u8> = match &self.transform {
+                | Some(transform) => transform.decode(index, data)?,
+                | None => data,
+            };
+
+            profiler.record(Phase::Transform, started);
+
+            let result: ioa::Result<()> =
+                verifier.update(&data, profiler).await;
+
+            match out_file {
+                | Some(out_file) => discard(out_file, result).await?,
+                | None => result?,
+            }
+
+            let started: Option<Instant> = profiler.start();
+
+            self.write(writer, &data).await?;
+
+            profiler.record(Phase::Write, started);
+
+            if let Some(control) = &self.control {
+                control.advance(data.len()).await?;
+            }
+
+            merged += data.len();
+
+            reporter.progress.bytes_written += data.len();
+
+            if last {
+                self.emit(Event::ChunkMerged { index, size: merged });
+
+                merged = 0;
+
+                reporter.progress.chunks_merged = index + 1;
+            }
+
+            reporter.report(false);
+        }
+
+        let started: Option<Instant> = profiler.start();
+
+        self.flush(writer).await?;
+
+        profiler.record(Phase::Write, started);
+
+        let result: ioa::Result<()> = verifier.finish(profiler).await;
+
+        match out_file {
+            | Some(out_file) => discard(out_file, result).await?,
+            | None => result?,
+        }
+
+        reporter.report(true);
+
+        Ok(reporter.progress.bytes_written)
+    }
+
+    /// Get the writer of the output file, bypassing the page cache if set.
+    async fn writer(
+        &self,
+        output: fsa::File,
+        buffer_capacity: usize,
+    ) -> ioa::Result<Box<dyn AsyncWrite + Send + Unpin>> {
+        Ok(match self.direct_io {
+            | true => {
+                Box::new(DirectWriter::new(output, buffer_capacity).await?)
+            },
+            | false => {
+                Box::new(ioa::BufWriter::with_capacity(buffer_capacity, output))
+            },
+        })
+    }
+
+    /// Write the data, watched by the stall monitor if any.
+    async fn write<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        data: &[u8],
+    ) -> ioa::Result<()> {
+        match &self.stall {
+            | Some(monitor) => monitor.watch(writer.write_all(data)).await,
+            | None => writer.write_all(data).await,
+        }
+    }
+
+    /// Flush the writer, watched by the stall monitor if any.
+    async fn flush<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> ioa::Result<()> {
+        match &self.stall {
+            | Some(monitor) => monitor.watch(writer.flush()).await,
+            | None => writer.flush().await,
+        }
+    }
+}
+
+impl Default for Merge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// List the chunks in a directory and its volume directories, sorted by
+/// their indices.
+pub(crate) async fn list_chunks(
+    in_dir: &Path,
+    naming: &ChunkNaming,
+) -> ioa::Result<Vec<PathBuf>> {
+    Ok(list_indexed_chunks(in_dir, naming)
+        .await?
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect())
+}
+
+/// List the chunks in a directory and its volume directories with their
+/// indices, sorted by their indices.
+pub(crate) async fn list_indexed_chunks(
+    in_dir: &Path,
+    naming: &ChunkNaming,
+) -> ioa::Result<Vec<(usize, PathBuf)>> {
+    let mut entries: Vec<(usize, PathBuf)> = Vec::new();
+
+    for dir in read_chunks(in_dir, naming, &mut entries).await? {
+        read_chunks(&dir, naming, &mut entries).await?;
+    }
+
+    entries.sort_unstable_by_key(|(index, _)| *index);
+
+    Ok(entries)
+}
+
+/// Read the chunks in a directory into `entries`, returning the volume
+/// directories found.
+pub(crate) async fn read_chunks(
+    dir: &Path,
+    naming: &ChunkNaming,
+    entries: &mut Vec<(usize, PathBuf)>,
+) -> ioa::Result<Vec<PathBuf>> {
+    let mut volumes: Vec<PathBuf> = Vec::new();
+
+    let mut read_dir: fsa::ReadDir = fsa::read_dir(dir).await?;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let path: PathBuf = entry.path();
+
+        let file_type: std::fs::FileType = entry.file_type().await?;
+
+        if file_type.is_dir() && is_volume_dir(&path) {
+            volumes.push(path);
+            continue;
+        }
+
+        let index: usize = match chunk_index(&path, naming) {
+            | Some(i) => i,
+            | None => continue,
+        };
+
+        // follow symlinks as the chunks may be linked from elsewhere
+        let is_file: bool = if file_type.is_symlink() {
+            fsa::metadata(&path).await.is_ok_and(|m| m.is_file())
+        } else {
+            file_type.is_file()
+        };
+
+        if is_file {
+            entries.push((index, path));
+        }
+    }
+
+    Ok(volumes)
+}
+
+/// Get the index of a chunk from its file name.
+fn chunk_index(
+    path: &Path,
+    naming: &ChunkNaming,
+) -> Option<usize> {
+    naming.index(path.file_name()?.to_str()?)
+}
+
+/// Check whether the directory is a volume directory, e.g. `vol0`.
+fn is_volume_dir(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(VOLUME_DIR_PREFIX))
+        .is_some_and(|v| v.parse::<usize>().is_ok())
+}
+
+/// Strip the header of a chunk of `size` bytes, or of its first piece,
+/// after checking it against the index and the split id.
+fn strip_header(
+    mut chunk: Vec<u8>,
+    size: usize,
+    index: usize,
+    split_id: Uuid,
+) -> ioa::Result<Vec<u8>> {
+    match header_of(&chunk, size) {
+        | Some(header) => header.expect(index, split_id)?,
+        | None => return Err(header_not_found(index)),
+    };
+
+    Ok(chunk.split_off(CHUNK_HEADER_SIZE))
+}
+
+/// Get the header at the start of a chunk of `size` bytes, or of its first
+/// piece, if it matches the size.
+fn header_of(
+    chunk: &[u8],
+    size: usize,
+) -> Option<ChunkHeader> {
+    ChunkHeader::decode(chunk).filter(|header| {
+        size.checked_sub(CHUNK_HEADER_SIZE) == Some(header.payload_size)
+    })
+}
+
+fn chunk_not_found(index: usize) -> ioa::Error {
+    ioa::Error::new(
+        ioa::ErrorKind::NotFound,
+        format!("chunk {} not found in store", index),
+    )
+}
+
+fn header_not_found(index: usize) -> ioa::Error {
+    ioa::Error::new(
+        ioa::ErrorKind::InvalidData,
+        format!("header of chunk {} not found", index),
+    )
+}
+
+/// Get the path of the output file in `out_dir` under its original name,
+/// rejecting names escaping the directory.
+fn named_output(
+    out_dir: &Path,
+    name: Option<&str>,
+) -> ioa::Result<PathBuf> {
+    let name: &str = match name {
+        | Some(name) => name,
+        | None => {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "original file name is not recorded",
+            ))
+        },
+    };
+
+    // only the final component, e.g. of a name recorded on another platform
+    let base: &str = name.rsplit(['/', '\\']).next().unwrap_or(name);
+
+    if base.is_empty() || base == "." || base == ".." {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("invalid original file name: {}", name),
+        ));
+    }
+
+    Ok(out_dir.join(base))
+}
+
+/// Delete the output file if exists and create a new one, or open the
+/// block device to be written in place. The file `claimed` by
+/// [`Collision::KeepBoth`] is created empty already, so it is kept.
+async fn create_output(
+    out_file: &Path,
+    claimed: bool,
+    direct_io: bool,
+) -> ioa::Result<fsa::File> {
+    let device: bool = is_block_device(out_file);
+
+    // delete outpath target if exists
+    if !device && !claimed && out_file.exists() {
+        if out_file.is_dir() {
+            fsa::remove_dir_all(&out_file).await?;
+        } else {
+            fsa::remove_file(&out_file).await?;
+        }
+    }
+
+    // create outpath
+    if let Some(parent) = out_file.parent().filter(|_| !device) {
+        fsa::create_dir_all(parent).await?;
+    }
+
+    let mut options: fs::OpenOptions = fs::OpenOptions::new();
+
+    options.create(!device).truncate(false).write(true);
+
+    if direct_io {
+        direct(&mut options)?;
+    }
+
+    fsa::OpenOptions::from(options).open(out_file).await
+}
+
+/// Open the output file left by a previous run, truncated to the `size`
+/// merged in full, or the block device, and positioned at the `size`.
+async fn resume_output(
+    out_file: &Path,
+    size: usize,
+    direct_io: bool,
+) -> ioa::Result<fsa::File> {
+    let mut options: fs::OpenOptions = fs::OpenOptions::new();
+
+    options.write(true);
+
+    if direct_io {
+        direct(&mut options)?;
+    }
+
+    let mut output: fsa::File =
+        fsa::OpenOptions::from(options).open(out_file).await?;
+
+    if !is_block_device(out_file) {
+        output.set_len(size as u64).await?;
+    }
+
+    output.seek(SeekFrom::Start(size as u64)).await?;
+
+    Ok(output)
+}
+
+/// Remove the output file if the result is an error, unless it is a block
+/// device.
+async fn discard<T>(
+    out_file: &Path,
+    result: ioa::Result<T>,
+) -> ioa::Result<T> {
+    if result.is_err() && !is_block_device(out_file) {
+        let _ = fsa::remove_file(out_file).await;
+    }
+
+    result
+}
+
+/// Get the checksums of the chunks in the manifest, verified against the
+/// Merkle root.
+#[cfg(feature = "checksum")]
+fn merkle_leaves(
+    manifest: Option<&Manifest>,
+    root: &str,
+) -> ioa::Result<Vec<Hash>> {
+    let manifest: &Manifest = manifest.ok_or_else(|| {
+        ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            "manifest is required to verify the Merkle root",
+        )
+    })?;
+
+    let leaves: Vec<Hash> = manifest
+        .chunks
+        .iter()
+        .map(|c| merkle::from_hex(c.get_metadata(Algorithm::Sha256.id())?))
+        .collect::<Option<Vec<Hash>>>()
+        .ok_or_else(|| {
+            ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "checksums are not recorded in the manifest",
+            )
+        })?;
+
+    let matched: bool = MerkleTree::new(&leaves)
+        .root_hex()
+        .is_some_and(|r| r.eq_ignore_ascii_case(root));
+
+    if !matched {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            "the Merkle root of the manifest is not equal to merkle_root \
+             parameter",
+        ));
+    }
+
+    Ok(leaves)
+}
+
+/// Get the BLAKE3 checksums of the chunks in the manifest, which are the
+/// roots of their bao outboards.
+#[cfg(feature = "bao")]
+fn bao_roots(manifest: Option<&Manifest>) -> ioa::Result<Vec<Hash>> {
+    let manifest: &Manifest = manifest.ok_or_else(|| {
+        ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            "manifest is required to verify the bao outboards",
+        )
+    })?;
+
+    manifest
+        .chunks
+        .iter()
+        .map(|c| merkle::from_hex(c.get_metadata(Algorithm::Blake3.id())?))
+        .collect::<Option<Vec<Hash>>>()
+        .ok_or_else(|| {
+            ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "BLAKE3 checksums are not recorded in the manifest",
+            )
+        })
+}
+
+/// Create the verifier of the chunk at `index` from its bao outboard.
+#[cfg(feature = "bao")]
+async fn bao_verifier(
+    roots: &[Hash],
+    index: usize,
+    entry: &Path,
+) -> ioa::Result<BaoVerifier> {
+    let root: Hash = roots.get(index).copied().ok_or_else(|| {
+        ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("BLAKE3 checksum of chunk {} not found", index),
+        )
+    })?;
+
+    BaoVerifier::new(root, fsa::read(outboard_path(entry)).await?)
+}
+
+/// Verify the checksum of the chunk at `index` against its leaf.
+#[cfg(feature = "checksum")]
+fn verify_leaf(
+    leaves: &[Hash],
+    index: usize,
+    checksum: Hash,
+) -> ioa::Result<()> {
+    if leaves.get(index) != Some(&checksum) {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("chunk {} does not match the Merkle root", index),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Verifier of the expected size and checksum of the output file, updated
+/// with the data as it is written.
+struct Verifier {
+    size: usize,
+    file_size: Option<usize>,
+    #[cfg(feature = "checksum")]
+    hash: Option<(FileHasher, String)>,
+}
+
+impl Verifier {
+    fn new(merge: &Merge) -> Self {
+        Self {
+            size: 0,
+            file_size: merge.expect_file_size,
+            #[cfg(feature = "checksum")]
+            hash: merge.expect_hash.clone().map(|(algorithm, digest)| {
+                (FileHasher::new(algorithm), digest)
+            }),
+        }
+    }
+
+    /// Update with the data to write, failing as soon as the size exceeds
+    /// the expected one.
+    #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+    async fn update(
+        &mut self,
+        data: &[u8],
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        self.size += data.len();
+
+        if let Some(file_size) = self.file_size {
+            if self.size > file_size {
+                return Err(size_mismatch(file_size, self.size));
+            }
+        }
+
+        #[cfg(feature = "checksum")]
+        if let Some((hasher, _)) = &mut self.hash {
+            hasher.update(data.to_vec(), profiler).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Update with the `size` bytes merged by a previous run, which are read
+    /// back from the output file only if its checksum is expected.
+    #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+    async fn resume(
+        &mut self,
+        out_file: &Path,
+        size: usize,
+        buffer: &mut [u8],
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        #[cfg(feature = "checksum")]
+        if self.hash.is_some() {
+            let mut input: ioa::Take<fsa::File> =
+                fsa::File::open(out_file).await?.take(size as u64);
+
+            loop {
+                let read: usize = input.read(buffer).await?;
+
+                if read == 0 {
+                    break;
+                }
+
+                self.update(&buffer[..read], profiler).await?;
+            }
+
+            return Ok(());
+        }
+
+        self.size += size;
+
+        Ok(())
+    }
+
+    /// Verify the size and the checksum after writing all data.
+    #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+    async fn finish(
+        self,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        if let Some(file_size) = self.file_size {
+            if self.size != file_size {
+                return Err(size_mismatch(file_size, self.size));
+            }
+        }
+
+        #[cfg(feature = "checksum")]
+        if let Some((hasher, expected)) = self.hash {
+            let digest: String = hasher.finish(profiler).await?;
+
+            if !digest.eq_ignore_ascii_case(&expected) {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidData,
+                    format!(
+                        "checksum of out_file {} does not match {}",
+                        digest, expected,
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn size_mismatch(
+    expected: usize,
+    actual: usize,
+) -> ioa::Error {
+    ioa::Error::new(
+        ioa::ErrorKind::InvalidData,
+        format!("size of out_file {} does not match {}", actual, expected,),
+    )
+}
+
+/// Task fetching a chunk with the latency of the store.
+type Fetch = JoinHandle<ioa::Result<(Vec<u8>, Duration)>>;
+
+/// Pieces being fetched, aborted when dropped, so none is left writing to
+/// the cache or holding memory after the merge returns.
+struct Pending(VecDeque<(StorePiece, Fetch)>);
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        for (_, handle) in &self.0 {
+            handle.abort();
+        }
+    }
+}
+
+/// Fetch a chunk from the cache or the store, or a piece of it from the
+/// store if its range is set, with the latency of the store.
+async fn fetch(
+    store: Arc<dyn ChunkStore>,
+    cache: Option<Arc<DirStore>>,
+    piece: StorePiece,
+) -> ioa::Result<(Vec<u8>, Duration)> {
+    let index: usize = piece.index;
+
+    if piece.size.is_some() {
+        let start: Instant = Instant::now();
+
+        let data: Vec<u8> = store.get_range(index, piece.range).await?;
+
+        return Ok((data, start.elapsed()));
+    }
+
+    if let Some(cache) = &cache {
+        if cache.head(index).await?.is_some() {
+            return Ok((cache.get(index).await?, Duration::ZERO));
+        }
+    }
+
+    let start: Instant = Instant::now();
+
+    let chunk: Vec<u8> = store.get(index).await?;
+
+    let latency: Duration = start.elapsed();
+
+    if let Some(cache) = &cache {
+        cache.put(index, chunk.clone()).await?;
+    }
+
+    Ok((chunk, latency))
+}
+
+/// Piece of a chunk fetched from the store.
+#[derive(Debug, Clone)]
+struct StorePiece {
+    index: usize,
+    /// Range of the piece within the chunk, empty if fetched in full.
+    range: Range<usize>,
+    /// Size of the whole chunk, `None` if fetched in full.
+    size: Option<usize>,
+}
+
+/// Window of the chunks fetched ahead, adjusted with AIMD when adaptive.
+#[derive(Debug, Clone)]
+struct PrefetchWindow {
+    size: usize,
+    max: usize,
+    adaptive: bool,
+    latency: Option<Duration>,
+}
+
+impl PrefetchWindow {
+    fn new(
+        max: usize,
+        adaptive: bool,
+    ) -> Self {
+        Self {
+            size: if adaptive { 1 } else { max },
+            max,
+            adaptive,
+            latency: None,
+        }
+    }
+
+    fn observe(
+        &mut self,
+        latency: Duration,
+    ) {
+        // cached chunks say nothing about the store
+        if !self.adaptive || latency.is_zero() {
+            return;
+        }
+
+        let average: Duration = match self.latency {
+            | Some(average) => average,
+            | None => latency,
+        };
+
+        if latency > average * 2 {
+            // multiplicative decrease
+            self.size = (self.size / 2).max(1);
+        } else {
+            // additive increase
+            self.size = (self.size + 1).min(self.max);
+        }
+
+        // moving average with a weight of 1/8
+        self.latency = Some((average * 7 + latency) / 8);
     }
 }
@@ -3,3 +3,13 @@ pub mod split;
 pub mod check;
 
 pub mod merge;
+
+pub mod inspect;
+
+pub mod peek;
+
+pub mod diff;
+
+pub mod sync;
+
+pub mod rechunk;
@@ -0,0 +1,375 @@
+use std::{
+    io::SeekFrom,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
+};
+
+use crate::{
+    functions::merge::list_indexed_chunks,
+    header::{ChunkHeader, CHUNK_HEADER_SIZE},
+    layout::LayoutRegistry,
+    manifest::{find_manifest, Manifest},
+    naming::ChunkNaming,
+    store::DirStore,
+    transform::ChunkTransform,
+};
+
+/// Process to read bytes at an offset of the original file straight from
+/// the chunks in a directory, without merging them.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::peek::Peek;
+///
+/// async fn example() {
+///     let bytes: Vec<u8> = Peek::new()
+///         .in_dir(PathBuf::from("path").join("to").join("dir"))
+///         .range(1024 * 1024..1024 * 1024 + 64)
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Peek {
+    in_dir: Option<PathBuf>,
+    range: Range<usize>,
+    transform: Option<Arc<dyn ChunkTransform>>,
+    naming: ChunkNaming,
+    layouts: LayoutRegistry,
+}
+
+impl Peek {
+    /// Create a new peek process.
+    pub fn new() -> Self {
+        Self {
+            in_dir: None,
+            range: 0..0,
+            transform: None,
+            naming: ChunkNaming::Index,
+            layouts: LayoutRegistry::new(),
+        }
+    }
+
+    /// Set the input directory.
+    pub fn in_dir<InDir: AsRef<Path>>(
+        mut self,
+        path: InDir,
+    ) -> Self {
+        self.in_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the range of the bytes in the original file.
+    ///
+    /// The range is clamped to the size of the original file.
+    pub fn range(
+        mut self,
+        range: Range<usize>,
+    ) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Set the transform to decode the chunks.
+    ///
+    /// The chunks are fully read and decoded with a transform, as the
+    /// offsets in the encoded chunks are unknown.
+    pub fn transform<T: ChunkTransform + 'static>(
+        mut self,
+        transform: T,
+    ) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+
+    /// Set the scheme of the names of the chunks in the input directory.
+    ///
+    /// By default, the names are the indices of the chunks. It is ignored
+    /// when the input directory contains a manifest.
+    pub fn naming(
+        mut self,
+        naming: ChunkNaming,
+    ) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Set the layouts to detect the chunk set in the input directory.
+    ///
+    /// By default, the layouts follow the [`LayoutRegistry::new`].
+    pub fn layouts(
+        mut self,
+        layouts: LayoutRegistry,
+    ) -> Self {
+        self.layouts = layouts;
+        self
+    }
+
+    /// Run the peek process.
+    ///
+    /// Without a manifest, the offsets of the chunks are computed from their
+    /// sizes in storage, so transformed chunks require a manifest, and the
+    /// process fails with [`ioa::ErrorKind::NotFound`] if a chunk is missing.
+    ///
+    /// The bytes are collected in memory, see [`Peek::run_to`] for large
+    /// ranges.
+    pub async fn run(self) -> ioa::Result<Vec<u8>> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        self.run_to(&mut bytes).await?;
+
+        Ok(bytes)
+    }
+
+    /// Run the peek process into the `writer`, e.g. the standard output,
+    /// instead of collecting the bytes in memory.
+    ///
+    /// The chunks are copied to the writer one by one, and only held in
+    /// full if decoded with a transform.
+    pub async fn run_to<W: AsyncWrite + Unpin>(
+        self,
+        mut writer: W,
+    ) -> ioa::Result<()> {
+        let in_dir: &Path = match self.in_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if in_dir not exists
+                if !p.exists() {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::NotFound,
+                        "in_dir path not found",
+                    ));
+                }
+
+                // if in_dir not a directory
+                if !p.is_dir() {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        "in_dir is not a directory",
+                    ));
+                }
+
+                p
+            },
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "in_dir is not set",
+                ))
+            },
+        };
+
+        let spans: Vec<Span> =
+            spans(in_dir, self.transform.as_ref(), &self.naming, &self.layouts)
+                .await?;
+
+        for span in spans {
+            let start: usize = self.range.start.max(span.offset);
+            let end: usize = self.range.end.min(span.offset + span.size);
+
+            if start >= end {
+                continue;
+            }
+
+            span.copy_to(
+                start - span.offset..end - span.offset,
+                self.transform.as_ref(),
+                &mut writer,
+            )
+            .await?;
+        }
+
+        writer.flush().await
+    }
+}
+
+impl Default for Peek {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Chunk at an offset of the original file.
+pub(crate) struct Span {
+    /// Path of the chunk.
+    pub(crate) path: PathBuf,
+    /// Index of the chunk.
+    pub(crate) index: usize,
+    /// Offset of the chunk in the original file.
+    pub(crate) offset: usize,
+    /// Size of the chunk before transform.
+    pub(crate) size: usize,
+    /// Whether the chunk comes with a header.
+    pub(crate) header: bool,
+}
+
+impl Span {
+    /// Read the bytes at a range within the chunk, decoded with the
+    /// transform if any.
+    pub(crate) async fn read(
+        &self,
+        range: Range<usize>,
+        transform: Option<&Arc<dyn ChunkTransform>>,
+    ) -> ioa::Result<Vec<u8>> {
+        let skip: usize = if self.header { CHUNK_HEADER_SIZE } else { 0 };
+
+        if let Some(transform) = transform {
+            let mut chunk: Vec<u8> = fsa::read(&self.path).await?;
+
+            chunk.drain(..skip.min(chunk.len()));
+
+            let chunk: Vec<u8> = transform.decode(self.index, chunk)?;
+
+            let from: usize = range.start.min(chunk.len());
+            let to: usize = range.end.min(chunk.len());
+
+            return Ok(chunk[from..to].to_vec());
+        }
+
+        let mut input: fsa::File =
+            fsa::OpenOptions::new().read(true).open(&self.path).await?;
+
+        input.seek(SeekFrom::Start((skip + range.start) as u64)).await?;
+
+        let mut buffer: Vec<u8> = vec![0; range.len()];
+
+        input.read_exact(&mut buffer).await?;
+
+        Ok(buffer)
+    }
+
+    /// Copy the bytes at a range within the chunk to the writer, decoded
+    /// with the transform if any, otherwise streamed without holding the
+    /// chunk.
+    async fn copy_to<W: AsyncWrite + Unpin>(
+        &self,
+        range: Range<usize>,
+        transform: Option<&Arc<dyn ChunkTransform>>,
+        writer: &mut W,
+    ) -> ioa::Result<()> {
+        if transform.is_some() {
+            return writer.write_all(&self.read(range, transform).await?).await;
+        }
+
+        let skip: usize = if self.header { CHUNK_HEADER_SIZE } else { 0 };
+
+        let mut input: fsa::File =
+            fsa::OpenOptions::new().read(true).open(&self.path).await?;
+
+        input.seek(SeekFrom::Start((skip + range.start) as u64)).await?;
+
+        let copied: u64 =
+            ioa::copy(&mut input.take(range.len() as u64), writer).await?;
+
+        if copied < range.len() as u64 {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::UnexpectedEof,
+                format!("chunk {} is shorter than expected", self.index),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the chunks of the original file in a directory, following the
+/// manifest if any.
+pub(crate) async fn spans(
+    in_dir: &Path,
+    transform: Option<&Arc<dyn ChunkTransform>>,
+    naming: &ChunkNaming,
+    layouts: &LayoutRegistry,
+) -> ioa::Result<Vec<Span>> {
+    let manifest: Manifest = match find_manifest(in_dir).await? {
+        | Some(manifest) => manifest,
+        | None if transform.is_some() => {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "manifest is required to read transformed chunks",
+            ))
+        },
+        | None => return spans_by_names(in_dir, naming, layouts).await,
+    };
+
+    // check transform recorded in manifest
+    if manifest.transform.as_deref() != transform.map(|t| t.id()) {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            "transform does not match the manifest",
+        ));
+    }
+
+    let store: DirStore = DirStore::with_manifest(in_dir, &manifest);
+
+    Ok(manifest
+        .chunks
+        .iter()
+        .map(|chunk| Span {
+            path: store.path(chunk.index),
+            index: chunk.index,
+            offset: chunk.offset,
+            size: chunk.size,
+            header: manifest.headers,
+        })
+        .collect())
+}
+
+/// Get the chunks in a directory without a manifest, with the offsets
+/// computed from their sizes in storage.
+async fn spans_by_names(
+    in_dir: &Path,
+    naming: &ChunkNaming,
+    layouts: &LayoutRegistry,
+) -> ioa::Result<Vec<Span>> {
+    let mut entries: Vec<(usize, PathBuf)> =
+        list_indexed_chunks(in_dir, naming).await?;
+
+    // chunk sets of the registered layouts
+    if entries.is_empty() && naming.is_index() {
+        if let Some(detected) = layouts.detect(in_dir).await? {
+            entries = detected.chunks.into_iter().collect();
+        }
+    }
+
+    let header: bool = match entries.first() {
+        | Some((_, first)) => ChunkHeader::read(first).await?.is_some(),
+        | None => false,
+    };
+
+    let mut spans: Vec<Span> = Vec::with_capacity(entries.len());
+
+    let mut offset: usize = 0;
+
+    for (expected, (index, path)) in entries.into_iter().enumerate() {
+        // the offsets of the later chunks are unknown after a gap
+        if index != expected {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                format!("chunk {} not found", expected),
+            ));
+        }
+
+        let mut size: usize = fsa::metadata(&path).await?.len() as usize;
+
+        if header {
+            size = size.saturating_sub(CHUNK_HEADER_SIZE);
+        }
+
+        spans.push(Span { path, index, offset, size, header });
+
+        offset += size;
+    }
+
+    Ok(spans)
+}
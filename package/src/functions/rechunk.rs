@@ -0,0 +1,254 @@
+use std::{
+    collections::BTreeMap,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt},
+};
+
+use crate::{
+    config::MANIFEST_FILE_NAME,
+    functions::peek::{spans, Span},
+    header::CHUNK_HEADER_SIZE,
+    layout::LayoutRegistry,
+    manifest::{find_manifest, ChunkInfo, Manifest, Uuid},
+    naming::ChunkNaming,
+};
+
+/// Result of the [`Rechunk`] process.
+#[derive(Debug, Clone)]
+pub struct RechunkResult {
+    /// Size of the original file.
+    pub file_size: usize,
+    /// The total number of chunks written.
+    pub total_chunks: usize,
+    /// Manifest of the chunks written, if the input directory has one.
+    pub manifest: Option<Manifest>,
+}
+
+/// Create a process converting the chunks in `in_dir` to chunks of
+/// `chunk_size` in `out_dir`, see [`Rechunk`].
+pub fn rechunk<InDir: AsRef<Path>, OutDir: AsRef<Path>>(
+    in_dir: InDir,
+    chunk_size: usize,
+    out_dir: OutDir,
+) -> Rechunk {
+    Rechunk::new(in_dir, chunk_size, out_dir)
+}
+
+/// Process to convert a chunk set to another chunk size, streaming the
+/// bytes across the boundaries of the chunks without merging the original
+/// file on disk first.
+///
+/// The new chunks are named by their indices without headers, and the
+/// memory is bounded by the new chunk size. If the input directory has a
+/// manifest, a manifest of the new chunks is written to the output
+/// directory, keeping the name, the checksums and the metadata of the
+/// original file. Transformed chunks are not supported.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::rechunk::{rechunk, RechunkResult};
+///
+/// async fn example() {
+///     let result: RechunkResult =
+///         rechunk("/path/to/dir", 8 * 1024 * 1024, "/path/to/rechunked")
+///             .run()
+///             .await
+///             .unwrap();
+///
+///     println!("{} chunks written", result.total_chunks);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rechunk {
+    in_dir: PathBuf,
+    chunk_size: usize,
+    out_dir: PathBuf,
+    naming: ChunkNaming,
+    layouts: LayoutRegistry,
+}
+
+impl Rechunk {
+    /// Create a new rechunk process from `in_dir` to chunks of
+    /// `chunk_size` in `out_dir`.
+    pub fn new<InDir: AsRef<Path>, OutDir: AsRef<Path>>(
+        in_dir: InDir,
+        chunk_size: usize,
+        out_dir: OutDir,
+    ) -> Self {
+        Self {
+            in_dir: in_dir.as_ref().to_path_buf(),
+            chunk_size,
+            out_dir: out_dir.as_ref().to_path_buf(),
+            naming: ChunkNaming::Index,
+            layouts: LayoutRegistry::new(),
+        }
+    }
+
+    /// Set the naming of the input chunks without a manifest.
+    ///
+    /// By default, it is [`ChunkNaming::Index`].
+    pub fn naming(
+        mut self,
+        naming: ChunkNaming,
+    ) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Set the registry of the layouts to detect the input chunks with.
+    pub fn layouts(
+        mut self,
+        layouts: LayoutRegistry,
+    ) -> Self {
+        self.layouts = layouts;
+        self
+    }
+
+    /// Run the rechunk process.
+    pub async fn run(self) -> ioa::Result<RechunkResult> {
+        if self.chunk_size == 0 {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "chunk_size must be greater than 0",
+            ));
+        }
+
+        if !self.in_dir.is_dir() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                "in_dir path not found",
+            ));
+        }
+
+        // the input chunks would be overwritten while being read
+        if self.out_dir.exists()
+            && fsa::canonicalize(&self.out_dir).await?
+                == fsa::canonicalize(&self.in_dir).await?
+        {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "out_dir must differ from in_dir",
+            ));
+        }
+
+        let spans: Vec<Span> =
+            spans(&self.in_dir, None, &self.naming, &self.layouts).await?;
+
+        if spans.is_empty() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                "No files found in in_dir",
+            ));
+        }
+
+        fsa::create_dir_all(&self.out_dir).await?;
+
+        let mut buffer: Vec<u8> = vec![0; self.chunk_size];
+
+        let mut filled: usize = 0;
+
+        let mut chunks: Vec<ChunkInfo> = Vec::new();
+
+        for span in &spans {
+            let mut input: fsa::File =
+                fsa::OpenOptions::new().read(true).open(&span.path).await?;
+
+            if span.header {
+                input.seek(SeekFrom::Start(CHUNK_HEADER_SIZE as u64)).await?;
+            }
+
+            let mut input: ioa::Take<fsa::File> = input.take(span.size as u64);
+
+            let mut remaining: usize = span.size;
+
+            while remaining > 0 {
+                let read: usize = input.read(&mut buffer[filled..]).await?;
+
+                if read == 0 {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::UnexpectedEof,
+                        format!(
+                            "chunk {} is shorter than expected",
+                            span.index
+                        ),
+                    ));
+                }
+
+                filled += read;
+                remaining -= read;
+
+                if filled == self.chunk_size {
+                    self.write(&mut chunks, &buffer).await?;
+                    filled = 0;
+                }
+            }
+        }
+
+        if filled > 0 {
+            self.write(&mut chunks, &buffer[..filled]).await?;
+        }
+
+        let file_size: usize =
+            chunks.last().map_or(0, |chunk| chunk.offset + chunk.size);
+
+        let total_chunks: usize = chunks.len();
+
+        let manifest: Option<Manifest> = match find_manifest(&self.in_dir)
+            .await?
+        {
+            | Some(manifest) => {
+                let manifest: Manifest = Manifest {
+                    id: Some(Uuid::new_v4()),
+                    chunk_size: self.chunk_size,
+                    total_chunks,
+                    zero_padding: false,
+                    naming: ChunkNaming::Index,
+                    transform: None,
+                    headers: false,
+                    volume: None,
+                    merkle_root: None,
+                    chunks,
+                    ..manifest
+                };
+
+                manifest.write(self.out_dir.join(MANIFEST_FILE_NAME)).await?;
+
+                Some(manifest)
+            },
+            | None => None,
+        };
+
+        Ok(RechunkResult { file_size, total_chunks, manifest })
+    }
+
+    /// Write the next chunk.
+    async fn write(
+        &self,
+        chunks: &mut Vec<ChunkInfo>,
+        data: &[u8],
+    ) -> ioa::Result<()> {
+        let index: usize = chunks.len();
+
+        let offset: usize =
+            chunks.last().map_or(0, |chunk| chunk.offset + chunk.size);
+
+        fsa::write(self.out_dir.join(index.to_string()), data).await?;
+
+        chunks.push(ChunkInfo {
+            index,
+            offset,
+            size: data.len(),
+            stored_size: data.len(),
+            volume: None,
+            metadata: BTreeMap::new(),
+        });
+
+        Ok(())
+    }
+}
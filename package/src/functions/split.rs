@@ -1,11 +1,62 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs,
+    future::Future,
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use tokio::{
     fs as fsa,
     io::{self as ioa, AsyncReadExt, AsyncWriteExt},
+    runtime::Handle,
+    sync::{mpsc, OwnedSemaphorePermit},
+    task::JoinHandle,
 };
 
-use crate::config::{BUFFER_CAPACITY_MAX_DEFAULT, CHUNK_SIZE_DEFAULT};
+#[cfg(feature = "bao")]
+use crate::bao::{outboard, outboard_path};
+use crate::{
+    attributes::FileMetadata,
+    boundary::{BoundaryPolicy, FixedSize, RecordAligned},
+    config::{
+        BUFFER_CAPACITY_MAX_DEFAULT, CHUNK_SIZE_DEFAULT, DIRECT_IO_ALIGNMENT,
+        PROGRESS_INTERVAL_DEFAULT, TOTAL_CHUNKS_MAX_DEFAULT,
+    },
+    control::{Control, OperationHandle},
+    device::{direct, input_size, is_block_device, read_direct},
+    event::{Event, EventBus, EventStream},
+    header::{ChunkHeader, CHUNK_HEADER_SIZE, CHUNK_HEADER_VERSION},
+    log::{Operation, OperationLog},
+    manifest::{
+        padding_width, ChunkInfo, Manifest, ManifestFormat, SourceInfo, Uuid,
+        MANIFEST_VERSION,
+    },
+    mime::sniff_file,
+    naming::ChunkNaming,
+    profile::{Phase, Profile, Profiler},
+    progress::{throughput, OnProgress, ProgressSink, Reporter, Tee},
+    rng::Rng,
+    store::{chunk_path, volume_dir},
+    transform::ChunkTransform,
+    tune::{memory_budget, require_space, MemoryBudget},
+};
+#[cfg(feature = "checksum")]
+use crate::{
+    checksum::{
+        file_sha256, format_sha256sums, Algorithm, ChunkChecksum, FileHasher,
+        Workers,
+    },
+    config::{CHECKSUM_WORKERS_DEFAULT, SHA256SUMS_FILE_NAME},
+    merkle::MerkleTree,
+    store::DirStore,
+};
 
 /// Process to split file from a path to a directory.
 ///
@@ -28,18 +79,217 @@ use crate::config::{BUFFER_CAPACITY_MAX_DEFAULT, CHUNK_SIZE_DEFAULT};
 #[derive(Debug, Clone)]
 pub struct Split {
     in_file: Option<PathBuf>,
+    in_files: Vec<PathBuf>,
     out_dir: Option<PathBuf>,
+    scoped: bool,
+    resume: bool,
     chunk_size: usize,
     cap_max: usize,
+    concurrency: usize,
+    direct_io: bool,
+    mode: SplitMode,
+    transform: Option<Arc<dyn ChunkTransform>>,
+    manifest: bool,
+    manifest_format: ManifestFormat,
+    max_chunks: usize,
+    require_free_space: bool,
+    zero_padding: bool,
+    naming: ChunkNaming,
+    volume_size: Option<usize>,
+    headers: bool,
+    preserve_metadata: bool,
+    preserve_xattrs: bool,
+    preserve_streams: bool,
+    sniff_mime: bool,
+    control: Option<Arc<Control>>,
+    events: Option<EventBus>,
+    progress: Option<Arc<dyn ProgressSink>>,
+    on_progress: Option<OnProgress<SplitProgress>>,
+    progress_interval: Duration,
+    log: Option<OperationLog>,
+    auto_tune: bool,
+    budget: Option<MemoryBudget>,
+    profile: bool,
+    rng: Option<Arc<dyn Rng>>,
+    #[cfg(feature = "checksum")]
+    checksum: Option<Algorithm>,
+    #[cfg(feature = "checksum")]
+    merkle: bool,
+    #[cfg(feature = "checksum")]
+    file_hash: Option<Algorithm>,
+    #[cfg(feature = "checksum")]
+    sha256sums: bool,
+    #[cfg(feature = "bao")]
+    bao: bool,
+}
+
+/// Mode of the split process.
+#[derive(Debug, Clone, Default)]
+pub enum SplitMode {
+    /// Every chunk except the last one has exactly `chunk_size` bytes.
+    #[default]
+    Fixed,
+    /// Every chunk ends right after the last `delimiter` found within
+    /// `chunk_size` bytes, so records are never splitted across chunks.
+    ///
+    /// A chunk without any `delimiter` still ends at `chunk_size`.
+    RecordAligned {
+        /// The byte that terminates a record, e.g. `b'\n'`.
+        delimiter: u8,
+    },
+    /// Every chunk ends where the custom [`BoundaryPolicy`] decides.
+    Custom(Arc<dyn BoundaryPolicy>),
+}
+
+impl SplitMode {
+    /// Get the boundary policy of the mode.
+    pub fn policy(&self) -> Arc<dyn BoundaryPolicy> {
+        match self {
+            | Self::Fixed => Arc::new(FixedSize),
+            | Self::RecordAligned { delimiter } => {
+                Arc::new(RecordAligned { delimiter: *delimiter })
+            },
+            | Self::Custom(policy) => policy.clone(),
+        }
+    }
 }
 
 /// Result of the split process.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct SplitResult {
+    /// Identity of the split, recorded in the manifest and chunk headers.
+    pub id: Uuid,
+    /// Directory the chunks are written to, which is within the output
+    /// directory if the split is [`scoped`](Split::scoped).
+    pub out_dir: PathBuf,
+    /// Indices of the chunks kept from a previous run, if
+    /// [`resumed`](Split::resume).
+    pub resumed: Vec<usize>,
     /// Size of the original file.
     pub file_size: usize,
     /// The total number of chunks splitted from the original file.
     pub total_chunks: usize,
+    /// Manifest describing the chunks.
+    pub manifest: Manifest,
+    /// Time spent in each phase, if profiled.
+    pub profile: Option<Profile>,
+    /// Time elapsed from the start to the end of the process.
+    pub elapsed: Duration,
+    /// Checksums of the chunks, if computed.
+    #[cfg(feature = "checksum")]
+    pub checksums: Vec<ChunkChecksum>,
+    /// Checksum of the original file in hex, if computed.
+    #[cfg(feature = "checksum")]
+    pub file_hash: Option<String>,
+}
+
+impl SplitResult {
+    /// Get the average throughput of the process in bytes of the original
+    /// file per second.
+    pub fn throughput(&self) -> f64 {
+        throughput(self.file_size, self.elapsed)
+    }
+
+    /// Get the size of the chunk at `index` before transform, or `None` if
+    /// out of range.
+    ///
+    /// The sizes are taken from the manifest, so the last chunk and the
+    /// chunks of a content-defined split are sized correctly.
+    pub fn chunk_size_of(
+        &self,
+        index: usize,
+    ) -> Option<usize> {
+        self.manifest.chunk(index).map(|chunk| chunk.size)
+    }
+
+    /// Get the offset of the chunk at `index` in the original file, or
+    /// `None` if out of range.
+    pub fn offset_of(
+        &self,
+        index: usize,
+    ) -> Option<usize> {
+        self.manifest.chunk(index).map(|chunk| chunk.offset)
+    }
+
+    /// Get the range of the original file in the chunk at `index`, or
+    /// `None` if out of range.
+    pub fn range_of(
+        &self,
+        index: usize,
+    ) -> Option<Range<usize>> {
+        self.manifest
+            .chunk(index)
+            .map(|chunk| chunk.offset..chunk.offset + chunk.size)
+    }
+
+    /// Get the index of the chunk containing the byte at `offset` of the
+    /// original file, or `None` if past the end of the file.
+    pub fn index_for_offset(
+        &self,
+        offset: usize,
+    ) -> Option<usize> {
+        let chunks: &[ChunkInfo] = &self.manifest.chunks;
+
+        let position: usize =
+            chunks.partition_point(|chunk| chunk.offset + chunk.size <= offset);
+
+        chunks.get(position).map(|chunk| chunk.index)
+    }
+}
+
+/// Progress of the split process, see [`Split::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitProgress {
+    /// The number of bytes read from the original file.
+    pub bytes_read: usize,
+    /// Size of the original file.
+    pub total_bytes: usize,
+    /// The number of chunks written.
+    pub chunks_written: usize,
+    /// The total number of chunks expected, which is estimated from the
+    /// chunk size until the process finishes.
+    pub total_chunks: usize,
+}
+
+impl SplitProgress {
+    /// Get the fraction of the original file read, from `0.0` to `1.0`.
+    pub fn fraction(&self) -> f64 {
+        match self.total_bytes {
+            | 0 => 1.0,
+            | total => self.bytes_read as f64 / total as f64,
+        }
+    }
+}
+
+/// Plan of the split process, see [`Split::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPlan {
+    /// Size of the original file.
+    pub file_size: usize,
+    /// Size of each chunk, which is the `chunk_size` or the size of the file
+    /// if smaller, so such a file is splitted into a single chunk.
+    pub chunk_size: usize,
+    /// The total number of chunks.
+    pub total_chunks: usize,
+    /// Capacity of the buffer of the reader and writers.
+    pub buffer_capacity: usize,
+    /// The chunks to be written in order.
+    pub chunks: Vec<ChunkPlan>,
+}
+
+/// Chunk planned by [`Split::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkPlan {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Range of the original file in the chunk.
+    pub range: Range<usize>,
+    /// Index of the volume of the chunk, if grouped into volumes.
+    pub volume: Option<usize>,
+    /// Path of the chunk relative to the directory the chunks are written
+    /// to, which is the output directory unless [`scoped`](Split::scoped).
+    pub path: PathBuf,
 }
 
 impl Split {
@@ -47,9 +297,48 @@ impl Split {
     pub fn new() -> Self {
         Self {
             in_file: None,
+            in_files: Vec::new(),
             out_dir: None,
+            scoped: false,
+            resume: false,
             chunk_size: CHUNK_SIZE_DEFAULT,
             cap_max: BUFFER_CAPACITY_MAX_DEFAULT,
+            concurrency: 1,
+            direct_io: false,
+            mode: SplitMode::Fixed,
+            transform: None,
+            manifest: false,
+            manifest_format: ManifestFormat::Json,
+            max_chunks: TOTAL_CHUNKS_MAX_DEFAULT,
+            require_free_space: false,
+            zero_padding: false,
+            naming: ChunkNaming::Index,
+            volume_size: None,
+            headers: false,
+            preserve_metadata: false,
+            preserve_xattrs: false,
+            preserve_streams: false,
+            sniff_mime: false,
+            control: None,
+            events: None,
+            progress: None,
+            on_progress: None,
+            progress_interval: PROGRESS_INTERVAL_DEFAULT,
+            log: None,
+            auto_tune: false,
+            budget: None,
+            profile: false,
+            rng: None,
+            #[cfg(feature = "checksum")]
+            checksum: None,
+            #[cfg(feature = "checksum")]
+            merkle: false,
+            #[cfg(feature = "checksum")]
+            file_hash: None,
+            #[cfg(feature = "checksum")]
+            sha256sums: false,
+            #[cfg(feature = "bao")]
+            bao: false,
         }
     }
 
@@ -62,6 +351,24 @@ impl Split {
         self
     }
 
+    /// Set the input files, which are concatenated in order and splitted as
+    /// a single file, e.g. to transfer many small files as one chunk set.
+    ///
+    /// It takes precedence over the [`in_file`](Self::in_file). The names,
+    /// offsets and sizes of the files are recorded in the manifest as its
+    /// [`sources`](Manifest::sources), so the files are restored from the
+    /// merged file with [`Manifest::unbundle`]. The names of the files must
+    /// be unique, the files are opened one at a time, and their metadata
+    /// cannot be preserved.
+    pub fn in_files<I: IntoIterator<Item = P>, P: AsRef<Path>>(
+        mut self,
+        paths: I,
+    ) -> Self {
+        self.in_files =
+            paths.into_iter().map(|p| p.as_ref().to_path_buf()).collect();
+        self
+    }
+
     /// Set the output directory.
     pub fn out_dir<OutDir: AsRef<Path>>(
         mut self,
@@ -71,9 +378,42 @@ impl Split {
         self
     }
 
+    /// Set whether to write the chunks to a directory named by the identity
+    /// of the split within the output directory, i.e. `out_dir/<id>/`, so
+    /// concurrent splits of files with the same name into a shared
+    /// directory never collide.
+    ///
+    /// By default, it is `false`. The directory written to is returned as
+    /// [`SplitResult::out_dir`], and the identity as [`SplitResult::id`].
+    pub fn scoped(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.scoped = enabled;
+        self
+    }
+
+    /// Set whether to keep the chunks written by a previous run into the
+    /// output directory, e.g. after a crash, only writing the chunks missing.
+    ///
+    /// By default, it is `false`. A chunk is kept if it has the expected
+    /// size, and with a [`checksum`](Self::checksum), if its payload has the
+    /// expected checksum. With [`headers`](Self::headers), the identity of
+    /// the previous run is kept as well, so the headers of the chunks
+    /// written by both runs match. The original file is still read in full.
+    pub fn resume(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.resume = enabled;
+        self
+    }
+
     /// Set the maximum size of each chunk.
     ///
-    /// By default, the chunk size follows the [`CHUNK_SIZE_DEFAULT`].
+    /// By default, the chunk size follows the [`CHUNK_SIZE_DEFAULT`]. A file
+    /// not larger than the chunk size is splitted into a single chunk, and
+    /// the buffers are sized by the file instead.
     pub fn chunk_size(
         mut self,
         size: usize,
@@ -82,156 +422,2011 @@ impl Split {
         self
     }
 
-    /// Set the maximum size of the buffer capacity.
+    /// Set the mode of the split process.
     ///
-    /// By default, the buffer capacity is based on the `chunk_size`.
-    /// The buffer capacity is limited and will not exceed
-    /// [`BUFFER_CAPACITY_MAX`]. The default value is recommended unless
-    /// a large size file will be processed through the split process.
-    pub fn max_buffer_capacity(
+    /// By default, the mode is [`SplitMode::Fixed`].
+    pub fn mode(
         mut self,
-        capacity: usize,
+        mode: SplitMode,
     ) -> Self {
-        self.cap_max = capacity;
+        self.mode = mode;
         self
     }
 
-    /// Run the split process.
-    pub async fn run(self) -> ioa::Result<SplitResult> {
-        let in_file: &Path = match self.in_file {
-            | Some(ref p) => {
-                let p: &Path = p.as_ref();
-
-                // if in_file not exists
-                if !p.exists() {
-                    return Err(ioa::Error::new(
-                        ioa::ErrorKind::NotFound,
-                        "in_file path not found",
-                    ));
-                }
+    /// Set the transform applied to each chunk before writing it to the
+    /// output directory.
+    ///
+    /// The identity of the transform is recorded in the manifest, and the
+    /// same transform should be set in the merge process to decode the
+    /// chunks. As the size of the chunks in storage changes, use the
+    /// [`Manifest::stored_size`] as the `file_size` in the check process.
+    pub fn transform<T: ChunkTransform + 'static>(
+        mut self,
+        transform: T,
+    ) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
 
-                // if in_file not a file
-                if !p.is_file() {
-                    return Err(ioa::Error::new(
-                        ioa::ErrorKind::InvalidInput,
-                        "in_file is not a path to file",
-                    ));
-                }
+    /// Set the algorithm of the checksum computed for each chunk.
+    ///
+    /// By default, no checksum is computed. When set, the checksum of the
+    /// payload of each chunk, excluding the header, is computed on worker
+    /// threads while reading and writing, returned in the
+    /// [`SplitResult::checksums`] and recorded in the metadata of the chunk
+    /// in the manifest under the [`Algorithm::id`].
+    #[cfg(feature = "checksum")]
+    pub fn checksum(
+        mut self,
+        algorithm: Algorithm,
+    ) -> Self {
+        self.checksum = Some(algorithm);
+        self
+    }
 
-                p
-            },
-            | None => {
-                return Err(ioa::Error::new(
-                    ioa::ErrorKind::InvalidInput,
-                    "in_file is not set",
-                ))
-            },
-        };
+    /// Set the algorithm of the checksum computed for the original file.
+    ///
+    /// By default, no checksum is computed. When set, the checksum is
+    /// computed on a worker thread while splitting, without reading the file
+    /// again, returned in the [`SplitResult::file_hash`] and recorded in the
+    /// manifest.
+    #[cfg(feature = "checksum")]
+    pub fn file_hash(
+        mut self,
+        algorithm: Algorithm,
+    ) -> Self {
+        self.file_hash = Some(algorithm);
+        self
+    }
 
-        let out_dir: &Path = match self.out_dir {
-            | Some(ref p) => {
-                let p: &Path = p.as_ref();
+    /// Set whether to record the root of the [`MerkleTree`] over the chunks
+    /// in the manifest.
+    ///
+    /// By default, it is `false`. When enabled, the SHA-256 checksums of the
+    /// chunks are computed in addition to the [`checksum`](Self::checksum),
+    /// so a single chunk can be verified against the root.
+    #[cfg(feature = "checksum")]
+    pub fn merkle(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.merkle = enabled;
+        self
+    }
 
-                // if out_dir not exists
-                if !p.exists() {
-                    fsa::create_dir_all(&p).await?;
-                } else {
-                    // if out_dir not a directory
-                    if p.is_file() {
-                        return Err(ioa::Error::new(
-                            ioa::ErrorKind::InvalidInput,
-                            "out_dir is not a directory",
-                        ));
-                    }
-                }
+    /// Set whether to write the SHA-256 checksums of the chunk files to the
+    /// output directory, so they can be verified with `sha256sum -c`.
+    ///
+    /// By default, it is `false`. When enabled, the checksums are written as
+    /// [`SHA256SUMS_FILE_NAME`] with one line per chunk file. Without headers
+    /// the checksums are computed while splitting, otherwise the chunk files
+    /// are read again once their headers are final.
+    #[cfg(feature = "checksum")]
+    pub fn sha256sums(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.sha256sums = enabled;
+        self
+    }
 
-                p
-            },
-            | None => {
-                return Err(ioa::Error::new(
-                    ioa::ErrorKind::InvalidInput,
-                    "out_dir is not set",
-                ))
-            },
-        };
+    /// Set whether to write the bao outboard of each chunk next to it, so
+    /// the chunks can be verified as they stream when merging with
+    /// [`Merge::bao`].
+    ///
+    /// By default, it is `false`. When enabled, the outboard is written to
+    /// the [`outboard_path`] of the chunk, and the BLAKE3 checksum of the
+    /// chunk is recorded in the manifest as with the
+    /// [`checksum`](Self::checksum).
+    ///
+    /// [`Merge::bao`]: crate::merge::Merge::bao
+    #[cfg(feature = "bao")]
+    pub fn bao(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.bao = enabled;
+        self
+    }
 
-        let chunk_size: usize = self.chunk_size;
+    /// Set whether to write the manifest to the output directory.
+    ///
+    /// By default, the manifest is not written. When enabled, the manifest
+    /// is written as [`MANIFEST_FILE_NAME`] in the output directory.
+    ///
+    /// [`MANIFEST_FILE_NAME`]: crate::config::MANIFEST_FILE_NAME
+    pub fn manifest(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.manifest = enabled;
+        self
+    }
 
-        let buffer_capacity: usize = chunk_size.min(self.cap_max);
+    /// Set the format of the manifests written to the output directory and
+    /// the volume directories.
+    ///
+    /// By default, the manifests are written in [`ManifestFormat::Json`].
+    /// The manifests in any format are found by the check and merge
+    /// processes.
+    pub fn manifest_format(
+        mut self,
+        format: ManifestFormat,
+    ) -> Self {
+        self.manifest_format = format;
+        self
+    }
 
-        let input: fsa::File =
-            fsa::OpenOptions::new().read(true).open(in_file).await?;
+    /// Set the maximum total number of chunks.
+    ///
+    /// By default, the maximum follows the [`TOTAL_CHUNKS_MAX_DEFAULT`].
+    /// The split process fails before writing any chunk if the `chunk_size`
+    /// would produce more chunks than the maximum, which prevents creating
+    /// directories with pathological number of files by accident.
+    pub fn max_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.max_chunks = chunks;
+        self
+    }
 
-        let file_size: usize = input.metadata().await?.len() as usize;
+    /// Set whether to check the space available for the chunks before
+    /// writing.
+    ///
+    /// By default, it is `false`. When enabled, the split process fails with
+    /// [`ioa::ErrorKind::StorageFull`] before writing any chunk if the file
+    /// system of the output directory has less space available than the
+    /// size of the file and the headers, instead of leaving a partially
+    /// written chunk set. The chunks kept by a [resume](Self::resume) are
+    /// not counted as available. The check passes where the space is
+    /// unknown, e.g. on systems other than Linux.
+    pub fn require_free_space(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.require_free_space = enabled;
+        self
+    }
 
-        let mut reader: ioa::BufReader<fsa::File> =
-            ioa::BufReader::with_capacity(buffer_capacity, input);
+    /// Set whether to pad the names of the chunks with zeros.
+    ///
+    /// By default, the names are not padded, e.g. `7`. When enabled, the
+    /// names are padded to the width of the last index, e.g. `007` out of
+    /// `120` chunks, so they are sorted in order by name. The check process
+    /// relies on the manifest to find the padded names.
+    pub fn zero_padding(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.zero_padding = enabled;
+        self
+    }
 
-        let mut buffer: Vec<u8> = vec![0; chunk_size];
+    /// Set the scheme of the names of the chunks.
+    ///
+    /// By default, the names are the indices of the chunks, e.g. `0`. With
+    /// [`ChunkNaming::coreutils`], the names follow `split(1)`, e.g. `xaa`,
+    /// so the chunks can be merged with `cat x* > file`. With
+    /// [`ChunkNaming::archive`], the names keep the name of the original
+    /// file, e.g. `file.7z.001`, as multi-part archives. The split process
+    /// fails before writing any chunk if the naming cannot name all chunks
+    /// in order, or if it is not [valid](ChunkNaming::validate).
+    pub fn naming(
+        mut self,
+        naming: ChunkNaming,
+    ) -> Self {
+        self.naming = naming;
+        self
+    }
 
-        let mut total_chunks: usize = 0;
+    /// Set the maximum size of each volume.
+    ///
+    /// By default, the chunks are not grouped into volumes. When set, the
+    /// chunks are grouped into volumes of at most `size` bytes, e.g. for
+    /// removable media, and each volume is written to its own directory
+    /// such as `vol0` with its own manifest. The merge process reads the
+    /// chunks across the volumes from the output directory.
+    pub fn volume_size(
+        mut self,
+        size: usize,
+    ) -> Self {
+        self.volume_size = Some(size);
+        self
+    }
 
-        let mut current: usize = 0;
+    /// Set whether to prepend a [`ChunkHeader`] to each chunk.
+    ///
+    /// By default, the chunks come without headers. When enabled, each chunk
+    /// starts with a header of [`CHUNK_HEADER_SIZE`] bytes identifying the
+    /// chunk and its split, so the chunk can be identified even
+    /// without the directory. The merge process strips the headers. As with
+    /// a transform, use the [`Manifest::stored_size`] as the `file_size` in
+    /// the check process.
+    pub fn headers(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.headers = enabled;
+        self
+    }
 
-        loop {
-            let read: usize = reader.read(&mut buffer[current..]).await?;
+    /// Set whether to record the metadata of the original file in the
+    /// manifest, i.e. its permissions, modification time and attributes on
+    /// Windows, e.g. hidden, so they can be restored by
+    /// [`Merge::restore_metadata`].
+    ///
+    /// By default, it is `false`.
+    ///
+    /// [`Merge::restore_metadata`]: crate::merge::Merge::restore_metadata
+    pub fn preserve_metadata(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.preserve_metadata = enabled;
+        self
+    }
 
-            if read == 0 {
-                if current > 0 {
-                    // write the remaining data
-                    let output_path: PathBuf =
-                        out_dir.join(total_chunks.to_string());
+    /// Set whether to record the extended attributes of the original file
+    /// in the `user` namespace along with its metadata.
+    ///
+    /// By default, it is `false`. When enabled, the metadata is recorded as
+    /// with [`Split::preserve_metadata`]. The extended attributes are only
+    /// supported on Linux, and the split process fails with
+    /// [`ioa::ErrorKind::Unsupported`] otherwise.
+    pub fn preserve_xattrs(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.preserve_xattrs = enabled;
+        self
+    }
 
-                    let output: fsa::File = fsa::OpenOptions::new()
-                        .create(true)
-                        .truncate(true)
-                        .write(true)
-                        .open(output_path)
-                        .await?;
+    /// Set whether to record the alternate data streams of the original file
+    /// on NTFS along with its metadata, e.g. for backups.
+    ///
+    /// By default, it is `false`. When enabled, the metadata is recorded as
+    /// with [`Split::preserve_metadata`]. The streams are recorded in the
+    /// manifest, so they should be small, e.g. `Zone.Identifier`. The
+    /// alternate data streams are only supported on Windows, and the split
+    /// process fails with [`ioa::ErrorKind::Unsupported`] otherwise.
+    pub fn preserve_streams(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.preserve_streams = enabled;
+        self
+    }
 
-                    let mut writer: ioa::BufWriter<fsa::File> =
-                        ioa::BufWriter::with_capacity(buffer_capacity, output);
+    /// Set whether to sniff the MIME type of the original file from its
+    /// signature and record it in the manifest, see [`sniff`].
+    ///
+    /// By default, it is `false`. The name of the original file is always
+    /// recorded, so the receiver of the chunks can restore the file with
+    /// [`Merge::out_dir`].
+    ///
+    /// [`sniff`]: crate::mime::sniff
+    /// [`Merge::out_dir`]: crate::merge::Merge::out_dir
+    pub fn sniff_mime(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.sniff_mime = enabled;
+        self
+    }
 
-                    writer.write_all(&buffer[..current]).await?;
+    /// Set the maximum size of the buffer capacity.
+    ///
+    /// By default, the buffer capacity is based on the `chunk_size`.
+    /// The buffer capacity is limited and will not exceed
+    /// [`BUFFER_CAPACITY_MAX`]. The default value is recommended unless
+    /// a large size file will be processed through the split process.
+    pub fn max_buffer_capacity(
+        mut self,
+        capacity: usize,
+    ) -> Self {
+        self.cap_max = capacity;
+        self
+    }
 
-                    writer.flush().await?;
+    /// Set the number of worker threads writing the chunks at the same time,
+    /// each reading the byte range of its chunk from its own handle of the
+    /// input, e.g. to use the bandwidth of an NVMe drive.
+    ///
+    /// By default, it is `1`. The chunks are still written one by one in
+    /// order by a single reader with a [`mode`](Self::mode) other than
+    /// [`SplitMode::Fixed`], [`in_files`](Self::in_files), a
+    /// [`volume_size`](Self::volume_size) or a
+    /// [`file_hash`](Self::file_hash), which need the bytes in order. Each
+    /// worker copies its chunk through its own buffer, or keeps a whole chunk
+    /// in memory if the chunks are held in full, see the
+    /// [crate documentation](crate#memory).
+    pub fn concurrency(
+        mut self,
+        workers: usize,
+    ) -> Self {
+        self.concurrency = workers.max(1);
+        self
+    }
 
-                    total_chunks += 1;
-                }
+    /// Set whether to read the input bypassing the page cache with
+    /// `O_DIRECT`, e.g. to split a disk image from a block device without
+    /// evicting the cache of the system.
+    ///
+    /// By default, it is `false`. When enabled, the chunks are read by the
+    /// workers of the [`concurrency`](Self::concurrency), so the options
+    /// that need the bytes in order are rejected, and the `chunk_size` must
+    /// be a multiple of the [`DIRECT_IO_ALIGNMENT`]. It is only supported on
+    /// Linux.
+    pub fn direct_io(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.direct_io = enabled;
+        self
+    }
 
-                break;
-            }
+    /// Set whether to cap the buffer capacity by the memory available to the
+    /// process, e.g. the limit of the container.
+    ///
+    /// By default, it is `false`. When enabled, the buffer capacity does not
+    /// exceed the [`memory_budget`](crate::tune::memory_budget).
+    pub fn auto_tune(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.auto_tune = enabled;
+        self
+    }
 
-            current += read;
+    /// Set the budget of the memory shared with other processes.
+    ///
+    /// By default, the memory is not limited. When set, the buffers of the
+    /// streamed chunks are capped to fit the budget and reserved from it
+    /// before running, while the process fails with
+    /// [`ioa::ErrorKind::InvalidInput`] if the chunks held in full exceed the
+    /// whole budget.
+    pub fn memory_budget(
+        mut self,
+        budget: MemoryBudget,
+    ) -> Self {
+        self.budget = Some(budget);
+        self
+    }
 
-            if current >= chunk_size {
-                // write chunk
-                let output_path: PathBuf =
-                    out_dir.join(total_chunks.to_string());
+    /// Set whether to collect the time spent in each phase of the process.
+    ///
+    /// By default, it is `false`. When enabled, the [`Profile`] is returned
+    /// in the [`SplitResult`] and emitted via `tracing` with the `tracing`
+    /// feature.
+    pub fn profile(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.profile = enabled;
+        self
+    }
+
+    /// Set the control shared with the owner of the process.
+    pub(crate) fn control(
+        mut self,
+        control: Arc<Control>,
+    ) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    /// Set the handle to pause, resume or cancel the process, and to get
+    /// its progress in bytes.
+    pub fn handle(
+        self,
+        handle: OperationHandle,
+    ) -> Self {
+        self.control(handle.control)
+    }
+
+    /// Set the source of the random identity of the split process.
+    ///
+    /// By default, it is the [`SystemRng`](crate::rng::SystemRng). With a
+    /// [`SeededRng`](crate::rng::SeededRng), the same seed gives the same
+    /// identity, e.g. to reproduce the chunks with headers byte for byte.
+    pub fn rng(
+        mut self,
+        rng: Arc<dyn Rng>,
+    ) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+
+    /// Set the bus to emit the lifecycle events to.
+    pub fn events(
+        mut self,
+        events: EventBus,
+    ) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Set the sink to pass the progress to, which receives every event
+    /// in order, unlike the subscribers of the [`events`](Self::events).
+    pub fn progress(
+        mut self,
+        sink: Arc<dyn ProgressSink>,
+    ) -> Self {
+        self.progress = Some(sink);
+        self
+    }
+
+    /// Set the callback to report the [`SplitProgress`] to, e.g. to drive
+    /// a progress bar.
+    ///
+    /// The callback is called on the task running the process when it
+    /// starts and finishes, and in between at most once per `interval`,
+    /// which follows the [`PROGRESS_INTERVAL_DEFAULT`] by default. It
+    /// should return quickly.
+    ///
+    /// [`PROGRESS_INTERVAL_DEFAULT`]: crate::config::PROGRESS_INTERVAL_DEFAULT
+    pub fn on_progress<F: Fn(SplitProgress) + Send + Sync + 'static>(
+        mut self,
+        f: F,
+    ) -> Self {
+        self.on_progress = Some(OnProgress::new(f));
+        self
+    }
+
+    /// Set the minimum interval between the calls of the callback set by
+    /// [`on_progress`](Self::on_progress).
+    pub fn progress_interval(
+        mut self,
+        interval: Duration,
+    ) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Set the log to record the inputs and results of the process to.
+    ///
+    /// By default, the process is not recorded. The process fails if the
+    /// record cannot be written.
+    pub fn log(
+        mut self,
+        log: OperationLog,
+    ) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    fn emit(
+        &self,
+        event: Event,
+    ) {
+        if let Some(progress) = &self.progress {
+            progress.on_event(&event);
+        }
+
+        if let Some(events) = &self.events {
+            events.emit(event);
+        }
+    }
+
+    /// Get the number of workers writing the chunks at the same time, or
+    /// `None` if they are written in order by a single reader.
+    fn parallel_workers(&self) -> Option<usize> {
+        let sequential: bool = !matches!(self.mode, SplitMode::Fixed)
+            || !self.in_files.is_empty()
+            || self.volume_size.is_some();
+
+        #[cfg(feature = "checksum")]
+        let sequential: bool = sequential || self.file_hash.is_some();
+
+        ((self.concurrency > 1 || self.direct_io) && !sequential)
+            .then_some(self.concurrency)
+    }
+
+    /// Get the width of the names padded with zeros for the minimum of
+    /// `min_chunks`, which is the width of the chunks written by the previous
+    /// run when resumed, as widened if it produced more chunks.
+    async fn previous_padding(
+        &self,
+        out_dir: &Path,
+        min_chunks: usize,
+    ) -> ioa::Result<usize> {
+        let padding: usize = padding_width(min_chunks);
+
+        if !self.resume || !self.naming.is_index() {
+            return Ok(padding);
+        }
+
+        // the widest name of the first chunk found on the disk
+        for width in (padding + 1..=padding_width(self.max_chunks)).rev() {
+            let path: PathBuf = chunk_path(
+                out_dir,
+                self.volume_size.map(|_| 0),
+                self.naming.name(0, width),
+            );
+
+            if fsa::try_exists(path).await? {
+                return Ok(width);
+            }
+        }
+
+        Ok(padding)
+    }
+
+    /// Get the identity of the previous run from the header of its first
+    /// chunk, or `None` if not found.
+    async fn previous_id(
+        &self,
+        out_dir: &Path,
+        padding: usize,
+    ) -> ioa::Result<Option<Uuid>> {
+        let path: PathBuf = chunk_path(
+            out_dir,
+            self.volume_size.map(|_| 0),
+            self.naming.name(0, padding),
+        );
+
+        match ChunkHeader::read(path).await {
+            | Ok(header) => Ok(header.map(|header| header.split_id)),
+            | Err(e) if e.kind() == ioa::ErrorKind::NotFound => Ok(None),
+            | Err(e) => Err(e),
+        }
+    }
+
+    /// Check whether the chunk written by a previous run at `path` is kept
+    /// by the resume, with the `data` and the header expected, reading the
+    /// previous chunk through a buffer of `buffer_capacity` bytes.
+    #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+    async fn resumable(
+        &self,
+        path: &Path,
+        header: Option<&ChunkHeader>,
+        data: &[u8],
+        buffer_capacity: usize,
+    ) -> ioa::Result<bool> {
+        let stored_size: usize =
+            data.len() + header.map_or(0, |_| CHUNK_HEADER_SIZE);
+
+        match fsa::metadata(path).await {
+            | Ok(metadata)
+                if metadata.is_file()
+                    && metadata.len() as usize == stored_size => {},
+            | Ok(_) => return Ok(false),
+            | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                return Ok(false)
+            },
+            | Err(e) => return Err(e),
+        }
+
+        if let Some(header) = header {
+            match ChunkHeader::read(path).await? {
+                | Some(previous)
+                    if previous.index == header.index
+                        && previous.split_id == header.split_id => {},
+                | _ => return Ok(false),
+            }
+        }
+
+        #[cfg(feature = "checksum")]
+        if let Some(algorithm) = self.checksum {
+            let skip: usize = header.map_or(0, |_| CHUNK_HEADER_SIZE);
+
+            let previous: String = crate::checksum::range_digest_with(
+                path.to_path_buf(),
+                skip..stored_size,
+                algorithm,
+                buffer_capacity,
+            )
+            .await?;
+
+            // the chunk is hashed in place instead of copied
+            return Ok(algorithm.digest(data) == previous);
+        }
+
+        Ok(true)
+    }
+
+    /// Get the volume of the next chunk of `stored_size` bytes with the size
+    /// of the volume so far, moving to the next volume when the current one
+    /// is full, or `None` if not grouped into volumes.
+    fn next_volume(
+        &self,
+        volume: Option<(usize, usize)>,
+        stored_size: usize,
+    ) -> Option<(usize, usize)> {
+        let volume_size: usize = self.volume_size?;
+
+        Some(match volume {
+            | Some((v, used))
+                if used == 0 || used + stored_size <= volume_size =>
+            {
+                (v, used + stored_size)
+            },
+            | Some((v, _)) => (v + 1, stored_size),
+            | None => (0, stored_size),
+        })
+    }
+
+    /// Check whether the chunks are copied through the buffer in pieces
+    /// instead of being held in full, which requires the chunks to be
+    /// written as they are read.
+    fn streams(&self) -> bool {
+        let streams: bool = self.transform.is_none()
+            && matches!(self.mode, SplitMode::Fixed)
+            && !self.resume
+            && !self.direct_io;
+
+        #[cfg(feature = "bao")]
+        let streams: bool = streams && !self.bao;
+
+        streams
+    }
+
+    /// Get the number of buffers held at a time when the chunks are
+    /// streamed, which are the piece copied by each worker, and the pieces
+    /// being hashed on worker threads with the next ones when sequential.
+    fn streamed_buffers(
+        &self,
+        writers: Option<usize>,
+    ) -> usize {
+        if let Some(writers) = writers {
+            return writers;
+        }
+
+        #[cfg(feature = "checksum")]
+        {
+            let hashes: bool =
+                self.checksum.is_some() || self.merkle || self.sha256sums;
+
+            1 + 2 * (hashes as usize + self.file_hash.is_some() as usize)
+        }
+
+        #[cfg(not(feature = "checksum"))]
+        1
+    }
+
+    /// Get the memory of the split process.
+    fn memory(
+        &self,
+        chunk_size: usize,
+        buffer_capacity: usize,
+        writers: Option<usize>,
+    ) -> usize {
+        match self.streams() {
+            | true => buffer_capacity * self.streamed_buffers(writers),
+            // buffers of the chunk, its encoding, the reader and the writer
+            // of each worker, the previous chunk being hashed by the resume,
+            // and the chunks being hashed
+            | false => {
+                #[cfg(feature = "checksum")]
+                let resumed: usize =
+                    (self.resume && self.checksum.is_some()) as usize;
+
+                #[cfg(not(feature = "checksum"))]
+                let resumed: usize = 0;
+
+                (chunk_size * (1 + self.transform.is_some() as usize)
+                    + buffer_capacity * (2 + resumed))
+                    * writers.unwrap_or(1)
+                    + self.checksum_memory(chunk_size)
+            },
+        }
+    }
+
+    /// Get the memory of the chunks kept by the checksum workers.
+    fn checksum_memory(
+        &self,
+        #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+        chunk_size: usize,
+    ) -> usize {
+        #[cfg(feature = "checksum")]
+        {
+            let workers: usize =
+                if self.checksum.is_some() || self.merkle || self.sha256sums {
+                    CHECKSUM_WORKERS_DEFAULT
+                } else {
+                    0
+                };
+
+            #[cfg(feature = "bao")]
+            let workers: usize = workers + self.bao as usize;
+
+            chunk_size * (workers + self.file_hash.is_some() as usize)
+        }
+
+        #[cfg(not(feature = "checksum"))]
+        0
+    }
+
+    /// Write the SHA-256 checksums of the chunk files in the manifest.
+    #[cfg(feature = "checksum")]
+    async fn write_sha256sums(
+        &self,
+        out_dir: &Path,
+        manifest: &Manifest,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<()> {
+        let store: DirStore = DirStore::with_manifest(out_dir, manifest);
+
+        let mut sums: Vec<(String, String)> =
+            Vec::with_capacity(manifest.chunks.len());
+
+        for chunk in &manifest.chunks {
+            let path: PathBuf = store.path(chunk.index);
+
+            let digest: String = match chunk
+                .get_metadata(Algorithm::Sha256.id())
+                .filter(|_| !self.headers)
+            {
+                | Some(digest) => digest.to_string(),
+                | None => {
+                    let started: Option<Instant> = profiler.start();
+
+                    let digest: String = file_sha256(path.clone()).await?;
+
+                    profiler.record(Phase::Hash, started);
+
+                    digest
+                },
+            };
+
+            // relative to the output directory, e.g. `vol0/3`
+            let relative: String = path
+                .strip_prefix(out_dir)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            sums.push((digest, relative));
+        }
+
+        fsa::write(out_dir.join(SHA256SUMS_FILE_NAME), format_sha256sums(&sums))
+            .await
+    }
+
+    /// Get the root of the Merkle tree over the chunks in hex, if enabled.
+    fn merkle_root(
+        &self,
+        #[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+        chunks: &[ChunkInfo],
+    ) -> Option<String> {
+        #[cfg(feature = "checksum")]
+        if self.merkle {
+            return MerkleTree::from_hex(chunks.iter().map(|c| {
+                c.get_metadata(Algorithm::Sha256.id()).unwrap_or_default()
+            }))
+            .and_then(|tree| tree.root_hex());
+        }
+
+        None
+    }
+
+    /// Check whether the metadata of the original file is recorded.
+    fn preserves_metadata(&self) -> bool {
+        self.preserve_metadata || self.preserve_xattrs || self.preserve_streams
+    }
+
+    /// Reserve `bytes` from the memory budget if any, failing if they exceed
+    /// the whole budget, e.g. as the chunks are held in full.
+    async fn reserve(
+        &self,
+        bytes: usize,
+    ) -> ioa::Result<Option<OwnedSemaphorePermit>> {
+        match &self.budget {
+            | Some(budget) if bytes > budget.total() => Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "chunk_size exceeds the memory budget",
+            )),
+            | Some(budget) => Ok(Some(budget.reserve(bytes).await?)),
+            | None => Ok(None),
+        }
+    }
+
+    /// Plan the split process of the input file without writing any chunk,
+    /// e.g. to display an upload plan or to create the records of the chunks
+    /// on a server first.
+    ///
+    /// The plan follows the same rules as [`Split::run`], and fails if the
+    /// `chunk_size` would produce more chunks than allowed. The input is
+    /// read to find the boundaries unless splitted in [`SplitMode::Fixed`],
+    /// and the chunks are transformed to find their volumes if both are set.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::split::{Split, SplitPlan};
+    ///
+    /// async fn example() {
+    ///     let plan: SplitPlan = Split::new()
+    ///         .in_file("/path/to/file")
+    ///         .chunk_size(1024 * 1024 * 1024)
+    ///         .plan()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     for chunk in &plan.chunks {
+    ///         println!("{}: {:?}", chunk.path.display(), chunk.range);
+    ///     }
+    /// }
+    /// ```
+    pub async fn plan(&self) -> ioa::Result<SplitPlan> {
+        self.naming.validate()?;
+
+        let inputs: Vec<PathBuf> = match self.in_files.is_empty() {
+            | true => vec![self.input()?.to_path_buf()],
+            | false => self.in_files.clone(),
+        };
+
+        let file_size: usize = match self.in_files.is_empty() {
+            | true => input_size(&inputs[0]).await?,
+            | false => {
+                self.sources().await?.last().map_or(0, |s| s.offset + s.size)
+            },
+        };
+
+        let mut plan: SplitPlan = self.plan_for(file_size)?;
+
+        // the size of each chunk and its size when stored
+        let sizes: Vec<(usize, usize)> = match self.mode {
+            | SplitMode::Fixed
+                if self.transform.is_none() || self.volume_size.is_none() =>
+            {
+                (0..plan.total_chunks)
+                    .map(|index| {
+                        let offset: usize = index * plan.chunk_size;
+                        let size: usize =
+                            plan.chunk_size.min(file_size - offset);
+
+                        (size, size)
+                    })
+                    .collect()
+            },
+            | _ => self.scan(inputs, file_size, plan.chunk_size).await?,
+        };
+
+        let total_chunks: usize = sizes.len();
+
+        if total_chunks > self.max_chunks {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "total_chunks exceeds max_chunks",
+            ));
+        }
+
+        if total_chunks > self.naming.capacity().unwrap_or(usize::MAX) {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "total_chunks exceeds the capacity of naming",
+            ));
+        }
+
+        // the padding is widened if more chunks than the minimum
+        let padding: usize = match self.zero_padding {
+            | true if self.naming.is_index() => padding_width(total_chunks),
+            | true => padding_width(plan.total_chunks),
+            | false => 0,
+        };
+
+        let header_size: usize =
+            if self.headers { CHUNK_HEADER_SIZE } else { 0 };
+
+        let mut offset: usize = 0;
+
+        // current volume and its size
+        let mut volume: Option<(usize, usize)> = None;
+
+        for (index, (size, stored_size)) in sizes.into_iter().enumerate() {
+            let stored_size: usize = stored_size + header_size;
+
+            volume = self.next_volume(volume, stored_size);
+
+            let volume: Option<usize> = volume.map(|(v, _)| v);
+
+            plan.chunks.push(ChunkPlan {
+                index,
+                range: offset..offset + size,
+                volume,
+                path: chunk_path(
+                    Path::new(""),
+                    volume,
+                    self.naming.name(index, padding),
+                ),
+            });
+
+            offset += size;
+        }
+
+        plan.total_chunks = total_chunks;
+
+        Ok(plan)
+    }
+
+    /// Read the inputs in order to get the size of each chunk and its size
+    /// when transformed.
+    async fn scan(
+        &self,
+        inputs: Vec<PathBuf>,
+        file_size: usize,
+        chunk_size: usize,
+    ) -> ioa::Result<Vec<(usize, usize)>> {
+        let policy: Arc<dyn BoundaryPolicy> = self.mode.policy();
+
+        let mut pending: VecDeque<PathBuf> = inputs.into();
+
+        let mut reader: Option<fsa::File> = None;
+
+        let mut buffer: Vec<u8> = Vec::new();
+
+        let mut sizes: Vec<(usize, usize)> = Vec::new();
+
+        let mut offset: usize = 0;
+        let mut current: usize = 0;
+
+        while offset < file_size {
+            // the buffer holds the bytes of the file from the offset
+            buffer.resize(chunk_size.min(file_size - offset), 0);
+
+            while current < buffer.len() {
+                let read: usize = match &mut reader {
+                    | Some(reader) => {
+                        reader.read(&mut buffer[current..]).await?
+                    },
+                    | None => 0,
+                };
+
+                if read > 0 {
+                    current += read;
+                    continue;
+                }
+
+                // continue with the next file of a bundle
+                match pending.pop_front() {
+                    | Some(path) => reader = Some(fsa::File::open(path).await?),
+                    | None => {
+                        return Err(ioa::Error::new(
+                            ioa::ErrorKind::UnexpectedEof,
+                            "in_file is shorter than expected",
+                        ))
+                    },
+                }
+            }
+
+            let end: usize =
+                policy.boundary(offset, &buffer[..current]).clamp(1, current);
+
+            let stored_size: usize = match &self.transform {
+                | Some(transform) => {
+                    transform.encode(sizes.len(), buffer[..end].to_vec())?.len()
+                },
+                | None => end,
+            };
+
+            sizes.push((end, stored_size));
+
+            // keep the rest for the next chunk
+            buffer.copy_within(end..current, 0);
+
+            offset += end;
+            current -= end;
+        }
+
+        Ok(sizes)
+    }
+
+    /// Get the validated path of the input file.
+    fn input(&self) -> ioa::Result<&Path> {
+        let p: &Path = match self.in_file {
+            | Some(ref p) => p.as_ref(),
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "in_file is not set",
+                ))
+            },
+        };
+
+        // if in_file not exists
+        if !p.exists() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                "in_file path not found",
+            ));
+        }
+
+        // if in_file not a file
+        if !p.is_file() && !is_block_device(p) {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "in_file is not a path to file",
+            ));
+        }
+
+        Ok(p)
+    }
+
+    /// Get the validated sources of the input files, if any.
+    async fn sources(&self) -> ioa::Result<Vec<SourceInfo>> {
+        let mut sources: Vec<SourceInfo> = Vec::new();
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        let mut offset: usize = 0;
+
+        for path in &self.in_files {
+            let metadata: std::fs::Metadata = match fsa::metadata(path).await {
+                | Ok(metadata) => metadata,
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::NotFound,
+                        format!("in_file path not found: {}", path.display()),
+                    ))
+                },
+                | Err(e) => return Err(e),
+            };
+
+            if !metadata.is_file() {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    format!(
+                        "in_file is not a path to file: {}",
+                        path.display()
+                    ),
+                ));
+            }
+
+            let name: String = match path.file_name() {
+                | Some(name) => name.to_string_lossy().into_owned(),
+                | None => {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        format!("in_file has no name: {}", path.display()),
+                    ))
+                },
+            };
+
+            if !names.insert(name.clone()) {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    format!("name of in_files is not unique: {}", name),
+                ));
+            }
+
+            let size: usize = metadata.len() as usize;
+
+            sources.push(SourceInfo { name, offset, size });
+
+            offset += size;
+        }
+
+        Ok(sources)
+    }
+
+    /// Plan the split process of a file of `file_size` bytes.
+    fn plan_for(
+        &self,
+        file_size: usize,
+    ) -> ioa::Result<SplitPlan> {
+        // a file not larger than chunk_size is a single chunk, so the
+        // buffers never exceed the file
+        let chunk_size: usize = self.chunk_size.min(file_size).max(1);
+
+        let mut buffer_capacity: usize = chunk_size.min(self.cap_max);
+
+        if self.auto_tune {
+            if let Some(budget) = memory_budget() {
+                buffer_capacity = buffer_capacity.min(budget);
+            }
+        }
+
+        // the streamed chunks fit the budget by their buffers
+        if let Some(budget) = self.budget.as_ref().filter(|_| self.streams()) {
+            let buffers: usize = self.streamed_buffers(self.parallel_workers());
+
+            buffer_capacity =
+                buffer_capacity.min(budget.total() / buffers).max(1);
+        }
+
+        // every chunk has at most chunk_size bytes
+        let total_chunks: usize = file_size.div_ceil(chunk_size);
+
+        if total_chunks > self.max_chunks {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                format!(
+                    "chunk_size would produce {} chunks, which exceeds max_chunks",
+                    total_chunks
+                ),
+            ));
+        }
+
+        let capacity: usize = self.naming.capacity().unwrap_or(usize::MAX);
+
+        if total_chunks > capacity {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                format!(
+                    "chunk_size would produce {} chunks, which exceeds the capacity of naming",
+                    total_chunks
+                ),
+            ));
+        }
+
+        Ok(SplitPlan {
+            file_size,
+            chunk_size,
+            total_chunks,
+            buffer_capacity,
+            chunks: Vec::new(),
+        })
+    }
+
+    /// Run the split process.
+    ///
+    /// The memory does not depend on the size of the file, nor on the
+    /// `chunk_size` unless the chunks are held in full, as described in the
+    /// [crate documentation](crate#memory).
+    pub async fn run(self) -> ioa::Result<SplitResult> {
+        self.naming.validate()?;
+
+        let started: Instant = Instant::now();
+
+        let sources: Vec<SourceInfo> = self.sources().await?;
+
+        // the first file of a bundle stands for the input
+        let in_file: &Path = match self.in_files.first() {
+            | Some(first) => first,
+            | None => self.input()?,
+        };
+
+        if !sources.is_empty() && self.preserves_metadata() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "metadata cannot be preserved for in_files",
+            ));
+        }
+
+        let id: Uuid = match &self.rng {
+            | Some(rng) => rng.next_uuid(),
+            | None => Uuid::new_v4(),
+        };
+
+        let out_dir: PathBuf = match self.out_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if out_dir not exists
+                if !p.exists() {
+                    fsa::create_dir_all(&p).await?;
+                } else {
+                    // if out_dir not a directory
+                    if p.is_file() {
+                        return Err(ioa::Error::new(
+                            ioa::ErrorKind::InvalidInput,
+                            "out_dir is not a directory",
+                        ));
+                    }
+                }
+
+                match self.scoped {
+                    | true => {
+                        let scoped: PathBuf = p.join(id.to_string());
+
+                        fsa::create_dir_all(&scoped).await?;
+
+                        scoped
+                    },
+                    | false => p.to_path_buf(),
+                }
+            },
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "out_dir is not set",
+                ))
+            },
+        };
+
+        let out_dir: &Path = &out_dir;
+
+        let policy: Arc<dyn BoundaryPolicy> = self.mode.policy();
+
+        let input: fsa::File =
+            fsa::OpenOptions::new().read(true).open(in_file).await?;
+
+        let file_size: usize = match sources.last() {
+            | Some(last) => last.offset + last.size,
+            | None => input_size(in_file).await?,
+        };
+
+        // the files of a bundle after the first, opened as they are reached
+        let mut pending: VecDeque<&Path> =
+            self.in_files.iter().skip(1).map(PathBuf::as_path).collect();
+
+        // metadata of the file when the split process starts
+        let file_metadata: Option<FileMetadata> = match self
+            .preserves_metadata()
+        {
+            | true => {
+                let metadata: FileMetadata =
+                    FileMetadata::read(in_file, self.preserve_xattrs).await?;
+
+                Some(match self.preserve_streams {
+                    | true => metadata.with_streams(in_file).await?,
+                    | false => metadata,
+                })
+            },
+            | false => None,
+        };
+
+        let mime_type: Option<String> =
+            match self.sniff_mime && sources.is_empty() {
+                | true => sniff_file(in_file).await?.map(str::to_string),
+                | false => None,
+            };
+
+        let plan: SplitPlan = self.plan_for(file_size)?;
+
+        let chunk_size: usize = plan.chunk_size;
+
+        let buffer_capacity: usize = plan.buffer_capacity;
+
+        let min_chunks: usize = plan.total_chunks;
+
+        if self.require_free_space {
+            let headers: usize = match self.headers {
+                | true => min_chunks * CHUNK_HEADER_SIZE,
+                | false => 0,
+            };
+
+            require_space(out_dir, file_size + headers)?;
+        }
+
+        let capacity: usize = self.naming.capacity().unwrap_or(usize::MAX);
+
+        let padding: usize = match self.zero_padding {
+            | true => self.previous_padding(out_dir, min_chunks).await?,
+            | false => 0,
+        };
+
+        // the chunks kept by the resume carry the identity of the previous
+        // run in their headers
+        let id: Uuid = match self.resume && self.headers {
+            | true => self.previous_id(out_dir, padding).await?.unwrap_or(id),
+            | false => id,
+        };
+
+        self.emit(Event::SplitStarted { id, file_size });
+
+        let mut reporter: Reporter<SplitProgress> = Reporter::new(
+            self.on_progress.as_ref(),
+            self.progress_interval,
+            SplitProgress {
+                bytes_read: 0,
+                total_bytes: file_size,
+                chunks_written: 0,
+                total_chunks: min_chunks,
+            },
+        );
+
+        reporter.report(true);
+
+        let writers: Option<usize> = self.parallel_workers();
+
+        if self.direct_io && writers.is_none() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "direct_io requires the chunks read at their ranges",
+            ));
+        }
+
+        if self.direct_io && !chunk_size.is_multiple_of(DIRECT_IO_ALIGNMENT) {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "chunk_size is not aligned for direct_io",
+            ));
+        }
+
+        let _reservation: Option<OwnedSemaphorePermit> = self
+            .reserve(self.memory(chunk_size, buffer_capacity, writers))
+            .await?;
+
+        let mut profiler: Profiler = Profiler::new(self.profile);
+
+        #[cfg(feature = "checksum")]
+        let algorithms: Vec<Algorithm> = {
+            let mut algorithms: Vec<Algorithm> =
+                self.checksum.into_iter().collect();
+
+            // the payload is the whole chunk file without headers
+            let sha256: bool =
+                self.merkle || (self.sha256sums && !self.headers);
+
+            if sha256 && !algorithms.contains(&Algorithm::Sha256) {
+                algorithms.push(Algorithm::Sha256);
+            }
+
+            algorithms
+        };
+
+        // the workers writing in parallel hash their own chunks
+        #[cfg(feature = "checksum")]
+        let mut workers: Option<Workers> =
+            (!algorithms.is_empty() && writers.is_none() && !self.streams())
+                .then(|| Workers::new(algorithms.clone()));
+
+        #[cfg(feature = "checksum")]
+        let mut file_hasher: Option<FileHasher> =
+            self.file_hash.map(FileHasher::new);
+
+        let mut total_chunks: usize = 0;
+
+        let mut chunks: Vec<ChunkInfo> = Vec::new();
+
+        let mut resumed: Vec<usize> = Vec::new();
+
+        // the chunks of fixed size are counted and checked by the plan
+        if let Some(writers) = writers {
+            // a worker waits while the results of every worker are queued,
+            // so the chunks written ahead of the events stay bounded
+            let (sender, mut receiver) = mpsc::channel(writers);
+
+            let writer: RangeWriter = RangeWriter {
+                split: Arc::new(self.clone()),
+                in_file: in_file.to_path_buf(),
+                out_dir: out_dir.to_path_buf(),
+                id,
+                file_size,
+                chunk_size,
+                buffer_capacity,
+                total_chunks: min_chunks,
+                padding,
+                #[cfg(feature = "checksum")]
+                algorithms: algorithms.into(),
+                next: Arc::new(AtomicUsize::new(0)),
+                sender,
+                runtime: Handle::current(),
+            };
+
+            let handles: Vec<JoinHandle<Profiler>> = (0..writers)
+                .map(|_| {
+                    let writer: RangeWriter = writer.clone();
+                    let profiler: Profiler = Profiler::new(self.profile);
+
+                    tokio::task::spawn_blocking(move || writer.run(profiler))
+                })
+                .collect();
+
+            let next: Arc<AtomicUsize> = writer.next.clone();
+
+            // the channel closes once every worker is done
+            drop(writer);
+
+            let mut failure: Option<ioa::Error> = None;
+
+            while let Some(written) = receiver.recv().await {
+                let (chunk, kept): (ChunkInfo, bool) = match written {
+                    | Ok(written) if failure.is_none() => written,
+                    | Ok(_) => continue,
+                    | Err(e) => {
+                        // stop the workers, and fail once all are done, so
+                        // none writes a chunk after the process returns
+                        next.store(min_chunks, Ordering::Relaxed);
+                        failure.get_or_insert(e);
+                        continue;
+                    },
+                };
+
+                if kept {
+                    resumed.push(chunk.index);
+                }
+
+                self.emit(Event::ChunkWritten {
+                    index: chunk.index,
+                    size: chunk.size,
+                });
+
+                reporter.progress.bytes_read += chunk.size;
+                reporter.progress.chunks_written += 1;
+                reporter.report(false);
+
+                chunks.push(chunk);
+            }
+
+            for handle in handles {
+                match handle.await {
+                    | Ok(worker) => profiler.merge(worker),
+                    | Err(e) => {
+                        failure.get_or_insert(ioa::Error::other(e));
+                    },
+                }
+            }
+
+            if let Some(e) = failure {
+                return Err(e);
+            }
+
+            chunks.sort_by_key(|chunk| chunk.index);
+            resumed.sort_unstable();
+
+            total_chunks = chunks.len();
+        } else if self.streams() {
+            let mut input: fsa::File = input;
+
+            // the piece of the chunk being copied
+            let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+
+            let mut offset: usize = 0;
+
+            // current volume and its size
+            let mut volume: Option<(usize, usize)> = None;
+
+            while offset < file_size {
+                let size: usize = chunk_size.min(file_size - offset);
+
+                let header: Option<ChunkHeader> =
+                    self.headers.then_some(ChunkHeader {
+                        version: CHUNK_HEADER_VERSION,
+                        index: total_chunks,
+                        total_chunks: min_chunks,
+                        split_id: id,
+                        payload_size: size,
+                    });
+
+                let stored_size: usize =
+                    size + header.map_or(0, |_| CHUNK_HEADER_SIZE);
+
+                volume = self.next_volume(volume, stored_size);
+
+                let volume_index: Option<usize> = volume.map(|(v, _)| v);
+
+                if let Some(v) = volume_index {
+                    fsa::create_dir_all(volume_dir(out_dir, v)).await?;
+                }
+
+                let mut output: fsa::File = fsa::OpenOptions::new()
+                    .create(true)
+                    .truncate(true)
+                    .write(true)
+                    .open(chunk_path(
+                        out_dir,
+                        volume_index,
+                        self.naming.name(total_chunks, padding),
+                    ))
+                    .await?;
+
+                if let Some(header) = &header {
+                    output.write_all(&header.encode()).await?;
+                }
+
+                #[cfg(feature = "checksum")]
+                let mut hasher: Option<FileHasher> = (!algorithms.is_empty())
+                    .then(|| FileHasher::with_algorithms(&algorithms));
+
+                let mut copied: usize = 0;
+
+                while copied < size {
+                    let piece: &mut [u8] =
+                        &mut buffer[..buffer_capacity.min(size - copied)];
+
+                    let started: Option<Instant> = profiler.start();
+
+                    let mut read: usize = input.read(piece).await?;
+
+                    // continue with the next file of a bundle
+                    while read == 0 {
+                        let path: &Path = match pending.pop_front() {
+                            | Some(path) => path,
+                            | None => {
+                                return Err(ioa::Error::new(
+                                    ioa::ErrorKind::UnexpectedEof,
+                                    "in_file is shorter than its size",
+                                ))
+                            },
+                        };
+
+                        input = fsa::OpenOptions::new()
+                            .read(true)
+                            .open(path)
+                            .await?;
+
+                        read = input.read(piece).await?;
+                    }
+
+                    profiler.record(Phase::Read, started);
 
-                let output: fsa::File = fsa::OpenOptions::new()
-                    .create(true)
-                    .truncate(true)
-                    .write(true)
-                    .open(output_path)
-                    .await?;
+                    let piece: &[u8] = &piece[..read];
+
+                    // hash the original file and the chunk on worker threads
+                    #[cfg(feature = "checksum")]
+                    if let Some(file_hasher) = &mut file_hasher {
+                        file_hasher
+                            .update(piece.to_vec(), &mut profiler)
+                            .await?;
+                    }
+
+                    #[cfg(feature = "checksum")]
+                    if let Some(hasher) = &mut hasher {
+                        hasher.update(piece.to_vec(), &mut profiler).await?;
+                    }
+
+                    let started: Option<Instant> = profiler.start();
+
+                    output.write_all(piece).await?;
+
+                    profiler.record(Phase::Write, started);
+
+                    copied += read;
+
+                    reporter.progress.bytes_read += read;
+                    reporter.report(false);
+                }
+
+                let started: Option<Instant> = profiler.start();
+
+                output.flush().await?;
+
+                profiler.record(Phase::Write, started);
+
+                #[cfg_attr(not(feature = "checksum"), allow(unused_mut))]
+                let mut chunk: ChunkInfo = ChunkInfo {
+                    index: total_chunks,
+                    offset,
+                    size,
+                    stored_size,
+                    volume: volume_index,
+                    metadata: policy.metadata(offset..offset + size),
+                };
+
+                #[cfg(feature = "checksum")]
+                if let Some(hasher) = hasher {
+                    let digests: Vec<String> =
+                        hasher.finish_all(&mut profiler).await?;
+
+                    for (algorithm, digest) in algorithms.iter().zip(digests) {
+                        chunk.set_metadata(algorithm.id(), digest);
+                    }
+                }
+
+                chunks.push(chunk);
+
+                self.emit(Event::ChunkWritten { index: total_chunks, size });
+
+                total_chunks += 1;
+
+                reporter.progress.chunks_written = total_chunks;
+                reporter.report(false);
+
+                offset += size;
+
+                if let Some(control) = &self.control {
+                    control.advance(size).await?;
+                }
+            }
+        } else {
+            let mut reader: ioa::BufReader<fsa::File> =
+                ioa::BufReader::with_capacity(buffer_capacity, input);
+
+            // allocated as the bytes are read, up to the remaining bytes
+            let mut buffer: Vec<u8> = Vec::new();
+
+            let mut current: usize = 0;
+
+            let mut offset: usize = 0;
+
+            // current volume and its size
+            let mut volume: Option<(usize, usize)> = None;
+
+            loop {
+                // the buffer holds the bytes of the file from the offset
+                buffer.resize(
+                    chunk_size.min(file_size.saturating_sub(offset)),
+                    0,
+                );
+
+                let started: Option<Instant> = profiler.start();
+
+                let mut read: usize =
+                    reader.read(&mut buffer[current..]).await?;
+
+                // continue with the next file of a bundle
+                while read == 0 && current < buffer.len() {
+                    let path: &Path = match pending.pop_front() {
+                        | Some(path) => path,
+                        | None => break,
+                    };
+
+                    let input: fsa::File =
+                        fsa::OpenOptions::new().read(true).open(path).await?;
+
+                    reader =
+                        ioa::BufReader::with_capacity(buffer_capacity, input);
+
+                    read = reader.read(&mut buffer[current..]).await?;
+                }
+
+                profiler.record(Phase::Read, started);
+
+                current += read;
+
+                reporter.progress.bytes_read += read;
+                reporter.report(false);
+
+                // wait for a full buffer unless the input is exhausted
+                if read > 0 && current < buffer.len() {
+                    continue;
+                }
+
+                if current == 0 {
+                    break;
+                }
+
+                // end of the chunk
+                let end: usize = policy
+                    .boundary(offset, &buffer[..current])
+                    .clamp(1, current);
+
+                if total_chunks >= self.max_chunks {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        "total_chunks exceeds max_chunks",
+                    ));
+                }
+
+                if total_chunks >= capacity {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        "total_chunks exceeds the capacity of naming",
+                    ));
+                }
+
+                // hash the original file on a worker thread
+                #[cfg(feature = "checksum")]
+                if let Some(file_hasher) = &mut file_hasher {
+                    file_hasher
+                        .update(buffer[..end].to_vec(), &mut profiler)
+                        .await?;
+                }
 
-                let mut writer: ioa::BufWriter<fsa::File> =
-                    ioa::BufWriter::with_capacity(buffer_capacity, output);
+                let started: Option<Instant> = profiler.start();
 
-                writer.write_all(&buffer[..chunk_size]).await?;
+                let encoded: Option<Vec<u8>> = match &self.transform {
+                    | Some(transform) => Some(
+                        transform
+                            .encode(total_chunks, buffer[..end].to_vec())?,
+                    ),
+                    | None => None,
+                };
 
-                writer.flush().await?;
+                profiler.record(Phase::Transform, started);
+
+                let data: &[u8] = encoded.as_deref().unwrap_or(&buffer[..end]);
+
+                // the total is patched later if the estimate is wrong
+                let header: Option<ChunkHeader> =
+                    self.headers.then_some(ChunkHeader {
+                        version: CHUNK_HEADER_VERSION,
+                        index: total_chunks,
+                        total_chunks: min_chunks,
+                        split_id: id,
+                        payload_size: data.len(),
+                    });
+
+                let stored_size: usize =
+                    data.len() + header.map_or(0, |_| CHUNK_HEADER_SIZE);
+
+                volume = self.next_volume(volume, stored_size);
+
+                let volume_index: Option<usize> = volume.map(|(v, _)| v);
+
+                if let Some(v) = volume_index {
+                    fsa::create_dir_all(volume_dir(out_dir, v)).await?;
+                }
+
+                // write chunk
+                let output_path: PathBuf = chunk_path(
+                    out_dir,
+                    volume_index,
+                    self.naming.name(total_chunks, padding),
+                );
+
+                // hash on a worker thread while writing
+                #[cfg(feature = "checksum")]
+                if let Some(workers) = &mut workers {
+                    workers
+                        .spawn(
+                            total_chunks,
+                            data.to_vec(),
+                            &mut chunks,
+                            &mut profiler,
+                        )
+                        .await?;
+                }
+
+                #[cfg(feature = "bao")]
+                let bao_hash: Option<String> = match self.bao {
+                    | true => Some(
+                        write_outboard(&output_path, data, &mut profiler)
+                            .await?,
+                    ),
+                    | false => None,
+                };
+
+                let started: Option<Instant> = profiler.start();
+
+                if self.resume
+                    && self
+                        .resumable(
+                            &output_path,
+                            header.as_ref(),
+                            data,
+                            buffer_capacity,
+                        )
+                        .await?
+                {
+                    resumed.push(total_chunks);
+                } else {
+                    let output: fsa::File = fsa::OpenOptions::new()
+                        .create(true)
+                        .truncate(true)
+                        .write(true)
+                        .open(output_path)
+                        .await?;
+
+                    let mut writer: ioa::BufWriter<fsa::File> =
+                        ioa::BufWriter::with_capacity(buffer_capacity, output);
+
+                    if let Some(header) = &header {
+                        writer.write_all(&header.encode()).await?;
+                    }
+
+                    writer.write_all(data).await?;
+
+                    writer.flush().await?;
+                }
+
+                profiler.record(Phase::Write, started);
+
+                chunks.push(ChunkInfo {
+                    index: total_chunks,
+                    offset,
+                    size: end,
+                    stored_size,
+                    volume: volume_index,
+                    metadata: policy.metadata(offset..offset + end),
+                });
+
+                #[cfg(feature = "bao")]
+                if let (Some(hash), Some(chunk)) = (bao_hash, chunks.last_mut())
+                {
+                    chunk.set_metadata(Algorithm::Blake3.id(), hash);
+                }
+
+                self.emit(Event::ChunkWritten {
+                    index: total_chunks,
+                    size: end,
+                });
 
                 total_chunks += 1;
 
+                reporter.progress.chunks_written = total_chunks;
+                reporter.progress.total_chunks =
+                    reporter.progress.total_chunks.max(total_chunks);
+                reporter.report(false);
+
                 // move remaining data to the start of the buffer
-                buffer.copy_within(chunk_size..current, 0);
-                current -= chunk_size;
+                buffer.copy_within(end..current, 0);
+                current -= end;
+                offset += end;
+
+                if let Some(control) = &self.control {
+                    control.advance(end).await?;
+                }
+            }
+        }
+
+        #[cfg(feature = "checksum")]
+        if let Some(workers) = workers {
+            workers.finish(&mut chunks, &mut profiler).await?;
+        }
+
+        #[cfg(feature = "checksum")]
+        let file_hash: Option<String> = match file_hasher {
+            | Some(file_hasher) => {
+                Some(file_hasher.finish(&mut profiler).await?)
+            },
+            | None => None,
+        };
+
+        // widen the padding if the boundary policy produced more chunks, or
+        // fit the padding of a previous run to the chunks produced
+        if self.zero_padding
+            && self.naming.is_index()
+            && padding_width(total_chunks) != padding
+        {
+            let width: usize = padding_width(total_chunks);
+
+            for chunk in &chunks {
+                let from: PathBuf = chunk_path(
+                    out_dir,
+                    chunk.volume,
+                    self.naming.name(chunk.index, padding),
+                );
+
+                let to: PathBuf = chunk_path(
+                    out_dir,
+                    chunk.volume,
+                    self.naming.name(chunk.index, width),
+                );
+
+                #[cfg(feature = "bao")]
+                if self.bao {
+                    fsa::rename(outboard_path(&from), outboard_path(&to))
+                        .await?;
+                }
+
+                fsa::rename(from, to).await?;
+            }
+        }
+
+        // patch the total in the headers
+        if self.headers && total_chunks != min_chunks {
+            let padding: usize = if self.zero_padding && self.naming.is_index()
+            {
+                padding_width(total_chunks)
+            } else {
+                padding
+            };
+
+            for chunk in &chunks {
+                ChunkHeader::patch_total_chunks(
+                    chunk_path(
+                        out_dir,
+                        chunk.volume,
+                        self.naming.name(chunk.index, padding),
+                    ),
+                    total_chunks,
+                )
+                .await?;
             }
         }
 
-        Ok(SplitResult { file_size, total_chunks })
+        #[cfg_attr(not(feature = "checksum"), allow(unused_mut))]
+        let mut file_hashes: BTreeMap<String, String> = BTreeMap::new();
+
+        #[cfg(feature = "checksum")]
+        if let (Some(algorithm), Some(digest)) = (self.file_hash, &file_hash) {
+            file_hashes.insert(algorithm.id().to_string(), digest.clone());
+        }
+
+        let manifest: Manifest = Manifest {
+            version: MANIFEST_VERSION,
+            id: Some(id),
+            file_name: match sources.is_empty() {
+                | true => in_file
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned()),
+                | false => None,
+            },
+            mime_type,
+            file_size,
+            file_hashes,
+            file_metadata,
+            sources,
+            chunk_size,
+            total_chunks,
+            zero_padding: self.zero_padding,
+            naming: self.naming.clone(),
+            transform: self.transform.as_ref().map(|t| t.id().to_string()),
+            headers: self.headers,
+            volume: None,
+            merkle_root: self.merkle_root(&chunks),
+            chunks,
+        };
+
+        if self.manifest {
+            manifest
+                .write_as(
+                    out_dir.join(self.manifest_format.file_name()),
+                    self.manifest_format,
+                )
+                .await?;
+        }
+
+        #[cfg(feature = "checksum")]
+        if self.sha256sums {
+            self.write_sha256sums(out_dir, &manifest, &mut profiler).await?;
+        }
+
+        // each volume comes with its own manifest
+        for v in 0..manifest.total_volumes() {
+            let volume_manifest: Manifest =
+                Manifest { volume: Some(v), ..manifest.clone() };
+
+            volume_manifest
+                .write_as(
+                    volume_dir(out_dir, v)
+                        .join(self.manifest_format.file_name()),
+                    self.manifest_format,
+                )
+                .await?;
+        }
+
+        reporter.progress.total_chunks = total_chunks;
+        reporter.report(true);
+
+        let profile: Option<Profile> = profiler.finish("split");
+
+        if let Some(control) = &self.control {
+            control.set_profile(profile);
+        }
+
+        if let Some(log) = &self.log {
+            log.append(Operation::Split {
+                in_file: in_file.to_path_buf(),
+                out_dir: out_dir.to_path_buf(),
+                id,
+                file_size,
+                stored_size: manifest.stored_size(),
+                total_chunks,
+                file_hashes: manifest.file_hashes.clone(),
+                // the root is verified against the written manifest
+                merkle_root: manifest
+                    .merkle_root
+                    .clone()
+                    .filter(|_| self.manifest),
+            })
+            .await?;
+        }
+
+        self.emit(Event::SplitFinished { id, total_chunks });
+
+        Ok(SplitResult {
+            id,
+            out_dir: out_dir.to_path_buf(),
+            resumed,
+            file_size,
+            total_chunks,
+            #[cfg(feature = "checksum")]
+            checksums: ChunkChecksum::of_chunks(&manifest.chunks),
+            #[cfg(feature = "checksum")]
+            file_hash,
+            manifest,
+            profile,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Run the split process with the stream of its events, e.g. to
+    /// forward the progress to a frontend without a callback.
+    ///
+    /// The events are passed to the sink set by
+    /// [`progress`](Self::progress) as well, and the stream ends once the
+    /// process finishes. See [`EventStream`] for an example.
+    pub fn run_with_events(
+        mut self
+    ) -> (impl Future<Output = ioa::Result<SplitResult>>, EventStream) {
+        let (sink, events): (Arc<dyn ProgressSink>, EventStream) =
+            EventStream::channel();
+
+        self.progress = Some(Tee::join(self.progress.take(), sink));
+
+        (self.run(), events)
+    }
+
+    /// Run the split process with the handle to pause, resume or cancel it,
+    /// e.g. to hold the work while the application is in the background.
+    ///
+    /// The handle set by [`handle`](Self::handle) is returned if any,
+    /// otherwise a new one. The process parks after the current chunk while
+    /// paused, and fails with [`ioa::ErrorKind::Interrupted`] once
+    /// cancelled.
+    pub fn run_with_handle(
+        mut self
+    ) -> (impl Future<Output = ioa::Result<SplitResult>>, OperationHandle) {
+        let control: Arc<Control> =
+            self.control.get_or_insert_with(Default::default).clone();
+
+        (self.run(), OperationHandle { control })
     }
 }
 
@@ -240,3 +2435,319 @@ impl Default for Split {
         Self::new()
     }
 }
+
+/// Writer of the chunks at their byte ranges of the input on a worker
+/// thread, each worker taking the next chunk not taken by another, see
+/// [`Split::concurrency`].
+#[derive(Clone)]
+struct RangeWriter {
+    split: Arc<Split>,
+    in_file: PathBuf,
+    out_dir: PathBuf,
+    id: Uuid,
+    file_size: usize,
+    chunk_size: usize,
+    buffer_capacity: usize,
+    total_chunks: usize,
+    padding: usize,
+    #[cfg(feature = "checksum")]
+    algorithms: Arc<[Algorithm]>,
+    next: Arc<AtomicUsize>,
+    sender: mpsc::Sender<ioa::Result<(ChunkInfo, bool)>>,
+    /// Handle to await the async checks of the resume and the control.
+    ///
+    /// The workers run on the blocking threads outside the runtime, where
+    /// [`Handle::block_on`] is allowed, and the input is read with the
+    /// blocking API there. The awaited futures wait for the throttle or the
+    /// process paused, or read the chunk left by a previous run, so only the
+    /// worker waits and no thread of the runtime is held meanwhile.
+    runtime: Handle,
+}
+
+impl RangeWriter {
+    /// Write the chunks until none is left or the process stops, sending
+    /// each chunk with whether it is kept by the resume, and returning the
+    /// time spent by the worker.
+    fn run(
+        self,
+        mut profiler: Profiler,
+    ) -> Profiler {
+        let mut input: fs::File = match self.open() {
+            | Ok(input) => input,
+            | Err(e) => {
+                let _ = self.sender.blocking_send(Err(e));
+                return profiler;
+            },
+        };
+
+        loop {
+            let index: usize = self.next.fetch_add(1, Ordering::Relaxed);
+
+            if index >= self.total_chunks {
+                break;
+            }
+
+            let written: ioa::Result<(ChunkInfo, bool)> =
+                self.write(index, &mut input, &mut profiler);
+
+            let failed: bool = written.is_err();
+
+            // the process is gone, e.g. after another worker failed
+            if self.sender.blocking_send(written).is_err() || failed {
+                break;
+            }
+        }
+
+        profiler
+    }
+
+    /// Open the input, bypassing the page cache if set.
+    fn open(&self) -> ioa::Result<fs::File> {
+        let mut options: fs::OpenOptions = fs::OpenOptions::new();
+
+        options.read(true);
+
+        if self.split.direct_io {
+            direct(&mut options)?;
+        }
+
+        options.open(&self.in_file)
+    }
+
+    /// Write the chunk at `index`.
+    fn write(
+        &self,
+        index: usize,
+        input: &mut fs::File,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<(ChunkInfo, bool)> {
+        let offset: usize = index * self.chunk_size;
+
+        let size: usize = self.chunk_size.min(self.file_size - offset);
+
+        if self.split.streams() {
+            return self
+                .stream(index, offset, size, input, profiler)
+                .map(|chunk| (chunk, false));
+        }
+
+        let started: Option<Instant> = profiler.start();
+
+        let buffer: Vec<u8> = match self.split.direct_io {
+            | true => read_direct(input, offset, size)?,
+            | false => {
+                let mut buffer: Vec<u8> = vec![0; size];
+
+                input.seek(SeekFrom::Start(offset as u64))?;
+                input.read_exact(&mut buffer)?;
+
+                buffer
+            },
+        };
+
+        profiler.record(Phase::Read, started);
+
+        let started: Option<Instant> = profiler.start();
+
+        let data: Vec<u8> = match &self.split.transform {
+            | Some(transform) => transform.encode(index, buffer)?,
+            | None => buffer,
+        };
+
+        profiler.record(Phase::Transform, started);
+
+        let header: Option<ChunkHeader> =
+            self.split.headers.then_some(ChunkHeader {
+                version: CHUNK_HEADER_VERSION,
+                index,
+                total_chunks: self.total_chunks,
+                split_id: self.id,
+                payload_size: data.len(),
+            });
+
+        #[cfg_attr(not(feature = "checksum"), allow(unused_mut))]
+        let mut chunk: ChunkInfo = ChunkInfo {
+            index,
+            offset,
+            size,
+            stored_size: data.len() + header.map_or(0, |_| CHUNK_HEADER_SIZE),
+            volume: None,
+            metadata: BTreeMap::new(),
+        };
+
+        let output_path: PathBuf = chunk_path(
+            &self.out_dir,
+            None,
+            self.split.naming.name(index, self.padding),
+        );
+
+        #[cfg(feature = "checksum")]
+        {
+            let started: Option<Instant> = profiler.start();
+
+            for algorithm in self.algorithms.iter() {
+                chunk.set_metadata(algorithm.id(), algorithm.digest(&data));
+            }
+
+            profiler.record(Phase::Hash, started);
+        }
+
+        #[cfg(feature = "bao")]
+        if self.split.bao {
+            let started: Option<Instant> = profiler.start();
+
+            let (hash, encoded) = outboard(&data);
+
+            profiler.record(Phase::Hash, started);
+
+            fs::write(outboard_path(&output_path), encoded)?;
+
+            chunk.set_metadata(
+                Algorithm::Blake3.id(),
+                hash.to_hex().to_string(),
+            );
+        }
+
+        let started: Option<Instant> = profiler.start();
+
+        let kept: bool = self.split.resume
+            && self.runtime.block_on(self.split.resumable(
+                &output_path,
+                header.as_ref(),
+                &data,
+                self.buffer_capacity,
+            ))?;
+
+        if !kept {
+            let mut output: fs::File = fs::File::create(&output_path)?;
+
+            if let Some(header) = &header {
+                output.write_all(&header.encode())?;
+            }
+
+            output.write_all(&data)?;
+        }
+
+        profiler.record(Phase::Write, started);
+
+        if let Some(control) = &self.split.control {
+            self.runtime.block_on(control.advance(size))?;
+        }
+
+        Ok((chunk, kept))
+    }
+
+    /// Write the chunk at `index` of `size` bytes at `offset`, copying it
+    /// through the buffer in pieces.
+    fn stream(
+        &self,
+        index: usize,
+        offset: usize,
+        size: usize,
+        input: &mut fs::File,
+        profiler: &mut Profiler,
+    ) -> ioa::Result<ChunkInfo> {
+        let header: Option<ChunkHeader> =
+            self.split.headers.then_some(ChunkHeader {
+                version: CHUNK_HEADER_VERSION,
+                index,
+                total_chunks: self.total_chunks,
+                split_id: self.id,
+                payload_size: size,
+            });
+
+        let mut output: fs::File = fs::File::create(chunk_path(
+            &self.out_dir,
+            None,
+            self.split.naming.name(index, self.padding),
+        ))?;
+
+        if let Some(header) = &header {
+            output.write_all(&header.encode())?;
+        }
+
+        #[cfg(feature = "checksum")]
+        let mut hasher: FileHasher =
+            FileHasher::with_algorithms(&self.algorithms);
+
+        let mut buffer: Vec<u8> = vec![0; self.buffer_capacity.min(size)];
+
+        input.seek(SeekFrom::Start(offset as u64))?;
+
+        let mut copied: usize = 0;
+
+        while copied < size {
+            let piece: &mut [u8] =
+                &mut buffer[..self.buffer_capacity.min(size - copied)];
+
+            let started: Option<Instant> = profiler.start();
+
+            input.read_exact(piece)?;
+
+            profiler.record(Phase::Read, started);
+
+            #[cfg(feature = "checksum")]
+            {
+                let started: Option<Instant> = profiler.start();
+
+                hasher.update_blocking(piece);
+
+                profiler.record(Phase::Hash, started);
+            }
+
+            let started: Option<Instant> = profiler.start();
+
+            output.write_all(piece)?;
+
+            profiler.record(Phase::Write, started);
+
+            copied += piece.len();
+        }
+
+        #[cfg_attr(not(feature = "checksum"), allow(unused_mut))]
+        let mut chunk: ChunkInfo = ChunkInfo {
+            index,
+            offset,
+            size,
+            stored_size: size + header.map_or(0, |_| CHUNK_HEADER_SIZE),
+            volume: None,
+            metadata: BTreeMap::new(),
+        };
+
+        #[cfg(feature = "checksum")]
+        for (algorithm, digest) in
+            self.algorithms.iter().zip(hasher.finish_blocking())
+        {
+            chunk.set_metadata(algorithm.id(), digest);
+        }
+
+        if let Some(control) = &self.split.control {
+            self.runtime.block_on(control.advance(size))?;
+        }
+
+        Ok(chunk)
+    }
+}
+
+/// Write the bao outboard of the chunk payload on a worker thread, returning
+/// its BLAKE3 checksum in hex.
+#[cfg(feature = "bao")]
+async fn write_outboard(
+    chunk: &Path,
+    data: &[u8],
+    profiler: &mut Profiler,
+) -> ioa::Result<String> {
+    let started: Option<Instant> = profiler.start();
+
+    let data: Vec<u8> = data.to_vec();
+
+    let (hash, encoded) = tokio::task::spawn_blocking(move || outboard(&data))
+        .await
+        .map_err(ioa::Error::other)?;
+
+    profiler.record(Phase::Hash, started);
+
+    fsa::write(outboard_path(chunk), encoded).await?;
+
+    Ok(hash.to_hex().to_string())
+}
@@ -0,0 +1,366 @@
+use std::sync::Arc;
+
+use tokio::{io as ioa, task::JoinSet};
+
+#[cfg(feature = "checksum")]
+use crate::{
+    checksum::{chunk_checksum, Algorithm},
+    header::CHUNK_HEADER_SIZE,
+};
+use crate::{
+    config::UPLOAD_CONCURRENCY_DEFAULT,
+    manifest::{ChunkInfo, Manifest},
+    store::{ChunkHead, ChunkStore},
+};
+
+/// Result of the [`StoreSync`] process.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncResult {
+    /// Indices of the chunks copied to the destination store, sorted.
+    pub copied: Vec<usize>,
+    /// Indices of the chunks already in the destination store, sorted.
+    pub skipped: Vec<usize>,
+    /// Total size of the chunks copied.
+    pub copied_size: usize,
+}
+
+/// Expected size and checksum of a chunk.
+#[derive(Debug, Clone, Default)]
+struct Expected {
+    size: Option<usize>,
+    #[cfg(feature = "checksum")]
+    checksum: Option<(Algorithm, String)>,
+    #[cfg(feature = "checksum")]
+    headers: bool,
+}
+
+/// Create a process copying the chunks missing in the `dst` store from the
+/// `src` store, see [`StoreSync`].
+pub fn sync(
+    src: Arc<dyn ChunkStore>,
+    dst: Arc<dyn ChunkStore>,
+) -> StoreSync {
+    StoreSync::new(src, dst)
+}
+
+/// Process to copy the chunks missing in a destination store from a source
+/// store, e.g. to replicate a chunk set between a local cache and a cloud
+/// storage.
+///
+/// The chunks to copy are found by comparing the sizes of the chunks in the
+/// stores, as described in the manifest if set, otherwise as listed in the
+/// source store. A chunk of the expected size in the destination store is
+/// not copied again, so an interrupted sync is resumed by running it again.
+/// The chunks are copied concurrently, and with the `checksum` feature, the
+/// chunks are verified against the checksums in the manifest before being
+/// written to the destination store.
+///
+/// Call [`TieredStore::flush`] after the sync if the destination store is a
+/// [`TieredStore`].
+///
+/// [`TieredStore`]: crate::store::TieredStore
+/// [`TieredStore::flush`]: crate::store::TieredStore::flush
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{path::PathBuf, sync::Arc};
+///
+/// use filego::{
+///     manifest::Manifest,
+///     store::DirStore,
+///     sync::{sync, SyncResult},
+/// };
+///
+/// async fn example() {
+///     let dir: PathBuf = PathBuf::from("path").join("to").join("dir");
+///     let copy: PathBuf = PathBuf::from("path").join("to").join("copy");
+///     let manifest: Manifest =
+///         Manifest::read(dir.join("manifest.json")).await.unwrap();
+///
+///     let result: SyncResult = sync(
+///         Arc::new(DirStore::with_manifest(&dir, &manifest)),
+///         Arc::new(DirStore::new(&copy)),
+///     )
+///     .manifest(manifest)
+///     .run()
+///     .await
+///     .unwrap();
+///
+///     println!("{} chunks copied", result.copied.len());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StoreSync {
+    src: Arc<dyn ChunkStore>,
+    dst: Arc<dyn ChunkStore>,
+    manifest: Option<Manifest>,
+    total_chunks: Option<usize>,
+    concurrency: usize,
+    #[cfg(feature = "checksum")]
+    verify: bool,
+}
+
+impl StoreSync {
+    /// Create a new sync process from the `src` store to the `dst` store.
+    pub fn new(
+        src: Arc<dyn ChunkStore>,
+        dst: Arc<dyn ChunkStore>,
+    ) -> Self {
+        Self {
+            src,
+            dst,
+            manifest: None,
+            total_chunks: None,
+            concurrency: UPLOAD_CONCURRENCY_DEFAULT,
+            #[cfg(feature = "checksum")]
+            verify: true,
+        }
+    }
+
+    /// Set the manifest describing the chunks to copy, with their sizes in
+    /// storage and their checksums.
+    pub fn manifest(
+        mut self,
+        manifest: Manifest,
+    ) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Set the total number of chunks to copy.
+    ///
+    /// It is ignored if the manifest is set. Without both, the chunks are
+    /// listed in the source store from index `0` until a chunk is not
+    /// found.
+    pub fn total_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.total_chunks = Some(chunks);
+        self
+    }
+
+    /// Set the number of chunks copied at the same time.
+    ///
+    /// By default, it follows the [`UPLOAD_CONCURRENCY_DEFAULT`].
+    ///
+    /// [`UPLOAD_CONCURRENCY_DEFAULT`]: crate::config::UPLOAD_CONCURRENCY_DEFAULT
+    pub fn concurrency(
+        mut self,
+        concurrency: usize,
+    ) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set whether to verify the chunks against the checksums in the
+    /// manifest before writing them, failing with
+    /// [`ioa::ErrorKind::InvalidData`] on a mismatch.
+    ///
+    /// By default, it is `true`. The header of a chunk, if any, is excluded
+    /// from its checksum.
+    #[cfg(feature = "checksum")]
+    pub fn verify(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.verify = enabled;
+        self
+    }
+
+    /// Run the sync process.
+    ///
+    /// The first error stops the process, and the chunks copied so far are
+    /// kept in the destination store.
+    pub async fn run(self) -> ioa::Result<SyncResult> {
+        let expected: Vec<Expected> = self.expected().await?;
+
+        let mut tasks: JoinSet<ioa::Result<(usize, Option<usize>)>> =
+            JoinSet::new();
+
+        let mut result: SyncResult = SyncResult::default();
+
+        for (index, expected) in expected.into_iter().enumerate() {
+            if tasks.len() >= self.concurrency {
+                if let Some(joined) = tasks.join_next().await {
+                    record(&mut result, joined)?;
+                }
+            }
+
+            tasks.spawn(copy(
+                self.src.clone(),
+                self.dst.clone(),
+                index,
+                expected,
+            ));
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            record(&mut result, joined)?;
+        }
+
+        result.copied.sort_unstable();
+        result.skipped.sort_unstable();
+
+        Ok(result)
+    }
+
+    /// Get the expected chunks, from the manifest or listed in the source
+    /// store.
+    async fn expected(&self) -> ioa::Result<Vec<Expected>> {
+        if let Some(manifest) = &self.manifest {
+            let mut chunks: Vec<Option<&ChunkInfo>> =
+                vec![None; manifest.total_chunks];
+
+            for chunk in &manifest.chunks {
+                if let Some(slot) = chunks.get_mut(chunk.index) {
+                    *slot = Some(chunk);
+                }
+            }
+
+            return chunks
+                .into_iter()
+                .enumerate()
+                .map(|(index, chunk)| {
+                    let chunk: &ChunkInfo = chunk.ok_or_else(|| {
+                        ioa::Error::new(
+                            ioa::ErrorKind::InvalidData,
+                            format!("chunk {} is not in the manifest", index),
+                        )
+                    })?;
+
+                    Ok(Expected {
+                        size: Some(chunk.stored_size),
+                        #[cfg(feature = "checksum")]
+                        checksum: chunk_checksum(chunk)
+                            .filter(|_| self.verify)
+                            .map(|(a, d)| (a, d.to_string())),
+                        #[cfg(feature = "checksum")]
+                        headers: manifest.headers,
+                    })
+                })
+                .collect();
+        }
+
+        match self.total_chunks {
+            | Some(total) => Ok(vec![Expected::default(); total]),
+            | None => {
+                let mut expected: Vec<Expected> = Vec::new();
+
+                while let Some(head) = self.src.head(expected.len()).await? {
+                    #[cfg_attr(
+                        not(feature = "checksum"),
+                        allow(clippy::needless_update)
+                    )]
+                    expected.push(Expected {
+                        size: Some(head.size),
+                        ..Expected::default()
+                    });
+                }
+
+                Ok(expected)
+            },
+        }
+    }
+}
+
+/// Record the chunk copied or skipped by a task.
+fn record(
+    result: &mut SyncResult,
+    joined: Result<ioa::Result<(usize, Option<usize>)>, tokio::task::JoinError>,
+) -> ioa::Result<()> {
+    match joined.map_err(ioa::Error::other)?? {
+        | (index, Some(size)) => {
+            result.copied.push(index);
+            result.copied_size += size;
+        },
+        | (index, None) => result.skipped.push(index),
+    }
+
+    Ok(())
+}
+
+/// Copy the chunk at `index` if not in the destination store, returning
+/// the size copied.
+async fn copy(
+    src: Arc<dyn ChunkStore>,
+    dst: Arc<dyn ChunkStore>,
+    index: usize,
+    expected: Expected,
+) -> ioa::Result<(usize, Option<usize>)> {
+    let size: usize = match expected.size {
+        | Some(size) => size,
+        | None => match src.head(index).await? {
+            | Some(head) => head.size,
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::NotFound,
+                    format!("chunk {} not found in the source store", index),
+                ))
+            },
+        },
+    };
+
+    let head: Option<ChunkHead> = dst.head(index).await?;
+
+    if head.is_some_and(|head| head.size == size) {
+        return Ok((index, None));
+    }
+
+    let data: Vec<u8> = src.get(index).await?;
+
+    if data.len() != size {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("size of chunk {} does not match", index),
+        ));
+    }
+
+    let data: Vec<u8> = verify(index, data, &expected).await?;
+
+    dst.put(index, data).await?;
+
+    Ok((index, Some(size)))
+}
+
+/// Verify the chunk against its expected checksum, if any.
+#[cfg(feature = "checksum")]
+async fn verify(
+    index: usize,
+    data: Vec<u8>,
+    expected: &Expected,
+) -> ioa::Result<Vec<u8>> {
+    let (algorithm, digest) = match &expected.checksum {
+        | Some((algorithm, digest)) => (*algorithm, digest.clone()),
+        | None => return Ok(data),
+    };
+
+    let headers: bool = expected.headers;
+
+    tokio::task::spawn_blocking(move || {
+        let payload: &[u8] = match headers {
+            | true => &data[CHUNK_HEADER_SIZE.min(data.len())..],
+            | false => &data,
+        };
+
+        match algorithm.digest(payload).eq_ignore_ascii_case(&digest) {
+            | true => Ok(data),
+            | false => Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                format!("checksum of chunk {} does not match", index),
+            )),
+        }
+    })
+    .await
+    .map_err(ioa::Error::other)?
+}
+
+#[cfg(not(feature = "checksum"))]
+async fn verify(
+    _index: usize,
+    data: Vec<u8>,
+    _expected: &Expected,
+) -> ioa::Result<Vec<u8>> {
+    Ok(data)
+}
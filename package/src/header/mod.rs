@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use std::io::SeekFrom;
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use uuid::Uuid;
+
+/// The magic bytes at the start of a chunk header.
+pub const CHUNK_HEADER_MAGIC: [u8; 4] = *b"FGCH";
+
+/// The current version of the chunk header.
+pub const CHUNK_HEADER_VERSION: u32 = 1;
+
+/// The size of a chunk header in bytes.
+pub const CHUNK_HEADER_SIZE: usize = 48;
+
+/// The offset of the total number of chunks within a chunk header.
+const TOTAL_CHUNKS_OFFSET: usize = 16;
+
+/// Header prepended to a chunk, so the chunk can be identified in isolation.
+///
+/// The header is encoded in little-endian as the magic bytes, the version,
+/// the index, the total number of chunks, the id of the split and the size
+/// of the payload following the header.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::header::ChunkHeader;
+///
+/// async fn example() {
+///     let header: Option<ChunkHeader> =
+///         ChunkHeader::read("/path/to/chunk").await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkHeader {
+    /// Version of the header.
+    pub version: u32,
+    /// Index of the chunk.
+    pub index: usize,
+    /// The total number of chunks splitted from the original file.
+    pub total_chunks: usize,
+    /// Identity of the split shared by its chunks.
+    pub split_id: Uuid,
+    /// Size of the payload following the header.
+    pub payload_size: usize,
+}
+
+impl ChunkHeader {
+    /// Encode the header into bytes.
+    pub fn encode(&self) -> [u8; CHUNK_HEADER_SIZE] {
+        let mut bytes: [u8; CHUNK_HEADER_SIZE] = [0; CHUNK_HEADER_SIZE];
+
+        bytes[0..4].copy_from_slice(&CHUNK_HEADER_MAGIC);
+        bytes[4..8].copy_from_slice(&self.version.to_le_bytes());
+        bytes[8..16].copy_from_slice(&(self.index as u64).to_le_bytes());
+        bytes[TOTAL_CHUNKS_OFFSET..TOTAL_CHUNKS_OFFSET + 8]
+            .copy_from_slice(&(self.total_chunks as u64).to_le_bytes());
+        bytes[24..40].copy_from_slice(self.split_id.as_bytes());
+        bytes[40..48]
+            .copy_from_slice(&(self.payload_size as u64).to_le_bytes());
+
+        bytes
+    }
+
+    /// Decode the header from the start of the bytes, or `None` if the
+    /// bytes do not start with a supported header.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < CHUNK_HEADER_SIZE || bytes[0..4] != CHUNK_HEADER_MAGIC
+        {
+            return None;
+        }
+
+        let u64_at = |i: usize| -> Option<u64> {
+            Some(u64::from_le_bytes(bytes[i..i + 8].try_into().ok()?))
+        };
+
+        let version: u32 = u32::from_le_bytes(bytes[4..8].try_into().ok()?);
+
+        if version != CHUNK_HEADER_VERSION {
+            return None;
+        }
+
+        Some(Self {
+            version,
+            index: u64_at(8)? as usize,
+            total_chunks: u64_at(TOTAL_CHUNKS_OFFSET)? as usize,
+            split_id: Uuid::from_slice(&bytes[24..40]).ok()?,
+            payload_size: u64_at(40)? as usize,
+        })
+    }
+
+    /// Decode the header of a whole chunk, or `None` if the chunk does not
+    /// start with a header matching its size.
+    pub fn of_chunk(chunk: &[u8]) -> Option<Self> {
+        Self::decode(chunk).filter(|header| {
+            chunk.len() - CHUNK_HEADER_SIZE == header.payload_size
+        })
+    }
+
+    /// Read the header of a chunk file, or `None` if the file does not
+    /// start with a header matching its size.
+    pub async fn read<P: AsRef<Path>>(path: P) -> ioa::Result<Option<Self>> {
+        let mut input: fsa::File =
+            fsa::OpenOptions::new().read(true).open(path.as_ref()).await?;
+
+        let size: usize = input.metadata().await?.len() as usize;
+
+        if size < CHUNK_HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let mut bytes: [u8; CHUNK_HEADER_SIZE] = [0; CHUNK_HEADER_SIZE];
+
+        input.read_exact(&mut bytes).await?;
+
+        Ok(Self::decode(&bytes)
+            .filter(|header| size - CHUNK_HEADER_SIZE == header.payload_size))
+    }
+
+    /// Overwrite the total number of chunks in the header of a chunk file,
+    /// leaving the rest of the file untouched.
+    pub(crate) async fn patch_total_chunks<P: AsRef<Path>>(
+        path: P,
+        total_chunks: usize,
+    ) -> ioa::Result<()> {
+        let mut output: fsa::File =
+            fsa::OpenOptions::new().write(true).open(path.as_ref()).await?;
+
+        output.seek(SeekFrom::Start(TOTAL_CHUNKS_OFFSET as u64)).await?;
+        output.write_all(&(total_chunks as u64).to_le_bytes()).await?;
+        output.flush().await
+    }
+
+    /// Check the header against the index and split id expected by the
+    /// merge process.
+    pub(crate) fn expect(
+        self,
+        index: usize,
+        split_id: Uuid,
+    ) -> ioa::Result<Self> {
+        if self.index != index || self.split_id != split_id {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                format!("header of chunk {} does not match", index),
+            ));
+        }
+
+        Ok(self)
+    }
+}
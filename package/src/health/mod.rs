@@ -0,0 +1,196 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tokio::{fs as fsa, io as ioa};
+
+use crate::tune::available_space;
+
+/// Result of a single check of a probe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProbeCheck {
+    /// Name of the check, e.g. `writable:/path/to/dir`.
+    pub name: String,
+    /// Whether the check passed.
+    pub ok: bool,
+    /// Details of the check, e.g. the reason of the failure.
+    pub detail: Option<String>,
+}
+
+/// Report of a probe, see [`Probes`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProbeReport {
+    /// Whether all checks passed.
+    pub ok: bool,
+    /// Results of the checks in order.
+    pub checks: Vec<ProbeCheck>,
+}
+
+impl ProbeReport {
+    fn new(checks: Vec<ProbeCheck>) -> Self {
+        Self { ok: checks.iter().all(|check| check.ok), checks }
+    }
+
+    /// Get the HTTP status code of the report, which is `200` if passed,
+    /// otherwise `503`.
+    pub fn status_code(&self) -> u16 {
+        match self.ok {
+            | true => 200,
+            | false => 503,
+        }
+    }
+
+    /// Get the report as JSON.
+    pub fn to_json(&self) -> String {
+        // the report has no map with non-string keys
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Health and readiness probes of a service built on the processes, e.g. a
+/// receiver storing chunks, for orchestrators such as Kubernetes.
+///
+/// The health probe tells whether the service is alive, and the readiness
+/// probe whether it should take more work: the directories are writable,
+/// the space available on their file systems is above the minimum, and the
+/// backlog is below the maximum.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::health::{ProbeReport, Probes};
+///
+/// async fn example() {
+///     let probes: Probes = Probes::new()
+///         .dir("/path/to/dir")
+///         .min_free_space(1024 * 1024 * 1024)
+///         .max_backlog(100);
+///
+///     let report: ProbeReport = probes.readiness(3).await;
+///
+///     println!("{} {}", report.status_code(), report.to_json());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Probes {
+    dirs: Vec<PathBuf>,
+    min_free_space: Option<usize>,
+    max_backlog: Option<usize>,
+}
+
+impl Probes {
+    /// Create new probes without checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a directory the service writes to, checked to be writable and to
+    /// have the [`min_free_space`](Self::min_free_space) available.
+    pub fn dir<Dir: AsRef<Path>>(
+        mut self,
+        dir: Dir,
+    ) -> Self {
+        self.dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the minimum space available on the file systems of the
+    /// directories in bytes.
+    ///
+    /// The check passes where the space is unknown, e.g. on systems other
+    /// than Linux.
+    pub fn min_free_space(
+        mut self,
+        bytes: usize,
+    ) -> Self {
+        self.min_free_space = Some(bytes);
+        self
+    }
+
+    /// Set the maximum backlog, e.g. the number of files or sessions
+    /// waiting to be processed.
+    pub fn max_backlog(
+        mut self,
+        backlog: usize,
+    ) -> Self {
+        self.max_backlog = Some(backlog);
+        self
+    }
+
+    /// Run the health probe, which passes as long as the service responds.
+    pub fn health(&self) -> ProbeReport {
+        ProbeReport::new(Vec::new())
+    }
+
+    /// Run the readiness probe with the current `backlog`.
+    pub async fn readiness(
+        &self,
+        backlog: usize,
+    ) -> ProbeReport {
+        let mut checks: Vec<ProbeCheck> = Vec::new();
+
+        for dir in &self.dirs {
+            let writable: Result<(), String> =
+                writable(dir).await.map_err(|e| e.to_string());
+
+            checks.push(ProbeCheck {
+                name: format!("writable:{}", dir.display()),
+                ok: writable.is_ok(),
+                detail: writable.err(),
+            });
+
+            if let Some(min) = self.min_free_space {
+                let space: Option<usize> = available_space(dir);
+
+                checks.push(ProbeCheck {
+                    name: format!("free_space:{}", dir.display()),
+                    ok: space.is_none_or(|space| space >= min),
+                    detail: Some(match space {
+                        | Some(space) => {
+                            format!(
+                                "{} bytes available, {} required",
+                                space, min
+                            )
+                        },
+                        | None => "unknown".to_string(),
+                    }),
+                });
+            }
+        }
+
+        if let Some(max) = self.max_backlog {
+            checks.push(ProbeCheck {
+                name: "backlog".to_string(),
+                ok: backlog <= max,
+                detail: Some(format!("{} of at most {}", backlog, max)),
+            });
+        }
+
+        ProbeReport::new(checks)
+    }
+
+    /// Respond to an HTTP request for `path` with its status code and JSON
+    /// body, serving the health probe at `/healthz` and the readiness probe
+    /// at `/readyz`, or `None` for other paths.
+    pub async fn respond(
+        &self,
+        path: &str,
+        backlog: usize,
+    ) -> Option<(u16, String)> {
+        let report: ProbeReport = match path {
+            | "/healthz" => self.health(),
+            | "/readyz" => self.readiness(backlog).await,
+            | _ => return None,
+        };
+
+        Some((report.status_code(), report.to_json()))
+    }
+}
+
+/// Check whether a file can be written to the directory.
+async fn writable(dir: &Path) -> ioa::Result<()> {
+    let path: PathBuf =
+        dir.join(format!(".filego-probe-{}", std::process::id()));
+
+    fsa::write(&path, b"probe").await?;
+    fsa::remove_file(&path).await
+}
@@ -0,0 +1,268 @@
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use tokio::io as ioa;
+
+/// The maximum length of a key in bytes, following the limit of S3.
+pub const KEY_LENGTH_MAX: usize = 1024;
+
+/// Template of the keys of the chunks in an object store, e.g.
+/// `{file_id}/{index:06}.bin`, so the chunks fit an existing bucket layout.
+///
+/// The placeholders are `{file_id}` and `{index}`, with `{index:0N}` padding
+/// the index with zeros to the width of `N`. Braces are escaped as `{{` and
+/// `}}`. The template is validated when parsed, and it must contain
+/// `{index}`, as the keys of the chunks would collide otherwise.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::key::KeyTemplate;
+///
+/// fn example() {
+///     let template: KeyTemplate =
+///         KeyTemplate::parse("{file_id}/{index:06}.bin").unwrap();
+///
+///     // `my-file/000007.bin`
+///     let key: String = template.key("my-file", 7);
+///
+///     // keys of the chunks, failing on the keys already in the bucket
+///     let keys: Vec<String> = template
+///         .keys("my-file", 10, ["my-file/000000.bin"])
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+/// Part of a [`KeyTemplate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    FileId,
+    Index { width: usize },
+}
+
+impl KeyTemplate {
+    /// Parse a template, failing with [`ioa::ErrorKind::InvalidInput`] if
+    /// it is invalid.
+    pub fn parse<T: AsRef<str>>(template: T) -> ioa::Result<Self> {
+        let template: &str = template.as_ref();
+
+        let mut parts: Vec<Part> = Vec::new();
+        let mut literal: String = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                | '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                },
+                | '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                },
+                | '{' => {
+                    let rest: &str = chars.as_str();
+
+                    let end: usize = rest
+                        .find('}')
+                        .ok_or_else(|| invalid("unclosed placeholder"))?;
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(placeholder(&rest[..end])?);
+
+                    chars = rest[end + 1..].chars();
+                },
+                | '}' => return Err(invalid("unmatched `}`")),
+                | c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        if !parts.iter().any(|p| matches!(p, Part::Index { .. })) {
+            return Err(invalid("template must contain `{index}`"));
+        }
+
+        Ok(Self { template: template.to_string(), parts })
+    }
+
+    /// Get the key of the chunk at `index`.
+    pub fn key<F: AsRef<str>>(
+        &self,
+        file_id: F,
+        index: usize,
+    ) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                | Part::Literal(literal) => literal.clone(),
+                | Part::FileId => file_id.as_ref().to_string(),
+                | Part::Index { width } => {
+                    format!("{:0width$}", index, width = width)
+                },
+            })
+            .collect()
+    }
+
+    /// Get the keys of `total_chunks` chunks, failing with
+    /// [`ioa::ErrorKind::AlreadyExists`] if any of them collides with
+    /// another chunk or with the `existing` keys, e.g. listed from the
+    /// bucket, and with [`ioa::ErrorKind::InvalidInput`] if any of them is
+    /// not a valid key.
+    pub fn keys<F: AsRef<str>, I: IntoIterator<Item = S>, S: AsRef<str>>(
+        &self,
+        file_id: F,
+        total_chunks: usize,
+        existing: I,
+    ) -> ioa::Result<Vec<String>> {
+        let mut seen: HashSet<String> =
+            existing.into_iter().map(|k| k.as_ref().to_string()).collect();
+
+        let mut keys: Vec<String> = Vec::with_capacity(total_chunks);
+
+        for index in 0..total_chunks {
+            let key: String = self.key(file_id.as_ref(), index);
+
+            validate(&key)?;
+
+            if !seen.insert(key.clone()) {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::AlreadyExists,
+                    format!("key of chunk {} collides: {}", index, key),
+                ));
+            }
+
+            keys.push(key);
+        }
+
+        Ok(keys)
+    }
+
+    /// Get the index of the chunk from its key, if it follows the template.
+    pub fn index<F: AsRef<str>>(
+        &self,
+        file_id: F,
+        key: &str,
+    ) -> Option<usize> {
+        let mut rest: &str = key;
+        let mut index: Option<usize> = None;
+
+        for (i, part) in self.parts.iter().enumerate() {
+            match part {
+                | Part::Literal(literal) => {
+                    rest = rest.strip_prefix(literal.as_str())?
+                },
+                | Part::FileId => rest = rest.strip_prefix(file_id.as_ref())?,
+                | Part::Index { width } => {
+                    // digits up to the next literal or the end
+                    let end: usize = match self.parts.get(i + 1) {
+                        | Some(Part::Literal(next)) => {
+                            rest.find(next.as_str())?
+                        },
+                        | _ => rest
+                            .find(|c: char| !c.is_ascii_digit())
+                            .unwrap_or(rest.len()),
+                    };
+
+                    let digits: &str = &rest[..end];
+
+                    if digits.len() < *width
+                        || digits.is_empty()
+                        || !digits.bytes().all(|c| c.is_ascii_digit())
+                    {
+                        return None;
+                    }
+
+                    let value: usize = digits.parse().ok()?;
+
+                    // the same chunk in every placeholder
+                    if index.is_some_and(|i| i != value) {
+                        return None;
+                    }
+
+                    index = Some(value);
+                    rest = &rest[end..];
+                },
+            }
+        }
+
+        if !rest.is_empty() {
+            return None;
+        }
+
+        index
+    }
+}
+
+impl fmt::Display for KeyTemplate {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+impl FromStr for KeyTemplate {
+    type Err = ioa::Error;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        Self::parse(template)
+    }
+}
+
+/// Parse the content of a placeholder, e.g. `index:06`.
+fn placeholder(content: &str) -> ioa::Result<Part> {
+    let (name, spec) = match content.split_once(':') {
+        | Some((name, spec)) => (name, Some(spec)),
+        | None => (content, None),
+    };
+
+    match (name, spec) {
+        | ("file_id", None) => Ok(Part::FileId),
+        | ("index", None) => Ok(Part::Index { width: 0 }),
+        | ("index", Some(spec)) => {
+            let width: usize = spec
+                .strip_prefix('0')
+                .and_then(|w| w.parse().ok())
+                .ok_or_else(|| {
+                    invalid(format!("invalid width of `{{{}}}`", content))
+                })?;
+
+            Ok(Part::Index { width })
+        },
+        | _ => Err(invalid(format!("unknown placeholder `{{{}}}`", content))),
+    }
+}
+
+/// Check whether the key is valid in object stores.
+fn validate(key: &str) -> ioa::Result<()> {
+    if key.is_empty() || key.len() > KEY_LENGTH_MAX {
+        return Err(invalid(format!(
+            "key must be 1 to {} bytes: {}",
+            KEY_LENGTH_MAX, key
+        )));
+    }
+
+    if key.split('/').any(|s| s.is_empty() || s == "." || s == "..")
+        || key.chars().any(char::is_control)
+    {
+        return Err(invalid(format!("invalid key: {}", key)));
+    }
+
+    Ok(())
+}
+
+fn invalid<M: Into<String>>(message: M) -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::InvalidInput, message.into())
+}
@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::{fs as fsa, io as ioa};
+
+use crate::{
+    naming::ChunkNaming,
+    store::{ChunkHead, ChunkStore, StoreFuture},
+};
+
+/// Layout of a chunk set produced by a third-party tool.
+///
+/// A layout parses the names of the files in a directory into the indices
+/// of the chunks, which decide the order of the chunks. Layouts are
+/// registered in a [`LayoutRegistry`] to be detected by the check and merge
+/// processes when the directory contains neither a manifest nor chunks
+/// named by their indices.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::layout::Layout;
+///
+/// /// Chunks named as `chunk-0.bin`, `chunk-1.bin`, ...
+/// #[derive(Debug)]
+/// struct Bin;
+///
+/// impl Layout for Bin {
+///     fn id(&self) -> &str {
+///         "bin"
+///     }
+///
+///     fn index(
+///         &self,
+///         name: &str,
+///     ) -> Option<usize> {
+///         name.strip_prefix("chunk-")?.strip_suffix(".bin")?.parse().ok()
+///     }
+/// }
+/// ```
+pub trait Layout: fmt::Debug + Send + Sync {
+    /// Identity of the layout, e.g. `hjsplit`.
+    fn id(&self) -> &str;
+
+    /// Get the index of the chunk from its file name, or `None` if the file
+    /// is not a chunk of the layout.
+    fn index(
+        &self,
+        name: &str,
+    ) -> Option<usize>;
+
+    /// Check whether the names of the files in a directory are a chunk set
+    /// of the layout.
+    ///
+    /// By default, any file name parsed by the layout is a match.
+    fn detect(
+        &self,
+        names: &[String],
+    ) -> bool {
+        names.iter().any(|name| self.index(name).is_some())
+    }
+
+    /// Discover the name of the original file from the names of the files
+    /// in a directory, if kept by the layout.
+    fn original_name(
+        &self,
+        _names: &[String],
+    ) -> Option<String> {
+        None
+    }
+}
+
+impl Layout for ChunkNaming {
+    fn id(&self) -> &str {
+        match self {
+            | Self::Index => "index",
+            | Self::Alphabetic { .. } => "alphabetic",
+            | Self::Numeric { .. } => "numeric",
+        }
+    }
+
+    fn index(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        ChunkNaming::index(self, name)
+    }
+
+    fn original_name(
+        &self,
+        _names: &[String],
+    ) -> Option<String> {
+        ChunkNaming::original_name(self).map(str::to_string)
+    }
+}
+
+/// Layout of multi-part sets, e.g. `file.ext.001`, as produced by HJSplit
+/// or 7-Zip.
+///
+/// Only a directory with a single multi-part set is detected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MultiPart;
+
+impl MultiPart {
+    fn split(name: &str) -> Option<(&str, usize)> {
+        let (prefix, number) = name.rsplit_once('.')?;
+
+        if prefix.is_empty()
+            || number.len() < 3
+            || !number.bytes().all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+
+        Some((prefix, number.parse::<usize>().ok()?.checked_sub(1)?))
+    }
+}
+
+impl Layout for MultiPart {
+    fn id(&self) -> &str {
+        "multi_part"
+    }
+
+    fn index(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        Self::split(name).map(|(_, index)| index)
+    }
+
+    fn detect(
+        &self,
+        names: &[String],
+    ) -> bool {
+        self.original_name(names).is_some()
+    }
+
+    fn original_name(
+        &self,
+        names: &[String],
+    ) -> Option<String> {
+        let mut prefixes = names.iter().filter_map(|n| Self::split(n));
+
+        let (prefix, _) = prefixes.next()?;
+
+        // ambiguous with more than one multi-part set
+        if prefixes.any(|(p, _)| p != prefix) {
+            return None;
+        }
+
+        Some(prefix.to_string())
+    }
+}
+
+/// Registry of the layouts detected by the check and merge processes.
+///
+/// By default, the registry contains [`MultiPart`] and the naming of
+/// `split(1)`. Registered layouts are tried in the order of registration
+/// before the default ones.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     layout::{Layout, LayoutRegistry},
+///     merge::{Merge, MergeResult},
+/// };
+///
+/// #[derive(Debug)]
+/// struct Bin;
+///
+/// impl Layout for Bin {
+///     fn id(&self) -> &str {
+///         "bin"
+///     }
+///
+///     fn index(
+///         &self,
+///         name: &str,
+///     ) -> Option<usize> {
+///         name.strip_prefix("chunk-")?.strip_suffix(".bin")?.parse().ok()
+///     }
+/// }
+///
+/// async fn example() {
+///     let result: MergeResult = Merge::new()
+///         .in_dir(PathBuf::from("path").join("to").join("dir"))
+///         .out_file(PathBuf::from("path").join("to").join("file"))
+///         .layouts(LayoutRegistry::new().register(Bin))
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LayoutRegistry {
+    layouts: Vec<Arc<dyn Layout>>,
+    defaults: Vec<Arc<dyn Layout>>,
+}
+
+impl LayoutRegistry {
+    /// Create a new registry with the default layouts.
+    pub fn new() -> Self {
+        Self {
+            layouts: Vec::new(),
+            defaults: vec![
+                Arc::new(MultiPart),
+                Arc::new(ChunkNaming::coreutils()),
+            ],
+        }
+    }
+
+    /// Create a new registry without any layout.
+    pub fn empty() -> Self {
+        Self { layouts: Vec::new(), defaults: Vec::new() }
+    }
+
+    /// Register a layout.
+    pub fn register<L: Layout + 'static>(
+        mut self,
+        layout: L,
+    ) -> Self {
+        self.layouts.push(Arc::new(layout));
+        self
+    }
+
+    /// Get the layouts in the order they are tried.
+    pub fn layouts(&self) -> impl Iterator<Item = &Arc<dyn Layout>> {
+        self.layouts.iter().chain(self.defaults.iter())
+    }
+
+    /// Detect the layout of the chunk set in a directory.
+    pub(crate) async fn detect(
+        &self,
+        dir: &Path,
+    ) -> ioa::Result<Option<DetectedLayout>> {
+        let mut names: Vec<String> = Vec::new();
+
+        let mut read_dir: fsa::ReadDir = fsa::read_dir(dir).await?;
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path: PathBuf = entry.path();
+
+            // follow symlinks as the chunks may be linked from elsewhere
+            if !fsa::metadata(&path).await.is_ok_and(|m| m.is_file()) {
+                continue;
+            }
+
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+
+        let layout: &Arc<dyn Layout> =
+            match self.layouts().find(|layout| layout.detect(&names)) {
+                | Some(layout) => layout,
+                | None => return Ok(None),
+            };
+
+        let chunks: BTreeMap<usize, PathBuf> = names
+            .iter()
+            .filter_map(|name| Some((layout.index(name)?, dir.join(name))))
+            .collect();
+
+        Ok(Some(DetectedLayout {
+            id: layout.id().to_string(),
+            original_name: layout.original_name(&names),
+            chunks,
+        }))
+    }
+}
+
+impl Default for LayoutRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Chunk set detected in a directory.
+#[derive(Debug, Clone)]
+pub(crate) struct DetectedLayout {
+    /// Identity of the layout.
+    pub(crate) id: String,
+    /// Name of the original file, if kept by the layout.
+    pub(crate) original_name: Option<String>,
+    /// Paths of the chunks by their indices.
+    pub(crate) chunks: BTreeMap<usize, PathBuf>,
+}
+
+impl ChunkStore for DetectedLayout {
+    fn head(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Option<ChunkHead>> {
+        Box::pin(async move {
+            let path: &PathBuf = match self.chunks.get(&index) {
+                | Some(path) => path,
+                | None => return Ok(None),
+            };
+
+            let metadata: std::fs::Metadata = fsa::metadata(path).await?;
+
+            Ok(Some(ChunkHead { size: metadata.len() as usize, etag: None }))
+        })
+    }
+
+    fn get(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Vec<u8>> {
+        Box::pin(async move {
+            match self.chunks.get(&index) {
+                | Some(path) => fsa::read(path).await,
+                | None => Err(ioa::Error::new(
+                    ioa::ErrorKind::NotFound,
+                    "chunk not found in layout",
+                )),
+            }
+        })
+    }
+
+    fn put(
+        &self,
+        _index: usize,
+        _data: Vec<u8>,
+    ) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            Err(ioa::Error::new(
+                ioa::ErrorKind::Unsupported,
+                "layout is read-only",
+            ))
+        })
+    }
+}
@@ -20,12 +20,95 @@
 //!         .unwrap();
 //! }
 //! ```
+//!
+//! ## Memory
+//!
+//! The memory of the split and merge processes does not depend on the size
+//! of the file, so files larger than the memory available are supported.
+//!
+//! - The chunks are streamed through a buffer bounded by the
+//!   `max_buffer_capacity`, so the memory does not depend on the
+//!   `chunk_size` either, including the checksums computed along the way.
+//! - The chunks are held in full where they cannot be streamed, which is
+//!   when they are transformed, when the split process finds boundaries
+//!   with a [`split::SplitMode`] other than `Fixed`, resumes, reads with
+//!   `direct_io` or writes bao outboards, and when the merge process
+//!   fetches from a store without ranges or through a cache directory.
+//! - The manifest keeps a record of each chunk, which is bounded by the
+//!   `max_chunks` of the split process.
+//!
+//! With a `memory_budget`, the buffers are capped to fit the budget and
+//! reserved from it before running, while a process holding chunks in full
+//! larger than the whole budget fails instead of exceeding it.
 
 mod functions;
 
 /// Config module.
 pub mod config;
 
+/// Boundary module.
+pub mod boundary;
+
+/// Transform module.
+pub mod transform;
+
+/// Naming module.
+pub mod naming;
+
+/// Layout module.
+pub mod layout;
+
+/// Header module.
+pub mod header;
+
+/// Attributes module.
+pub mod attributes;
+
+/// Device module.
+pub mod device;
+
+/// Manifest module.
+pub mod manifest;
+
+/// MIME module.
+pub mod mime;
+
+/// Store module.
+pub mod store;
+
+/// Key module.
+pub mod key;
+
+/// ETag module.
+#[cfg(feature = "etag")]
+pub mod etag;
+
+/// PAR2 module.
+#[cfg(feature = "par2")]
+pub mod par2;
+
+/// Checksum module.
+#[cfg(feature = "checksum")]
+pub mod checksum;
+
+/// Merkle module.
+#[cfg(feature = "checksum")]
+pub mod merkle;
+
+/// Integrity module.
+#[cfg(feature = "checksum")]
+pub mod integrity {
+    pub use crate::merkle::*;
+}
+
+/// Bao module.
+#[cfg(feature = "bao")]
+pub mod bao;
+
+/// Unicode module.
+#[cfg(feature = "unicode")]
+pub mod unicode;
+
 /// Split module.
 pub mod split {
     pub use crate::functions::split::*;
@@ -40,3 +123,71 @@ pub mod check {
 pub mod merge {
     pub use crate::functions::merge::*;
 }
+
+/// Inspect module.
+pub mod inspect {
+    pub use crate::functions::inspect::*;
+}
+
+/// Peek module.
+pub mod peek {
+    pub use crate::functions::peek::*;
+}
+
+/// Diff module.
+pub mod diff {
+    pub use crate::functions::diff::*;
+}
+
+/// Sync module.
+pub mod sync {
+    pub use crate::functions::sync::*;
+}
+
+/// Rechunk module.
+pub mod rechunk {
+    pub use crate::functions::rechunk::*;
+}
+
+/// Session module.
+pub mod session;
+
+/// Serve module.
+#[cfg(feature = "serve")]
+pub mod serve;
+
+/// Control module.
+pub mod control;
+
+/// Event module.
+pub mod event;
+
+/// Progress module.
+pub mod progress;
+
+/// Stall module.
+pub mod stall;
+
+/// Log module.
+pub mod log;
+
+/// Tune module.
+pub mod tune;
+
+/// Health module.
+pub mod health;
+
+/// Profile module.
+pub mod profile;
+
+/// Scheduler module.
+pub mod scheduler;
+
+/// Blocking module.
+pub mod blocking;
+
+/// Clock module.
+pub mod clock;
+
+/// Rng module.
+pub mod rng;
@@ -0,0 +1,378 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncWriteExt},
+    sync::Mutex,
+};
+
+#[cfg(feature = "checksum")]
+use crate::checksum::{file_digest, Algorithm};
+use crate::{
+    check::{Check, CheckResult},
+    manifest::Uuid,
+};
+
+/// Operation recorded in the [`OperationLog`], with its inputs and results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum Operation {
+    /// The split process finished.
+    Split {
+        /// Path of the original file.
+        in_file: PathBuf,
+        /// Directory of the chunks.
+        out_dir: PathBuf,
+        /// Identity of the split.
+        id: Uuid,
+        /// Size of the original file.
+        file_size: usize,
+        /// Total size of the chunks in storage, as expected by the check
+        /// process.
+        stored_size: usize,
+        /// The total number of chunks.
+        total_chunks: usize,
+        /// Checksums of the original file in hex, keyed by the id of their
+        /// algorithms.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        file_hashes: BTreeMap<String, String>,
+        /// Root of the Merkle tree over the chunks in hex, if recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        merkle_root: Option<String>,
+    },
+    /// The check process finished with a result.
+    Check {
+        /// Directory of the chunks, or `None` if checked in a store.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        in_dir: Option<PathBuf>,
+        /// Expected size of the chunks.
+        file_size: usize,
+        /// Expected number of chunks.
+        total_chunks: usize,
+        /// Whether the check passed.
+        success: bool,
+        /// Code of the error type if failed, see
+        /// [`CheckResultErrorType::as_code`].
+        ///
+        /// [`CheckResultErrorType::as_code`]: crate::check::CheckResultErrorType::as_code
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error_type: Option<String>,
+    },
+    /// The merge process finished.
+    Merge {
+        /// Directory of the chunks, or `None` if merged from a store.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        in_dir: Option<PathBuf>,
+        /// Path of the output file.
+        out_file: PathBuf,
+        /// Size of the output file.
+        file_size: usize,
+        /// Checksums of the output file in hex verified while merging, keyed
+        /// by the id of their algorithms.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        file_hashes: BTreeMap<String, String>,
+    },
+}
+
+/// Entry of the [`OperationLog`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationRecord {
+    /// Time of the record in milliseconds since the Unix epoch.
+    pub time: u64,
+    /// The operation recorded.
+    #[serde(flatten)]
+    pub operation: Operation,
+}
+
+/// Outcome of replaying an [`OperationRecord`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayOutcome {
+    /// The current state matches the recorded results.
+    Verified,
+    /// The current state differs from the recorded results.
+    Mismatch(String),
+    /// The record cannot be replayed, e.g. the chunks were in a store.
+    Unverifiable,
+}
+
+/// Result of replaying an [`OperationRecord`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayResult {
+    /// The record replayed.
+    pub record: OperationRecord,
+    /// Outcome of the replay.
+    pub outcome: ReplayOutcome,
+}
+
+/// Log of the operations in a JSONL file, with a record of each finished
+/// split, check and merge process on a line.
+///
+/// The log gives batch pipelines an audit trail, and [`OperationLog::replay`]
+/// verifies the chunks and files against the recorded results later, e.g.
+/// before merging an old chunk set. Only the processes finishing with a
+/// result are recorded, and a process fails if its record cannot be
+/// written. The log can be shared between processes.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     log::{OperationLog, ReplayOutcome},
+///     split::Split,
+/// };
+///
+/// async fn example() {
+///     let log: OperationLog = OperationLog::new("/path/to/log.jsonl");
+///
+///     Split::new()
+///         .in_file("/path/to/file")
+///         .out_dir(PathBuf::from("path").join("to").join("dir"))
+///         .log(log.clone())
+///         .run()
+///         .await
+///         .unwrap();
+///
+///     // later
+///     for result in log.replay().await.unwrap() {
+///         if let ReplayOutcome::Mismatch(message) = result.outcome {
+///             println!("{}", message);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OperationLog {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+impl OperationLog {
+    /// Create a new log appending to the file at `path`, which is created
+    /// when the first record is written.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Get the path of the log.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record of the operation at the current time.
+    pub async fn append(
+        &self,
+        operation: Operation,
+    ) -> ioa::Result<()> {
+        let time: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        let mut line: Vec<u8> =
+            serde_json::to_vec(&OperationRecord { time, operation })
+                .map_err(|e| ioa::Error::new(ioa::ErrorKind::InvalidData, e))?;
+
+        line.push(b'\n');
+
+        // records of concurrent processes are not interleaved
+        let _guard = self.lock.lock().await;
+
+        let mut file: fsa::File = fsa::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        file.write_all(&line).await?;
+        file.flush().await
+    }
+
+    /// Read the records in order, failing with
+    /// [`ioa::ErrorKind::InvalidData`] if a line is not a record.
+    pub async fn records(&self) -> ioa::Result<Vec<OperationRecord>> {
+        let content: String = match fsa::read_to_string(&self.path).await {
+            | Ok(content) => content,
+            | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                return Ok(Vec::new())
+            },
+            | Err(e) => return Err(e),
+        };
+
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    ioa::Error::new(
+                        ioa::ErrorKind::InvalidData,
+                        format!("invalid record at line {}: {}", i + 1, e),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Replay the records in order, verifying the current state against the
+    /// recorded results.
+    ///
+    /// - A split is verified by checking its chunks, and against the
+    ///   recorded Merkle root with the `checksum` feature.
+    /// - A check is verified by running it again with the same result.
+    /// - A merge is verified by the size of the output file, and its
+    ///   recorded checksums with the `checksum` feature.
+    pub async fn replay(&self) -> ioa::Result<Vec<ReplayResult>> {
+        let mut results: Vec<ReplayResult> = Vec::new();
+
+        for record in self.records().await? {
+            let outcome: ReplayOutcome = replay(&record.operation).await?;
+
+            results.push(ReplayResult { record, outcome });
+        }
+
+        Ok(results)
+    }
+}
+
+async fn replay(operation: &Operation) -> ioa::Result<ReplayOutcome> {
+    match operation {
+        | Operation::Split {
+            out_dir,
+            stored_size,
+            total_chunks,
+            merkle_root,
+            ..
+        } => {
+            if !out_dir.is_dir() {
+                return Ok(ReplayOutcome::Mismatch(format!(
+                    "{} not found",
+                    out_dir.display()
+                )));
+            }
+
+            let check: Check = Check::new()
+                .in_dir(out_dir)
+                .file_size(*stored_size)
+                .total_chunks(*total_chunks);
+
+            #[cfg(feature = "checksum")]
+            let check: Check = match merkle_root {
+                | Some(root) => check.merkle_root(root),
+                | None => check,
+            };
+
+            #[cfg(not(feature = "checksum"))]
+            let _ = merkle_root;
+
+            let result: CheckResult = check.run().await?;
+
+            Ok(match result.error {
+                | Some(error) => ReplayOutcome::Mismatch(error.message),
+                | None => ReplayOutcome::Verified,
+            })
+        },
+        | Operation::Check {
+            in_dir: Some(in_dir),
+            file_size,
+            total_chunks,
+            success,
+            error_type,
+        } => {
+            if !in_dir.is_dir() {
+                return Ok(ReplayOutcome::Mismatch(format!(
+                    "{} not found",
+                    in_dir.display()
+                )));
+            }
+
+            let result: CheckResult = Check::new()
+                .in_dir(in_dir)
+                .file_size(*file_size)
+                .total_chunks(*total_chunks)
+                .run()
+                .await?;
+
+            let replayed: Option<&str> =
+                result.error.as_ref().map(|e| e.error_type.as_code());
+
+            Ok(
+                if result.success == *success
+                    && replayed == error_type.as_deref()
+                {
+                    ReplayOutcome::Verified
+                } else {
+                    ReplayOutcome::Mismatch(format!(
+                        "check resulted in {}, recorded {}",
+                        replayed.unwrap_or("success"),
+                        error_type.as_deref().unwrap_or("success"),
+                    ))
+                },
+            )
+        },
+        | Operation::Check { in_dir: None, .. } => {
+            Ok(ReplayOutcome::Unverifiable)
+        },
+        | Operation::Merge { out_file, file_size, file_hashes, .. } => {
+            let size: usize = match fsa::metadata(out_file).await {
+                | Ok(metadata) if metadata.is_file() => metadata.len() as usize,
+                | Ok(_) => {
+                    return Ok(ReplayOutcome::Mismatch(format!(
+                        "{} is not a file",
+                        out_file.display()
+                    )))
+                },
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                    return Ok(ReplayOutcome::Mismatch(format!(
+                        "{} not found",
+                        out_file.display()
+                    )))
+                },
+                | Err(e) => return Err(e),
+            };
+
+            if size != *file_size {
+                return Ok(ReplayOutcome::Mismatch(format!(
+                    "size of {} is {}, recorded {}",
+                    out_file.display(),
+                    size,
+                    file_size
+                )));
+            }
+
+            #[cfg(feature = "checksum")]
+            for (id, digest) in file_hashes {
+                let algorithm: Algorithm = match Algorithm::from_id(id) {
+                    | Some(algorithm) => algorithm,
+                    | None => continue,
+                };
+
+                let actual: String =
+                    file_digest(out_file.clone(), algorithm).await?;
+
+                if !actual.eq_ignore_ascii_case(digest) {
+                    return Ok(ReplayOutcome::Mismatch(format!(
+                        "{} of {} does not match",
+                        id,
+                        out_file.display()
+                    )));
+                }
+            }
+
+            #[cfg(not(feature = "checksum"))]
+            let _ = file_hashes;
+
+            Ok(ReplayOutcome::Verified)
+        },
+    }
+}
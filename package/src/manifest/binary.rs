@@ -0,0 +1,393 @@
+use std::collections::BTreeMap;
+
+use tokio::io as ioa;
+
+use crate::{
+    attributes::FileMetadata,
+    manifest::{ChunkInfo, Manifest, SourceInfo, Uuid},
+    naming::ChunkNaming,
+};
+
+/// Magic bytes at the start of a binary manifest.
+pub(crate) const MAGIC: [u8; 4] = *b"FGMF";
+
+/// Version of the binary encoding, where version `2` adds the metadata of
+/// the original file, version `3` adds its MIME type, version `4` adds the
+/// sources, and version `5` adds the attributes and alternate data streams
+/// on Windows.
+const ENCODING_VERSION: u8 = 5;
+
+/// Smallest encoded size of a chunk, used to bound the allocation.
+const CHUNK_SIZE_MIN: usize = 4 * 8 + 1 + 4;
+
+/// Smallest encoded size of a source, used to bound the allocation.
+const SOURCE_SIZE_MIN: usize = 4 + 2 * 8;
+
+/// Encode the manifest in the binary format.
+///
+/// The integers are little-endian, the strings and maps are prefixed with
+/// their lengths, and the optional values with a byte of `0` or `1`.
+pub(crate) fn encode(manifest: &Manifest) -> Vec<u8> {
+    let mut writer: Writer =
+        Writer(Vec::with_capacity(64 + manifest.chunks.len() * 64));
+
+    writer.bytes(&MAGIC);
+    writer.u8(ENCODING_VERSION);
+    writer.u32(manifest.version);
+    writer.option(manifest.id.as_ref(), |w, id| w.bytes(id.as_bytes()));
+    writer.option(manifest.file_name.as_ref(), |w, name| w.str(name));
+    writer.option(manifest.mime_type.as_ref(), |w, mime| w.str(mime));
+    writer.usize(manifest.file_size);
+    writer.map(&manifest.file_hashes);
+    writer.option(manifest.file_metadata.as_ref(), |w, metadata| {
+        w.option(metadata.mode.as_ref(), |w, mode| w.u32(*mode));
+        w.bool(metadata.readonly);
+        w.option(metadata.modified.as_ref(), |w, m| w.u64(*m));
+        w.map(&metadata.xattrs);
+        w.option(metadata.attributes.as_ref(), |w, a| w.u32(*a));
+        w.map(&metadata.streams);
+    });
+    writer.usize(manifest.sources.len());
+
+    for source in &manifest.sources {
+        writer.str(&source.name);
+        writer.usize(source.offset);
+        writer.usize(source.size);
+    }
+
+    writer.usize(manifest.chunk_size);
+    writer.usize(manifest.total_chunks);
+    writer.bool(manifest.zero_padding);
+
+    match &manifest.naming {
+        | ChunkNaming::Index => writer.u8(0),
+        | ChunkNaming::Alphabetic { prefix, suffix_length } => {
+            writer.u8(1);
+            writer.str(prefix);
+            writer.usize(*suffix_length);
+        },
+        | ChunkNaming::Numeric { prefix, width, start } => {
+            writer.u8(2);
+            writer.str(prefix);
+            writer.usize(*width);
+            writer.usize(*start);
+        },
+    }
+
+    writer.option(manifest.transform.as_ref(), |w, id| w.str(id));
+    writer.bool(manifest.headers);
+    writer.option(manifest.volume.as_ref(), |w, v| w.usize(*v));
+    writer.option(manifest.merkle_root.as_ref(), |w, root| w.str(root));
+    writer.usize(manifest.chunks.len());
+
+    for chunk in &manifest.chunks {
+        writer.usize(chunk.index);
+        writer.usize(chunk.offset);
+        writer.usize(chunk.size);
+        writer.usize(chunk.stored_size);
+        writer.option(chunk.volume.as_ref(), |w, v| w.usize(*v));
+        writer.map(&chunk.metadata);
+    }
+
+    writer.0
+}
+
+/// Decode the manifest from the binary format.
+pub(crate) fn decode(data: &[u8]) -> ioa::Result<Manifest> {
+    let mut reader: Reader = Reader(data);
+
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(invalid("magic bytes not found"));
+    }
+
+    let encoding: u8 = reader.u8()?;
+
+    if encoding == 0 || encoding > ENCODING_VERSION {
+        return Err(invalid("unsupported encoding version"));
+    }
+
+    let version: u32 = reader.u32()?;
+
+    let id: Option<Uuid> = reader.option(|r| {
+        Uuid::from_slice(r.bytes(16)?).map_err(|_| invalid("invalid id"))
+    })?;
+
+    let file_name: Option<String> = reader.option(Reader::string)?;
+
+    let mime_type: Option<String> = match encoding {
+        | 1 | 2 => None,
+        | _ => reader.option(Reader::string)?,
+    };
+
+    let file_size: usize = reader.usize()?;
+    let file_hashes: BTreeMap<String, String> = reader.map()?;
+
+    let file_metadata: Option<FileMetadata> = match encoding {
+        | 1 => None,
+        | _ => reader.option(|r| {
+            Ok(FileMetadata {
+                mode: r.option(Reader::u32)?,
+                readonly: r.bool()?,
+                modified: r.option(Reader::u64)?,
+                xattrs: r.map()?,
+                attributes: match encoding {
+                    | 1..=4 => None,
+                    | _ => r.option(Reader::u32)?,
+                },
+                streams: match encoding {
+                    | 1..=4 => BTreeMap::new(),
+                    | _ => r.map()?,
+                },
+            })
+        })?,
+    };
+
+    let sources: Vec<SourceInfo> = match encoding {
+        | 1..=3 => Vec::new(),
+        | _ => {
+            let count: usize = reader.usize()?;
+
+            let mut sources: Vec<SourceInfo> =
+                Vec::with_capacity(count.min(reader.0.len() / SOURCE_SIZE_MIN));
+
+            for _ in 0..count {
+                sources.push(SourceInfo {
+                    name: reader.string()?,
+                    offset: reader.usize()?,
+                    size: reader.usize()?,
+                });
+            }
+
+            sources
+        },
+    };
+
+    let chunk_size: usize = reader.usize()?;
+    let total_chunks: usize = reader.usize()?;
+    let zero_padding: bool = reader.bool()?;
+
+    let naming: ChunkNaming = match reader.u8()? {
+        | 0 => ChunkNaming::Index,
+        | 1 => ChunkNaming::Alphabetic {
+            prefix: reader.string()?,
+            suffix_length: reader.usize()?,
+        },
+        | 2 => ChunkNaming::Numeric {
+            prefix: reader.string()?,
+            width: reader.usize()?,
+            start: reader.usize()?,
+        },
+        | _ => return Err(invalid("unknown naming")),
+    };
+
+    naming.validate()?;
+
+    let transform: Option<String> = reader.option(Reader::string)?;
+    let headers: bool = reader.bool()?;
+    let volume: Option<usize> = reader.option(Reader::usize)?;
+    let merkle_root: Option<String> = reader.option(Reader::string)?;
+
+    let count: usize = reader.usize()?;
+
+    let mut chunks: Vec<ChunkInfo> =
+        Vec::with_capacity(count.min(reader.0.len() / CHUNK_SIZE_MIN));
+
+    for _ in 0..count {
+        chunks.push(ChunkInfo {
+            index: reader.usize()?,
+            offset: reader.usize()?,
+            size: reader.usize()?,
+            stored_size: reader.usize()?,
+            volume: reader.option(Reader::usize)?,
+            metadata: reader.map()?,
+        });
+    }
+
+    if !reader.0.is_empty() {
+        return Err(invalid("trailing bytes found"));
+    }
+
+    Ok(Manifest {
+        version,
+        id,
+        file_name,
+        mime_type,
+        file_size,
+        file_hashes,
+        file_metadata,
+        sources,
+        chunk_size,
+        total_chunks,
+        zero_padding,
+        naming,
+        transform,
+        headers,
+        volume,
+        merkle_root,
+        chunks,
+    })
+}
+
+fn invalid(message: &str) -> ioa::Error {
+    ioa::Error::new(
+        ioa::ErrorKind::InvalidData,
+        format!("invalid binary manifest: {}", message),
+    )
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(
+        &mut self,
+        data: &[u8],
+    ) {
+        self.0.extend_from_slice(data);
+    }
+
+    fn u8(
+        &mut self,
+        value: u8,
+    ) {
+        self.0.push(value);
+    }
+
+    fn bool(
+        &mut self,
+        value: bool,
+    ) {
+        self.u8(value as u8);
+    }
+
+    fn u32(
+        &mut self,
+        value: u32,
+    ) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(
+        &mut self,
+        value: u64,
+    ) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn usize(
+        &mut self,
+        value: usize,
+    ) {
+        self.u64(value as u64);
+    }
+
+    fn str(
+        &mut self,
+        value: &str,
+    ) {
+        self.u32(value.len() as u32);
+        self.bytes(value.as_bytes());
+    }
+
+    fn option<T>(
+        &mut self,
+        value: Option<&T>,
+        write: impl FnOnce(&mut Self, &T),
+    ) {
+        match value {
+            | Some(value) => {
+                self.u8(1);
+                write(self, value);
+            },
+            | None => self.u8(0),
+        }
+    }
+
+    fn map(
+        &mut self,
+        map: &BTreeMap<String, String>,
+    ) {
+        self.u32(map.len() as u32);
+
+        for (key, value) in map {
+            self.str(key);
+            self.str(value);
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(
+        &mut self,
+        len: usize,
+    ) -> ioa::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(invalid("unexpected end of data"));
+        }
+
+        let (bytes, rest) = self.0.split_at(len);
+
+        self.0 = rest;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> ioa::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bool(&mut self) -> ioa::Result<bool> {
+        match self.u8()? {
+            | 0 => Ok(false),
+            | 1 => Ok(true),
+            | _ => Err(invalid("invalid boolean")),
+        }
+    }
+
+    fn u32(&mut self) -> ioa::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> ioa::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> ioa::Result<usize> {
+        let value: u64 = self.u64()?;
+
+        usize::try_from(value).map_err(|_| invalid("integer out of range"))
+    }
+
+    fn string(&mut self) -> ioa::Result<String> {
+        let len: usize = self.u32()? as usize;
+
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| invalid("invalid UTF-8 string"))
+    }
+
+    fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> ioa::Result<T>,
+    ) -> ioa::Result<Option<T>> {
+        match self.u8()? {
+            | 0 => Ok(None),
+            | 1 => Ok(Some(read(self)?)),
+            | _ => Err(invalid("invalid option")),
+        }
+    }
+
+    fn map(&mut self) -> ioa::Result<BTreeMap<String, String>> {
+        let len: u32 = self.u32()?;
+
+        let mut map: BTreeMap<String, String> = BTreeMap::new();
+
+        for _ in 0..len {
+            let key: String = self.string()?;
+
+            map.insert(key, self.string()?);
+        }
+
+        Ok(map)
+    }
+}
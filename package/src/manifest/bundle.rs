@@ -0,0 +1,231 @@
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+
+use super::{ChunkInfo, Manifest, SourceInfo};
+use crate::{header::CHUNK_HEADER_SIZE, store::DirStore};
+
+impl Manifest {
+    /// Restore the original files bundled by
+    /// [`Split::in_files`](crate::split::Split::in_files) from the `file`
+    /// merged from the chunks, writing them to `out_dir` under their names
+    /// and returning their paths in order.
+    ///
+    /// It fails with [`ioa::ErrorKind::InvalidInput`] if the manifest does
+    /// not record the sources, with [`ioa::ErrorKind::InvalidData`] if a
+    /// name is not a plain file name, and with
+    /// [`ioa::ErrorKind::UnexpectedEof`] if the file is shorter than the
+    /// sources.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::path::PathBuf;
+    ///
+    /// use filego::{manifest::Manifest, merge::Merge};
+    ///
+    /// async fn example() {
+    ///     let manifest: Manifest =
+    ///         Manifest::read("/path/to/dir/manifest.json").await.unwrap();
+    ///
+    ///     Merge::new()
+    ///         .from_manifest("/path/to/dir/manifest.json")
+    ///         .out_file("/path/to/bundle")
+    ///         .run()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let files: Vec<PathBuf> = manifest
+    ///         .unbundle("/path/to/bundle", "/path/to/files")
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub async fn unbundle<File: AsRef<Path>, OutDir: AsRef<Path>>(
+        &self,
+        file: File,
+        out_dir: OutDir,
+    ) -> ioa::Result<Vec<PathBuf>> {
+        if self.sources.is_empty() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "manifest does not record the sources",
+            ));
+        }
+
+        let paths: Vec<PathBuf> = self
+            .sources
+            .iter()
+            .map(|source| source_path(out_dir.as_ref(), source))
+            .collect::<ioa::Result<_>>()?;
+
+        fsa::create_dir_all(out_dir.as_ref()).await?;
+
+        let mut input: fsa::File =
+            fsa::OpenOptions::new().read(true).open(file.as_ref()).await?;
+
+        for (source, path) in self.sources.iter().zip(&paths) {
+            input.seek(SeekFrom::Start(source.offset as u64)).await?;
+
+            let mut output: fsa::File = fsa::File::create(path).await?;
+
+            let copied: u64 = ioa::copy(
+                &mut (&mut input).take(source.size as u64),
+                &mut output,
+            )
+            .await?;
+
+            if copied != source.size as u64 {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::UnexpectedEof,
+                    format!("file is shorter than the source {}", source.name),
+                ));
+            }
+
+            output.flush().await?;
+        }
+
+        Ok(paths)
+    }
+
+    /// Extract the source `name` bundled by
+    /// [`Split::in_files`](crate::split::Split::in_files) straight from the
+    /// chunks in `chunk_dir` to `out_file`, without merging the bundle.
+    ///
+    /// Only the chunks overlapping the range of the source are read, from
+    /// the offset of the source within the first one. It fails with
+    /// [`ioa::ErrorKind::NotFound`] if the manifest does not record the
+    /// source, with [`ioa::ErrorKind::InvalidInput`] if the chunks are
+    /// transformed, as the offsets within them are unknown, and with
+    /// [`ioa::ErrorKind::UnexpectedEof`] if a chunk is shorter than
+    /// expected.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::manifest::Manifest;
+    ///
+    /// async fn example() {
+    ///     let manifest: Manifest =
+    ///         Manifest::read("/path/to/dir/manifest.json").await.unwrap();
+    ///
+    ///     manifest
+    ///         .extract_member(
+    ///             "/path/to/dir",
+    ///             "notes.txt",
+    ///             "/path/to/notes.txt",
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub async fn extract_member<ChunkDir: AsRef<Path>, OutFile: AsRef<Path>>(
+        &self,
+        chunk_dir: ChunkDir,
+        name: &str,
+        out_file: OutFile,
+    ) -> ioa::Result<()> {
+        let source: &SourceInfo =
+            match self.sources.iter().find(|source| source.name == name) {
+                | Some(source) => source,
+                | None => {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::NotFound,
+                        format!("source {} not found in the manifest", name),
+                    ))
+                },
+            };
+
+        if self.transform.is_some() {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "transformed chunks are not supported",
+            ));
+        }
+
+        let store: DirStore = DirStore::with_manifest(chunk_dir, self);
+
+        let skip: usize = if self.headers { CHUNK_HEADER_SIZE } else { 0 };
+
+        let start: usize = source.offset;
+        let end: usize = source.offset + source.size;
+
+        let mut chunks: Vec<&ChunkInfo> = self.chunks.iter().collect();
+
+        chunks.sort_unstable_by_key(|chunk| chunk.offset);
+
+        let first: usize =
+            chunks.partition_point(|chunk| chunk.offset + chunk.size <= start);
+
+        if let Some(parent) = out_file.as_ref().parent() {
+            fsa::create_dir_all(parent).await?;
+        }
+
+        let mut output: fsa::File =
+            fsa::File::create(out_file.as_ref()).await?;
+
+        let mut written: usize = 0;
+
+        for chunk in chunks[first..].iter().take_while(|c| c.offset < end) {
+            let from: usize = start.max(chunk.offset) - chunk.offset;
+            let to: usize = end.min(chunk.offset + chunk.size) - chunk.offset;
+
+            let mut input: fsa::File = fsa::OpenOptions::new()
+                .read(true)
+                .open(store.path(chunk.index))
+                .await?;
+
+            input.seek(SeekFrom::Start((skip + from) as u64)).await?;
+
+            let copied: u64 =
+                ioa::copy(&mut input.take((to - from) as u64), &mut output)
+                    .await?;
+
+            if copied != (to - from) as u64 {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::UnexpectedEof,
+                    format!("chunk {} is shorter than expected", chunk.index),
+                ));
+            }
+
+            written += to - from;
+        }
+
+        if written != source.size {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::UnexpectedEof,
+                format!("chunks are shorter than the source {}", name),
+            ));
+        }
+
+        output.flush().await
+    }
+}
+
+/// Get the path of the source in `out_dir`, rejecting the names escaping
+/// the directory.
+fn source_path(
+    out_dir: &Path,
+    source: &SourceInfo,
+) -> ioa::Result<PathBuf> {
+    let name: &str = &source.name;
+
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\'])
+    {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("invalid source name: {}", name),
+        ));
+    }
+
+    Ok(out_dir.join(name))
+}
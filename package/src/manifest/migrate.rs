@@ -0,0 +1,126 @@
+use std::{error, fmt};
+
+use serde_json::{Map, Value};
+use tokio::io as ioa;
+
+use super::{binary, Manifest, ManifestFormat, SchemaVersion};
+
+/// Step upgrading the JSON manifest from a version to the next.
+type Migration = fn(&mut Map<String, Value>) -> Result<(), MigrateError>;
+
+/// Migrations of the JSON manifest keyed by the version they upgrade from,
+/// in order. A new schema version adds its migration here.
+const MIGRATIONS: &[(u32, Migration)] = &[];
+
+/// Error of reading the version of a manifest or migrating it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrateError {
+    /// The manifest has no `version` field.
+    MissingVersion,
+    /// The version is not known to this version of the crate, e.g. written
+    /// by a newer version.
+    UnknownVersion(u32),
+    /// The manifest cannot be decoded.
+    Invalid(String),
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            | Self::MissingVersion => write!(f, "manifest version is missing"),
+            | Self::UnknownVersion(version) => write!(
+                f,
+                "manifest version {} is unknown, the latest version is {}",
+                version,
+                SchemaVersion::LATEST.number()
+            ),
+            | Self::Invalid(message) => {
+                write!(f, "invalid manifest: {}", message)
+            },
+        }
+    }
+}
+
+impl error::Error for MigrateError {}
+
+impl From<MigrateError> for ioa::Error {
+    fn from(e: MigrateError) -> Self {
+        ioa::Error::new(ioa::ErrorKind::InvalidData, e)
+    }
+}
+
+impl Manifest {
+    /// Get the schema version of the manifest, failing with
+    /// [`MigrateError::UnknownVersion`] if it is not known to this version
+    /// of the crate.
+    pub fn version(&self) -> Result<SchemaVersion, MigrateError> {
+        SchemaVersion::from_number(self.version)
+            .ok_or(MigrateError::UnknownVersion(self.version))
+    }
+}
+
+/// Decode the manifest in any format and upgrade it to the
+/// [`SchemaVersion::LATEST`], so the manifests of long-lived chunk sets are
+/// read after upgrading the crate.
+///
+/// The JSON manifest is upgraded one version at a time before being
+/// decoded, as the fields of an older version may not decode as the latest
+/// one. The binary manifest is upgraded by its decoder.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::manifest::{migrate, Manifest};
+///
+/// async fn example() {
+///     let data: Vec<u8> = tokio::fs::read("/path/to/manifest.json")
+///         .await
+///         .unwrap();
+///
+///     let manifest: Manifest = migrate(&data).unwrap();
+/// }
+/// ```
+pub fn migrate(data: &[u8]) -> Result<Manifest, MigrateError> {
+    let mut manifest: Manifest = match ManifestFormat::detect(data) {
+        | ManifestFormat::Json => migrate_json(data)?,
+        | ManifestFormat::Binary => binary::decode(data)
+            .map_err(|e| MigrateError::Invalid(e.to_string()))?,
+    };
+
+    manifest.version()?;
+
+    manifest.version = SchemaVersion::LATEST.number();
+
+    Ok(manifest)
+}
+
+fn migrate_json(data: &[u8]) -> Result<Manifest, MigrateError> {
+    let mut object: Map<String, Value> = serde_json::from_slice(data)
+        .map_err(|e| MigrateError::Invalid(e.to_string()))?;
+
+    let mut version: u32 = match object.get("version") {
+        | Some(version) => {
+            version.as_u64().and_then(|v| u32::try_from(v).ok()).ok_or_else(
+                || MigrateError::Invalid("version is not a number".to_string()),
+            )?
+        },
+        | None => return Err(MigrateError::MissingVersion),
+    };
+
+    SchemaVersion::from_number(version)
+        .ok_or(MigrateError::UnknownVersion(version))?;
+
+    for (from, migration) in MIGRATIONS {
+        if version == *from {
+            migration(&mut object)?;
+            version += 1;
+            object.insert("version".to_string(), Value::from(version));
+        }
+    }
+
+    serde_json::from_value(Value::Object(object))
+        .map_err(|e| MigrateError::Invalid(e.to_string()))
+}
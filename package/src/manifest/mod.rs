@@ -0,0 +1,478 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs as fsa, io as ioa};
+pub use uuid::Uuid;
+
+#[cfg(feature = "checksum")]
+use crate::checksum::Algorithm;
+use crate::{
+    attributes::FileMetadata,
+    config::{MANIFEST_BINARY_FILE_NAME, MANIFEST_FILE_NAME},
+    naming::ChunkNaming,
+    store::volume_dir,
+};
+
+mod binary;
+mod bundle;
+mod migrate;
+mod upgrade;
+
+#[cfg(feature = "signing")]
+mod signing;
+
+pub use migrate::{migrate, MigrateError};
+#[cfg(feature = "signing")]
+pub(crate) use signing::secure_checksum;
+#[cfg(feature = "signing")]
+pub use signing::{
+    read_signature, sign, verify, write_signature, Signature, SigningKey,
+    VerifyingKey,
+};
+pub use upgrade::{ChunkPlan, Piece, UpgradePlan};
+
+/// The current version of the manifest, which is the number of the
+/// [`SchemaVersion::LATEST`].
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Ids of the algorithms of the checksums in the manifest, whose digests
+/// are hex.
+const HASH_IDS: [&str; 6] =
+    ["sha256", "blake3", "crc32c", "crc32", "xxh3", "xxh64"];
+
+/// Version of the manifest schema shared with the other implementations of
+/// FileGo, e.g. the Node.js port.
+///
+/// Each version is described by a JSON Schema, see [`SchemaVersion::schema`].
+/// The field names are in snake case, the digests are lowercase hex keyed by
+/// the id of their algorithms, e.g. `sha256`, and the names of the chunks
+/// follow the `naming`, which is the index of the chunk by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaVersion {
+    /// The first version.
+    V1,
+}
+
+impl SchemaVersion {
+    /// The latest version, written by the split process.
+    pub const LATEST: Self = Self::V1;
+
+    /// Get the version from its number in the `version` field.
+    pub fn from_number(number: u32) -> Option<Self> {
+        match number {
+            | 1 => Some(Self::V1),
+            | _ => None,
+        }
+    }
+
+    /// Get the number of the version in the `version` field.
+    pub fn number(&self) -> u32 {
+        match self {
+            | Self::V1 => 1,
+        }
+    }
+
+    /// Get the JSON Schema of the manifest in this version.
+    pub fn schema(&self) -> &'static str {
+        match self {
+            | Self::V1 => include_str!("schema.v1.json"),
+        }
+    }
+}
+
+/// Format of the manifest file.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{manifest::ManifestFormat, split::Split};
+///
+/// let split: Split = Split::new()
+///     .manifest(true)
+///     .manifest_format(ManifestFormat::Binary);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ManifestFormat {
+    /// JSON, written as [`MANIFEST_FILE_NAME`].
+    #[default]
+    Json,
+    /// Compact binary starting with the magic bytes `FGMF` and the version
+    /// of the encoding, written as [`MANIFEST_BINARY_FILE_NAME`], e.g. for
+    /// embedded receivers without a JSON parser.
+    Binary,
+}
+
+impl ManifestFormat {
+    /// Get the file name of the manifest in this format.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            | Self::Json => MANIFEST_FILE_NAME,
+            | Self::Binary => MANIFEST_BINARY_FILE_NAME,
+        }
+    }
+
+    /// Detect the format of the encoded manifest.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(&binary::MAGIC) {
+            Self::Binary
+        } else {
+            Self::Json
+        }
+    }
+}
+
+/// Information of an original file concatenated into the chunks, see
+/// [`Split::in_files`](crate::split::Split::in_files).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceInfo {
+    /// Name of the file.
+    pub name: String,
+    /// Offset of the file in the concatenated files.
+    pub offset: usize,
+    /// Size of the file.
+    pub size: usize,
+}
+
+/// Information of a chunk in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Offset of the chunk in the original file.
+    pub offset: usize,
+    /// Size of the chunk in the original file.
+    pub size: usize,
+    /// Size of the chunk in storage, which differs from `size` when a
+    /// transform is applied.
+    pub stored_size: usize,
+    /// Volume containing the chunk, if splitted into volumes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<usize>,
+    /// Custom metadata of the chunk, e.g. upload URL or etag.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ChunkInfo {
+    /// Get the value of custom metadata.
+    pub fn get_metadata<K: AsRef<str>>(
+        &self,
+        key: K,
+    ) -> Option<&str> {
+        self.metadata.get(key.as_ref()).map(String::as_str)
+    }
+
+    /// Set the value of custom metadata, returns the previous value.
+    pub fn set_metadata<K: Into<String>, V: Into<String>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Option<String> {
+        self.metadata.insert(key.into(), value.into())
+    }
+}
+
+/// Manifest describing the chunks splitted from the original file.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::manifest::Manifest;
+///
+/// async fn example() {
+///     let manifest: Manifest = Manifest::read(
+///         PathBuf::from("path").join("to").join("dir").join("manifest.json"),
+///     )
+///     .await
+///     .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of the manifest.
+    pub version: u32,
+    /// Identity of the split, shared by the chunk headers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    /// Name of the original file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    /// MIME type of the original file, if sniffed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Size of the original file.
+    pub file_size: usize,
+    /// Checksums of the original file in hex, keyed by the id of their
+    /// algorithms, e.g. `sha256`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub file_hashes: BTreeMap<String, String>,
+    /// Metadata of the original file, if preserved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_metadata: Option<FileMetadata>,
+    /// Original files concatenated into the chunks in order, if bundled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<SourceInfo>,
+    /// Maximum size of each chunk.
+    pub chunk_size: usize,
+    /// The total number of chunks splitted from the original file.
+    pub total_chunks: usize,
+    /// Whether the names of the chunks are padded with zeros to the same
+    /// width, e.g. `007`.
+    #[serde(default)]
+    pub zero_padding: bool,
+    /// Scheme of the names of the chunks.
+    #[serde(default, skip_serializing_if = "ChunkNaming::is_index")]
+    pub naming: ChunkNaming,
+    /// Identity of the transform applied to the chunks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    /// Whether the chunks come with headers.
+    #[serde(default)]
+    pub headers: bool,
+    /// Volume described by the manifest, if splitted into volumes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<usize>,
+    /// Root of the Merkle tree over the SHA-256 checksums of the chunks in
+    /// hex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merkle_root: Option<String>,
+    /// Information of each chunk.
+    pub chunks: Vec<ChunkInfo>,
+}
+
+impl Manifest {
+    /// Get the information of the chunk at `index`.
+    pub fn chunk(
+        &self,
+        index: usize,
+    ) -> Option<&ChunkInfo> {
+        self.chunks.get(index).filter(|c| c.index == index)
+    }
+
+    /// Get the mutable information of the chunk at `index`.
+    pub fn chunk_mut(
+        &mut self,
+        index: usize,
+    ) -> Option<&mut ChunkInfo> {
+        self.chunks.get_mut(index).filter(|c| c.index == index)
+    }
+
+    /// Get the width of the names of the chunks, or `0` if the names are not
+    /// padded with zeros.
+    pub fn padding(&self) -> usize {
+        if self.zero_padding {
+            padding_width(self.total_chunks)
+        } else {
+            0
+        }
+    }
+
+    /// Get the total number of volumes, or `0` if not splitted into volumes.
+    pub fn total_volumes(&self) -> usize {
+        self.chunks.iter().filter_map(|c| c.volume).max().map_or(0, |v| v + 1)
+    }
+
+    /// Get the total size of the chunks in storage.
+    pub fn stored_size(&self) -> usize {
+        self.chunks.iter().map(|c| c.stored_size).sum()
+    }
+
+    /// Get the algorithm and the checksum of the original file, if any.
+    #[cfg(feature = "checksum")]
+    pub fn file_hash(&self) -> Option<(Algorithm, &str)> {
+        Algorithm::ALL.into_iter().find_map(|a| {
+            self.file_hashes.get(a.id()).map(|digest| (a, digest.as_str()))
+        })
+    }
+
+    /// Convert the manifest to the schema `version`, so the other
+    /// implementations of FileGo produce and consume identical metadata.
+    ///
+    /// The digests of the original file, the chunks and the Merkle root are
+    /// lowercased. The conversion fails with [`ioa::ErrorKind::InvalidData`]
+    /// if the manifest is newer than the version, a digest is not hex, or the
+    /// chunks are not ordered by index and contiguous up to the file size.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::manifest::{Manifest, SchemaVersion};
+    ///
+    /// async fn example() {
+    ///     let manifest: Manifest = Manifest::read("/path/to/manifest.json")
+    ///         .await
+    ///         .unwrap()
+    ///         .compat(SchemaVersion::V1)
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn compat(
+        &self,
+        version: SchemaVersion,
+    ) -> ioa::Result<Self> {
+        if self.version > version.number() {
+            return Err(incompatible(format!(
+                "manifest version {} is newer than the schema version {}",
+                self.version,
+                version.number()
+            )));
+        }
+
+        if self.chunks.len() != self.total_chunks {
+            return Err(incompatible(
+                "total_chunks is not equal to the number of chunks",
+            ));
+        }
+
+        let mut offset: usize = 0;
+
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            if chunk.index != i || chunk.offset != offset {
+                return Err(incompatible(format!(
+                    "chunk {} is not ordered or contiguous",
+                    i
+                )));
+            }
+
+            offset += chunk.size;
+        }
+
+        if offset != self.file_size {
+            return Err(incompatible(
+                "the size of chunks is not equal to file_size",
+            ));
+        }
+
+        let mut manifest: Manifest = self.clone();
+
+        manifest.version = version.number();
+
+        for (_, digest) in manifest.file_hashes.iter_mut() {
+            *digest = lowercase_hex(digest)?;
+        }
+
+        if let Some(root) = &mut manifest.merkle_root {
+            *root = lowercase_hex(root)?;
+        }
+
+        for chunk in &mut manifest.chunks {
+            for (key, value) in chunk.metadata.iter_mut() {
+                if HASH_IDS.contains(&key.as_str()) {
+                    *value = lowercase_hex(value)?;
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Decode the manifest in any format, detected from the data.
+    pub fn from_bytes(data: &[u8]) -> ioa::Result<Self> {
+        match ManifestFormat::detect(data) {
+            | ManifestFormat::Json => serde_json::from_slice(data)
+                .map_err(|e| ioa::Error::new(ioa::ErrorKind::InvalidData, e)),
+            | ManifestFormat::Binary => binary::decode(data),
+        }
+    }
+
+    /// Encode the manifest in the format.
+    pub fn to_bytes(
+        &self,
+        format: ManifestFormat,
+    ) -> ioa::Result<Vec<u8>> {
+        match format {
+            | ManifestFormat::Json => serde_json::to_vec_pretty(self)
+                .map_err(|e| ioa::Error::new(ioa::ErrorKind::InvalidData, e)),
+            | ManifestFormat::Binary => Ok(binary::encode(self)),
+        }
+    }
+
+    /// Read the manifest in any format from a path.
+    pub async fn read<P: AsRef<Path>>(path: P) -> ioa::Result<Self> {
+        Self::from_bytes(&fsa::read(path.as_ref()).await?)
+    }
+
+    /// Write the manifest to a path in JSON.
+    pub async fn write<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> ioa::Result<()> {
+        self.write_as(path, ManifestFormat::Json).await
+    }
+
+    /// Write the manifest to a path in the format.
+    pub async fn write_as<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: ManifestFormat,
+    ) -> ioa::Result<()> {
+        fsa::write(path.as_ref(), self.to_bytes(format)?).await
+    }
+}
+
+/// Get the width of the names of `total_chunks` chunks padded with zeros.
+pub(crate) fn padding_width(total_chunks: usize) -> usize {
+    total_chunks.saturating_sub(1).to_string().len()
+}
+
+fn incompatible<M: Into<String>>(message: M) -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::InvalidData, message.into())
+}
+
+/// Lowercase the digest in hex.
+fn lowercase_hex(digest: &str) -> ioa::Result<String> {
+    if digest.is_empty() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(incompatible(format!("digest {} is not hex", digest)));
+    }
+
+    Ok(digest.to_ascii_lowercase())
+}
+
+/// Get the directory of the chunks described by the manifest at `path`.
+pub(crate) fn manifest_dir(
+    path: &Path,
+    manifest: &Manifest,
+) -> PathBuf {
+    let dir: PathBuf = match path.parent() {
+        | Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        | _ => PathBuf::from("."),
+    };
+
+    // the manifest of a volume is in its volume directory
+    match manifest.volume {
+        | Some(_) => dir.join(".."),
+        | None => dir,
+    }
+}
+
+/// Find the manifest in `dir`, or in its first volume if splitted into
+/// volumes.
+pub(crate) async fn find_manifest(dir: &Path) -> ioa::Result<Option<Manifest>> {
+    match find_manifest_path(dir).await? {
+        | Some(path) => Ok(Some(Manifest::read(&path).await?)),
+        | None => Ok(None),
+    }
+}
+
+/// Get the path of the manifest in the directory or its first volume.
+pub(crate) async fn find_manifest_path(
+    dir: &Path
+) -> ioa::Result<Option<PathBuf>> {
+    for path in [
+        dir.join(MANIFEST_FILE_NAME),
+        dir.join(MANIFEST_BINARY_FILE_NAME),
+        volume_dir(dir, 0).join(MANIFEST_FILE_NAME),
+        volume_dir(dir, 0).join(MANIFEST_BINARY_FILE_NAME),
+    ] {
+        if fsa::try_exists(&path).await? {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use ed25519_dalek::Signer;
+pub use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use tokio::{fs as fsa, io as ioa};
+
+use crate::{
+    checksum::Algorithm,
+    config::SIGNATURE_FILE_NAME,
+    manifest::{ChunkInfo, Manifest},
+};
+
+/// Sign the manifest with the key of the sender.
+///
+/// The signature covers the manifest with the checksums of the chunks, so
+/// the chunks can be verified against it after travelling over untrusted
+/// networks. Every chunk requires a checksum resisting deliberate
+/// tampering, i.e. [`Algorithm::Sha256`] or [`Algorithm::Blake3`] recorded
+/// by [`Split::checksum`].
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{
+///     checksum::Algorithm,
+///     manifest::{sign, write_signature, Signature, SigningKey},
+///     split::{Split, SplitResult},
+/// };
+///
+/// async fn example(key: SigningKey) {
+///     let result: SplitResult = Split::new()
+///         .in_file("/path/to/file")
+///         .out_dir("/path/to/dir")
+///         .checksum(Algorithm::Sha256)
+///         .run()
+///         .await
+///         .unwrap();
+///
+///     let signature: Signature = sign(&result.manifest, &key).unwrap();
+///
+///     write_signature("/path/to/dir", &signature).await.unwrap();
+/// }
+/// ```
+///
+/// [`Split::checksum`]: crate::split::Split::checksum
+pub fn sign(
+    manifest: &Manifest,
+    key: &SigningKey,
+) -> ioa::Result<Signature> {
+    if !manifest.chunks.iter().all(|c| secure_checksum(c).is_some()) {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            "secure checksums are not recorded in the manifest",
+        ));
+    }
+
+    Ok(key.sign(&message(manifest)?))
+}
+
+/// Verify the signature of the manifest with the key of the sender.
+///
+/// The process fails with [`ioa::ErrorKind::InvalidData`] if the manifest
+/// is not signed by the key or has been modified since.
+pub fn verify(
+    manifest: &Manifest,
+    signature: &Signature,
+    key: &VerifyingKey,
+) -> ioa::Result<()> {
+    key.verify_strict(&message(manifest)?, signature).map_err(|_| {
+        ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            "the signature of the manifest is invalid",
+        )
+    })
+}
+
+/// Read the signature in the [`SIGNATURE_FILE_NAME`] file of `dir`.
+pub async fn read_signature<P: AsRef<Path>>(dir: P) -> ioa::Result<Signature> {
+    let data: Vec<u8> =
+        fsa::read(dir.as_ref().join(SIGNATURE_FILE_NAME)).await?;
+
+    Signature::from_slice(&data).map_err(|_| {
+        ioa::Error::new(ioa::ErrorKind::InvalidData, "invalid signature file")
+    })
+}
+
+/// Write the signature to the [`SIGNATURE_FILE_NAME`] file of `dir`, next
+/// to the manifest.
+pub async fn write_signature<P: AsRef<Path>>(
+    dir: P,
+    signature: &Signature,
+) -> ioa::Result<()> {
+    fsa::write(dir.as_ref().join(SIGNATURE_FILE_NAME), signature.to_bytes())
+        .await
+}
+
+/// Get the checksum of a chunk resisting deliberate tampering, if any.
+pub(crate) fn secure_checksum(chunk: &ChunkInfo) -> Option<(Algorithm, &str)> {
+    [Algorithm::Sha256, Algorithm::Blake3]
+        .into_iter()
+        .find_map(|a| chunk.get_metadata(a.id()).map(|digest| (a, digest)))
+}
+
+/// Get the signed message of the manifest, which is its compact JSON.
+fn message(manifest: &Manifest) -> ioa::Result<Vec<u8>> {
+    serde_json::to_vec(manifest)
+        .map_err(|e| ioa::Error::new(ioa::ErrorKind::InvalidData, e))
+}
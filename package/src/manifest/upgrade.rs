@@ -0,0 +1,215 @@
+use std::ops::Range;
+
+use tokio::io as ioa;
+
+use super::{ChunkInfo, Manifest};
+
+/// Source of a part of a new chunk in an [`UpgradePlan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    /// Bytes within an existing chunk, before transform.
+    Chunk {
+        /// Index of the existing chunk.
+        index: usize,
+        /// Range of the bytes within the existing chunk.
+        range: Range<usize>,
+    },
+    /// Bytes to read from the original file.
+    Source {
+        /// Range of the bytes in the original file.
+        range: Range<usize>,
+    },
+}
+
+/// Plan of a new chunk in an [`UpgradePlan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkPlan {
+    /// Index of the new chunk.
+    pub index: usize,
+    /// Range of the new chunk in the original file.
+    pub range: Range<usize>,
+    /// Parts of the new chunk in order.
+    pub pieces: Vec<Piece>,
+}
+
+impl ChunkPlan {
+    /// Check whether the chunk is derived from the existing chunks alone.
+    pub fn is_derived(&self) -> bool {
+        self.pieces.iter().all(|piece| matches!(piece, Piece::Chunk { .. }))
+    }
+}
+
+/// Plan to derive the chunks of a new manifest from the chunks of an
+/// existing manifest of the same file, see [`Manifest::upgrade_plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradePlan {
+    /// Plans of the new chunks, ordered by index.
+    pub chunks: Vec<ChunkPlan>,
+}
+
+impl UpgradePlan {
+    /// Get the indices of the new chunks derived from the existing chunks
+    /// alone.
+    pub fn derived(&self) -> Vec<usize> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.is_derived())
+            .map(|chunk| chunk.index)
+            .collect()
+    }
+
+    /// Get the ranges of the original file to read, merged and ordered.
+    pub fn source_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+
+        for piece in self.chunks.iter().flat_map(|chunk| &chunk.pieces) {
+            if let Piece::Source { range } = piece {
+                match ranges.last_mut() {
+                    | Some(last) if last.end == range.start => {
+                        last.end = range.end
+                    },
+                    | _ => ranges.push(range.clone()),
+                }
+            }
+        }
+
+        ranges
+    }
+
+    /// Get the number of bytes to read from the original file.
+    pub fn source_size(&self) -> usize {
+        self.source_ranges().iter().map(|range| range.len()).sum()
+    }
+}
+
+impl Manifest {
+    /// Plan how to derive the chunks described by the `new` manifest, e.g.
+    /// of the same file split with another chunk size, from the chunks
+    /// described by this manifest, except the `missing` ones.
+    ///
+    /// Each new chunk is sliced from the existing chunks overlapping its
+    /// range, so changing the chunk size of a large stored file reads the
+    /// original file only for the bytes not in the existing chunks. The
+    /// plan fails with [`ioa::ErrorKind::InvalidInput`] if the manifests
+    /// record different checksums of the original file.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::manifest::{Manifest, UpgradePlan};
+    ///
+    /// async fn example() {
+    ///     let old: Manifest =
+    ///         Manifest::read("/path/to/old/manifest.json").await.unwrap();
+    ///     let new: Manifest =
+    ///         Manifest::read("/path/to/new/manifest.json").await.unwrap();
+    ///
+    ///     let plan: UpgradePlan = old.upgrade_plan(&new, &[]).unwrap();
+    ///
+    ///     println!("{} bytes to read", plan.source_size());
+    /// }
+    /// ```
+    pub fn upgrade_plan(
+        &self,
+        new: &Manifest,
+        missing: &[usize],
+    ) -> ioa::Result<UpgradePlan> {
+        let differs: bool = self.file_hashes.iter().any(|(id, digest)| {
+            new.file_hashes
+                .get(id)
+                .is_some_and(|other| !other.eq_ignore_ascii_case(digest))
+        });
+
+        if differs {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "manifests describe different files",
+            ));
+        }
+
+        let mut old: Vec<&ChunkInfo> = self.chunks.iter().collect();
+
+        old.sort_unstable_by_key(|chunk| chunk.offset);
+
+        let chunks: Vec<ChunkPlan> = new
+            .chunks
+            .iter()
+            .map(|chunk| {
+                let range: Range<usize> =
+                    chunk.offset..chunk.offset + chunk.size;
+
+                ChunkPlan {
+                    index: chunk.index,
+                    pieces: pieces(&old, missing, range.clone()),
+                    range,
+                }
+            })
+            .collect();
+
+        Ok(UpgradePlan { chunks })
+    }
+}
+
+/// Get the pieces of a `range` of the original file from the `old` chunks
+/// ordered by offset.
+fn pieces(
+    old: &[&ChunkInfo],
+    missing: &[usize],
+    range: Range<usize>,
+) -> Vec<Piece> {
+    let mut pieces: Vec<Piece> = Vec::new();
+
+    let first: usize =
+        old.partition_point(|chunk| chunk.offset + chunk.size <= range.start);
+
+    let mut position: usize = range.start;
+
+    for chunk in &old[first..] {
+        if position >= range.end || chunk.offset >= range.end {
+            break;
+        }
+
+        // gap before the chunk
+        if chunk.offset > position {
+            push_source(&mut pieces, position..chunk.offset);
+            position = chunk.offset;
+        }
+
+        let end: usize = range.end.min(chunk.offset + chunk.size);
+
+        if end <= position {
+            continue;
+        }
+
+        match missing.contains(&chunk.index) {
+            | true => push_source(&mut pieces, position..end),
+            | false => pieces.push(Piece::Chunk {
+                index: chunk.index,
+                range: position - chunk.offset..end - chunk.offset,
+            }),
+        }
+
+        position = end;
+    }
+
+    // bytes past the existing chunks
+    if position < range.end {
+        push_source(&mut pieces, position..range.end);
+    }
+
+    pieces
+}
+
+/// Push the bytes at the `source` range of the original file, merged with
+/// the previous piece if adjacent.
+fn push_source(
+    pieces: &mut Vec<Piece>,
+    source: Range<usize>,
+) {
+    match pieces.last_mut() {
+        | Some(Piece::Source { range }) if range.end == source.start => {
+            range.end = source.end
+        },
+        | _ => pieces.push(Piece::Source { range: source }),
+    }
+}
@@ -0,0 +1,306 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{fs as fsa, io as ioa};
+
+use crate::{
+    checksum::Algorithm,
+    header::CHUNK_HEADER_SIZE,
+    manifest::{find_manifest, Manifest},
+    store::DirStore,
+};
+
+/// Hash of a node in the [`MerkleTree`].
+pub type Hash = [u8; 32];
+
+/// Merkle tree over the SHA-256 checksums of the chunks.
+///
+/// The leaves and the inner nodes are hashed with distinct prefixes, and a
+/// node without a sibling is promoted to the next level unchanged. The root
+/// is recorded in the manifest when splitting with [`Split::merkle`], so a
+/// single chunk can be verified with a [`MerkleProof`] without the others.
+///
+/// [`Split::merkle`]: crate::split::Split::merkle
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{
+///     checksum::Algorithm,
+///     merkle::{self, MerkleProof, MerkleTree},
+/// };
+///
+/// fn example(chunks: &[Vec<u8>]) {
+///     let checksums: Vec<String> =
+///         chunks.iter().map(|c| Algorithm::Sha256.digest(c)).collect();
+///
+///     let tree: MerkleTree = MerkleTree::from_hex(&checksums).unwrap();
+///
+///     let proof: MerkleProof = tree.proof(0).unwrap();
+///
+///     assert!(proof.verify(
+///         &merkle::from_hex(&checksums[0]).unwrap(),
+///         &tree.root().unwrap(),
+///     ));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    /// Create a new tree over the checksums of the chunks in order.
+    pub fn new(checksums: &[Hash]) -> Self {
+        let mut levels: Vec<Vec<Hash>> =
+            vec![checksums.iter().map(leaf).collect()];
+
+        while levels[levels.len() - 1].len() > 1 {
+            let level: Vec<Hash> = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    | [left, right] => node(left, right),
+                    | _ => pair[0],
+                })
+                .collect();
+
+            levels.push(level);
+        }
+
+        Self { levels }
+    }
+
+    /// Create a new tree over the checksums of the chunks in hex, or `None`
+    /// if any of them is not a SHA-256 checksum.
+    pub fn from_hex<I: IntoIterator<Item = S>, S: AsRef<str>>(
+        checksums: I
+    ) -> Option<Self> {
+        let checksums: Vec<Hash> = checksums
+            .into_iter()
+            .map(|c| from_hex(c.as_ref()))
+            .collect::<Option<Vec<Hash>>>()?;
+
+        Some(Self::new(&checksums))
+    }
+
+    /// Get the number of chunks in the tree.
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Check whether the tree contains no chunk.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the root of the tree, or `None` if it is empty.
+    pub fn root(&self) -> Option<Hash> {
+        self.levels[self.levels.len() - 1].first().copied()
+    }
+
+    /// Get the root of the tree in hex, or `None` if it is empty.
+    pub fn root_hex(&self) -> Option<String> {
+        self.root().map(|root| to_hex(&root))
+    }
+
+    /// Get the proof of the chunk at `index`, or `None` if out of range.
+    pub fn proof(
+        &self,
+        index: usize,
+    ) -> Option<MerkleProof> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut path: Vec<ProofStep> = Vec::new();
+        let mut position: usize = index;
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling: usize = position ^ 1;
+
+            // promoted without a sibling
+            if let Some(hash) = level.get(sibling) {
+                path.push(ProofStep { hash: *hash, left: sibling < position });
+            }
+
+            position /= 2;
+        }
+
+        Some(MerkleProof { index, path })
+    }
+}
+
+/// Proof that a chunk belongs to a [`MerkleTree`] with a known root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Siblings from the leaf up to the root.
+    pub path: Vec<ProofStep>,
+}
+
+impl MerkleProof {
+    /// Verify the proof of the chunk with its SHA-256 checksum against the
+    /// root of the tree.
+    pub fn verify(
+        &self,
+        checksum: &Hash,
+        root: &Hash,
+    ) -> bool {
+        let computed: Hash =
+            self.path.iter().fold(leaf(checksum), |hash, step| {
+                if step.left {
+                    node(&step.hash, &hash)
+                } else {
+                    node(&hash, &step.hash)
+                }
+            });
+
+        computed == *root
+    }
+}
+
+/// Sibling in the path of a [`MerkleProof`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    /// Hash of the sibling.
+    pub hash: Hash,
+    /// Whether the sibling is on the left.
+    pub left: bool,
+}
+
+/// Proof that a storage node holds a chunk, from the [`prove`] function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkProof {
+    /// SHA-256 checksum of the payload of the chunk, computed from the data
+    /// held by the storage node.
+    pub checksum: Hash,
+    /// Proof of the chunk in the Merkle tree.
+    pub proof: MerkleProof,
+}
+
+/// Prove that the chunk at `index` is held in the directory, without
+/// transferring the chunk.
+///
+/// The chunk is read and hashed again, so a missing or corrupted chunk
+/// fails the [`verify_proof`]. The directory requires a manifest from
+/// [`Split::merkle`]. Note that the proof is the same every time, so a node
+/// keeping only the proofs could pass it too.
+///
+/// [`Split::merkle`]: crate::split::Split::merkle
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::merkle::{prove, verify_proof, ChunkProof};
+///
+/// async fn example() {
+///     let proof: ChunkProof =
+///         prove(PathBuf::from("path").join("to").join("dir"), 0)
+///             .await
+///             .unwrap();
+///
+///     let root: &str = "..."; // root kept by the coordinator...
+///
+///     assert!(verify_proof(root, &proof));
+/// }
+/// ```
+pub async fn prove<InDir: AsRef<Path>>(
+    in_dir: InDir,
+    index: usize,
+) -> ioa::Result<ChunkProof> {
+    let in_dir: &Path = in_dir.as_ref();
+
+    let manifest: Manifest = match find_manifest(in_dir).await? {
+        | Some(manifest) => manifest,
+        | None => {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                "manifest not found in in_dir",
+            ))
+        },
+    };
+
+    let tree: MerkleTree =
+        MerkleTree::from_hex(manifest.chunks.iter().map(|c| {
+            c.get_metadata(Algorithm::Sha256.id()).unwrap_or_default()
+        }))
+        .ok_or_else(|| {
+            ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                "checksums are not recorded in the manifest",
+            )
+        })?;
+
+    let proof: MerkleProof = tree.proof(index).ok_or_else(|| {
+        ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            "index is out of the chunks",
+        )
+    })?;
+
+    let store: DirStore = DirStore::with_manifest(in_dir, &manifest);
+
+    let mut data: Vec<u8> = fsa::read(store.path(index)).await?;
+
+    if manifest.headers {
+        data.drain(..CHUNK_HEADER_SIZE.min(data.len()));
+    }
+
+    let checksum: Hash =
+        tokio::task::spawn_blocking(move || Sha256::digest(&data).into())
+            .await
+            .map_err(ioa::Error::other)?;
+
+    Ok(ChunkProof { checksum, proof })
+}
+
+/// Verify the proof of a chunk from the [`prove`] function against the root
+/// of the Merkle tree in hex.
+pub fn verify_proof<R: AsRef<str>>(
+    root: R,
+    proof: &ChunkProof,
+) -> bool {
+    match from_hex(root.as_ref()) {
+        | Some(root) => proof.proof.verify(&proof.checksum, &root),
+        | None => false,
+    }
+}
+
+fn leaf(checksum: &Hash) -> Hash {
+    Sha256::new().chain_update([0]).chain_update(checksum).finalize().into()
+}
+
+fn node(
+    left: &Hash,
+    right: &Hash,
+) -> Hash {
+    Sha256::new()
+        .chain_update([1])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Parse a hash in hex, or `None` if invalid.
+pub fn from_hex(hex: &str) -> Option<Hash> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut hash: Hash = [0; 32];
+
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    Some(hash)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt},
+};
+
+/// The number of bytes at the start of a file read to sniff its MIME type.
+pub const SNIFF_SIZE: usize = 512;
+
+/// Signatures of the file formats, as the offset and bytes of the signature
+/// and the MIME type, in order of precedence.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (8, b"WEBP", "image/webp"),
+    (0, b"II*\x00", "image/tiff"),
+    (0, b"MM\x00*", "image/tiff"),
+    (8, b"AVI ", "video/x-msvideo"),
+    (8, b"WAVE", "audio/wav"),
+    (4, b"ftypqt", "video/quicktime"),
+    (4, b"ftypM4A", "audio/mp4"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"PAR1", "application/vnd.apache.parquet"),
+    (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (0, b"\x00asm", "application/wasm"),
+    (0, b"\x7fELF", "application/x-elf"),
+];
+
+/// Sniff the MIME type of a file from the signature at its start, e.g.
+/// `image/png`.
+///
+/// Only the first [`SNIFF_SIZE`] bytes are required. Returns `None` if no
+/// known signature is found, as the type of text and other formats without
+/// a signature cannot be told reliably.
+///
+/// ## Example
+///
+/// ```
+/// use filego::mime::sniff;
+///
+/// assert_eq!(sniff(b"\x89PNG\r\n\x1a\n..."), Some("image/png"));
+/// assert_eq!(sniff(b"plain text"), None);
+/// ```
+pub fn sniff(data: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(offset, signature, _)| {
+            data.get(*offset..*offset + signature.len()) == Some(signature)
+        })
+        .map(|(_, _, mime)| *mime)
+}
+
+/// Sniff the MIME type of the file at `path`, see [`sniff`].
+pub async fn sniff_file<P: AsRef<Path>>(
+    path: P
+) -> ioa::Result<Option<&'static str>> {
+    let file: fsa::File = fsa::File::open(path.as_ref()).await?;
+
+    let mut data: Vec<u8> = Vec::with_capacity(SNIFF_SIZE);
+
+    file.take(SNIFF_SIZE as u64).read_to_end(&mut data).await?;
+
+    Ok(sniff(&data))
+}
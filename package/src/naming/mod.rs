@@ -0,0 +1,253 @@
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+use tokio::io as ioa;
+
+/// Scheme of the names of the chunks.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     naming::ChunkNaming,
+///     split::{Split, SplitResult},
+/// };
+///
+/// async fn example() {
+///     // mergeable with `cat x* > file`
+///     let result: SplitResult = Split::new()
+///         .in_file("/path/to/file")
+///         .out_dir(PathBuf::from("path").join("to").join("dir"))
+///         .naming(ChunkNaming::coreutils())
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+///
+/// The naming is validated when it is deserialized, e.g. from a manifest, so
+/// the names never resolve outside the directory of the chunks.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(
+    tag = "scheme",
+    rename_all = "snake_case",
+    try_from = "UncheckedNaming"
+)]
+pub enum ChunkNaming {
+    /// The index of the chunk, e.g. `0`, `1`, or `007` with zero padding.
+    #[default]
+    Index,
+    /// A prefix followed by a suffix of lowercase letters of a fixed length,
+    /// e.g. `xaa`, `xab`, as produced by `split(1)`.
+    Alphabetic {
+        /// The prefix of the names, e.g. `x`.
+        prefix: String,
+        /// The length of the suffix, e.g. `2`.
+        suffix_length: usize,
+    },
+    /// A prefix followed by a number padded with zeros, e.g. `file.part001`.
+    Numeric {
+        /// The prefix of the names, e.g. `file.part`.
+        prefix: String,
+        /// The width of the number, e.g. `3`.
+        width: usize,
+        /// The number of the first chunk, e.g. `1`.
+        start: usize,
+    },
+}
+
+impl ChunkNaming {
+    /// Get the naming of `split(1)` by default, e.g. `xaa`, `xab`.
+    pub fn coreutils() -> Self {
+        Self::Alphabetic { prefix: "x".to_string(), suffix_length: 2 }
+    }
+
+    /// Get the naming of multi-part archives, e.g. `file.7z.001`, which keeps
+    /// the name and extension of the original file.
+    pub fn archive<Name: AsRef<str>>(name: Name) -> Self {
+        Self::Numeric {
+            prefix: format!("{}.", name.as_ref()),
+            width: 3,
+            start: 1,
+        }
+    }
+
+    /// Detect the naming of multi-part archives from the path of the first
+    /// part, e.g. `file.7z.001`.
+    pub fn from_first_part<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let name: &str = path.as_ref().file_name()?.to_str()?;
+
+        let (name, number) = name.rsplit_once('.')?;
+
+        if name.is_empty()
+            || number.is_empty()
+            || !number.bytes().all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+
+        Some(Self::Numeric {
+            prefix: format!("{}.", name),
+            width: number.len(),
+            start: number.parse::<usize>().ok()?,
+        })
+    }
+
+    /// Get the name of the original file kept in the names, e.g. `file.7z`
+    /// out of `file.7z.001`.
+    pub fn original_name(&self) -> Option<&str> {
+        match self {
+            | Self::Numeric { prefix, .. } => {
+                prefix.strip_suffix('.').filter(|name| !name.is_empty())
+            },
+            | _ => None,
+        }
+    }
+
+    /// Validate the naming, returning [`InvalidData`] if a name would not be
+    /// a plain file name in the directory of the chunks.
+    ///
+    /// The prefix must not contain path separators, be absolute or refer to
+    /// the parent directory, and the suffix or the number must have at least
+    /// one character.
+    ///
+    /// [`InvalidData`]: std::io::ErrorKind::InvalidData
+    pub fn validate(&self) -> ioa::Result<()> {
+        let (prefix, length): (&str, usize) = match self {
+            | Self::Index => return Ok(()),
+            | Self::Alphabetic { prefix, suffix_length } => {
+                (prefix, *suffix_length)
+            },
+            | Self::Numeric { prefix, width, .. } => (prefix, *width),
+        };
+
+        if length == 0 {
+            return Err(invalid_naming("empty suffix of chunk names"));
+        }
+
+        if prefix.contains(['/', '\\', '\0'])
+            || !Path::new(prefix)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(invalid_naming("invalid prefix of chunk names"));
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the naming is [`ChunkNaming::Index`].
+    pub fn is_index(&self) -> bool {
+        matches!(self, Self::Index)
+    }
+
+    /// Get the maximum number of chunks that can be named, if limited.
+    ///
+    /// Names beyond the maximum would not be sorted in order.
+    pub fn capacity(&self) -> Option<usize> {
+        match self {
+            | Self::Index => None,
+            | Self::Alphabetic { suffix_length, .. } => {
+                26usize.checked_pow(*suffix_length as u32)
+            },
+            | Self::Numeric { width, start, .. } => 10usize
+                .checked_pow(*width as u32)
+                .map(|max| max.saturating_sub(*start)),
+        }
+    }
+
+    /// Get the name of the chunk at `index`, padded with zeros to the width
+    /// of `padding` for [`ChunkNaming::Index`].
+    pub fn name(
+        &self,
+        index: usize,
+        padding: usize,
+    ) -> String {
+        match self {
+            | Self::Index => format!("{:0width$}", index, width = padding),
+            | Self::Alphabetic { prefix, suffix_length } => {
+                let mut suffix: Vec<u8> = vec![b'a'; *suffix_length];
+                let mut rest: usize = index;
+
+                for c in suffix.iter_mut().rev() {
+                    *c = b'a' + (rest % 26) as u8;
+                    rest /= 26;
+                }
+
+                format!("{}{}", prefix, String::from_utf8_lossy(&suffix))
+            },
+            | Self::Numeric { prefix, width, start } => {
+                format!("{}{:0width$}", prefix, index + start, width = width)
+            },
+        }
+    }
+
+    /// Get the index of the chunk from its name, if it follows the naming.
+    pub fn index(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        match self {
+            | Self::Index => name.parse::<usize>().ok(),
+            | Self::Alphabetic { prefix, suffix_length } => {
+                let suffix: &str = name.strip_prefix(prefix.as_str())?;
+
+                if suffix.len() != *suffix_length
+                    || !suffix.bytes().all(|c| c.is_ascii_lowercase())
+                {
+                    return None;
+                }
+
+                suffix.bytes().try_fold(0usize, |index, c| {
+                    index.checked_mul(26)?.checked_add((c - b'a') as usize)
+                })
+            },
+            | Self::Numeric { prefix, width, start } => {
+                let number: &str = name.strip_prefix(prefix.as_str())?;
+
+                if number.len() < *width
+                    || !number.bytes().all(|c| c.is_ascii_digit())
+                {
+                    return None;
+                }
+
+                number.parse::<usize>().ok()?.checked_sub(*start)
+            },
+        }
+    }
+}
+
+/// Naming deserialized before validation.
+#[derive(Deserialize)]
+#[serde(tag = "scheme", rename_all = "snake_case")]
+enum UncheckedNaming {
+    Index,
+    Alphabetic { prefix: String, suffix_length: usize },
+    Numeric { prefix: String, width: usize, start: usize },
+}
+
+impl TryFrom<UncheckedNaming> for ChunkNaming {
+    type Error = ioa::Error;
+
+    fn try_from(naming: UncheckedNaming) -> ioa::Result<Self> {
+        let naming: Self = match naming {
+            | UncheckedNaming::Index => Self::Index,
+            | UncheckedNaming::Alphabetic { prefix, suffix_length } => {
+                Self::Alphabetic { prefix, suffix_length }
+            },
+            | UncheckedNaming::Numeric { prefix, width, start } => {
+                Self::Numeric { prefix, width, start }
+            },
+        };
+
+        naming.validate()?;
+
+        Ok(naming)
+    }
+}
+
+fn invalid_naming(message: &str) -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,565 @@
+use std::path::{Component, Path, PathBuf};
+
+use crc32fast::Hasher;
+use md5::{Digest, Md5};
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncWriteExt},
+};
+
+use crate::{
+    config::{PAR2_FILE_NAME, PAR2_REDUNDANCY_DEFAULT},
+    manifest::find_manifest,
+    store::DirStore,
+};
+
+const MAGIC: &[u8; 8] = b"PAR2\0PKT";
+
+const TYPE_MAIN: &[u8; 16] = b"PAR 2.0\0Main\0\0\0\0";
+
+const TYPE_FILE_DESC: &[u8; 16] = b"PAR 2.0\0FileDesc";
+
+const TYPE_IFSC: &[u8; 16] = b"PAR 2.0\0IFSC\0\0\0\0";
+
+const TYPE_RECOVERY: &[u8; 16] = b"PAR 2.0\0RecvSlic";
+
+const TYPE_CREATOR: &[u8; 16] = b"PAR 2.0\0Creator\0";
+
+const CREATOR: &str = "Created by filego";
+
+/// The maximum number of input slices allowed by PAR2.
+const INPUT_SLICES_MAX: usize = 32768;
+
+/// The number of input slices aimed at when the slice size is not set.
+const INPUT_SLICES_TARGET: usize = 2000;
+
+/// The size of the head of a file hashed to identify it.
+const HASH_16K: usize = 16 * 1024;
+
+/// Process to create a PAR2 recovery file for the chunks in a directory.
+///
+/// The recovery file follows the PAR 2.0 specification, so a damaged or
+/// incomplete chunk set can be repaired with standard tooling such as
+/// `par2 repair`. Each chunk is protected as a file of the recovery set.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::par2::{Par2, Par2Result};
+///
+/// async fn example() {
+///     let result: Par2Result = Par2::new()
+///         .in_dir(PathBuf::from("path").join("to").join("dir"))
+///         .total_chunks(0) // result from split function...
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Par2 {
+    in_dir: Option<PathBuf>,
+    out_file: Option<PathBuf>,
+    total_chunks: Option<usize>,
+    slice_size: Option<usize>,
+    redundancy: usize,
+}
+
+/// Result of the PAR2 process.
+#[derive(Debug, Clone)]
+pub struct Par2Result {
+    /// Size of each slice in bytes.
+    pub slice_size: usize,
+    /// The total number of slices of the chunks.
+    pub input_slices: usize,
+    /// The total number of recovery slices, which is the number of damaged
+    /// slices that can be repaired.
+    pub recovery_slices: usize,
+}
+
+impl Par2 {
+    /// Create a new PAR2 process.
+    pub fn new() -> Self {
+        Self {
+            in_dir: None,
+            out_file: None,
+            total_chunks: None,
+            slice_size: None,
+            redundancy: PAR2_REDUNDANCY_DEFAULT,
+        }
+    }
+
+    /// Set the input directory.
+    pub fn in_dir<InDir: AsRef<Path>>(
+        mut self,
+        path: InDir,
+    ) -> Self {
+        self.in_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the output file.
+    ///
+    /// By default, the recovery file is written as [`PAR2_FILE_NAME`] in the
+    /// input directory.
+    pub fn out_file<OutFile: AsRef<Path>>(
+        mut self,
+        path: OutFile,
+    ) -> Self {
+        self.out_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the total number of chunks.
+    ///
+    /// It is not required when the input directory contains a manifest.
+    pub fn total_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.total_chunks = Some(chunks);
+        self
+    }
+
+    /// Set the size of each slice, rounded up to a multiple of 4.
+    ///
+    /// By default, the size is chosen to produce about 2000 slices. Smaller
+    /// slices repair smaller damages, but take more time to compute.
+    pub fn slice_size(
+        mut self,
+        size: usize,
+    ) -> Self {
+        self.slice_size = Some(size);
+        self
+    }
+
+    /// Set the size of the recovery data as a percentage of the chunks.
+    ///
+    /// By default, the redundancy follows the [`PAR2_REDUNDANCY_DEFAULT`].
+    pub fn redundancy(
+        mut self,
+        percent: usize,
+    ) -> Self {
+        self.redundancy = percent;
+        self
+    }
+
+    /// Run the PAR2 process.
+    ///
+    /// The chunks are read twice, and the recovery slices are kept in
+    /// memory until written, which takes `redundancy` percent of the size
+    /// of the chunks.
+    pub async fn run(self) -> ioa::Result<Par2Result> {
+        let in_dir: &Path = match self.in_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if in_dir not exists
+                if !p.exists() {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::NotFound,
+                        "in_dir path not found",
+                    ));
+                }
+
+                // if in_dir not a directory
+                if !p.is_dir() {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::InvalidInput,
+                        "in_dir is not a directory",
+                    ));
+                }
+
+                p
+            },
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "in_dir is not set",
+                ))
+            },
+        };
+
+        let out_file: PathBuf = match self.out_file {
+            | Some(ref p) => p.clone(),
+            | None => in_dir.join(PAR2_FILE_NAME),
+        };
+
+        // names and volumes of the chunks follow the manifest
+        let (chunks, total_chunks): (DirStore, Option<usize>) =
+            match find_manifest(in_dir).await? {
+                | Some(manifest) => (
+                    DirStore::with_manifest(in_dir, &manifest),
+                    Some(manifest.total_chunks),
+                ),
+                | None => (DirStore::new(in_dir), self.total_chunks),
+            };
+
+        let total_chunks: usize = match total_chunks {
+            | Some(s) => s,
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    "total_chunks is not set",
+                ))
+            },
+        };
+
+        let mut paths: Vec<(PathBuf, usize)> = Vec::with_capacity(total_chunks);
+
+        for i in 0..total_chunks {
+            let path: PathBuf = chunks.path(i);
+
+            let size: usize = fsa::metadata(&path).await?.len() as usize;
+
+            // empty files are not part of the recovery set
+            if size > 0 {
+                paths.push((path, size));
+            }
+        }
+
+        let total_size: usize = paths.iter().map(|(_, size)| *size).sum();
+
+        if total_size == 0 {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::NotFound,
+                "No data found in in_dir",
+            ));
+        }
+
+        let slice_size: usize = self
+            .slice_size
+            .unwrap_or_else(|| total_size.div_ceil(INPUT_SLICES_TARGET))
+            .max(1)
+            .next_multiple_of(4);
+
+        let input_slices: usize =
+            paths.iter().map(|(_, size)| size.div_ceil(slice_size)).sum();
+
+        if input_slices > INPUT_SLICES_MAX {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidInput,
+                format!(
+                    "slice_size would produce {} slices, which exceeds {}",
+                    input_slices, INPUT_SLICES_MAX
+                ),
+            ));
+        }
+
+        let recovery_slices: usize = (input_slices * self.redundancy)
+            .div_ceil(100)
+            .min(u16::MAX as usize);
+
+        // describe each file
+        let mut files: Vec<FileDesc> = Vec::with_capacity(paths.len());
+
+        for (path, _) in paths {
+            let name: String = relative_name(in_dir, &path)?;
+
+            let data: Vec<u8> = fsa::read(&path).await?;
+
+            files.push(FileDesc::new(path, name, &data, slice_size));
+        }
+
+        // the order of the files decides the constants of the slices
+        files.sort_unstable_by_key(|f| u128::from_le_bytes(f.id));
+
+        let mut main: Vec<u8> = Vec::new();
+
+        main.extend_from_slice(&(slice_size as u64).to_le_bytes());
+        main.extend_from_slice(&(files.len() as u32).to_le_bytes());
+
+        for file in &files {
+            main.extend_from_slice(&file.id);
+        }
+
+        let set_id: [u8; 16] = Md5::digest(&main).into();
+
+        // compute recovery slices
+        let gf: Galois = Galois::new();
+
+        let mut recovery: Vec<Vec<u16>> =
+            vec![vec![0; slice_size / 2]; recovery_slices];
+
+        let mut words: Vec<u16> = vec![0; slice_size / 2];
+
+        let mut log_base: usize = 0;
+
+        for file in &files {
+            let data: Vec<u8> = fsa::read(&file.path).await?;
+
+            for slice in data.chunks(slice_size) {
+                while gcd(log_base, Galois::LIMIT) != 1 {
+                    log_base += 1;
+                }
+
+                words.fill(0);
+
+                for (word, bytes) in words.iter_mut().zip(slice.chunks(2)) {
+                    *word = u16::from_le_bytes([
+                        bytes[0],
+                        bytes.get(1).copied().unwrap_or(0),
+                    ]);
+                }
+
+                for (exponent, output) in recovery.iter_mut().enumerate() {
+                    gf.mul_add(
+                        output,
+                        &words,
+                        log_base * exponent % Galois::LIMIT,
+                    );
+                }
+
+                log_base += 1;
+            }
+        }
+
+        // write packets
+        if let Some(parent) = out_file.parent() {
+            fsa::create_dir_all(parent).await?;
+        }
+
+        let output: fsa::File = fsa::File::create(&out_file).await?;
+
+        let mut writer: ioa::BufWriter<fsa::File> = ioa::BufWriter::new(output);
+
+        writer.write_all(&packet(&set_id, TYPE_MAIN, &main)).await?;
+
+        for file in &files {
+            writer
+                .write_all(&packet(&set_id, TYPE_FILE_DESC, &file.desc_body()))
+                .await?;
+
+            writer
+                .write_all(&packet(&set_id, TYPE_IFSC, &file.ifsc_body()))
+                .await?;
+        }
+
+        writer
+            .write_all(&packet(&set_id, TYPE_CREATOR, &padded(CREATOR)))
+            .await?;
+
+        for (exponent, output) in recovery.iter().enumerate() {
+            let mut body: Vec<u8> = Vec::with_capacity(4 + slice_size);
+
+            body.extend_from_slice(&(exponent as u32).to_le_bytes());
+
+            for word in output {
+                body.extend_from_slice(&word.to_le_bytes());
+            }
+
+            writer.write_all(&packet(&set_id, TYPE_RECOVERY, &body)).await?;
+        }
+
+        writer.flush().await?;
+
+        Ok(Par2Result { slice_size, input_slices, recovery_slices })
+    }
+}
+
+impl Default for Par2 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Description of a file in the recovery set.
+#[derive(Debug, Clone)]
+struct FileDesc {
+    path: PathBuf,
+    name: String,
+    id: [u8; 16],
+    hash: [u8; 16],
+    hash_16k: [u8; 16],
+    size: usize,
+    /// MD5 and CRC32 of each slice padded with zeros.
+    slices: Vec<([u8; 16], u32)>,
+}
+
+impl FileDesc {
+    fn new(
+        path: PathBuf,
+        name: String,
+        data: &[u8],
+        slice_size: usize,
+    ) -> Self {
+        let hash_16k: [u8; 16] =
+            Md5::digest(&data[..data.len().min(HASH_16K)]).into();
+
+        let mut id: Md5 = Md5::new();
+
+        id.update(hash_16k);
+        id.update((data.len() as u64).to_le_bytes());
+        id.update(name.as_bytes());
+
+        let padding: Vec<u8> = vec![0; slice_size];
+
+        let slices: Vec<([u8; 16], u32)> = data
+            .chunks(slice_size)
+            .map(|slice| {
+                let rest: &[u8] = &padding[slice.len()..];
+
+                let mut md5: Md5 = Md5::new();
+                let mut crc: Hasher = Hasher::new();
+
+                md5.update(slice);
+                md5.update(rest);
+                crc.update(slice);
+                crc.update(rest);
+
+                (md5.finalize().into(), crc.finalize())
+            })
+            .collect();
+
+        Self {
+            path,
+            name,
+            id: id.finalize().into(),
+            hash: Md5::digest(data).into(),
+            hash_16k,
+            size: data.len(),
+            slices,
+        }
+    }
+
+    fn desc_body(&self) -> Vec<u8> {
+        let mut body: Vec<u8> = Vec::new();
+
+        body.extend_from_slice(&self.id);
+        body.extend_from_slice(&self.hash);
+        body.extend_from_slice(&self.hash_16k);
+        body.extend_from_slice(&(self.size as u64).to_le_bytes());
+        body.extend_from_slice(&padded(&self.name));
+
+        body
+    }
+
+    fn ifsc_body(&self) -> Vec<u8> {
+        let mut body: Vec<u8> = Vec::with_capacity(16 + self.slices.len() * 20);
+
+        body.extend_from_slice(&self.id);
+
+        for (md5, crc) in &self.slices {
+            body.extend_from_slice(md5);
+            body.extend_from_slice(&crc.to_le_bytes());
+        }
+
+        body
+    }
+}
+
+/// Arithmetic in GF(2^16) with the generator polynomial of PAR2.
+struct Galois {
+    log: Vec<usize>,
+    exp: Vec<u16>,
+}
+
+impl Galois {
+    /// The order of the multiplicative group.
+    const LIMIT: usize = 65535;
+
+    /// The generator polynomial `x^16 + x^12 + x^3 + x + 1`.
+    const POLYNOMIAL: u32 = 0x1100B;
+
+    fn new() -> Self {
+        let mut log: Vec<usize> = vec![0; Self::LIMIT + 1];
+        let mut exp: Vec<u16> = vec![0; Self::LIMIT];
+
+        let mut x: u32 = 1;
+
+        for (i, e) in exp.iter_mut().enumerate() {
+            *e = x as u16;
+            log[x as usize] = i;
+
+            x <<= 1;
+
+            if x & 0x10000 != 0 {
+                x ^= Self::POLYNOMIAL;
+            }
+        }
+
+        Self { log, exp }
+    }
+
+    /// Add `words` multiplied by the factor with the logarithm of `factor`
+    /// to `output`.
+    fn mul_add(
+        &self,
+        output: &mut [u16],
+        words: &[u16],
+        factor: usize,
+    ) {
+        for (o, w) in output.iter_mut().zip(words) {
+            if *w != 0 {
+                *o ^= self.exp[(self.log[*w as usize] + factor) % Self::LIMIT];
+            }
+        }
+    }
+}
+
+fn gcd(
+    a: usize,
+    b: usize,
+) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Create a packet of the recovery set.
+fn packet(
+    set_id: &[u8; 16],
+    kind: &[u8; 16],
+    body: &[u8],
+) -> Vec<u8> {
+    let mut hash: Md5 = Md5::new();
+
+    hash.update(set_id);
+    hash.update(kind);
+    hash.update(body);
+
+    let mut packet: Vec<u8> = Vec::with_capacity(64 + body.len());
+
+    packet.extend_from_slice(MAGIC);
+    packet.extend_from_slice(&((64 + body.len()) as u64).to_le_bytes());
+    packet.extend_from_slice(&hash.finalize());
+    packet.extend_from_slice(set_id);
+    packet.extend_from_slice(kind);
+    packet.extend_from_slice(body);
+
+    packet
+}
+
+/// Get the bytes of a string padded with zeros to a multiple of 4.
+fn padded(s: &str) -> Vec<u8> {
+    let mut bytes: Vec<u8> = s.as_bytes().to_vec();
+
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+
+    bytes
+}
+
+/// Get the name of a chunk relative to the input directory, separated by
+/// `/` as in PAR2.
+fn relative_name(
+    in_dir: &Path,
+    path: &Path,
+) -> ioa::Result<String> {
+    let relative: &Path = path.strip_prefix(in_dir).map_err(|_| {
+        ioa::Error::new(ioa::ErrorKind::InvalidInput, "chunk outside in_dir")
+    })?;
+
+    let names: Vec<&str> = relative
+        .components()
+        .filter_map(|c| match c {
+            | Component::Normal(name) => name.to_str(),
+            | _ => None,
+        })
+        .collect();
+
+    Ok(names.join("/"))
+}
@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+/// Time spent in each phase of a split or merge process.
+///
+/// The phases may overlap when they run concurrently, e.g. fetching chunks
+/// ahead while writing, so their sum may differ from the `total`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Time spent reading the input.
+    pub read: Duration,
+    /// Time spent writing the output.
+    pub write: Duration,
+    /// Time spent hashing the data.
+    pub hash: Duration,
+    /// Time spent encoding or decoding the chunks.
+    pub transform: Duration,
+    /// Total time of the process.
+    pub total: Duration,
+}
+
+/// Phase of a split or merge process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Read,
+    Write,
+    #[cfg(feature = "checksum")]
+    Hash,
+    Transform,
+}
+
+/// Profiler collecting the [`Profile`] of a process if enabled.
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    start: Instant,
+    profile: Option<Profile>,
+}
+
+impl Profiler {
+    /// Create a new profiler, which does nothing if not enabled.
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self { start: Instant::now(), profile: enabled.then(Profile::default) }
+    }
+
+    /// Start timing a phase.
+    pub(crate) fn start(&self) -> Option<Instant> {
+        self.profile.as_ref().map(|_| Instant::now())
+    }
+
+    /// Record the time of a phase since its start.
+    pub(crate) fn record(
+        &mut self,
+        phase: Phase,
+        start: Option<Instant>,
+    ) {
+        if let Some(start) = start {
+            self.add(phase, start.elapsed());
+        }
+    }
+
+    /// Add the time of a phase measured elsewhere, e.g. on a worker thread.
+    pub(crate) fn add(
+        &mut self,
+        phase: Phase,
+        elapsed: Duration,
+    ) {
+        let profile: &mut Profile = match &mut self.profile {
+            | Some(profile) => profile,
+            | None => return,
+        };
+
+        match phase {
+            | Phase::Read => profile.read += elapsed,
+            | Phase::Write => profile.write += elapsed,
+            #[cfg(feature = "checksum")]
+            | Phase::Hash => profile.hash += elapsed,
+            | Phase::Transform => profile.transform += elapsed,
+        }
+    }
+
+    /// Add the time of each phase measured by the profiler of a worker.
+    pub(crate) fn merge(
+        &mut self,
+        worker: Profiler,
+    ) {
+        if let (Some(profile), Some(worker)) =
+            (&mut self.profile, worker.profile)
+        {
+            profile.read += worker.read;
+            profile.write += worker.write;
+            profile.hash += worker.hash;
+            profile.transform += worker.transform;
+        }
+    }
+
+    /// Finish the profile of the process named `process`.
+    pub(crate) fn finish(
+        self,
+        #[allow(unused_variables)] process: &str,
+    ) -> Option<Profile> {
+        let mut profile: Profile = self.profile?;
+
+        profile.total = self.start.elapsed();
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "filego",
+            process,
+            read = ?profile.read,
+            write = ?profile.write,
+            hash = ?profile.hash,
+            transform = ?profile.transform,
+            total = ?profile.total,
+            "profile"
+        );
+
+        Some(profile)
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc as mpsca;
+
+use crate::event::Event;
+
+/// Sink of the progress of the split, check and merge processes.
+///
+/// Each [`Event`] is passed to the sink as it is emitted, in order and
+/// without being dropped, unlike the subscribers of the
+/// [`EventBus`](crate::event::EventBus). The sink is called on the task
+/// running the process, so it should return quickly, e.g. by forwarding the
+/// event to a GUI thread or updating a metric. The same sink can be shared
+/// between processes.
+///
+/// It is implemented for the senders of the channels, the
+/// [`ProgressCounter`] and closures wrapped in [`FnSink`].
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{path::PathBuf, sync::Arc};
+///
+/// use filego::{
+///     progress::{ProgressCounter, ProgressSink},
+///     split::Split,
+/// };
+///
+/// async fn example() {
+///     let counter: Arc<ProgressCounter> = Arc::new(ProgressCounter::new());
+///
+///     Split::new()
+///         .in_file("/path/to/file")
+///         .out_dir(PathBuf::from("path").join("to").join("dir"))
+///         .progress(counter.clone())
+///         .run()
+///         .await
+///         .unwrap();
+///
+///     println!("{} bytes splitted", counter.bytes());
+/// }
+/// ```
+pub trait ProgressSink: fmt::Debug + Send + Sync {
+    /// Handle an event of the process.
+    fn on_event(
+        &self,
+        event: &Event,
+    );
+}
+
+impl ProgressSink for mpsc::Sender<Event> {
+    fn on_event(
+        &self,
+        event: &Event,
+    ) {
+        // a dropped receiver is not an error
+        let _ = self.send(event.clone());
+    }
+}
+
+/// The event is dropped if the channel is full, so the process is not
+/// blocked by a slow receiver.
+impl ProgressSink for mpsc::SyncSender<Event> {
+    fn on_event(
+        &self,
+        event: &Event,
+    ) {
+        let _ = self.try_send(event.clone());
+    }
+}
+
+impl ProgressSink for mpsca::UnboundedSender<Event> {
+    fn on_event(
+        &self,
+        event: &Event,
+    ) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// The event is dropped if the channel is full, so the process is not
+/// blocked by a slow receiver.
+impl ProgressSink for mpsca::Sender<Event> {
+    fn on_event(
+        &self,
+        event: &Event,
+    ) {
+        let _ = self.try_send(event.clone());
+    }
+}
+
+/// Counter of the progress in atomics, which can be read from any thread,
+/// e.g. polled by a GUI or exported as metrics.
+///
+/// The counter is reset when a split or merge process starts, so it follows
+/// the latest process when shared between processes.
+#[derive(Debug, Default)]
+pub struct ProgressCounter {
+    bytes: AtomicUsize,
+    chunks: AtomicUsize,
+    total_chunks: AtomicUsize,
+    finished: AtomicBool,
+}
+
+impl ProgressCounter {
+    /// Create a new counter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of bytes of the chunks written or merged.
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of chunks written or merged.
+    pub fn chunks(&self) -> usize {
+        self.chunks.load(Ordering::Relaxed)
+    }
+
+    /// Get the total number of chunks, once known.
+    ///
+    /// It is known when the merge process starts, and when the split
+    /// process finishes, as the number of chunks may depend on the content.
+    pub fn total_chunks(&self) -> Option<usize> {
+        match self.total_chunks.load(Ordering::Relaxed) {
+            | 0 => None,
+            | total => Some(total),
+        }
+    }
+
+    /// Get whether the process finished, including a check with a result.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    fn reset(
+        &self,
+        total_chunks: usize,
+    ) {
+        self.bytes.store(0, Ordering::Relaxed);
+        self.chunks.store(0, Ordering::Relaxed);
+        self.total_chunks.store(total_chunks, Ordering::Relaxed);
+        self.finished.store(false, Ordering::Release);
+    }
+}
+
+impl ProgressSink for ProgressCounter {
+    fn on_event(
+        &self,
+        event: &Event,
+    ) {
+        match event {
+            | Event::SplitStarted { .. } => self.reset(0),
+            | Event::MergeStarted { total_chunks } => self.reset(*total_chunks),
+            | Event::ChunkWritten { size, .. }
+            | Event::ChunkMerged { size, .. } => {
+                self.bytes.fetch_add(*size, Ordering::Relaxed);
+                self.chunks.fetch_add(1, Ordering::Relaxed);
+            },
+            | Event::SplitFinished { total_chunks, .. } => {
+                self.total_chunks.store(*total_chunks, Ordering::Relaxed);
+                self.finished.store(true, Ordering::Release);
+            },
+            | Event::CheckPassed
+            | Event::CheckFailed { .. }
+            | Event::MergeFinished { .. } => {
+                self.finished.store(true, Ordering::Release);
+            },
+        }
+    }
+}
+
+/// Sink calling a closure with each event.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use filego::{
+///     event::Event,
+///     progress::{FnSink, ProgressSink},
+/// };
+///
+/// fn example() -> Arc<dyn ProgressSink> {
+///     Arc::new(FnSink::new(|event: &Event| println!("{:?}", event)))
+/// }
+/// ```
+pub struct FnSink<F> {
+    f: F,
+}
+
+impl<F: Fn(&Event) + Send + Sync> FnSink<F> {
+    /// Create a new sink calling `f` with each event.
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F> fmt::Debug for FnSink<F> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.debug_struct("FnSink").finish_non_exhaustive()
+    }
+}
+
+impl<F: Fn(&Event) + Send + Sync> ProgressSink for FnSink<F> {
+    fn on_event(
+        &self,
+        event: &Event,
+    ) {
+        (self.f)(event)
+    }
+}
+
+/// Sink passing each event to both sinks in order.
+#[derive(Debug)]
+pub(crate) struct Tee(Arc<dyn ProgressSink>, Arc<dyn ProgressSink>);
+
+impl Tee {
+    /// Add the `sink` to the sink set before if any.
+    pub(crate) fn join(
+        before: Option<Arc<dyn ProgressSink>>,
+        sink: Arc<dyn ProgressSink>,
+    ) -> Arc<dyn ProgressSink> {
+        match before {
+            | Some(before) => Arc::new(Self(before, sink)),
+            | None => sink,
+        }
+    }
+}
+
+impl ProgressSink for Tee {
+    fn on_event(
+        &self,
+        event: &Event,
+    ) {
+        self.0.on_event(event);
+        self.1.on_event(event);
+    }
+}
+
+/// Callback of the progress of a process, e.g. set by
+/// [`Split::on_progress`](crate::split::Split::on_progress).
+pub(crate) struct OnProgress<P>(Arc<dyn Fn(P) + Send + Sync>);
+
+impl<P> OnProgress<P> {
+    pub(crate) fn new<F: Fn(P) + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl<P> Clone for OnProgress<P> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<P> fmt::Debug for OnProgress<P> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.debug_tuple("OnProgress").finish_non_exhaustive()
+    }
+}
+
+/// Reporter of the progress to the callback, limited to the interval.
+pub(crate) struct Reporter<'a, P> {
+    on: Option<&'a OnProgress<P>>,
+    interval: Duration,
+    last: Option<Instant>,
+    pub(crate) progress: P,
+}
+
+impl<'a, P: Copy> Reporter<'a, P> {
+    pub(crate) fn new(
+        on: Option<&'a OnProgress<P>>,
+        interval: Duration,
+        progress: P,
+    ) -> Self {
+        Self { on, interval, last: None, progress }
+    }
+
+    /// Report the progress if the interval elapsed, or always if `force`.
+    pub(crate) fn report(
+        &mut self,
+        force: bool,
+    ) {
+        let on: &OnProgress<P> = match self.on {
+            | Some(on) => on,
+            | None => return,
+        };
+
+        let due: bool =
+            self.last.is_none_or(|last| last.elapsed() >= self.interval);
+
+        if force || due {
+            self.last = Some(Instant::now());
+            (on.0)(self.progress);
+        }
+    }
+}
+
+/// Get the throughput of `bytes` processed in `elapsed` in bytes per second.
+pub(crate) fn throughput(
+    bytes: usize,
+    elapsed: Duration,
+) -> f64 {
+    match elapsed.is_zero() {
+        | true => 0.0,
+        | false => bytes as f64 / elapsed.as_secs_f64(),
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use uuid::Uuid;
+
+/// Increment of the state of the [`SeededRng`], the golden ratio in 64 bits.
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Source of the random numbers, such as the sample of the spot check and
+/// the identity of the split process.
+///
+/// It defaults to the [`SystemRng`], and the [`SeededRng`] reproduces a run
+/// exactly, e.g. when debugging a failed check.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{path::PathBuf, sync::Arc};
+///
+/// use filego::{rng::SeededRng, split::Split};
+///
+/// async fn example() {
+///     Split::new()
+///         .in_file(PathBuf::from("path").join("to").join("file"))
+///         .out_dir(PathBuf::from("path").join("to").join("dir"))
+///         .rng(Arc::new(SeededRng::new(42)))
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+pub trait Rng: fmt::Debug + Send + Sync {
+    /// Get the next random number.
+    fn next_u64(&self) -> u64;
+
+    /// Get the next random version 4 UUID.
+    fn next_uuid(&self) -> Uuid {
+        let mut bytes: [u8; 16] = [0; 16];
+
+        bytes[..8].copy_from_slice(&self.next_u64().to_le_bytes());
+        bytes[8..].copy_from_slice(&self.next_u64().to_le_bytes());
+
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+/// Random numbers from the operating system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemRng;
+
+impl Rng for SystemRng {
+    fn next_u64(&self) -> u64 {
+        Uuid::new_v4().as_u64_pair().0
+    }
+
+    fn next_uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// Random numbers from a seed with SplitMix64, so the same seed gives the
+/// same numbers.
+#[derive(Debug)]
+pub struct SeededRng {
+    state: AtomicU64,
+}
+
+impl SeededRng {
+    /// Create a new generator from the seed.
+    pub fn new(seed: u64) -> Self {
+        Self { state: AtomicU64::new(seed) }
+    }
+}
+
+impl Rng for SeededRng {
+    fn next_u64(&self) -> u64 {
+        let mut z: u64 =
+            self.state.fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA);
+
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+}
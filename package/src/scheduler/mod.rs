@@ -0,0 +1,579 @@
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::BinaryHeap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tokio::{
+    io as ioa,
+    sync::oneshot,
+    task::{AbortHandle, JoinHandle},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    config::{JOB_MEMORY_ESTIMATE, SCHEDULER_CONCURRENCY_DEFAULT},
+    control::{Control, OperationHandle, Throttle},
+    merge::{Merge, MergeResult},
+    profile::Profile,
+    split::{Split, SplitResult},
+    tune::{memory_budget, MemoryBudget},
+};
+
+/// Job run by the [`Scheduler`].
+#[derive(Debug, Clone)]
+pub struct Job {
+    kind: JobKind,
+    priority: i32,
+}
+
+#[derive(Debug, Clone)]
+enum JobKind {
+    Split(Box<Split>),
+    Merge(Box<Merge>),
+}
+
+impl JobKind {
+    fn memory_budget(
+        self,
+        budget: MemoryBudget,
+    ) -> Self {
+        match self {
+            | Self::Split(split) => {
+                Self::Split(Box::new(split.memory_budget(budget)))
+            },
+            | Self::Merge(merge) => {
+                Self::Merge(Box::new(merge.memory_budget(budget)))
+            },
+        }
+    }
+}
+
+impl Job {
+    /// Create a new job of the split process.
+    pub fn split(split: Split) -> Self {
+        Self { kind: JobKind::Split(Box::new(split)), priority: 0 }
+    }
+
+    /// Create a new job of the merge process.
+    pub fn merge(merge: Merge) -> Self {
+        Self { kind: JobKind::Merge(Box::new(merge)), priority: 0 }
+    }
+
+    /// Set the priority of the job.
+    ///
+    /// By default, the priority is `0`. Queued jobs with a higher priority
+    /// start first, and jobs with the same priority start in the order of
+    /// submission.
+    pub fn priority(
+        mut self,
+        priority: i32,
+    ) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+impl From<Split> for Job {
+    fn from(split: Split) -> Self {
+        Self::split(split)
+    }
+}
+
+impl From<Merge> for Job {
+    fn from(merge: Merge) -> Self {
+        Self::merge(merge)
+    }
+}
+
+/// Output of a job run by the [`Scheduler`].
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum JobOutput {
+    /// Result of the split process.
+    Split(SplitResult),
+    /// Result of the merge process.
+    Merge(MergeResult),
+}
+
+/// Status of a job run by the [`Scheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    /// The job is waiting for a slot.
+    Queued,
+    /// The job is running.
+    Running,
+    /// The job is finished, successfully or not.
+    Finished,
+    /// The job is cancelled.
+    Cancelled,
+}
+
+/// Scheduler running split and merge jobs with a global budget.
+///
+/// At most `concurrency` jobs run at the same time, and the bytes processed
+/// by all jobs are limited by the `bandwidth` if set.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     scheduler::{Job, JobHandle, JobOutput, Scheduler},
+///     split::Split,
+/// };
+///
+/// async fn example() {
+///     let scheduler: Scheduler = Scheduler::new().concurrency(2);
+///
+///     let handle: JobHandle = scheduler.submit(
+///         Job::split(
+///             Split::new()
+///                 .in_file("/path/to/file")
+///                 .out_dir(PathBuf::from("path").join("to").join("dir")),
+///         )
+///         .priority(1),
+///     );
+///
+///     let output: JobOutput = handle.wait().await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    concurrency: usize,
+    auto_tune: bool,
+    throttle: Option<Arc<Throttle>>,
+    clock: Arc<dyn Clock>,
+    budget: Option<MemoryBudget>,
+    queue: Mutex<Queue>,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    pending: BinaryHeap<Pending>,
+    running: usize,
+    next_id: usize,
+}
+
+#[derive(Debug)]
+struct Pending {
+    id: usize,
+    priority: i32,
+    job: JobKind,
+    state: Arc<JobState>,
+    sender: oneshot::Sender<ioa::Result<JobOutput>>,
+}
+
+impl PartialEq for Pending {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(
+        &self,
+        other: &Self,
+    ) -> CmpOrdering {
+        // higher priority first, then earlier submission first
+        self.priority.cmp(&other.priority).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+#[derive(Debug)]
+struct JobState {
+    control: Arc<Control>,
+    status: AtomicUsize,
+    cancelled: AtomicBool,
+    abort: Mutex<Option<AbortHandle>>,
+}
+
+impl JobState {
+    fn status(&self) -> JobStatus {
+        match self.status.load(Ordering::Acquire) {
+            | 0 => JobStatus::Queued,
+            | 1 => JobStatus::Running,
+            | 2 => JobStatus::Finished,
+            | _ => JobStatus::Cancelled,
+        }
+    }
+
+    /// Move the job from the status `from` to `to`, returning whether it
+    /// was still `from`, so a cancelled job is never reported otherwise.
+    fn transition(
+        &self,
+        from: JobStatus,
+        to: JobStatus,
+    ) -> bool {
+        self.status
+            .compare_exchange(
+                from as usize,
+                to as usize,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok()
+    }
+}
+
+impl Scheduler {
+    /// Create a new scheduler.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                concurrency: SCHEDULER_CONCURRENCY_DEFAULT,
+                auto_tune: false,
+                throttle: None,
+                clock: Arc::new(SystemClock),
+                budget: None,
+                queue: Mutex::new(Queue::default()),
+            }),
+        }
+    }
+
+    /// Set the maximum number of jobs running at the same time.
+    ///
+    /// By default, the number follows the [`SCHEDULER_CONCURRENCY_DEFAULT`].
+    pub fn concurrency(
+        self,
+        jobs: usize,
+    ) -> Self {
+        self.rebuild(|inner| inner.concurrency = jobs.max(1))
+    }
+
+    /// Set whether to cap the number of jobs running at the same time by
+    /// the memory available to the process, e.g. the limit of the container.
+    ///
+    /// By default, it is `false`. When enabled, each job is estimated to use
+    /// the [`JOB_MEMORY_ESTIMATE`] within the
+    /// [`memory_budget`](crate::tune::memory_budget). The jobs themselves are
+    /// auto-tuned separately.
+    pub fn auto_tune(
+        self,
+        enabled: bool,
+    ) -> Self {
+        self.rebuild(|inner| inner.auto_tune = enabled)
+    }
+
+    /// Set the maximum number of bytes processed per second by all jobs.
+    ///
+    /// By default, the bandwidth is not limited.
+    pub fn bandwidth(
+        self,
+        bytes_per_second: usize,
+    ) -> Self {
+        self.rebuild(|inner| {
+            inner.throttle = Some(Arc::new(Throttle::new(
+                bytes_per_second,
+                inner.clock.clone(),
+            )))
+        })
+    }
+
+    /// Set the clock of the time-dependent parts, such as the bandwidth.
+    ///
+    /// By default, it is the [`SystemClock`].
+    pub fn clock(
+        self,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        self.rebuild(|inner| {
+            inner.throttle = inner
+                .throttle
+                .as_ref()
+                .map(|t| Arc::new(Throttle::new(t.rate(), clock.clone())));
+            inner.clock = clock;
+        })
+    }
+
+    /// Set the budget of the memory shared by all jobs.
+    ///
+    /// By default, the memory is not limited. When set, it overrides the
+    /// budget of each job.
+    pub fn memory_budget(
+        self,
+        budget: MemoryBudget,
+    ) -> Self {
+        self.rebuild(|inner| inner.budget = Some(budget))
+    }
+
+    fn rebuild<F: FnOnce(&mut Inner)>(
+        self,
+        f: F,
+    ) -> Self {
+        let mut inner: Inner = Inner {
+            concurrency: self.inner.concurrency,
+            auto_tune: self.inner.auto_tune,
+            throttle: self.inner.throttle.clone(),
+            clock: self.inner.clock.clone(),
+            budget: self.inner.budget.clone(),
+            queue: Mutex::new(Queue::default()),
+        };
+
+        f(&mut inner);
+
+        Self { inner: Arc::new(inner) }
+    }
+
+    /// Submit a job to the scheduler.
+    ///
+    /// The job starts as soon as a slot is available, so it must be called
+    /// within a tokio runtime.
+    pub fn submit<J: Into<Job>>(
+        &self,
+        job: J,
+    ) -> JobHandle {
+        let job: Job = job.into();
+
+        let (sender, receiver) = oneshot::channel();
+
+        let state: Arc<JobState> = Arc::new(JobState {
+            control: Arc::new(Control::new(self.inner.throttle.clone())),
+            status: AtomicUsize::new(JobStatus::Queued as usize),
+            cancelled: AtomicBool::new(false),
+            abort: Mutex::new(None),
+        });
+
+        let id: usize = {
+            let mut queue = self.inner.lock();
+
+            let id: usize = queue.next_id;
+
+            queue.next_id += 1;
+
+            queue.pending.push(Pending {
+                id,
+                priority: job.priority,
+                job: job.kind,
+                state: state.clone(),
+                sender,
+            });
+
+            id
+        };
+
+        self.inner.clone().dispatch();
+
+        JobHandle { id, state, receiver }
+    }
+
+    /// Get the number of jobs waiting for a slot.
+    pub fn queued(&self) -> usize {
+        let mut queue = self.inner.lock();
+
+        // the jobs cancelled while queued never start
+        queue
+            .pending
+            .retain(|pending| !pending.state.cancelled.load(Ordering::Acquire));
+
+        queue.pending.len()
+    }
+
+    /// Get the number of jobs running.
+    pub fn running(&self) -> usize {
+        self.inner.lock().running
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get the maximum number of jobs running at the same time.
+    fn concurrency(&self) -> usize {
+        match memory_budget().filter(|_| self.auto_tune) {
+            | Some(budget) => {
+                self.concurrency.min(budget / JOB_MEMORY_ESTIMATE).max(1)
+            },
+            | None => self.concurrency,
+        }
+    }
+
+    /// Start the queued jobs while slots are available.
+    fn dispatch(self: Arc<Self>) {
+        let concurrency: usize = self.concurrency();
+
+        let mut queue = self.lock();
+
+        while queue.running < concurrency {
+            let pending: Pending = match queue.pending.pop() {
+                | Some(pending) => pending,
+                | None => break,
+            };
+
+            if pending.state.cancelled.load(Ordering::Acquire)
+                || !pending
+                    .state
+                    .transition(JobStatus::Queued, JobStatus::Running)
+            {
+                let _ = pending.sender.send(Err(cancelled()));
+                continue;
+            }
+
+            queue.running += 1;
+
+            let slot: Slot = Slot { inner: self.clone() };
+
+            let state: Arc<JobState> = pending.state.clone();
+
+            let job: JobKind = match &self.budget {
+                | Some(budget) => pending.job.memory_budget(budget.clone()),
+                | None => pending.job,
+            };
+
+            let task: JoinHandle<()> = tokio::spawn(async move {
+                let _slot: Slot = slot;
+
+                let control: Arc<Control> = pending.state.control.clone();
+
+                let output: ioa::Result<JobOutput> = match job {
+                    | JobKind::Split(split) => {
+                        split.control(control).run().await.map(JobOutput::Split)
+                    },
+                    | JobKind::Merge(merge) => {
+                        merge.control(control).run().await.map(JobOutput::Merge)
+                    },
+                };
+
+                // cancelled once the work is done but before reported
+                let output: ioa::Result<JobOutput> = match pending
+                    .state
+                    .transition(JobStatus::Running, JobStatus::Finished)
+                {
+                    | true => output,
+                    | false => Err(cancelled()),
+                };
+
+                let _ = pending.sender.send(output);
+            });
+
+            *state.abort.lock().unwrap_or_else(|e| e.into_inner()) =
+                Some(task.abort_handle());
+
+            // cancelled before the abort handle is set
+            if state.cancelled.load(Ordering::Acquire) {
+                task.abort();
+            }
+        }
+    }
+}
+
+/// Slot of a running job, released when the job ends or is aborted.
+struct Slot {
+    inner: Arc<Inner>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.inner.lock().running -= 1;
+        self.inner.clone().dispatch();
+    }
+}
+
+/// Handle of a job submitted to the [`Scheduler`].
+#[derive(Debug)]
+pub struct JobHandle {
+    id: usize,
+    state: Arc<JobState>,
+    receiver: oneshot::Receiver<ioa::Result<JobOutput>>,
+}
+
+impl JobHandle {
+    /// Get the id of the job, in the order of submission.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the status of the job.
+    pub fn status(&self) -> JobStatus {
+        self.state.status()
+    }
+
+    /// Get the number of bytes processed by the job.
+    pub fn progress(&self) -> usize {
+        self.state.control.progress()
+    }
+
+    /// Pause the job after its current chunk.
+    ///
+    /// A paused job keeps its slot until it is resumed or cancelled.
+    pub fn pause(&self) {
+        self.operation().pause();
+    }
+
+    /// Resume the paused job.
+    pub fn resume(&self) {
+        self.operation().resume();
+    }
+
+    /// Get the time spent in each phase of the finished job, if profiled.
+    pub fn profile(&self) -> Option<Profile> {
+        self.operation().profile()
+    }
+
+    fn operation(&self) -> OperationHandle {
+        OperationHandle { control: self.state.control.clone() }
+    }
+
+    /// Cancel the job.
+    ///
+    /// A queued job never starts, and a running job is aborted at its next
+    /// await point, which may leave partial output behind.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Release);
+
+        // a finished job stays finished
+        for from in [JobStatus::Queued, JobStatus::Running] {
+            if self.state.transition(from, JobStatus::Cancelled) {
+                break;
+            }
+        }
+
+        if let Some(abort) =
+            self.state.abort.lock().unwrap_or_else(|e| e.into_inner()).take()
+        {
+            abort.abort();
+        }
+    }
+
+    /// Wait for the output of the job.
+    ///
+    /// A cancelled job fails with [`ioa::ErrorKind::Interrupted`].
+    pub async fn wait(self) -> ioa::Result<JobOutput> {
+        self.receiver.await.unwrap_or_else(|_| Err(cancelled()))
+    }
+}
+
+fn cancelled() -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::Interrupted, "job cancelled")
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    io::SeekFrom,
+    ops::Range,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub use bytes::Bytes;
+use bytes::BytesMut;
+pub use futures_core::Stream;
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt},
+};
+
+use crate::{
+    config::RANGE_BUFFER_SIZE_DEFAULT,
+    functions::peek::{spans, Span},
+    header::CHUNK_HEADER_SIZE,
+    layout::LayoutRegistry,
+    naming::ChunkNaming,
+};
+
+/// Future reading the next bytes of a [`RangeStream`].
+type Next =
+    Pin<Box<dyn Future<Output = (Option<ioa::Result<Bytes>>, Reader)> + Send>>;
+
+/// Create a stream of the bytes at a range of the original file, read
+/// straight from the chunks in `chunk_dir`, e.g. for the body of an HTTP
+/// `206 Partial Content` response.
+///
+/// The range is clamped to the size of the original file, and fails with
+/// [`ioa::ErrorKind::InvalidInput`] if nothing is left, e.g. to respond
+/// with `416 Range Not Satisfiable`. Only the chunks overlapping the range
+/// are opened, starting at the offset of the range within the first chunk,
+/// and the bytes are read through a buffer of [`RANGE_BUFFER_SIZE_DEFAULT`]
+/// reused once the previous bytes are dropped. Transformed chunks are not
+/// supported, as the offsets within them are unknown.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{future::poll_fn, pin::Pin};
+///
+/// use filego::serve::{range_stream, Bytes, RangeStream, Stream};
+///
+/// async fn example() {
+///     let mut stream: RangeStream =
+///         range_stream("/path/to/dir", 1024..4096).await.unwrap();
+///
+///     println!("Content-Range: {}", stream.content_range());
+///
+///     while let Some(bytes) =
+///         poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+///     {
+///         let bytes: Bytes = bytes.unwrap();
+///         // write the bytes to the response...
+///     }
+/// }
+/// ```
+pub async fn range_stream<P: AsRef<Path>>(
+    chunk_dir: P,
+    range: Range<usize>,
+) -> ioa::Result<RangeStream> {
+    let spans: Vec<Span> = spans(
+        chunk_dir.as_ref(),
+        None,
+        &ChunkNaming::Index,
+        &LayoutRegistry::new(),
+    )
+    .await?;
+
+    let file_size: usize =
+        spans.last().map_or(0, |span| span.offset + span.size);
+
+    let range: Range<usize> = range.start..range.end.min(file_size);
+
+    if range.start >= range.end {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidInput,
+            format!("range is not satisfiable for {} bytes", file_size),
+        ));
+    }
+
+    // the chunks outside of the range are skipped without being opened
+    let spans: VecDeque<(Span, Range<usize>)> = spans
+        .into_iter()
+        .filter_map(|span| {
+            let start: usize = range.start.max(span.offset);
+            let end: usize = range.end.min(span.offset + span.size);
+
+            (start < end)
+                .then(|| (start - span.offset..end - span.offset, span))
+                .map(|(within, span)| (span, within))
+        })
+        .collect();
+
+    let reader: Reader = Reader {
+        spans,
+        current: None,
+        buffer: BytesMut::new(),
+        buffer_size: RANGE_BUFFER_SIZE_DEFAULT,
+    };
+
+    Ok(RangeStream { file_size, range, reader: Some(reader), next: None })
+}
+
+/// Stream of the bytes at a range of the original file, created by
+/// [`range_stream`].
+///
+/// The stream ends after the first error.
+pub struct RangeStream {
+    file_size: usize,
+    range: Range<usize>,
+    reader: Option<Reader>,
+    next: Option<Next>,
+}
+
+impl RangeStream {
+    /// Get the size of the original file.
+    pub fn file_size(&self) -> usize {
+        self.file_size
+    }
+
+    /// Get the range streamed, clamped to the size of the original file.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Get the value of the `Content-Range` header of the range, e.g.
+    /// `bytes 0-1023/4096`.
+    pub fn content_range(&self) -> String {
+        format!(
+            "bytes {}-{}/{}",
+            self.range.start,
+            self.range.end - 1,
+            self.file_size
+        )
+    }
+}
+
+impl fmt::Debug for RangeStream {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.debug_struct("RangeStream")
+            .field("file_size", &self.file_size)
+            .field("range", &self.range)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for RangeStream {
+    type Item = ioa::Result<Bytes>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.next.is_none() {
+            match self.reader.take() {
+                | Some(reader) => self.next = Some(Box::pin(reader.next())),
+                | None => return Poll::Ready(None),
+            }
+        }
+
+        let next: &mut Next = self.next.as_mut().unwrap();
+
+        let (item, reader): (Option<ioa::Result<Bytes>>, Reader) =
+            match next.as_mut().poll(cx) {
+                | Poll::Ready(output) => output,
+                | Poll::Pending => return Poll::Pending,
+            };
+
+        self.next = None;
+
+        if let Some(Ok(_)) = item {
+            self.reader = Some(reader);
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+/// State of a [`RangeStream`] between the reads.
+struct Reader {
+    spans: VecDeque<(Span, Range<usize>)>,
+    current: Option<(usize, fsa::File, usize)>,
+    buffer: BytesMut,
+    buffer_size: usize,
+}
+
+impl Reader {
+    /// Read the next bytes, returning the state for the following read.
+    async fn next(mut self) -> (Option<ioa::Result<Bytes>>, Self) {
+        let item: Option<ioa::Result<Bytes>> = self.read().await.transpose();
+
+        (item, self)
+    }
+
+    async fn read(&mut self) -> ioa::Result<Option<Bytes>> {
+        loop {
+            if let Some((index, file, remaining)) = &mut self.current {
+                if *remaining == 0 {
+                    self.current = None;
+                    continue;
+                }
+
+                let limit: usize = self.buffer_size.min(*remaining);
+
+                // the allocation is reclaimed once the previous bytes are
+                // dropped by the consumer
+                self.buffer.reserve(limit);
+
+                let read: usize = (&mut *file)
+                    .take(limit as u64)
+                    .read_buf(&mut self.buffer)
+                    .await?;
+
+                if read == 0 {
+                    return Err(ioa::Error::new(
+                        ioa::ErrorKind::UnexpectedEof,
+                        format!("chunk {} is shorter than expected", index),
+                    ));
+                }
+
+                *remaining -= read;
+
+                return Ok(Some(self.buffer.split().freeze()));
+            }
+
+            let (span, within): (Span, Range<usize>) =
+                match self.spans.pop_front() {
+                    | Some(next) => next,
+                    | None => return Ok(None),
+                };
+
+            let skip: usize = if span.header { CHUNK_HEADER_SIZE } else { 0 };
+
+            let mut file: fsa::File =
+                fsa::OpenOptions::new().read(true).open(&span.path).await?;
+
+            file.seek(SeekFrom::Start((skip + within.start) as u64)).await?;
+
+            self.current = Some((span.index, file, within.len()));
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+
+use tokio::{fs as fsa, io as ioa};
+
+use crate::{
+    check::{Check, CheckResult},
+    config::MANIFEST_FILE_NAME,
+    manifest::{find_manifest_path, ChunkInfo, Manifest},
+    merge::Merge,
+    split::{Split, SplitResult},
+    store::DirStore,
+};
+#[cfg(feature = "checksum")]
+use crate::{
+    checksum::{chunk_checksum, ChunkChecksum},
+    header::CHUNK_HEADER_SIZE,
+};
+
+/// Status of the chunks in a [`Session`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionStatus {
+    /// The total number of chunks.
+    pub total_chunks: usize,
+    /// The number of chunks received in full.
+    pub received: usize,
+    /// Total size of the chunks received in storage.
+    pub received_size: usize,
+    /// Indices of the chunks missing or incomplete, sorted.
+    pub missing: Vec<usize>,
+}
+
+impl SessionStatus {
+    /// Check whether all chunks are received.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Session owning a chunk directory, which ties the split, check and merge
+/// processes together without passing the file size and the total number
+/// of chunks around.
+///
+/// The state of the session is the manifest in the directory, so a session
+/// is resumed by opening the directory again, e.g. after the receiver
+/// restarts. The sender splits the file into the session, and the receiver
+/// starts its session with the manifest of the sender, receives the chunks
+/// in any order and finalizes the session into the original file.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{
+///     session::{Session, SessionStatus},
+///     split::Split,
+/// };
+///
+/// async fn example() {
+///     // sender
+///     let mut sender: Session = Session::open("/path/to/dir").await.unwrap();
+///
+///     sender.split(Split::new().in_file("/path/to/file")).await.unwrap();
+///
+///     // receiver
+///     let mut receiver: Session =
+///         Session::open("/path/to/received").await.unwrap();
+///
+///     receiver
+///         .receive_manifest(sender.manifest().unwrap().clone())
+///         .await
+///         .unwrap();
+///
+///     receiver.receive_chunk(0, vec![0; 1024]).await.unwrap();
+///
+///     let status: SessionStatus = receiver.status().await.unwrap();
+///
+///     if status.is_complete() {
+///         receiver.finalize("/path/to/output").await.unwrap();
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Session {
+    dir: PathBuf,
+    manifest: Option<(PathBuf, Manifest)>,
+}
+
+impl Session {
+    /// Open the session in `dir`, resuming its state if the directory
+    /// contains a manifest.
+    pub async fn open<Dir: AsRef<Path>>(dir: Dir) -> ioa::Result<Self> {
+        let dir: PathBuf = dir.as_ref().to_path_buf();
+
+        let manifest: Option<(PathBuf, Manifest)> =
+            match find_manifest_path(&dir).await? {
+                | Some(path) => {
+                    let manifest: Manifest = Manifest::read(&path).await?;
+
+                    Some((path, manifest))
+                },
+                | None => None,
+            };
+
+        Ok(Self { dir, manifest })
+    }
+
+    /// Get the directory of the session.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Get the manifest of the session, or `None` if not started.
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref().map(|(_, manifest)| manifest)
+    }
+
+    /// Split the file into the session with the `split` process, whose
+    /// output directory is the directory of the session and whose manifest
+    /// is always written. The split is never [`scoped`](Split::scoped), as
+    /// the session owns its directory.
+    pub async fn split(
+        &mut self,
+        split: Split,
+    ) -> ioa::Result<SplitResult> {
+        let result: SplitResult =
+            split.out_dir(&self.dir).scoped(false).manifest(true).run().await?;
+
+        *self = Self::open(&self.dir).await?;
+
+        Ok(result)
+    }
+
+    /// Start receiving the chunks described by the `manifest` of the sender,
+    /// which is written to the directory.
+    ///
+    /// Starting a resumed session again with the same manifest does nothing,
+    /// and with another manifest fails with
+    /// [`ioa::ErrorKind::AlreadyExists`]. A manifest whose chunks would be
+    /// written outside the directory fails with
+    /// [`ioa::ErrorKind::InvalidData`] before anything is written.
+    pub async fn receive_manifest(
+        &mut self,
+        manifest: Manifest,
+    ) -> ioa::Result<()> {
+        // the paths of the chunks are resolved from the manifest of the
+        // sender, which is not trusted
+        manifest.naming.validate()?;
+
+        let store: DirStore = DirStore::with_manifest(&self.dir, &manifest);
+
+        for chunk in &manifest.chunks {
+            within(&self.dir, &store.path(chunk.index), chunk.index)?;
+        }
+
+        if let Some((_, current)) = &self.manifest {
+            if current.id == manifest.id && current.chunks == manifest.chunks {
+                return Ok(());
+            }
+
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::AlreadyExists,
+                "session is started with another manifest",
+            ));
+        }
+
+        let path: PathBuf = self.dir.join(MANIFEST_FILE_NAME);
+
+        fsa::create_dir_all(&self.dir).await?;
+
+        manifest.write(&path).await?;
+
+        self.manifest = Some((path, manifest));
+
+        Ok(())
+    }
+
+    /// Receive the chunk at `index`, which is verified against the size and
+    /// the checksum in the manifest before being written.
+    ///
+    /// A chunk not matching the manifest fails with
+    /// [`ioa::ErrorKind::InvalidData`] without being written. The chunk is
+    /// written to a temporary file first, so an interrupted write is not
+    /// taken as received, and never outside the directory.
+    pub async fn receive_chunk(
+        &self,
+        index: usize,
+        data: Vec<u8>,
+    ) -> ioa::Result<()> {
+        let manifest: &Manifest = self.started()?;
+
+        let chunk: &ChunkInfo = match manifest.chunk(index) {
+            | Some(chunk) => chunk,
+            | None => {
+                return Err(ioa::Error::new(
+                    ioa::ErrorKind::InvalidInput,
+                    format!("chunk {} is not in the manifest", index),
+                ))
+            },
+        };
+
+        if data.len() != chunk.stored_size {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                format!("size of chunk {} does not match", index),
+            ));
+        }
+
+        #[cfg(feature = "checksum")]
+        let data: Vec<u8> = match chunk_checksum(chunk) {
+            | Some((algorithm, digest)) => {
+                let digest: String = digest.to_string();
+                let headers: bool = manifest.headers;
+
+                tokio::task::spawn_blocking(move || {
+                    let payload: &[u8] = match headers {
+                        | true => &data[CHUNK_HEADER_SIZE.min(data.len())..],
+                        | false => &data,
+                    };
+
+                    match algorithm
+                        .digest(payload)
+                        .eq_ignore_ascii_case(&digest)
+                    {
+                        | true => Ok(data),
+                        | false => Err(ioa::Error::new(
+                            ioa::ErrorKind::InvalidData,
+                            format!(
+                                "checksum of chunk {} does not match",
+                                index
+                            ),
+                        )),
+                    }
+                })
+                .await
+                .map_err(ioa::Error::other)??
+            },
+            | None => data,
+        };
+
+        let path: PathBuf =
+            DirStore::with_manifest(&self.dir, manifest).path(index);
+
+        within(&self.dir, &path, index)?;
+
+        let mut partial: OsString = path.clone().into_os_string();
+        partial.push(".part");
+
+        if let Some(parent) = path.parent() {
+            fsa::create_dir_all(parent).await?;
+        }
+
+        fsa::write(&partial, data).await?;
+        fsa::rename(&partial, &path).await
+    }
+
+    /// Get the status of the chunks in the directory, comparing their sizes
+    /// with the manifest.
+    pub async fn status(&self) -> ioa::Result<SessionStatus> {
+        let manifest: &Manifest = self.started()?;
+
+        let store: DirStore = DirStore::with_manifest(&self.dir, manifest);
+
+        let mut status: SessionStatus = SessionStatus {
+            total_chunks: manifest.total_chunks,
+            received: 0,
+            received_size: 0,
+            missing: Vec::new(),
+        };
+
+        for chunk in &manifest.chunks {
+            let size: Option<usize> =
+                match fsa::metadata(store.path(chunk.index)).await {
+                    | Ok(metadata) if metadata.is_file() => {
+                        Some(metadata.len() as usize)
+                    },
+                    | Ok(_) => None,
+                    | Err(e) if e.kind() == ioa::ErrorKind::NotFound => None,
+                    | Err(e) => return Err(e),
+                };
+
+            if size == Some(chunk.stored_size) {
+                status.received += 1;
+                status.received_size += chunk.stored_size;
+            } else {
+                status.missing.push(chunk.index);
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Check the chunks and merge them into `out_file`, failing with
+    /// [`ioa::ErrorKind::InvalidData`] if the check fails.
+    ///
+    /// With the `checksum` feature, the chunks are verified against the
+    /// checksums in the manifest as well.
+    pub async fn finalize<OutFile: AsRef<Path>>(
+        &self,
+        out_file: OutFile,
+    ) -> ioa::Result<()> {
+        let path: &Path = match &self.manifest {
+            | Some((path, _)) => path,
+            | None => return Err(not_started()),
+        };
+
+        let check: Check = Check::new().from_manifest(path);
+
+        #[cfg(feature = "checksum")]
+        let check: Check = check.verify_checksums(ChunkChecksum::of_chunks(
+            &self.started()?.chunks,
+        ));
+
+        let result: CheckResult = check.run().await?;
+
+        if let Some(error) = result.error {
+            return Err(ioa::Error::new(
+                ioa::ErrorKind::InvalidData,
+                error.message,
+            ));
+        }
+
+        Merge::new().from_manifest(path).out_file(out_file).run().await?;
+
+        Ok(())
+    }
+
+    fn started(&self) -> ioa::Result<&Manifest> {
+        self.manifest().ok_or_else(not_started)
+    }
+}
+
+/// Check that the `path` of the chunk at `index` is within `dir`.
+fn within(
+    dir: &Path,
+    path: &Path,
+    index: usize,
+) -> ioa::Result<()> {
+    let within: bool = path.strip_prefix(dir).is_ok_and(|relative| {
+        relative.components().all(|c| matches!(c, Component::Normal(_)))
+    });
+
+    match within {
+        | true => Ok(()),
+        | false => Err(ioa::Error::new(
+            ioa::ErrorKind::InvalidData,
+            format!("path of chunk {} is outside the session", index),
+        )),
+    }
+}
+
+fn not_started() -> ioa::Error {
+    ioa::Error::new(ioa::ErrorKind::InvalidInput, "session is not started")
+}
@@ -0,0 +1,169 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use tokio::{io as ioa, time};
+
+/// Statistics of the stalled writes from a [`StallMonitor`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StallStats {
+    /// The number of writes.
+    pub writes: usize,
+    /// The number of writes taking at least the threshold.
+    pub stalls: usize,
+    /// Total time of the stalled writes.
+    pub stalled: Duration,
+    /// Time of the longest write.
+    pub longest: Duration,
+}
+
+/// Monitor of the writes of the merge process, detecting the writes stalled
+/// by a slow sink, e.g. a socket to a slow client, with an optional policy
+/// to abort the process.
+///
+/// A write is stalled if it takes at least the threshold, and the
+/// statistics can be read from any thread while merging. With a timeout,
+/// the process fails with [`ioa::ErrorKind::TimedOut`] once a single write
+/// takes longer, and with a budget, once the stalled writes take longer in
+/// total, so a service streaming to slow clients can shed load
+/// deliberately.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{sync::Arc, time::Duration};
+///
+/// use filego::{merge::Merge, stall::StallMonitor};
+/// use tokio::net::TcpStream;
+///
+/// async fn example(socket: TcpStream) {
+///     let monitor: Arc<StallMonitor> = Arc::new(
+///         StallMonitor::new(Duration::from_millis(100))
+///             .timeout(Duration::from_secs(10)),
+///     );
+///
+///     Merge::new()
+///         .from_manifest("/path/to/manifest.json")
+///         .stall_monitor(monitor.clone())
+///         .run_to(socket)
+///         .await
+///         .unwrap();
+///
+///     println!("{} writes stalled", monitor.stats().stalls);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StallMonitor {
+    threshold: Duration,
+    timeout: Option<Duration>,
+    budget: Option<Duration>,
+    writes: AtomicUsize,
+    stalls: AtomicUsize,
+    stalled: AtomicU64,
+    longest: AtomicU64,
+}
+
+impl StallMonitor {
+    /// Create a new monitor counting the writes taking at least `threshold`
+    /// as stalled.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            timeout: None,
+            budget: None,
+            writes: AtomicUsize::new(0),
+            stalls: AtomicUsize::new(0),
+            stalled: AtomicU64::new(0),
+            longest: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the maximum time of a single write before aborting.
+    ///
+    /// By default, a write is waited for without a limit.
+    pub fn timeout(
+        mut self,
+        timeout: Duration,
+    ) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum total time of the stalled writes before aborting.
+    ///
+    /// By default, the stalled writes are not limited in total.
+    pub fn budget(
+        mut self,
+        budget: Duration,
+    ) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Get the threshold of a stalled write.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Get the statistics of the writes so far.
+    pub fn stats(&self) -> StallStats {
+        StallStats {
+            writes: self.writes.load(Ordering::Relaxed),
+            stalls: self.stalls.load(Ordering::Relaxed),
+            stalled: Duration::from_nanos(self.stalled.load(Ordering::Relaxed)),
+            longest: Duration::from_nanos(self.longest.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Wait for the write, recording its time and applying the policy.
+    pub(crate) async fn watch<F: Future<Output = ioa::Result<()>>>(
+        &self,
+        write: F,
+    ) -> ioa::Result<()> {
+        let started: Instant = Instant::now();
+
+        let result: ioa::Result<()> = match self.timeout {
+            | Some(timeout) => match time::timeout(timeout, write).await {
+                | Ok(result) => result,
+                | Err(_) => Err(ioa::Error::new(
+                    ioa::ErrorKind::TimedOut,
+                    format!("write stalled for more than {:?}", timeout),
+                )),
+            },
+            | None => write.await,
+        };
+
+        let elapsed: Duration = started.elapsed();
+        let nanos: u64 = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.longest.fetch_max(nanos, Ordering::Relaxed);
+
+        if elapsed < self.threshold {
+            return result;
+        }
+
+        self.stalls.fetch_add(1, Ordering::Relaxed);
+
+        let stalled: Duration = Duration::from_nanos(
+            self.stalled
+                .fetch_add(nanos, Ordering::Relaxed)
+                .saturating_add(nanos),
+        );
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(?elapsed, ?stalled, "write stalled");
+
+        result?;
+
+        match self.budget {
+            | Some(budget) if stalled > budget => Err(ioa::Error::new(
+                ioa::ErrorKind::TimedOut,
+                format!("writes stalled for {:?} in total", stalled),
+            )),
+            | _ => Ok(()),
+        }
+    }
+}
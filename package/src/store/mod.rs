@@ -0,0 +1,328 @@
+use std::{
+    fmt,
+    future::Future,
+    io::SeekFrom,
+    ops::Range,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+use tokio::{
+    fs as fsa,
+    io::{self as ioa, AsyncReadExt, AsyncSeekExt},
+};
+
+use crate::{
+    config::VOLUME_DIR_PREFIX, manifest::Manifest, naming::ChunkNaming,
+};
+
+mod tiered;
+
+#[cfg(feature = "mmap")]
+pub use memmap2::Mmap;
+pub use tiered::TieredStore;
+
+/// Future returned by the methods of [`ChunkStore`].
+pub type StoreFuture<'a, T> =
+    Pin<Box<dyn Future<Output = ioa::Result<T>> + Send + 'a>>;
+
+/// Metadata of a chunk in a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHead {
+    /// Size of the chunk in the store.
+    pub size: usize,
+    /// Entity tag of the chunk exposed by the store, e.g. S3 ETag.
+    pub etag: Option<String>,
+}
+
+/// Storage backend of the chunks.
+///
+/// It abstracts where the chunks live, so the chunks can be checked and
+/// merged from a remote backend, such as an object store, in the same way as
+/// a local directory.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{path::PathBuf, sync::Arc};
+///
+/// use filego::{
+///     check::{Check, CheckResult},
+///     store::DirStore,
+/// };
+///
+/// async fn example() {
+///     let result: CheckResult = Check::new()
+///         .remote(Arc::new(DirStore::new(
+///             PathBuf::from("path").join("to").join("dir"),
+///         )))
+///         .file_size(0) // result from split function...
+///         .total_chunks(0) // result from split function...
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+pub trait ChunkStore: fmt::Debug + Send + Sync {
+    /// Get the metadata of the chunk at `index` without fetching its data.
+    ///
+    /// Returns `None` if the chunk does not exist.
+    fn head(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Option<ChunkHead>>;
+
+    /// Get the data of the chunk at `index`.
+    fn get(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Vec<u8>>;
+
+    /// Get the bytes of the chunk at `index` within `range`, or until the end
+    /// of the chunk if shorter.
+    ///
+    /// By default, the whole chunk is fetched and sliced. Stores able to
+    /// fetch a range, e.g. with an HTTP range request, should override it
+    /// along with [`ChunkStore::ranged`].
+    fn get_range(
+        &self,
+        index: usize,
+        range: Range<usize>,
+    ) -> StoreFuture<'_, Vec<u8>> {
+        Box::pin(async move {
+            let mut chunk: Vec<u8> = self.get(index).await?;
+
+            chunk.truncate(range.end);
+
+            Ok(chunk.split_off(range.start.min(chunk.len())))
+        })
+    }
+
+    /// Put the data of the chunk at `index`.
+    fn put(
+        &self,
+        index: usize,
+        data: Vec<u8>,
+    ) -> StoreFuture<'_, ()>;
+
+    /// Check whether [`ChunkStore::get_range`] fetches the range only, so
+    /// the chunks are merged through a buffer bounded by the
+    /// `max_buffer_capacity` instead of being held in full.
+    ///
+    /// By default, it is `false`.
+    fn ranged(&self) -> bool {
+        false
+    }
+}
+
+/// Store of the chunks in a local directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirStore {
+    dir: PathBuf,
+    padding: usize,
+    naming: ChunkNaming,
+    volumes: Vec<usize>,
+}
+
+impl DirStore {
+    /// Create a new store of the chunks in `dir`.
+    pub fn new<Dir: AsRef<Path>>(dir: Dir) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            padding: 0,
+            naming: ChunkNaming::Index,
+            volumes: Vec::new(),
+        }
+    }
+
+    /// Create a new store of the chunks in `dir` laid out as described in
+    /// the manifest, including the names and volumes.
+    pub fn with_manifest<Dir: AsRef<Path>>(
+        dir: Dir,
+        manifest: &Manifest,
+    ) -> Self {
+        let volumes: Vec<usize> =
+            if manifest.chunks.iter().any(|c| c.volume.is_some()) {
+                manifest.chunks.iter().map(|c| c.volume.unwrap_or(0)).collect()
+            } else {
+                Vec::new()
+            };
+
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            padding: manifest.padding(),
+            naming: manifest.naming.clone(),
+            volumes,
+        }
+    }
+
+    /// Set the width of the names of the chunks padded with zeros.
+    ///
+    /// By default, the names are not padded.
+    pub fn padding(
+        mut self,
+        width: usize,
+    ) -> Self {
+        self.padding = width;
+        self
+    }
+
+    /// Set the scheme of the names of the chunks.
+    ///
+    /// By default, the names are the indices of the chunks.
+    pub fn naming(
+        mut self,
+        naming: ChunkNaming,
+    ) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Get the directory of the store.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Get the path of the chunk at `index`.
+    pub fn path(
+        &self,
+        index: usize,
+    ) -> PathBuf {
+        chunk_path(
+            &self.dir,
+            self.volumes.get(index).copied(),
+            self.naming.name(index, self.padding),
+        )
+    }
+
+    /// Map the chunk file at `index` into memory, including its header if
+    /// any.
+    ///
+    /// The data is read from the page cache on access, so it can be handed
+    /// to the writes of a socket without copying it into a buffer first.
+    /// The chunk file must not be modified while mapped, otherwise the data
+    /// may change or the process may crash when accessing it.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use filego::{
+    ///     split::{Split, SplitResult},
+    ///     store::{DirStore, Mmap},
+    /// };
+    ///
+    /// async fn example() {
+    ///     let result: SplitResult = Split::new()
+    ///         .in_file("/path/to/file")
+    ///         .out_dir("/path/to/dir")
+    ///         .run()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let store: DirStore =
+    ///         DirStore::with_manifest("/path/to/dir", &result.manifest);
+    ///
+    ///     for index in 0..result.total_chunks {
+    ///         let chunk: Mmap = store.map(index).await.unwrap();
+    ///
+    ///         println!("chunk {} has {} bytes", index, chunk.len());
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub async fn map(
+        &self,
+        index: usize,
+    ) -> ioa::Result<Mmap> {
+        let path: PathBuf = self.path(index);
+
+        tokio::task::spawn_blocking(move || {
+            let file: std::fs::File = std::fs::File::open(path)?;
+
+            // the chunk file is required not to be modified while mapped
+            unsafe { Mmap::map(&file) }
+        })
+        .await
+        .map_err(ioa::Error::other)?
+    }
+}
+
+impl ChunkStore for DirStore {
+    fn head(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Option<ChunkHead>> {
+        Box::pin(async move {
+            match fsa::metadata(self.path(index)).await {
+                | Ok(metadata) if metadata.is_file() => Ok(Some(ChunkHead {
+                    size: metadata.len() as usize,
+                    etag: None,
+                })),
+                | Ok(_) => Ok(None),
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => Ok(None),
+                | Err(e) => Err(e),
+            }
+        })
+    }
+
+    fn get(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Vec<u8>> {
+        Box::pin(async move { fsa::read(self.path(index)).await })
+    }
+
+    fn get_range(
+        &self,
+        index: usize,
+        range: Range<usize>,
+    ) -> StoreFuture<'_, Vec<u8>> {
+        Box::pin(async move {
+            let mut input: fsa::File =
+                fsa::File::open(self.path(index)).await?;
+
+            input.seek(SeekFrom::Start(range.start as u64)).await?;
+
+            let mut data: Vec<u8> = Vec::with_capacity(range.len());
+
+            input.take(range.len() as u64).read_to_end(&mut data).await?;
+
+            Ok(data)
+        })
+    }
+
+    fn put(
+        &self,
+        index: usize,
+        data: Vec<u8>,
+    ) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            fsa::create_dir_all(&self.dir).await?;
+            fsa::write(self.path(index), data).await
+        })
+    }
+
+    fn ranged(&self) -> bool {
+        true
+    }
+}
+
+/// Get the path of the volume directory in `dir`.
+pub fn volume_dir(
+    dir: &Path,
+    volume: usize,
+) -> PathBuf {
+    dir.join(format!("{}{}", VOLUME_DIR_PREFIX, volume))
+}
+
+/// Get the path of the chunk in `dir`, within the volume directory if any.
+pub(crate) fn chunk_path(
+    dir: &Path,
+    volume: Option<usize>,
+    name: String,
+) -> PathBuf {
+    match volume {
+        | Some(volume) => volume_dir(dir, volume).join(name),
+        | None => dir.join(name),
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tokio::{fs as fsa, io as ioa, sync::Semaphore, task::JoinHandle};
+
+use super::{ChunkHead, ChunkStore, DirStore, StoreFuture};
+use crate::config::UPLOAD_CONCURRENCY_DEFAULT;
+
+/// Task uploading a chunk to the remote store.
+type Upload = JoinHandle<ioa::Result<()>>;
+
+/// Store writing the chunks to a local directory first and uploading them
+/// to a remote store in the background.
+///
+/// The chunks are read from the local directory if present, or from the
+/// remote store otherwise. By default, the local copy of a chunk is evicted
+/// once it is uploaded. Call [`TieredStore::flush`] to wait for the pending
+/// uploads and get their errors.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{path::PathBuf, sync::Arc};
+///
+/// use filego::store::{ChunkStore, DirStore, TieredStore};
+///
+/// async fn example() {
+///     let store: TieredStore = TieredStore::new(
+///         DirStore::new(PathBuf::from("path").join("to").join("cache")),
+///         Arc::new(DirStore::new(PathBuf::from("path").join("to").join("dir"))),
+///     );
+///
+///     store.put(0, b"chunk".to_vec()).await.unwrap();
+///
+///     // wait for the chunks to be uploaded
+///     store.flush().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TieredStore {
+    local: DirStore,
+    remote: Arc<dyn ChunkStore>,
+    evict: bool,
+    uploads: Arc<Semaphore>,
+    pending: Mutex<Vec<Upload>>,
+}
+
+impl TieredStore {
+    /// Create a new store of the chunks in the local directory, uploaded to
+    /// the remote store.
+    pub fn new(
+        local: DirStore,
+        remote: Arc<dyn ChunkStore>,
+    ) -> Self {
+        Self {
+            local,
+            remote,
+            evict: true,
+            uploads: Arc::new(Semaphore::new(UPLOAD_CONCURRENCY_DEFAULT)),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set whether to remove the local copy of a chunk after uploading it.
+    ///
+    /// By default, it is `true`.
+    pub fn evict(
+        mut self,
+        evict: bool,
+    ) -> Self {
+        self.evict = evict;
+        self
+    }
+
+    /// Set the number of chunks uploaded at the same time.
+    ///
+    /// By default, it follows the [`UPLOAD_CONCURRENCY_DEFAULT`].
+    pub fn concurrency(
+        mut self,
+        concurrency: usize,
+    ) -> Self {
+        self.uploads = Arc::new(Semaphore::new(concurrency.max(1)));
+        self
+    }
+
+    /// Get the local tier of the store.
+    pub fn local(&self) -> &DirStore {
+        &self.local
+    }
+
+    /// Get the remote tier of the store.
+    pub fn remote(&self) -> &Arc<dyn ChunkStore> {
+        &self.remote
+    }
+
+    /// Get the number of uploads not yet waited for by
+    /// [`TieredStore::flush`], including the finished ones.
+    pub fn pending(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Wait for all pending uploads, returning the first error if any.
+    pub async fn flush(&self) -> ioa::Result<()> {
+        let pending: Vec<Upload> = std::mem::take(&mut *self.lock());
+
+        let mut result: ioa::Result<()> = Ok(());
+
+        for upload in pending {
+            let uploaded: ioa::Result<()> =
+                upload.await.map_err(ioa::Error::other).and_then(|r| r);
+
+            if result.is_ok() {
+                result = uploaded;
+            }
+        }
+
+        result
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Upload>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ChunkStore for TieredStore {
+    fn head(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Option<ChunkHead>> {
+        Box::pin(async move {
+            match self.local.head(index).await? {
+                | Some(head) => Ok(Some(head)),
+                | None => self.remote.head(index).await,
+            }
+        })
+    }
+
+    fn get(
+        &self,
+        index: usize,
+    ) -> StoreFuture<'_, Vec<u8>> {
+        Box::pin(async move {
+            // the local copy may be evicted after the upload
+            match self.local.get(index).await {
+                | Ok(data) => Ok(data),
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                    self.remote.get(index).await
+                },
+                | Err(e) => Err(e),
+            }
+        })
+    }
+
+    fn get_range(
+        &self,
+        index: usize,
+        range: Range<usize>,
+    ) -> StoreFuture<'_, Vec<u8>> {
+        Box::pin(async move {
+            match self.local.get_range(index, range.clone()).await {
+                | Ok(data) => Ok(data),
+                | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                    self.remote.get_range(index, range).await
+                },
+                | Err(e) => Err(e),
+            }
+        })
+    }
+
+    fn put(
+        &self,
+        index: usize,
+        data: Vec<u8>,
+    ) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            self.local.put(index, data).await?;
+
+            let local: DirStore = self.local.clone();
+            let remote: Arc<dyn ChunkStore> = self.remote.clone();
+            let uploads: Arc<Semaphore> = self.uploads.clone();
+            let evict: bool = self.evict;
+
+            // the chunk is read back when uploading, so it is not kept in
+            // memory while waiting for the other uploads
+            let upload: Upload = tokio::spawn(async move {
+                let _permit =
+                    uploads.acquire_owned().await.map_err(ioa::Error::other)?;
+
+                remote.put(index, local.get(index).await?).await?;
+
+                if evict {
+                    match fsa::remove_file(local.path(index)).await {
+                        | Err(e) if e.kind() != ioa::ErrorKind::NotFound => {
+                            return Err(e)
+                        },
+                        | _ => {},
+                    }
+                }
+
+                Ok(())
+            });
+
+            self.lock().push(upload);
+
+            Ok(())
+        })
+    }
+
+    fn ranged(&self) -> bool {
+        self.remote.ranged()
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fmt, io};
+
+/// Transform applied to the data of each chunk.
+///
+/// It allows custom storage encodings of the chunks, such as encryption or
+/// compression. The split process encodes each chunk before writing it, and
+/// the merge process decodes each chunk before writing it to the output file.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::io;
+///
+/// use filego::{merge::Merge, split::Split, transform::ChunkTransform};
+///
+/// #[derive(Debug, Clone)]
+/// struct Xor(u8);
+///
+/// impl ChunkTransform for Xor {
+///     fn id(&self) -> &str {
+///         "xor"
+///     }
+///
+///     fn encode(
+///         &self,
+///         _index: usize,
+///         chunk: Vec<u8>,
+///     ) -> io::Result<Vec<u8>> {
+///         Ok(chunk.into_iter().map(|b| b ^ self.0).collect())
+///     }
+///
+///     fn decode(
+///         &self,
+///         index: usize,
+///         chunk: Vec<u8>,
+///     ) -> io::Result<Vec<u8>> {
+///         self.encode(index, chunk)
+///     }
+/// }
+///
+/// let split: Split = Split::new().transform(Xor(0x5A));
+/// let merge: Merge = Merge::new().transform(Xor(0x5A));
+/// ```
+pub trait ChunkTransform: fmt::Debug + Send + Sync {
+    /// Get the identity of the transform, e.g. `"gzip"`.
+    ///
+    /// The identity is recorded in the manifest, so the merge process can
+    /// verify that the chunks are decoded with the same transform.
+    fn id(&self) -> &str;
+
+    /// Encode the data of the chunk at `index` during the split process.
+    fn encode(
+        &self,
+        index: usize,
+        chunk: Vec<u8>,
+    ) -> io::Result<Vec<u8>>;
+
+    /// Decode the data of the chunk at `index` during the merge process.
+    fn decode(
+        &self,
+        index: usize,
+        chunk: Vec<u8>,
+    ) -> io::Result<Vec<u8>>;
+}
@@ -0,0 +1,299 @@
+use std::{path::Path, sync::Arc};
+
+use tokio::{
+    io as ioa,
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+
+use crate::config::AUTO_TUNE_MEMORY_FRACTION;
+
+/// Granularity of the memory budget in bytes.
+const BUDGET_UNIT: usize = 1024;
+
+/// Budget of the memory shared by concurrent split and merge processes.
+///
+/// Each process reserves the memory of its buffers from the budget before
+/// allocating them, and waits while the budget is exhausted. The buffers of
+/// the streamed chunks are capped to fit the budget, while a process holding
+/// chunks in full larger than the whole budget fails, see the
+/// [crate documentation](crate#memory).
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{scheduler::Scheduler, tune::MemoryBudget};
+///
+/// let scheduler: Scheduler = Scheduler::new()
+///     .concurrency(20)
+///     .memory_budget(MemoryBudget::new(256 * 1024 * 1024));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    semaphore: Arc<Semaphore>,
+    units: usize,
+}
+
+impl MemoryBudget {
+    /// Create a new budget of `bytes`.
+    pub fn new(bytes: usize) -> Self {
+        let units: usize = bytes
+            .div_ceil(BUDGET_UNIT)
+            .clamp(1, (u32::MAX as usize).min(Semaphore::MAX_PERMITS));
+
+        Self { semaphore: Arc::new(Semaphore::new(units)), units }
+    }
+
+    /// Get the total bytes of the budget.
+    pub fn total(&self) -> usize {
+        self.units * BUDGET_UNIT
+    }
+
+    /// Get the bytes not reserved yet.
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits() * BUDGET_UNIT
+    }
+
+    /// Reserve `bytes` from the budget until the reservation is dropped.
+    pub(crate) async fn reserve(
+        &self,
+        bytes: usize,
+    ) -> ioa::Result<OwnedSemaphorePermit> {
+        let units: usize = bytes.div_ceil(BUDGET_UNIT).clamp(1, self.units);
+
+        self.semaphore
+            .clone()
+            .acquire_many_owned(units as u32)
+            .await
+            .map_err(ioa::Error::other)
+    }
+}
+
+/// Detect the memory available to the process in bytes.
+///
+/// The memory is the lowest of the cgroup limit, the address space limit and
+/// the available memory of the system, or `None` if none of them is found,
+/// e.g. on systems other than Linux.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::tune::available_memory;
+///
+/// if let Some(memory) = available_memory() {
+///     println!("{} bytes available", memory);
+/// }
+/// ```
+pub fn available_memory() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        [
+            linux::cgroup_v2(),
+            linux::cgroup_v1(),
+            linux::rlimit(),
+            linux::meminfo(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Get the memory a process may use when auto-tuned, which is a fraction of
+/// the [`available_memory`] following the [`AUTO_TUNE_MEMORY_FRACTION`].
+pub fn memory_budget() -> Option<usize> {
+    available_memory().map(|memory| (memory / AUTO_TUNE_MEMORY_FRACTION).max(1))
+}
+
+/// Detect the space available to the process on the file system of
+/// `path` in bytes, or `None` if not found, e.g. on systems other than
+/// Linux.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::tune::available_space;
+///
+/// if let Some(space) = available_space("/path/to/dir") {
+///     println!("{} bytes available", space);
+/// }
+/// ```
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+pub fn available_space<P: AsRef<Path>>(path: P) -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::statvfs(path.as_ref())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Check that `bytes` are available on the file system of `path`, or of
+/// its nearest existing ancestor, failing with
+/// [`ioa::ErrorKind::StorageFull`] otherwise.
+///
+/// The check passes where the space is unknown.
+pub(crate) fn require_space(
+    path: &Path,
+    bytes: usize,
+) -> ioa::Result<()> {
+    let existing: Option<&Path> = path.ancestors().find(|p| p.exists());
+
+    let space: usize = match existing.and_then(available_space) {
+        | Some(space) => space,
+        | None => return Ok(()),
+    };
+
+    if space < bytes {
+        return Err(ioa::Error::new(
+            ioa::ErrorKind::StorageFull,
+            format!(
+                "{} bytes required on the file system of {}, {} available",
+                bytes,
+                path.display(),
+                space
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        ffi::CString,
+        fs,
+        os::unix::ffi::OsStrExt,
+        path::{Path, PathBuf},
+    };
+
+    /// Limits above it are treated as unlimited.
+    const UNLIMITED: u64 = 1 << 60;
+
+    /// Get the path of the cgroup of the process for the controller, or the
+    /// unified hierarchy if `None`.
+    fn cgroup_path(controller: Option<&str>) -> Option<String> {
+        let cgroup: String = fs::read_to_string("/proc/self/cgroup").ok()?;
+
+        cgroup.lines().find_map(|line| {
+            let mut parts = line.splitn(3, ':');
+
+            let (_, controllers, path) =
+                (parts.next()?, parts.next()?, parts.next()?);
+
+            let matched: bool = match controller {
+                | Some(controller) => {
+                    controllers.split(',').any(|c| c == controller)
+                },
+                | None => controllers.is_empty(),
+            };
+
+            matched.then(|| path.to_string())
+        })
+    }
+
+    /// Read the first limit found in the files.
+    fn read_limit(paths: Vec<PathBuf>) -> Option<usize> {
+        paths.iter().find_map(|path| {
+            let value: String = fs::read_to_string(path).ok()?;
+
+            value
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|limit| *limit < UNLIMITED)
+                .map(|limit| limit as usize)
+        })
+    }
+
+    /// Get the memory limit of the cgroup v2.
+    pub(super) fn cgroup_v2() -> Option<usize> {
+        let root: PathBuf = PathBuf::from("/sys/fs/cgroup");
+
+        let mut paths: Vec<PathBuf> = Vec::new();
+
+        if let Some(path) = cgroup_path(None) {
+            paths.push(
+                root.join(path.trim_start_matches('/')).join("memory.max"),
+            );
+        }
+
+        paths.push(root.join("memory.max"));
+
+        read_limit(paths)
+    }
+
+    /// Get the memory limit of the cgroup v1.
+    pub(super) fn cgroup_v1() -> Option<usize> {
+        let root: PathBuf = PathBuf::from("/sys/fs/cgroup/memory");
+
+        let mut paths: Vec<PathBuf> = Vec::new();
+
+        if let Some(path) = cgroup_path(Some("memory")) {
+            paths.push(
+                root.join(path.trim_start_matches('/'))
+                    .join("memory.limit_in_bytes"),
+            );
+        }
+
+        paths.push(root.join("memory.limit_in_bytes"));
+
+        read_limit(paths)
+    }
+
+    /// Get the limit of the address space from the ulimit.
+    pub(super) fn rlimit() -> Option<usize> {
+        let mut limit: libc::rlimit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+
+        if unsafe { libc::getrlimit(libc::RLIMIT_AS, &mut limit) } != 0 {
+            return None;
+        }
+
+        if limit.rlim_cur == libc::RLIM_INFINITY {
+            return None;
+        }
+
+        usize::try_from(limit.rlim_cur).ok()
+    }
+
+    /// Get the available memory of the system.
+    pub(super) fn meminfo() -> Option<usize> {
+        let meminfo: String = fs::read_to_string("/proc/meminfo").ok()?;
+
+        meminfo.lines().find_map(|line| {
+            let kb: &str = line.strip_prefix("MemAvailable:")?;
+
+            let kb: usize =
+                kb.trim().strip_suffix("kB")?.trim().parse().ok()?;
+
+            Some(kb * 1024)
+        })
+    }
+
+    /// Get the space available to unprivileged users on the file system of
+    /// the path.
+    pub(super) fn statvfs(path: &Path) -> Option<usize> {
+        let path: CString = CString::new(path.as_os_str().as_bytes()).ok()?;
+
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+
+        // the fields are narrower on 32-bit targets
+        #[allow(clippy::useless_conversion)]
+        let space: u64 =
+            u64::from(stat.f_bavail).checked_mul(u64::from(stat.f_frsize))?;
+
+        usize::try_from(space).ok()
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use tokio::{fs as fsa, io as ioa};
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization form of the output paths, e.g. for the names
+/// recorded on Linux, usually in NFC, merged on macOS, where HFS+ stores
+/// the names in NFD.
+///
+/// The names that look the same may differ in their bytes, so a file named
+/// on one platform is not found by its name on another, and a duplicate
+/// looking file is created next to it.
+///
+/// ## Example
+///
+/// ```no_run
+/// use filego::{merge::Merge, unicode::Normalization};
+///
+/// async fn example() {
+///     Merge::new()
+///         .from_manifest("/path/to/dir/manifest.json")
+///         .out_dir("/path/to/output")
+///         .normalization(Normalization::Nfd)
+///         .run()
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Normalization Form C, composing the characters, e.g. `é` as a single
+    /// code point, which is common on Linux and Windows.
+    Nfc,
+    /// Normalization Form D, decomposing the characters, e.g. `é` as `e`
+    /// followed by a combining accent, which is used by HFS+ on macOS.
+    Nfd,
+}
+
+impl Normalization {
+    /// Get the `name` in the normalization form.
+    pub fn normalize(
+        &self,
+        name: &str,
+    ) -> String {
+        match self {
+            | Self::Nfc => name.nfc().collect(),
+            | Self::Nfd => name.nfd().collect(),
+        }
+    }
+
+    /// Resolve the `path` with its file name in the normalization form, or
+    /// the path of an existing entry next to it whose name differs from it
+    /// only in the normalization.
+    ///
+    /// The path is returned as is if its file name is not valid UTF-8.
+    pub async fn resolve<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> ioa::Result<PathBuf> {
+        let path: &Path = path.as_ref();
+
+        let name: &str = match path.file_name().and_then(|name| name.to_str()) {
+            | Some(name) => name,
+            | None => return Ok(path.to_path_buf()),
+        };
+
+        let normalized: String = self.normalize(name);
+
+        let parent: &Path = path.parent().unwrap_or(Path::new(""));
+
+        // the names are compared in the same form regardless of the policy
+        let key: String = Self::Nfc.normalize(name);
+
+        let dir: &Path = match parent.as_os_str().is_empty() {
+            | true => Path::new("."),
+            | false => parent,
+        };
+
+        let mut entries: fsa::ReadDir = match fsa::read_dir(dir).await {
+            | Ok(entries) => entries,
+            | Err(e) if e.kind() == ioa::ErrorKind::NotFound => {
+                return Ok(parent.join(normalized))
+            },
+            | Err(e) => return Err(e),
+        };
+
+        let mut found: Option<String> = None;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let entry: String = match entry.file_name().into_string() {
+                | Ok(entry) => entry,
+                | Err(_) => continue,
+            };
+
+            if entry == normalized {
+                return Ok(parent.join(normalized));
+            }
+
+            if found.is_none() && Self::Nfc.normalize(&entry) == key {
+                found = Some(entry);
+            }
+        }
+
+        Ok(parent.join(found.unwrap_or(normalized)))
+    }
+}